@@ -0,0 +1,117 @@
+// Several echo requests per probe cycle. Whether the cycle counts as up is decided by how many
+// of them were answered, judged by a `SuccessCriterion`; the loss ratio is kept alongside.
+
+use crate::command::{burst_args, request_spacing_ms, CommandRunner, Platform};
+use crate::parse::{self, parse_rtt_ms, LATE_MARKER};
+
+/// How many replies a cycle needs to count as up.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SuccessCriterion {
+  #[default]
+  Any,
+  All,
+  /// At least this many; more than were sent means all of them.
+  AtLeast(u32),
+}
+
+impl SuccessCriterion {
+  pub fn is_met(self, received: u32, sent: u32) -> bool {
+    match self {
+      SuccessCriterion::Any => received > 0,
+      SuccessCriterion::All => received > 0 && received >= sent,
+      SuccessCriterion::AtLeast(needed) => received >= needed.clamp(1, sent.max(1)),
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Burst {
+  pub sent: u32,
+  pub received: u32,
+  /// Round-trip times of the replies that reported one, in order.
+  pub rtts_ms: Vec<f64>,
+  /// The verdict of the criterion.
+  pub success: bool,
+  /// The line `summarize` picked, followed by `[received/sent]` when more than one was sent and
+  /// `[LATE xN]` when replies came back after the next request had left.
+  pub line: String,
+}
+
+impl Burst {
+  pub fn loss_ratio(&self) -> f64 {
+    if self.sent == 0 {
+      return 0.0;
+    }
+    f64::from(self.sent - self.received) / f64::from(self.sent)
+  }
+
+  pub fn avg_rtt_ms(&self) -> Option<f64> {
+    (!self.rtts_ms.is_empty()).then(|| self.rtts_ms.iter().sum::<f64>() / self.rtts_ms.len() as f64)
+  }
+
+  /// The line as a probe result: `Ok` when the criterion was met.
+  pub fn into_result(self) -> Result<String, String> {
+    if self.success {
+      Ok(self.line)
+    } else {
+      Err(self.line)
+    }
+  }
+}
+
+/// Sends `count` echo requests in one ping run and judges the answers by `criterion`.
+pub fn ping_burst(
+  runner: &dyn CommandRunner,
+  platform: Platform,
+  address: &str,
+  source: Option<&str>,
+  count: u32,
+  criterion: SuccessCriterion,
+) -> Result<Burst, String> {
+  let sent = count.max(1);
+  let args = burst_args(platform, address, source, sent);
+  let spacing_ms = request_spacing_ms(platform, sent);
+  run_burst(runner, address, &args, sent, spacing_ms, criterion)
+}
+
+/// Runs ping with `args`, which send `sent` echo requests `spacing_ms` apart to `address`, and
+/// judges the answers.
+pub(crate) fn run_burst(
+  runner: &dyn CommandRunner,
+  address: &str,
+  args: &[String],
+  sent: u32,
+  spacing_ms: Option<f64>,
+  criterion: SuccessCriterion,
+) -> Result<Burst, String> {
+  let output = runner
+    .run("ping", args)
+    .map_err(|e| format!("failed to spawn ping: {e}"))?;
+  let replies = reply_lines(&output.stdout);
+  let received = (replies.len() as u32).min(sent);
+  let summary = match parse::summarize(address, &output) {
+    Ok(line) | Err(line) => line,
+  };
+  let late = spacing_ms.map_or(0, |spacing_ms| parse::count_late_replies(&output.stdout, spacing_ms));
+  Ok(Burst {
+    sent,
+    received,
+    rtts_ms: replies.iter().filter_map(|line| parse_rtt_ms(line)).collect(),
+    success: criterion.is_met(received, sent),
+    line: match (sent > 1, late) {
+      (false, _) => summary,
+      (true, 0) => format!("{summary} [{received}/{sent}]"),
+      (true, late) => format!("{summary} [{received}/{sent}] {LATE_MARKER}{late}]"),
+    },
+  })
+}
+
+/// Echo replies carry a TTL on every platform and in every language; `Destination host
+/// unreachable` answers and the statistics lines don't. Duplicates are not extra replies.
+fn reply_lines(output: &str) -> Vec<&str> {
+  output
+    .lines()
+    .map(str::trim)
+    .filter(|line| line.to_ascii_lowercase().contains("ttl=") && !line.contains("(DUP!)"))
+    .collect()
+}
@@ -0,0 +1,49 @@
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+pub trait Clock {
+  fn now(&self) -> Instant;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+  fn now(&self) -> Instant {
+    Instant::now()
+  }
+}
+
+/// A clock that only moves when told to, for tests.
+pub struct ManualClock {
+  now: Cell<Instant>,
+}
+
+impl ManualClock {
+  pub fn new() -> Self {
+    Self {
+      now: Cell::new(Instant::now()),
+    }
+  }
+
+  pub fn advance(&self, by: Duration) {
+    self.now.set(self.now.get() + by);
+  }
+}
+
+impl Default for ManualClock {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Clock for ManualClock {
+  fn now(&self) -> Instant {
+    self.now.get()
+  }
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+  fn now(&self) -> Instant {
+    (**self).now()
+  }
+}
@@ -0,0 +1,49 @@
+// Decoding of Windows console output. Double-byte and Cyrillic codepages go through
+// encoding_rs; the single-byte DOS codepages it does not cover are tables here, each mapping
+// bytes 0x80..=0xFF (the lower half is plain ASCII).
+
+pub const CP437: [char; 128] = [
+  'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å',
+  'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ',
+  'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»',
+  '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐',
+  '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧',
+  '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀',
+  'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩',
+  '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{a0}',
+];
+
+pub const CP850: [char; 128] = [
+  'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å',
+  'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', 'ø', '£', 'Ø', '×', 'ƒ',
+  'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '®', '¬', '½', '¼', '¡', '«', '»',
+  '░', '▒', '▓', '│', '┤', 'Á', 'Â', 'À', '©', '╣', '║', '╗', '╝', '¢', '¥', '┐',
+  '└', '┴', '┬', '├', '─', '┼', 'ã', 'Ã', '╚', '╔', '╩', '╦', '╠', '═', '╬', '¤',
+  'ð', 'Ð', 'Ê', 'Ë', 'È', 'ı', 'Í', 'Î', 'Ï', '┘', '┌', '█', '▄', '¦', 'Ì', '▀',
+  'Ó', 'ß', 'Ô', 'Ò', 'õ', 'Õ', 'µ', 'þ', 'Þ', 'Ú', 'Û', 'Ù', 'ý', 'Ý', '¯', '´',
+  '\u{ad}', '±', '‗', '¾', '¶', '§', '÷', '¸', '°', '¨', '·', '¹', '³', '²', '■', '\u{a0}',
+];
+
+pub fn decode(bytes: &[u8], table: &[char; 128]) -> String {
+  bytes
+    .iter()
+    .map(|&b| if b < 0x80 { b as char } else { table[(b - 0x80) as usize] })
+    .collect()
+}
+
+/// Decodes output written in console codepage `cp`; unknown codepages are read as UTF-8.
+pub fn decode_oem(bytes: &[u8], cp: u32) -> String {
+  let encoding = match cp {
+    936 => encoding_rs::GBK,
+    950 => encoding_rs::BIG5,
+    932 => encoding_rs::SHIFT_JIS,
+    949 => encoding_rs::EUC_KR,
+    866 => encoding_rs::IBM866,
+    1251 => encoding_rs::WINDOWS_1251,
+    437 => return decode(bytes, &CP437),
+    850 => return decode(bytes, &CP850),
+    _ => return String::from_utf8_lossy(bytes).into_owned(),
+  };
+  let (cow, _, _) = encoding.decode(bytes);
+  cow.into_owned()
+}
@@ -0,0 +1,191 @@
+use std::io;
+
+use crate::parse;
+
+/// A finished command with its output already decoded to text.
+pub struct CommandOutput {
+  pub success: bool,
+  pub stdout: String,
+  pub stderr: String,
+}
+
+/// Runs an external program; the app spawns a real process, tests return captured output.
+pub trait CommandRunner {
+  fn run(&self, program: &str, args: &[String]) -> io::Result<CommandOutput>;
+}
+
+/// Ping's command line differs between Windows, Linux (iputils) and the BSDs/macOS.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Platform {
+  Windows,
+  Linux,
+  Bsd,
+}
+
+impl Platform {
+  pub fn current() -> Self {
+    if cfg!(target_os = "windows") {
+      Platform::Windows
+    } else if cfg!(target_os = "linux") {
+      Platform::Linux
+    } else {
+      Platform::Bsd
+    }
+  }
+}
+
+/// Arguments for a single echo request; `source` pins it to an interface or source address.
+pub fn ping_args(platform: Platform, address: &str, source: Option<&str>) -> Vec<String> {
+  burst_args(platform, address, source, 1)
+}
+
+/// Arguments for `count` echo requests. Linux spaces them 200 ms apart, the shortest interval
+/// allowed without privileges; Windows and the BSDs send one a second.
+pub fn burst_args(platform: Platform, address: &str, source: Option<&str>, count: u32) -> Vec<String> {
+  let (count_flag, source_flag) = match platform {
+    Platform::Windows => ("-n", "-S"),
+    // Linux accepts an interface name or address; BSD/macOS only a source address.
+    Platform::Linux => ("-c", "-I"),
+    Platform::Bsd => ("-c", "-S"),
+  };
+  let mut args = vec![count_flag.to_string(), count.max(1).to_string()];
+  if platform == Platform::Linux && count > 1 {
+    args.push("-i".to_string());
+    args.push("0.2".to_string());
+  }
+  if let Some(source) = source {
+    args.push(source_flag.to_string());
+    args.push(source.to_string());
+  }
+  args.push(address.to_string());
+  args
+}
+
+/// Milliseconds between the requests `burst_args` sends, `None` for a single request.
+pub fn request_spacing_ms(platform: Platform, count: u32) -> Option<f64> {
+  match platform {
+    _ if count <= 1 => None,
+    Platform::Linux => Some(200.0),
+    Platform::Windows | Platform::Bsd => Some(1000.0),
+  }
+}
+
+/// Whether the platform's ping can set the ToS byte. Windows ping still documents `-v`, but the
+/// stack has ignored it since Vista.
+pub fn supports_dscp(platform: Platform) -> bool {
+  platform != Platform::Windows
+}
+
+/// Arguments marking echo requests with DSCP code point `dscp` (0-63, e.g. 46 for EF). Both
+/// iputils (`-Q`) and the BSDs (`-z`) take the whole ToS byte, the code point shifted past the
+/// two ECN bits.
+pub fn dscp_args(platform: Platform, dscp: u8) -> Vec<String> {
+  let tos = (u32::from(dscp.min(63)) << 2).to_string();
+  match platform {
+    Platform::Windows => Vec::new(),
+    Platform::Linux => vec!["-Q".to_string(), tos],
+    Platform::Bsd => vec!["-z".to_string(), tos],
+  }
+}
+
+/// Wraps a runner so every ping it runs is DSCP-marked; the marking goes before the address,
+/// which is always the last argument.
+pub struct DscpMarked<'a> {
+  pub runner: &'a dyn CommandRunner,
+  pub platform: Platform,
+  pub dscp: u8,
+}
+
+impl CommandRunner for DscpMarked<'_> {
+  fn run(&self, program: &str, args: &[String]) -> io::Result<CommandOutput> {
+    let mut args = args.to_vec();
+    if program == "ping" && !args.is_empty() {
+      let at = args.len() - 1;
+      args.splice(at..at, dscp_args(self.platform, self.dscp));
+    }
+    self.runner.run(program, &args)
+  }
+}
+
+/// Placeholders a `CustomPing` template may use.
+pub const TEMPLATE_PLACEHOLDERS: [&str; 4] = ["args", "address", "count", "source"];
+
+/// The command line for another ping executable, built from `template` and the `args` the
+/// system ping would have been given. The template is split on whitespace; `{args}` stands for
+/// those arguments without the address, `{address}`, `{count}` and `{source}` for their parts.
+/// An argument with `{source}` is left out when no source is set, together with an option
+/// right before it. An empty template means `{args} {address}`.
+pub fn custom_args(platform: Platform, template: &str, args: &[String]) -> Vec<String> {
+  let (count_flag, source_flag) = match platform {
+    Platform::Windows => ("-n", "-S"),
+    Platform::Linux => ("-c", "-I"),
+    Platform::Bsd => ("-c", "-S"),
+  };
+  let (options, address) = match args.split_last() {
+    Some((address, options)) => (options, address.as_str()),
+    None => (args, ""),
+  };
+  let value_after = |flag: &str| {
+    options
+      .iter()
+      .position(|arg| arg == flag)
+      .and_then(|at| options.get(at + 1))
+      .map(String::as_str)
+  };
+  let count = value_after(count_flag).unwrap_or("1");
+  let source = value_after(source_flag);
+
+  let template = if template.trim().is_empty() { "{args} {address}" } else { template };
+  let mut expanded: Vec<String> = Vec::new();
+  for token in template.split_whitespace() {
+    if token == "{args}" {
+      expanded.extend(options.iter().cloned());
+      continue;
+    }
+    if token.contains("{source}") && source.is_none() {
+      if expanded.last().is_some_and(|last| last.starts_with('-')) {
+        expanded.pop();
+      }
+      continue;
+    }
+    expanded.push(
+      token
+        .replace("{address}", address)
+        .replace("{count}", count)
+        .replace("{source}", source.unwrap_or_default()),
+    );
+  }
+  expanded
+}
+
+/// Wraps a runner so pings run `program` with a command line from `template` (see
+/// `custom_args`) instead of the system ping, e.g. busybox ping on a locked-down machine. The
+/// output is parsed as ping output, so the program has to print replies the way ping does.
+pub struct CustomPing<'a> {
+  pub runner: &'a dyn CommandRunner,
+  pub platform: Platform,
+  pub program: &'a str,
+  pub template: &'a str,
+}
+
+impl CommandRunner for CustomPing<'_> {
+  fn run(&self, program: &str, args: &[String]) -> io::Result<CommandOutput> {
+    if program != "ping" {
+      return self.runner.run(program, args);
+    }
+    self.runner.run(self.program, &custom_args(self.platform, self.template, args))
+  }
+}
+
+/// Pings `address` once and returns the line that best describes the result.
+pub fn ping_once(
+  runner: &dyn CommandRunner,
+  platform: Platform,
+  address: &str,
+  source: Option<&str>,
+) -> Result<String, String> {
+  let output = runner
+    .run("ping", &ping_args(platform, address, source))
+    .map_err(|e| format!("failed to spawn ping: {e}"))?;
+  parse::summarize(address, &output)
+}
@@ -0,0 +1,28 @@
+//! The parts of probing that don't need the app: building the ping command line, turning its
+//! output into a one-line result, sweeping payload sizes, decoding console codepages and
+//! deciding when failures are an outage. Process execution and time are behind the
+//! `CommandRunner` and `Clock` traits so all of it can be tested against captured outputs.
+
+pub mod burst;
+pub mod clock;
+pub mod codepage;
+pub mod command;
+pub mod outage;
+pub mod parse;
+pub mod sweep;
+pub mod ttl;
+
+pub use burst::{ping_burst, Burst, SuccessCriterion};
+pub use clock::{Clock, ManualClock, SystemClock};
+pub use command::{
+  burst_args, custom_args, dscp_args, ping_args, ping_once, request_spacing_ms, supports_dscp, CommandOutput,
+  CommandRunner, CustomPing, DscpMarked, Platform, TEMPLATE_PLACEHOLDERS,
+};
+pub use outage::{
+  FailureRun, FailureTracker, LinkState, OutageConfig, OutageDetector, OutageEvent, Transition, CONFIRM_FAILURES,
+};
+pub use parse::{
+  count_duplicate_replies, count_late_replies, count_reordered_replies, is_blocked_locally, parse_duplicate_count,
+  parse_marker_count, parse_rtt_ms, summarize, DUPLICATE_MARKER, LATE_MARKER, REORDERED_MARKER,
+};
+pub use sweep::{payload_sweep, sweep_args, Sweep, SweepStep};
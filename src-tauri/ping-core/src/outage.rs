@@ -0,0 +1,284 @@
+// Turning probe results into link states. A run of failures becomes an outage once it has
+// enough failures and has lasted the grace period; shorter runs end up as blips or
+// micro-outages. An outage ends after enough consecutive successes.
+//
+//   Up ──failure──▶ Degraded ──confirmed──▶ Down ──success──▶ Recovering ──successes──▶ Up
+//    ▲                 │                     ▲                     │
+//    └────success──────┘                     └──────failure────────┘
+//
+// With `recover_successes` at 1, Down goes straight back to Up.
+
+use std::time::{Duration, Instant};
+
+use crate::clock::{Clock, SystemClock};
+
+/// Consecutive failures needed before a run can count as an outage, unless configured.
+pub const CONFIRM_FAILURES: u32 = 3;
+
+#[derive(Clone, Copy, Debug)]
+pub struct OutageConfig {
+  /// Consecutive failures before a run can be an outage.
+  pub confirm_failures: u32,
+  /// How long, counted from the first failure, the run must also have lasted.
+  pub grace: Duration,
+  /// Consecutive successes that end an outage.
+  pub recover_successes: u32,
+}
+
+impl Default for OutageConfig {
+  fn default() -> Self {
+    Self {
+      confirm_failures: CONFIRM_FAILURES,
+      grace: Duration::ZERO,
+      recover_successes: 1,
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinkState {
+  Up,
+  /// Failing, but not (yet) an outage.
+  Degraded,
+  Down,
+  /// Replying again during an outage, but not for long enough to end it.
+  Recovering,
+}
+
+/// A run of failures that ended with a successful probe.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FailureRun {
+  /// Timestamp of the first failure, as passed to `record_failure`.
+  pub started: String,
+  pub failures: u32,
+  pub duration: Duration,
+  /// Enough failures for an outage, but shorter than the grace period.
+  pub micro_outage: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum OutageEvent {
+  /// The first failure after the link was up.
+  Degraded,
+  /// The run reached the thresholds; the outage counts from its first failure.
+  Confirmed { started: String, failures: u32 },
+  /// A run of failures ended before it became an outage.
+  Cleared(FailureRun),
+  /// The first success during an outage, when more are needed to end it.
+  Recovering,
+  /// A failure while recovering; the outage goes on.
+  Relapsed,
+  Recovered { started: String, duration: Duration },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transition {
+  pub from: LinkState,
+  pub to: LinkState,
+  pub event: OutageEvent,
+}
+
+/// Counts consecutive failures and when the run started.
+pub struct FailureTracker<C: Clock = SystemClock> {
+  clock: C,
+  confirm_failures: u32,
+  grace: Duration,
+  failures: u32,
+  first: Option<(String, Instant)>,
+}
+
+impl FailureTracker {
+  pub fn new(confirm_failures: u32, grace: Duration) -> Self {
+    Self::with_clock(confirm_failures, grace, SystemClock)
+  }
+}
+
+impl<C: Clock> FailureTracker<C> {
+  pub fn with_clock(confirm_failures: u32, grace: Duration, clock: C) -> Self {
+    Self {
+      clock,
+      confirm_failures: confirm_failures.max(1),
+      grace,
+      failures: 0,
+      first: None,
+    }
+  }
+
+  /// Records a failed probe; returns whether the run is now long enough to be an outage.
+  pub fn record_failure(&mut self, timestamp: &str) -> bool {
+    self.failures = self.failures.saturating_add(1);
+    if self.first.is_none() {
+      self.first = Some((timestamp.to_string(), self.clock.now()));
+    }
+    self.failures >= self.confirm_failures && self.elapsed() >= self.grace
+  }
+
+  /// Records a successful probe, returning the run of failures it ended, if any.
+  pub fn record_success(&mut self) -> Option<FailureRun> {
+    let duration = self.elapsed();
+    let run = self.first.take().map(|(started, _)| FailureRun {
+      started,
+      failures: self.failures,
+      duration,
+      micro_outage: self.failures >= self.confirm_failures,
+    });
+    self.failures = 0;
+    run
+  }
+
+  /// Forgets the current run without reporting it.
+  pub fn reset(&mut self) {
+    self.failures = 0;
+    self.first = None;
+  }
+
+  pub fn failures(&self) -> u32 {
+    self.failures
+  }
+
+  pub fn first_failure(&self) -> Option<&str> {
+    self.first.as_ref().map(|(timestamp, _)| timestamp.as_str())
+  }
+
+  /// When the current run started, on the tracker's clock.
+  pub fn started_at(&self) -> Option<Instant> {
+    self.first.as_ref().map(|(_, at)| *at)
+  }
+
+  /// How long the current run has lasted; zero when there is none.
+  pub fn elapsed(&self) -> Duration {
+    self
+      .started_at()
+      .map_or(Duration::ZERO, |at| self.clock.now().saturating_duration_since(at))
+  }
+
+  pub fn clock(&self) -> &C {
+    &self.clock
+  }
+}
+
+/// Feeds probe results through the state machine above and reports each state change.
+pub struct OutageDetector<C: Clock = SystemClock> {
+  run: FailureTracker<C>,
+  recover_successes: u32,
+  state: LinkState,
+  /// First failure of the current outage, as a timestamp and on the clock.
+  outage: Option<(String, Instant)>,
+  successes: u32,
+  loss_ratio: f64,
+}
+
+impl OutageDetector {
+  pub fn new(config: OutageConfig) -> Self {
+    Self::with_clock(config, SystemClock)
+  }
+}
+
+impl<C: Clock> OutageDetector<C> {
+  pub fn with_clock(config: OutageConfig, clock: C) -> Self {
+    Self {
+      run: FailureTracker::with_clock(config.confirm_failures, config.grace, clock),
+      recover_successes: config.recover_successes.max(1),
+      state: LinkState::Up,
+      outage: None,
+      successes: 0,
+      loss_ratio: 0.0,
+    }
+  }
+
+  /// Records a cycle of several echo requests: the verdict of its success criterion and the
+  /// share of requests that went unanswered.
+  pub fn record_cycle(&mut self, timestamp: &str, success: bool, loss_ratio: f64) -> Option<Transition> {
+    let transition = if success {
+      self.record_success()
+    } else {
+      self.record_failure(timestamp)
+    };
+    self.loss_ratio = loss_ratio.clamp(0.0, 1.0);
+    transition
+  }
+
+  pub fn record_failure(&mut self, timestamp: &str) -> Option<Transition> {
+    self.loss_ratio = 1.0;
+    let confirmed = self.run.record_failure(timestamp);
+    match self.state {
+      LinkState::Up | LinkState::Degraded if confirmed => {
+        let started = self.run.first_failure().unwrap_or(timestamp).to_string();
+        let at = self.run.started_at().unwrap_or_else(|| self.run.clock().now());
+        self.outage = Some((started.clone(), at));
+        let failures = self.run.failures();
+        self.move_to(LinkState::Down, OutageEvent::Confirmed { started, failures })
+      }
+      LinkState::Up => self.move_to(LinkState::Degraded, OutageEvent::Degraded),
+      LinkState::Recovering => {
+        self.successes = 0;
+        self.move_to(LinkState::Down, OutageEvent::Relapsed)
+      }
+      LinkState::Degraded | LinkState::Down => None,
+    }
+  }
+
+  pub fn record_success(&mut self) -> Option<Transition> {
+    self.loss_ratio = 0.0;
+    match self.state {
+      LinkState::Up => None,
+      LinkState::Degraded => {
+        let run = self.run.record_success()?;
+        self.move_to(LinkState::Up, OutageEvent::Cleared(run))
+      }
+      LinkState::Down | LinkState::Recovering => {
+        self.run.reset();
+        self.successes += 1;
+        if self.successes >= self.recover_successes {
+          let duration = self.outage_duration().unwrap_or_default();
+          let (started, _) = self.outage.take()?;
+          self.successes = 0;
+          self.move_to(LinkState::Up, OutageEvent::Recovered { started, duration })
+        } else if self.state == LinkState::Down {
+          self.move_to(LinkState::Recovering, OutageEvent::Recovering)
+        } else {
+          None
+        }
+      }
+    }
+  }
+
+  /// Back to Up without reporting anything, e.g. when monitoring pauses.
+  pub fn reset(&mut self) {
+    self.run.reset();
+    self.state = LinkState::Up;
+    self.outage = None;
+    self.successes = 0;
+    self.loss_ratio = 0.0;
+  }
+
+  pub fn state(&self) -> LinkState {
+    self.state
+  }
+
+  /// Loss of the last cycle: 0 or 1 for a single probe, the unanswered share for a burst.
+  pub fn loss_ratio(&self) -> f64 {
+    self.loss_ratio
+  }
+
+  /// Consecutive failures so far; during an outage, since the last success.
+  pub fn failures(&self) -> u32 {
+    self.run.failures()
+  }
+
+  /// Timestamp of the current outage's first failure.
+  pub fn outage_started(&self) -> Option<&str> {
+    self.outage.as_ref().map(|(started, _)| started.as_str())
+  }
+
+  pub fn outage_duration(&self) -> Option<Duration> {
+    let (_, at) = self.outage.as_ref()?;
+    Some(self.run.clock().now().saturating_duration_since(*at))
+  }
+
+  fn move_to(&mut self, to: LinkState, event: OutageEvent) -> Option<Transition> {
+    let from = self.state;
+    self.state = to;
+    Some(Transition { from, to, event })
+  }
+}
@@ -0,0 +1,209 @@
+// Turns ping's output into the one line that is logged for a probe. Windows ping is localized,
+// so the matchers know the English, Chinese and Russian wordings; other locales fall back to the
+// first line that isn't the `Pinging ...` header.
+
+use crate::command::CommandOutput;
+
+/// Appended to a probe summary as `[DUP x2]` when duplicate replies were seen.
+pub const DUPLICATE_MARKER: &str = "[DUP x";
+/// Appended as `[OOO x1]` when replies came back in a different order than they were sent.
+pub const REORDERED_MARKER: &str = "[OOO x";
+/// Appended as `[LATE x1]` when replies came back after the next request of the run had left.
+pub const LATE_MARKER: &str = "[LATE x";
+
+/// The line that best describes the result: `Ok` with the reply line, `Err` with the error.
+pub fn summarize(address: &str, output: &CommandOutput) -> Result<String, String> {
+  let success = output.success;
+  let text: &str = if success {
+    output.stdout.as_str()
+  } else if !output.stderr.trim().is_empty() {
+    output.stderr.as_str()
+  } else {
+    output.stdout.as_str()
+  };
+
+  let lines: Vec<&str> = text
+    .lines()
+    .map(str::trim)
+    .filter(|line| !line.is_empty())
+    .collect();
+
+  let preferred = if success {
+    select_success_line(&lines)
+      .or_else(|| select_non_header_line(&lines))
+      .or_else(|| lines.first().copied())
+  } else {
+    select_error_line(&lines)
+      .or_else(|| select_non_header_line(&lines))
+      .or_else(|| lines.first().copied())
+  };
+
+  let summary = preferred.unwrap_or("");
+
+  let mut summary = if summary.is_empty() {
+    format!("ping {address} {}", if success { "ok" } else { "failed" })
+  } else {
+    summary.to_string()
+  };
+  let duplicates = count_duplicate_replies(&output.stdout);
+  if duplicates > 0 {
+    summary.push_str(&format!(" {DUPLICATE_MARKER}{duplicates}]"));
+  }
+  let reordered = count_reordered_replies(&output.stdout);
+  if reordered > 0 {
+    summary.push_str(&format!(" {REORDERED_MARKER}{reordered}]"));
+  }
+
+  if success {
+    Ok(summary)
+  } else {
+    Err(summary)
+  }
+}
+
+/// Duplicate echo replies as reported by Unix ping, either per reply (`(DUP!)`) or in the
+/// iputils summary (`+2 duplicates`). Windows ping does not report duplicates.
+pub fn count_duplicate_replies(output: &str) -> u32 {
+  let from_summary = output.split(',').find_map(|part| {
+    let part = part.trim();
+    part
+      .strip_prefix('+')?
+      .strip_suffix("duplicates")
+      .and_then(|n| n.trim().parse::<u32>().ok())
+  });
+  from_summary.unwrap_or_else(|| output.lines().filter(|line| line.contains("(DUP!)")).count() as u32)
+}
+
+/// The sequence number of a Unix reply line (`icmp_seq=3`). Windows ping doesn't print one.
+fn reply_seq(line: &str) -> Option<u32> {
+  let rest = line.split_once("icmp_seq=")?.1;
+  let digits: String = rest.chars().take_while(char::is_ascii_digit).collect();
+  digits.parse().ok()
+}
+
+/// Replies, not counting duplicates, whose sequence number is below one already received: they
+/// were overtaken by a later request's reply. Needs more than one request per run, and the
+/// sequence numbers only Unix ping prints.
+pub fn count_reordered_replies(output: &str) -> u32 {
+  let mut highest: Option<u32> = None;
+  let mut reordered = 0;
+  for seq in output
+    .lines()
+    .filter(|line| !line.contains("(DUP!)"))
+    .filter_map(reply_seq)
+  {
+    if highest.is_some_and(|highest| seq < highest) {
+      reordered += 1;
+    }
+    highest = Some(highest.map_or(seq, |highest| highest.max(seq)));
+  }
+  reordered
+}
+
+/// Replies, not counting duplicates, that took longer than `spacing_ms`, the time between two
+/// requests of the run: the next request had already been sent when they arrived.
+pub fn count_late_replies(output: &str, spacing_ms: f64) -> u32 {
+  output
+    .lines()
+    .filter(|line| !line.contains("(DUP!)") && line.to_ascii_lowercase().contains("ttl="))
+    .filter_map(parse_rtt_ms)
+    .filter(|rtt| *rtt > spacing_ms)
+    .count() as u32
+}
+
+/// The count appended after `marker` (one of the `*_MARKER`s), or 0.
+pub fn parse_marker_count(line: &str, marker: &str) -> u32 {
+  line
+    .rsplit_once(marker)
+    .and_then(|(_, rest)| rest.split_once(']'))
+    .and_then(|(n, _)| n.parse().ok())
+    .unwrap_or(0)
+}
+
+/// The count `summarize` appended, or 0.
+pub fn parse_duplicate_count(line: &str) -> u32 {
+  parse_marker_count(line, DUPLICATE_MARKER)
+}
+
+pub fn select_success_line<'a>(lines: &'a [&'a str]) -> Option<&'a str> {
+  lines.iter().copied().find(|line| {
+    let lower = line.to_ascii_lowercase();
+    line.contains("Reply from")
+      || line.contains("bytes from")
+      || line.contains("bytes=")
+      || lower.contains("time=")
+      || lower.contains("time<")
+      || lower.contains("ttl=")
+      || lower.contains("ms")
+      || line.contains("时间")
+      || line.contains("字节=")
+  })
+}
+
+/// Extracts the round-trip time from a reply line such as `time=14ms`, `time=14.2 ms` or `时间<1ms`.
+pub fn parse_rtt_ms(line: &str) -> Option<f64> {
+  for (idx, ch) in line.char_indices() {
+    if ch != '=' && ch != '<' {
+      continue;
+    }
+    let rest = line[idx + 1..].trim_start();
+    let number: String = rest
+      .chars()
+      .take_while(|c| c.is_ascii_digit() || *c == '.' || *c == ',')
+      .collect();
+    if number.is_empty() {
+      continue;
+    }
+    if rest[number.len()..].trim_start().starts_with("ms") {
+      if let Ok(value) = number.replace(',', ".").parse::<f64>() {
+        return Some(value);
+      }
+    }
+  }
+  None
+}
+
+pub fn select_error_line<'a>(lines: &'a [&'a str]) -> Option<&'a str> {
+  lines.iter().copied().find(|line| {
+    let lower = line.to_ascii_lowercase();
+    lower.contains("timed out")
+      || lower.contains("timeout")
+      || lower.contains("unreachable")
+      || lower.contains("general failure")
+      || lower.contains("could not find host")
+      || lower.contains("name or service not known")
+      || line.contains("请求超时")
+      || line.contains("无法访问")
+      || line.contains("一般故障")
+      || line.contains("找不到主机")
+      || line.contains("无法解析")
+      || line.contains("Превышен интервал ожидания")
+      || line.contains("недоступен")
+  })
+}
+
+/// Ping couldn't even send the request: Windows says `General failure.` or `PING: transmit
+/// failed` when the firewall or an IPsec policy drops outbound ICMP, Linux `sendmsg: Operation
+/// not permitted` when netfilter rejects it. A lost or unanswered request says nothing like it.
+pub fn is_blocked_locally(line: &str) -> bool {
+  let lower = line.to_ascii_lowercase();
+  lower.contains("general failure")
+    || lower.contains("transmit failed")
+    || lower.contains("operation not permitted")
+    || line.contains("一般故障")
+    || line.contains("传输失败")
+    || line.contains("Общий сбой")
+}
+
+pub fn select_non_header_line<'a>(lines: &'a [&'a str]) -> Option<&'a str> {
+  lines.iter().copied().find(|line| !is_header_line(line))
+}
+
+pub fn is_header_line(line: &str) -> bool {
+  let lower = line.to_ascii_lowercase();
+  lower.starts_with("pinging ")
+    || lower.starts_with("ping ")
+    || line.contains("正在 Ping")
+    || line.contains("正在ping")
+    || line.starts_with("Обмен пакетами с")
+}
@@ -0,0 +1,154 @@
+// Payload sweeps: the same target pinged with growing payloads, don't-fragment set, and with
+// fill patterns. Loss that starts at one size and stays points at the path MTU or a broken
+// fragmentation path; loss with one pattern only points at a NIC, modem or line that mangles
+// certain bit sequences. Neither shows up with the fixed 32 or 56 byte payload of a normal probe.
+
+use crate::burst::{run_burst, Burst, SuccessCriterion};
+use crate::command::{burst_args, request_spacing_ms, CommandRunner, Platform};
+
+/// From a minimal echo up to the largest payload that fits a 1500-byte Ethernet frame.
+pub const DEFAULT_SIZES: &[u32] = &[64, 256, 512, 1024, 1280, 1400, 1450, 1472];
+/// All zeros, all ones and alternating bits.
+pub const DEFAULT_PATTERNS: &[&str] = &["00", "ff", "55aa"];
+/// The largest payload Windows ping accepts; iputils allows a few bytes more.
+pub const MAX_SIZE: u32 = 65_500;
+/// `ping -p` takes at most 16 pattern bytes.
+pub const MAX_PATTERN_BYTES: usize = 16;
+
+/// One run of `count` echo requests at one payload size.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SweepStep {
+  pub size: u32,
+  /// Hex fill pattern; `None` is ping's default payload.
+  pub pattern: Option<String>,
+  pub burst: Burst,
+}
+
+impl SweepStep {
+  pub fn lossy(&self) -> bool {
+    self.burst.received < self.burst.sent
+  }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Sweep {
+  /// Ordered by pattern (default payload first), then size.
+  pub steps: Vec<SweepStep>,
+  /// The largest default-payload size answered in full, narrowed down by bisection where a
+  /// size in the plan was lost completely right after it. Smaller sizes with some loss don't
+  /// cap it: that loss is intermittent, not a size limit.
+  pub largest_clean: Option<u32>,
+}
+
+impl Sweep {
+  /// The smallest size with loss, from which every larger size also lost replies.
+  pub fn loss_starts_at(&self, pattern: Option<&str>) -> Option<u32> {
+    let steps: Vec<&SweepStep> = self
+      .steps
+      .iter()
+      .filter(|step| step.pattern.as_deref() == pattern)
+      .collect();
+    let clean_after = steps.iter().rposition(|step| !step.lossy()).map_or(0, |at| at + 1);
+    steps.get(clean_after).map(|step| step.size)
+  }
+}
+
+/// Patterns are hex, a whole number of bytes, as `ping -p` takes them.
+pub fn valid_pattern(pattern: &str) -> bool {
+  !pattern.is_empty()
+    && pattern.len().is_multiple_of(2)
+    && pattern.len() <= MAX_PATTERN_BYTES * 2
+    && pattern.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Windows ping has no fill pattern option.
+pub fn supports_patterns(platform: Platform) -> bool {
+  platform != Platform::Windows
+}
+
+/// Arguments for `count` echo requests with a `size`-byte payload, don't-fragment set, filled
+/// with `pattern` where the platform supports it.
+pub fn sweep_args(platform: Platform, address: &str, count: u32, size: u32, pattern: Option<&str>) -> Vec<String> {
+  let mut args = burst_args(platform, address, None, count);
+  let address = args.pop().unwrap_or_default();
+  let size = size.min(MAX_SIZE).to_string();
+  match platform {
+    Platform::Windows => args.extend(["-l".to_string(), size, "-f".to_string()]),
+    Platform::Linux => args.extend(["-s".to_string(), size, "-M".to_string(), "do".to_string()]),
+    Platform::Bsd => args.extend(["-s".to_string(), size, "-D".to_string()]),
+  }
+  if let Some(pattern) = pattern.filter(|_| supports_patterns(platform)) {
+    args.push("-p".to_string());
+    args.push(pattern.to_string());
+  }
+  args.push(address);
+  args
+}
+
+/// Pings `address` `count` times at each of `sizes` with the default payload, bisects the gap
+/// where loss begins, then repeats the sizes with each of `patterns`. Patterns are skipped where
+/// the platform can't send them.
+pub fn payload_sweep(
+  runner: &dyn CommandRunner,
+  platform: Platform,
+  address: &str,
+  sizes: &[u32],
+  patterns: &[&str],
+  count: u32,
+) -> Result<Sweep, String> {
+  let count = count.max(1);
+  let mut sizes: Vec<u32> = sizes.iter().map(|size| (*size).min(MAX_SIZE)).collect();
+  sizes.sort_unstable();
+  sizes.dedup();
+  let spacing_ms = request_spacing_ms(platform, count);
+  let step = |size: u32, pattern: Option<&str>| -> Result<SweepStep, String> {
+    let args = sweep_args(platform, address, count, size, pattern);
+    Ok(SweepStep {
+      size,
+      pattern: pattern.map(str::to_string),
+      burst: run_burst(runner, address, &args, count, spacing_ms, SuccessCriterion::All)?,
+    })
+  };
+
+  let mut sweep = Sweep::default();
+  let mut default_steps = Vec::new();
+  for &size in &sizes {
+    default_steps.push(step(size, None)?);
+  }
+  sweep.largest_clean = default_steps
+    .iter()
+    .filter(|step| !step.lossy())
+    .map(|step| step.size)
+    .next_back();
+  let edge = default_steps.iter().position(|step| step.burst.received == 0);
+  if let (Some(mut good), Some(edge)) = (sweep.largest_clean, edge) {
+    let mut bad = default_steps[edge].size;
+    if edge > 0 && default_steps[edge - 1].size == good {
+      while bad - good > 1 {
+        let probe = step(good + (bad - good) / 2, None)?;
+        match probe.burst.received {
+          received if received == probe.burst.sent => good = probe.size,
+          0 => bad = probe.size,
+          // Partial loss in the gap isn't a size threshold; stop narrowing.
+          _ => {
+            default_steps.push(probe);
+            break;
+          }
+        }
+        default_steps.push(probe);
+      }
+      sweep.largest_clean = Some(good);
+    }
+  }
+  default_steps.sort_by_key(|step| step.size);
+  sweep.steps = default_steps;
+
+  if supports_patterns(platform) {
+    for pattern in patterns {
+      for &size in &sizes {
+        sweep.steps.push(step(size, Some(pattern))?);
+      }
+    }
+  }
+  Ok(sweep)
+}
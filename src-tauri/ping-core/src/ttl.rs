@@ -0,0 +1,42 @@
+/// Replies needed at a new TTL before it counts as a path change, so per-packet
+/// load balancing across paths of different length doesn't flap.
+const CONFIRM_REPLIES: u32 = 3;
+
+/// `ttl=54` (Unix) or `TTL=54` (Windows, every locale).
+pub fn parse(line: &str) -> Option<u8> {
+  let lower = line.to_ascii_lowercase();
+  let start = lower.find("ttl=")? + 4;
+  let digits: String = lower[start..].chars().take_while(char::is_ascii_digit).collect();
+  digits.parse().ok()
+}
+
+#[derive(Default)]
+pub struct TtlTracker {
+  current: Option<u8>,
+  candidate: Option<(u8, u32)>,
+}
+
+impl TtlTracker {
+  /// Returns `(old, new)` once a different TTL has been seen on enough consecutive replies.
+  pub fn observe(&mut self, ttl: u8) -> Option<(u8, u8)> {
+    let Some(current) = self.current else {
+      self.current = Some(ttl);
+      return None;
+    };
+    if ttl == current {
+      self.candidate = None;
+      return None;
+    }
+    let count = match self.candidate {
+      Some((candidate, count)) if candidate == ttl => count + 1,
+      _ => 1,
+    };
+    if count < CONFIRM_REPLIES {
+      self.candidate = Some((ttl, count));
+      return None;
+    }
+    self.current = Some(ttl);
+    self.candidate = None;
+    Some((current, ttl))
+  }
+}
@@ -0,0 +1,101 @@
+//! Multi-packet probes: reply counting on captured output and the success criteria.
+
+mod common;
+
+use common::Captured;
+use ping_core::{burst_args, parse_marker_count, ping_burst, Platform, SuccessCriterion, LATE_MARKER, REORDERED_MARKER};
+
+const LINUX_PARTIAL: Captured = Captured {
+  success: true,
+  stdout: include_str!("fixtures/linux_burst_partial.txt"),
+};
+
+#[test]
+fn counts_linux_replies() {
+  let burst =
+    ping_burst(&LINUX_PARTIAL, Platform::Linux, "192.168.1.20", None, 5, SuccessCriterion::Any).unwrap();
+  assert_eq!((burst.sent, burst.received), (5, 3));
+  assert_eq!(burst.rtts_ms, [2.1, 3.9, 2.0]);
+  assert!((burst.loss_ratio() - 0.4).abs() < 1e-9);
+  assert!((burst.avg_rtt_ms().unwrap() - 8.0 / 3.0).abs() < 1e-9);
+  assert!(burst.success);
+  assert_eq!(burst.line, "64 bytes from 192.168.1.20: icmp_seq=1 ttl=64 time=2.10 ms [3/5]");
+}
+
+#[test]
+fn criteria_decide_the_verdict() {
+  let verdict = |criterion| {
+    ping_burst(&LINUX_PARTIAL, Platform::Linux, "192.168.1.20", None, 5, criterion)
+      .unwrap()
+      .success
+  };
+  assert!(!verdict(SuccessCriterion::All));
+  assert!(verdict(SuccessCriterion::AtLeast(3)));
+  assert!(!verdict(SuccessCriterion::AtLeast(4)));
+}
+
+#[test]
+fn failed_burst_keeps_the_line() {
+  let lost = Captured {
+    success: false,
+    stdout: include_str!("fixtures/linux_burst_lost.txt"),
+  };
+  let burst = ping_burst(&lost, Platform::Linux, "192.168.1.20", None, 3, SuccessCriterion::Any).unwrap();
+  assert_eq!((burst.received, burst.loss_ratio()), (0, 1.0));
+  assert_eq!(burst.avg_rtt_ms(), None);
+  let line = burst.into_result().unwrap_err();
+  assert!(line.ends_with(" [0/3]"), "{line}");
+}
+
+#[test]
+fn late_and_reordered_replies_are_marked() {
+  let reordered = Captured {
+    success: true,
+    stdout: include_str!("fixtures/linux_burst_reordered.txt"),
+  };
+  let burst =
+    ping_burst(&reordered, Platform::Linux, "192.168.1.20", None, 4, SuccessCriterion::All).unwrap();
+  assert!(burst.success);
+  assert_eq!(
+    burst.line,
+    "64 bytes from 192.168.1.20: icmp_seq=1 ttl=64 time=2.40 ms [OOO x1] [4/4] [LATE x1]"
+  );
+  assert_eq!(parse_marker_count(&burst.line, REORDERED_MARKER), 1);
+  assert_eq!(parse_marker_count(&burst.line, LATE_MARKER), 1);
+  // Windows sends a request a second, so the same reply is not late there.
+  let burst =
+    ping_burst(&reordered, Platform::Windows, "192.168.1.20", None, 4, SuccessCriterion::All).unwrap();
+  assert_eq!(parse_marker_count(&burst.line, LATE_MARKER), 0);
+}
+
+#[test]
+fn unreachable_answers_are_not_replies() {
+  let windows = Captured {
+    success: true,
+    stdout: include_str!("fixtures/windows_en_burst.txt"),
+  };
+  let burst =
+    ping_burst(&windows, Platform::Windows, "192.168.1.1", None, 4, SuccessCriterion::AtLeast(3)).unwrap();
+  assert_eq!(burst.received, 2);
+  assert_eq!(burst.rtts_ms, [3.0, 1.0]);
+  assert!(!burst.success);
+}
+
+#[test]
+fn at_least_more_than_sent_means_all() {
+  assert!(SuccessCriterion::AtLeast(9).is_met(3, 3));
+  assert!(!SuccessCriterion::AtLeast(9).is_met(2, 3));
+  assert!(!SuccessCriterion::AtLeast(0).is_met(0, 3));
+  assert!(!SuccessCriterion::All.is_met(0, 0));
+}
+
+#[test]
+fn burst_arguments() {
+  assert_eq!(burst_args(Platform::Linux, "1.1.1.1", None, 5), ["-c", "5", "-i", "0.2", "1.1.1.1"]);
+  assert_eq!(burst_args(Platform::Linux, "1.1.1.1", None, 1), ["-c", "1", "1.1.1.1"]);
+  assert_eq!(burst_args(Platform::Windows, "1.1.1.1", None, 4), ["-n", "4", "1.1.1.1"]);
+  assert_eq!(
+    burst_args(Platform::Bsd, "1.1.1.1", Some("10.0.0.2"), 0),
+    ["-c", "1", "-S", "10.0.0.2", "1.1.1.1"]
+  );
+}
@@ -0,0 +1,21 @@
+//! Fakes shared by the integration tests.
+
+use std::io;
+
+use ping_core::{CommandOutput, CommandRunner};
+
+/// Answers every command with the same captured output, whatever it was asked to run.
+pub struct Captured {
+  pub success: bool,
+  pub stdout: &'static str,
+}
+
+impl CommandRunner for Captured {
+  fn run(&self, _program: &str, _args: &[String]) -> io::Result<CommandOutput> {
+    Ok(CommandOutput {
+      success: self.success,
+      stdout: self.stdout.to_string(),
+      stderr: String::new(),
+    })
+  }
+}
@@ -0,0 +1,226 @@
+//! Failure runs and link states tracked against a manual clock.
+
+use std::time::Duration;
+
+use ping_core::{
+  FailureRun, FailureTracker, LinkState, ManualClock, OutageConfig, OutageDetector, OutageEvent,
+};
+
+const SECOND: Duration = Duration::from_secs(1);
+
+#[test]
+fn three_failures_confirm_without_grace() {
+  let clock = ManualClock::new();
+  let mut tracker = FailureTracker::with_clock(3, Duration::ZERO, &clock);
+  assert!(!tracker.record_failure("12:00:00"));
+  clock.advance(SECOND);
+  assert!(!tracker.record_failure("12:00:01"));
+  clock.advance(SECOND);
+  assert!(tracker.record_failure("12:00:02"));
+  assert_eq!(tracker.failures(), 3);
+  assert_eq!(tracker.first_failure(), Some("12:00:00"));
+  assert_eq!(tracker.elapsed(), 2 * SECOND);
+}
+
+#[test]
+fn grace_period_delays_confirmation() {
+  let clock = ManualClock::new();
+  let mut tracker = FailureTracker::with_clock(3, Duration::from_secs(10), &clock);
+  for second in 0..10 {
+    assert!(!tracker.record_failure(&format!("12:00:{second:02}")), "second {second}");
+    clock.advance(SECOND);
+  }
+  assert!(tracker.record_failure("12:00:10"));
+}
+
+#[test]
+fn success_reports_the_run_and_resets() {
+  let clock = ManualClock::new();
+  let mut tracker = FailureTracker::with_clock(3, Duration::from_secs(30), &clock);
+  assert_eq!(tracker.record_success(), None);
+  for second in 0..4 {
+    tracker.record_failure(&format!("12:00:{second:02}"));
+    clock.advance(SECOND);
+  }
+  assert_eq!(
+    tracker.record_success(),
+    Some(FailureRun {
+      started: "12:00:00".to_string(),
+      failures: 4,
+      duration: 4 * SECOND,
+      micro_outage: true,
+    })
+  );
+  assert_eq!(tracker.failures(), 0);
+  assert_eq!(tracker.first_failure(), None);
+  assert_eq!(tracker.elapsed(), Duration::ZERO);
+}
+
+#[test]
+fn short_runs_are_blips() {
+  let clock = ManualClock::new();
+  let mut tracker = FailureTracker::with_clock(3, Duration::ZERO, &clock);
+  tracker.record_failure("12:00:00");
+  tracker.record_failure("12:00:01");
+  assert!(!tracker.record_success().unwrap().micro_outage);
+}
+
+#[test]
+fn reset_forgets_the_run() {
+  let clock = ManualClock::new();
+  let mut tracker = FailureTracker::with_clock(3, Duration::ZERO, &clock);
+  tracker.record_failure("12:00:00");
+  tracker.record_failure("12:00:01");
+  tracker.reset();
+  assert_eq!(tracker.record_success(), None);
+  assert!(!tracker.record_failure("12:00:05"));
+  assert_eq!(tracker.first_failure(), Some("12:00:05"));
+}
+
+fn states(detector: &mut OutageDetector<&ManualClock>, clock: &ManualClock, results: &str) -> Vec<LinkState> {
+  results
+    .chars()
+    .enumerate()
+    .map(|(second, result)| {
+      if result == '.' {
+        detector.record_success();
+      } else {
+        detector.record_failure(&format!("12:00:{second:02}"));
+      }
+      clock.advance(SECOND);
+      detector.state()
+    })
+    .collect()
+}
+
+#[test]
+fn detector_goes_down_and_back_up() {
+  use LinkState::*;
+  let clock = ManualClock::new();
+  let mut detector = OutageDetector::with_clock(OutageConfig::default(), &clock);
+  assert_eq!(
+    states(&mut detector, &clock, ".xx.xxxx."),
+    [Up, Degraded, Degraded, Up, Degraded, Degraded, Down, Down, Up]
+  );
+}
+
+#[test]
+fn detector_reports_transitions() {
+  let clock = ManualClock::new();
+  let mut detector = OutageDetector::with_clock(OutageConfig::default(), &clock);
+  let first = detector.record_failure("12:00:00").unwrap();
+  assert_eq!(
+    (first.from, first.to, first.event),
+    (LinkState::Up, LinkState::Degraded, OutageEvent::Degraded)
+  );
+  clock.advance(SECOND);
+  assert_eq!(detector.record_failure("12:00:01"), None);
+  clock.advance(SECOND);
+  let confirmed = detector.record_failure("12:00:02").unwrap();
+  assert_eq!(
+    confirmed.event,
+    OutageEvent::Confirmed {
+      started: "12:00:00".to_string(),
+      failures: 3,
+    }
+  );
+  assert_eq!(detector.outage_started(), Some("12:00:00"));
+  clock.advance(10 * SECOND);
+  assert_eq!(detector.record_failure("12:00:12"), None);
+  assert_eq!(detector.outage_duration(), Some(12 * SECOND));
+  let recovered = detector.record_success().unwrap();
+  assert_eq!((recovered.from, recovered.to), (LinkState::Down, LinkState::Up));
+  assert_eq!(
+    recovered.event,
+    OutageEvent::Recovered {
+      started: "12:00:00".to_string(),
+      duration: 12 * SECOND,
+    }
+  );
+  assert_eq!(detector.outage_started(), None);
+}
+
+#[test]
+fn detector_clears_short_runs() {
+  let clock = ManualClock::new();
+  let config = OutageConfig {
+    grace: Duration::from_secs(30),
+    ..OutageConfig::default()
+  };
+  let mut detector = OutageDetector::with_clock(config, &clock);
+  for second in 0..5 {
+    detector.record_failure(&format!("12:00:{second:02}"));
+    clock.advance(SECOND);
+  }
+  assert_eq!(detector.state(), LinkState::Degraded);
+  let cleared = detector.record_success().unwrap();
+  let OutageEvent::Cleared(run) = cleared.event else {
+    panic!("expected a cleared run, got {:?}", cleared.event);
+  };
+  assert_eq!((run.failures, run.duration, run.micro_outage), (5, 5 * SECOND, true));
+}
+
+#[test]
+fn detector_needs_consecutive_successes_to_recover() {
+  use LinkState::*;
+  let clock = ManualClock::new();
+  let config = OutageConfig {
+    recover_successes: 3,
+    ..OutageConfig::default()
+  };
+  let mut detector = OutageDetector::with_clock(config, &clock);
+  assert_eq!(
+    states(&mut detector, &clock, "xxx..x..."),
+    [Degraded, Degraded, Down, Recovering, Recovering, Down, Recovering, Recovering, Up]
+  );
+}
+
+#[test]
+fn detector_relapse_keeps_the_outage() {
+  let clock = ManualClock::new();
+  let config = OutageConfig {
+    recover_successes: 2,
+    ..OutageConfig::default()
+  };
+  let mut detector = OutageDetector::with_clock(config, &clock);
+  states(&mut detector, &clock, "xxx");
+  assert_eq!(detector.record_success().map(|t| t.event), Some(OutageEvent::Recovering));
+  assert_eq!(detector.record_failure("12:00:04").map(|t| t.event), Some(OutageEvent::Relapsed));
+  assert_eq!(detector.outage_started(), Some("12:00:00"));
+}
+
+#[test]
+fn detector_confirms_on_a_single_failure_when_configured() {
+  let clock = ManualClock::new();
+  let config = OutageConfig {
+    confirm_failures: 1,
+    ..OutageConfig::default()
+  };
+  let mut detector = OutageDetector::with_clock(config, &clock);
+  let transition = detector.record_failure("12:00:00").unwrap();
+  assert_eq!((transition.from, transition.to), (LinkState::Up, LinkState::Down));
+}
+
+#[test]
+fn detector_reset_is_silent() {
+  let clock = ManualClock::new();
+  let mut detector = OutageDetector::with_clock(OutageConfig::default(), &clock);
+  states(&mut detector, &clock, "xxxx");
+  detector.reset();
+  assert_eq!(detector.state(), LinkState::Up);
+  assert_eq!(detector.outage_started(), None);
+  assert_eq!(detector.record_success(), None);
+}
+
+#[test]
+fn detector_keeps_the_cycle_loss() {
+  let clock = ManualClock::new();
+  let mut detector = OutageDetector::with_clock(OutageConfig::default(), &clock);
+  assert_eq!(detector.record_cycle("12:00:00", true, 0.4), None);
+  assert_eq!(detector.loss_ratio(), 0.4);
+  let transition = detector.record_cycle("12:00:01", false, 0.8).unwrap();
+  assert_eq!(transition.event, OutageEvent::Degraded);
+  assert_eq!(detector.loss_ratio(), 0.8);
+  detector.record_failure("12:00:02");
+  assert_eq!(detector.loss_ratio(), 1.0);
+}
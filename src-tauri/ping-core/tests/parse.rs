@@ -0,0 +1,237 @@
+//! Summaries of captured ping output from different systems and console languages.
+
+use ping_core::codepage::decode_oem;
+use ping_core::{
+  count_duplicate_replies, count_late_replies, count_reordered_replies, is_blocked_locally, parse_duplicate_count,
+  parse_rtt_ms, summarize, ttl, CommandOutput,
+};
+
+fn output(success: bool, stdout: &str) -> CommandOutput {
+  CommandOutput {
+    success,
+    stdout: stdout.to_string(),
+    stderr: String::new(),
+  }
+}
+
+#[test]
+fn windows_english_reply() {
+  let out = output(true, include_str!("fixtures/windows_en_reply.txt"));
+  let line = summarize("192.168.1.1", &out).unwrap();
+  assert_eq!(line, "Reply from 192.168.1.1: bytes=32 time=3ms TTL=64");
+  assert_eq!(parse_rtt_ms(&line), Some(3.0));
+  assert_eq!(ttl::parse(&line), Some(64));
+}
+
+#[test]
+fn windows_english_timeout() {
+  let out = output(false, include_str!("fixtures/windows_en_timeout.txt"));
+  assert_eq!(summarize("10.255.255.1", &out), Err("Request timed out.".to_string()));
+}
+
+#[test]
+fn windows_english_unreachable() {
+  let out = output(false, include_str!("fixtures/windows_en_unreachable.txt"));
+  assert_eq!(
+    summarize("10.0.0.99", &out),
+    Err("Reply from 10.0.0.5: Destination host unreachable.".to_string())
+  );
+}
+
+#[test]
+fn windows_english_general_failure_is_blocked_locally() {
+  let out = output(false, include_str!("fixtures/windows_en_general_failure.txt"));
+  let line = summarize("192.168.1.1", &out).unwrap_err();
+  assert_eq!(line, "General failure.");
+  assert!(is_blocked_locally(&line));
+}
+
+#[test]
+fn timeouts_and_unreachable_are_not_blocked_locally() {
+  for (address, fixture) in [
+    ("10.255.255.1", include_str!("fixtures/windows_en_timeout.txt")),
+    ("10.0.0.99", include_str!("fixtures/windows_en_unreachable.txt")),
+    ("10.0.0.99", include_str!("fixtures/linux_unreachable.txt")),
+  ] {
+    let line = summarize(address, &output(false, fixture)).unwrap_err();
+    assert!(!is_blocked_locally(&line), "{line}");
+  }
+}
+
+#[test]
+fn windows_english_unknown_host() {
+  let out = output(false, include_str!("fixtures/windows_en_unknown_host.txt"));
+  let line = summarize("nosuch.example", &out).unwrap_err();
+  assert!(line.starts_with("Ping request could not find host nosuch.example."));
+}
+
+#[test]
+fn windows_chinese_reply() {
+  let stdout = decode_oem(include_bytes!("fixtures/windows_zh_reply.gbk"), 936);
+  let line = summarize("192.168.1.1", &output(true, &stdout)).unwrap();
+  assert_eq!(line, "来自 192.168.1.1 的回复: 字节=32 时间<1ms TTL=64");
+  assert_eq!(parse_rtt_ms(&line), Some(1.0));
+  assert_eq!(ttl::parse(&line), Some(64));
+}
+
+#[test]
+fn windows_chinese_timeout() {
+  let stdout = decode_oem(include_bytes!("fixtures/windows_zh_timeout.gbk"), 936);
+  assert_eq!(summarize("10.255.255.1", &output(false, &stdout)), Err("请求超时。".to_string()));
+}
+
+#[test]
+fn windows_german_reply() {
+  let stdout = decode_oem(include_bytes!("fixtures/windows_de_reply.cp850"), 850);
+  assert!(stdout.contains("Ping wird ausgeführt für"));
+  let line = summarize("192.168.1.1", &output(true, &stdout)).unwrap();
+  assert_eq!(line, "Antwort von 192.168.1.1: Bytes=32 Zeit=12ms TTL=64");
+  assert_eq!(parse_rtt_ms(&line), Some(12.0));
+}
+
+#[test]
+fn windows_german_timeout_falls_back_to_first_line_after_header() {
+  let stdout = decode_oem(include_bytes!("fixtures/windows_de_timeout.cp850"), 850);
+  assert_eq!(
+    summarize("10.255.255.1", &output(false, &stdout)),
+    Err("Zeitüberschreitung der Anforderung.".to_string())
+  );
+}
+
+#[test]
+fn windows_russian_timeout() {
+  let stdout = decode_oem(include_bytes!("fixtures/windows_ru_timeout.cp866"), 866);
+  assert_eq!(
+    summarize("10.255.255.1", &output(false, &stdout)),
+    Err("Превышен интервал ожидания для запроса.".to_string())
+  );
+}
+
+#[test]
+fn linux_reply() {
+  let out = output(true, include_str!("fixtures/linux_reply.txt"));
+  let line = summarize("1.1.1.1", &out).unwrap();
+  assert_eq!(line, "64 bytes from 1.1.1.1: icmp_seq=1 ttl=57 time=14.2 ms");
+  assert_eq!(parse_rtt_ms(&line), Some(14.2));
+  assert_eq!(ttl::parse(&line), Some(57));
+}
+
+#[test]
+fn linux_duplicates_are_counted_from_the_summary() {
+  let stdout = include_str!("fixtures/linux_duplicates.txt");
+  assert_eq!(count_duplicate_replies(stdout), 2);
+  let line = summarize("192.168.1.255", &output(true, stdout)).unwrap();
+  assert!(line.ends_with(" [DUP x2]"), "{line}");
+  assert_eq!(parse_duplicate_count(&line), 2);
+  assert_eq!(parse_rtt_ms(&line), Some(0.512));
+}
+
+#[test]
+fn duplicates_are_counted_per_reply_without_a_summary() {
+  let stdout = "64 bytes from 192.168.1.1: icmp_seq=1 ttl=64 time=0.5 ms\n\
+                64 bytes from 192.168.1.20: icmp_seq=1 ttl=64 time=0.8 ms (DUP!)\n";
+  assert_eq!(count_duplicate_replies(stdout), 1);
+  assert_eq!(parse_duplicate_count("64 bytes from 192.168.1.1: time=0.5 ms"), 0);
+}
+
+#[test]
+fn duplicates_are_found_before_other_markers() {
+  assert_eq!(parse_duplicate_count("64 bytes from 192.168.1.1: time=0.5 ms [DUP x3] [2/2]"), 3);
+}
+
+#[test]
+fn replies_overtaken_by_a_later_one_are_reordered() {
+  let stdout = "64 bytes from 192.168.1.1: icmp_seq=2 ttl=64 time=0.5 ms
+                64 bytes from 192.168.1.1: icmp_seq=1 ttl=64 time=210 ms
+                64 bytes from 192.168.1.1: icmp_seq=1 ttl=64 time=211 ms (DUP!)
+                64 bytes from 192.168.1.1: icmp_seq=3 ttl=64 time=0.6 ms
+";
+  assert_eq!(count_reordered_replies(stdout), 1);
+  assert_eq!(count_late_replies(stdout, 200.0), 1);
+  assert_eq!(count_late_replies(stdout, 1000.0), 0);
+  let line = summarize("192.168.1.1", &output(true, stdout)).unwrap();
+  assert!(line.ends_with(" [DUP x1] [OOO x1]"), "{line}");
+}
+
+#[test]
+fn linux_timeout_is_a_failure() {
+  let out = output(false, include_str!("fixtures/linux_timeout.txt"));
+  let line = summarize("10.255.255.1", &out).unwrap_err();
+  assert!(!line.starts_with("PING"), "{line}");
+}
+
+#[test]
+fn linux_unreachable() {
+  let out = output(false, include_str!("fixtures/linux_unreachable.txt"));
+  assert_eq!(
+    summarize("10.0.0.99", &out),
+    Err("From 10.0.0.5 icmp_seq=1 Destination Host Unreachable".to_string())
+  );
+}
+
+#[test]
+fn linux_unknown_host_uses_stderr() {
+  let out = CommandOutput {
+    success: false,
+    stdout: String::new(),
+    stderr: include_str!("fixtures/linux_unknown_host.txt").to_string(),
+  };
+  assert_eq!(
+    summarize("nosuch.example", &out),
+    Err("ping: nosuch.example: Name or service not known".to_string())
+  );
+}
+
+#[test]
+fn linux_sendmsg_denied_is_blocked_locally() {
+  let out = CommandOutput {
+    success: false,
+    stdout: include_str!("fixtures/linux_timeout.txt").to_string(),
+    stderr: include_str!("fixtures/linux_sendmsg_denied.txt").to_string(),
+  };
+  let line = summarize("192.168.1.1", &out).unwrap_err();
+  assert_eq!(line, "ping: sendmsg: Operation not permitted");
+  assert!(is_blocked_locally(&line));
+}
+
+#[test]
+fn linux_german_reply_with_decimal_comma() {
+  let out = output(true, include_str!("fixtures/linux_de_reply.txt"));
+  let line = summarize("1.1.1.1", &out).unwrap();
+  assert_eq!(line, "64 Bytes von 1.1.1.1: icmp_seq=1 ttl=57 Zeit=14,2 ms");
+  assert_eq!(parse_rtt_ms(&line), Some(14.2));
+}
+
+#[test]
+fn macos_reply() {
+  let out = output(true, include_str!("fixtures/macos_reply.txt"));
+  let line = summarize("1.1.1.1", &out).unwrap();
+  assert_eq!(line, "64 bytes from 1.1.1.1: icmp_seq=0 ttl=58 time=12.345 ms");
+  assert_eq!(parse_rtt_ms(&line), Some(12.345));
+  assert_eq!(ttl::parse(&line), Some(58));
+}
+
+#[test]
+fn macos_timeout() {
+  let out = output(false, include_str!("fixtures/macos_timeout.txt"));
+  assert_eq!(summarize("10.255.255.1", &out), Err("Request timeout for icmp_seq 0".to_string()));
+}
+
+#[test]
+fn empty_output_names_the_address() {
+  assert_eq!(summarize("example.com", &output(true, "")), Ok("ping example.com ok".to_string()));
+  assert_eq!(summarize("example.com", &output(false, "\n\n")), Err("ping example.com failed".to_string()));
+}
+
+#[test]
+fn rtt_needs_a_millisecond_unit() {
+  assert_eq!(parse_rtt_ms("icmp_seq=1 ttl=57"), None);
+  assert_eq!(parse_rtt_ms("time=14 s"), None);
+  assert_eq!(parse_rtt_ms("time = 7 ms"), Some(7.0));
+}
+
+#[test]
+fn unknown_codepages_are_read_as_utf8() {
+  assert_eq!(decode_oem("时间<1ms".as_bytes(), 65001), "时间<1ms");
+  assert_eq!(decode_oem(b"time=1ms", 12345), "time=1ms");
+}
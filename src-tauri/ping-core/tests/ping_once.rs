@@ -0,0 +1,132 @@
+//! `ping_once` against a fake runner: the command line it builds, DSCP marking, custom ping
+//! executables and how runner errors surface.
+
+use std::cell::RefCell;
+use std::io;
+
+use ping_core::{
+  custom_args, dscp_args, ping_args, ping_once, CommandOutput, CommandRunner, CustomPing, DscpMarked, Platform,
+};
+
+struct FakeRunner {
+  result: fn() -> io::Result<CommandOutput>,
+  calls: RefCell<Vec<(String, Vec<String>)>>,
+}
+
+impl FakeRunner {
+  fn new(result: fn() -> io::Result<CommandOutput>) -> Self {
+    Self {
+      result,
+      calls: RefCell::new(Vec::new()),
+    }
+  }
+}
+
+impl CommandRunner for FakeRunner {
+  fn run(&self, program: &str, args: &[String]) -> io::Result<CommandOutput> {
+    self.calls.borrow_mut().push((program.to_string(), args.to_vec()));
+    (self.result)()
+  }
+}
+
+fn reply() -> io::Result<CommandOutput> {
+  Ok(CommandOutput {
+    success: true,
+    stdout: include_str!("fixtures/linux_reply.txt").to_string(),
+    stderr: String::new(),
+  })
+}
+
+#[test]
+fn runs_ping_once_and_summarizes() {
+  let runner = FakeRunner::new(reply);
+  let line = ping_once(&runner, Platform::Linux, "1.1.1.1", None).unwrap();
+  assert_eq!(line, "64 bytes from 1.1.1.1: icmp_seq=1 ttl=57 time=14.2 ms");
+  let calls = runner.calls.borrow();
+  assert_eq!(calls.len(), 1);
+  assert_eq!(calls[0].0, "ping");
+  assert_eq!(calls[0].1, ["-c", "1", "1.1.1.1"]);
+}
+
+#[test]
+fn spawn_errors_are_reported() {
+  let runner = FakeRunner::new(|| Err(io::Error::new(io::ErrorKind::NotFound, "no such file")));
+  assert_eq!(
+    ping_once(&runner, Platform::Windows, "1.1.1.1", None),
+    Err("failed to spawn ping: no such file".to_string())
+  );
+}
+
+#[test]
+fn arguments_per_platform() {
+  assert_eq!(ping_args(Platform::Windows, "8.8.8.8", None), ["-n", "1", "8.8.8.8"]);
+  assert_eq!(
+    ping_args(Platform::Windows, "8.8.8.8", Some("192.168.1.10")),
+    ["-n", "1", "-S", "192.168.1.10", "8.8.8.8"]
+  );
+  assert_eq!(ping_args(Platform::Linux, "8.8.8.8", Some("eth1")), ["-c", "1", "-I", "eth1", "8.8.8.8"]);
+  assert_eq!(
+    ping_args(Platform::Bsd, "8.8.8.8", Some("192.168.1.10")),
+    ["-c", "1", "-S", "192.168.1.10", "8.8.8.8"]
+  );
+}
+
+#[test]
+fn dscp_marking_goes_before_the_address() {
+  let runner = FakeRunner::new(reply);
+  let marked = DscpMarked {
+    runner: &runner,
+    platform: Platform::Linux,
+    dscp: 46,
+  };
+  ping_once(&marked, Platform::Linux, "1.1.1.1", Some("eth1")).unwrap();
+  assert_eq!(runner.calls.borrow()[0].1, ["-c", "1", "-I", "eth1", "-Q", "184", "1.1.1.1"]);
+  assert_eq!(dscp_args(Platform::Bsd, 46), ["-z", "184"]);
+  assert!(dscp_args(Platform::Windows, 46).is_empty());
+}
+
+#[test]
+fn custom_ping_runs_the_given_program() {
+  let runner = FakeRunner::new(reply);
+  let custom = CustomPing {
+    runner: &runner,
+    platform: Platform::Linux,
+    program: "/bin/busybox",
+    template: "ping {args} {address}",
+  };
+  ping_once(&custom, Platform::Linux, "1.1.1.1", Some("eth1")).unwrap();
+  let calls = runner.calls.borrow();
+  assert_eq!(calls[0].0, "/bin/busybox");
+  assert_eq!(calls[0].1, ["ping", "-c", "1", "-I", "eth1", "1.1.1.1"]);
+}
+
+#[test]
+fn custom_templates_fill_in_placeholders() {
+  let args = ping_args(Platform::Linux, "8.8.8.8", Some("eth1"));
+  assert_eq!(custom_args(Platform::Linux, "", &args), ["-c", "1", "-I", "eth1", "8.8.8.8"]);
+  assert_eq!(
+    custom_args(Platform::Linux, "-1 -c {count} -I {source} --target={address}", &args),
+    ["-1", "-c", "1", "-I", "eth1", "--target=8.8.8.8"]
+  );
+  // Without a source, `-I {source}` goes away entirely.
+  let args = ping_args(Platform::Windows, "8.8.8.8", None);
+  assert_eq!(custom_args(Platform::Windows, "-n {count} -I {source} {address}", &args), ["-n", "1", "8.8.8.8"]);
+}
+
+#[test]
+fn custom_ping_keeps_dscp_marking() {
+  let runner = FakeRunner::new(reply);
+  let custom = CustomPing {
+    runner: &runner,
+    platform: Platform::Linux,
+    program: "/opt/iputils/ping",
+    template: "",
+  };
+  let marked = DscpMarked {
+    runner: &custom,
+    platform: Platform::Linux,
+    dscp: 46,
+  };
+  ping_once(&marked, Platform::Linux, "1.1.1.1", None).unwrap();
+  assert_eq!(runner.calls.borrow()[0].1, ["-c", "1", "-Q", "184", "1.1.1.1"]);
+}
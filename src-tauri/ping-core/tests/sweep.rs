@@ -0,0 +1,169 @@
+//! Payload sweeps against a fake path with a fixed MTU: the command lines per platform, where
+//! loss begins and the bisection of the gap.
+
+use std::cell::RefCell;
+use std::io;
+
+use ping_core::sweep::{self, DEFAULT_SIZES};
+use ping_core::{payload_sweep, sweep_args, CommandOutput, CommandRunner, Platform};
+
+/// Answers every echo request up to `max_payload` bytes, and none above, like a path whose MTU
+/// is `max_payload + 28`. Requests with `broken_pattern` are lost at any size; at `flaky_size`
+/// one reply of each run goes missing.
+struct Path {
+  max_payload: u32,
+  broken_pattern: Option<&'static str>,
+  flaky_size: Option<u32>,
+  calls: RefCell<Vec<Vec<String>>>,
+}
+
+impl Path {
+  fn new(max_payload: u32) -> Self {
+    Self {
+      max_payload,
+      broken_pattern: None,
+      flaky_size: None,
+      calls: RefCell::new(Vec::new()),
+    }
+  }
+}
+
+fn arg_after<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+  let at = args.iter().position(|arg| arg == flag)?;
+  args.get(at + 1).map(String::as_str)
+}
+
+impl CommandRunner for Path {
+  fn run(&self, _program: &str, args: &[String]) -> io::Result<CommandOutput> {
+    self.calls.borrow_mut().push(args.to_vec());
+    let count: u32 = arg_after(args, "-c")
+      .or(arg_after(args, "-n"))
+      .unwrap()
+      .parse()
+      .unwrap();
+    let size: u32 = arg_after(args, "-s")
+      .or(arg_after(args, "-l"))
+      .unwrap()
+      .parse()
+      .unwrap();
+    let lost = size > self.max_payload || self.broken_pattern.is_some_and(|p| arg_after(args, "-p") == Some(p));
+    let received = if lost {
+      0
+    } else if self.flaky_size == Some(size) {
+      count - 1
+    } else {
+      count
+    };
+    let mut stdout = format!("PING 10.0.0.1 (10.0.0.1) {size}({}) bytes of data.\n", size + 28);
+    for seq in 1..=received {
+      stdout.push_str(&format!(
+        "{} bytes from 10.0.0.1: icmp_seq={seq} ttl=64 time=1.50 ms\n",
+        size + 8
+      ));
+    }
+    stdout.push_str(&format!(
+      "\n--- 10.0.0.1 ping statistics ---\n{count} packets transmitted, {received} received\n"
+    ));
+    Ok(CommandOutput {
+      success: !lost,
+      stdout,
+      stderr: String::new(),
+    })
+  }
+}
+
+#[test]
+fn sets_size_and_dont_fragment_per_platform() {
+  assert_eq!(
+    sweep_args(Platform::Windows, "10.0.0.1", 2, 1472, Some("ff")),
+    ["-n", "2", "-l", "1472", "-f", "10.0.0.1"]
+  );
+  assert_eq!(
+    sweep_args(Platform::Linux, "10.0.0.1", 2, 1472, Some("ff")),
+    ["-c", "2", "-i", "0.2", "-s", "1472", "-M", "do", "-p", "ff", "10.0.0.1"]
+  );
+  assert_eq!(
+    sweep_args(Platform::Bsd, "10.0.0.1", 1, 70_000, None),
+    ["-c", "1", "-s", "65500", "-D", "10.0.0.1"]
+  );
+}
+
+#[test]
+fn clean_path_has_no_loss() {
+  let path = Path::new(1472);
+  let sweep = payload_sweep(&path, Platform::Linux, "10.0.0.1", DEFAULT_SIZES, &[], 3).unwrap();
+  assert_eq!(sweep.steps.len(), DEFAULT_SIZES.len());
+  assert_eq!(sweep.largest_clean, Some(1472));
+  assert_eq!(sweep.loss_starts_at(None), None);
+}
+
+#[test]
+fn bisects_to_the_largest_payload_that_fits() {
+  // A PPPoE link: 1492-byte MTU, 1464 bytes of ICMP payload.
+  let path = Path::new(1464);
+  let sweep = payload_sweep(&path, Platform::Linux, "10.0.0.1", DEFAULT_SIZES, &[], 1).unwrap();
+  assert_eq!(sweep.largest_clean, Some(1464));
+  assert_eq!(sweep.loss_starts_at(None), Some(1465));
+  let sizes: Vec<u32> = sweep.steps.iter().map(|step| step.size).collect();
+  assert!(sizes.windows(2).all(|pair| pair[0] < pair[1]));
+  // 8 planned sizes, then log2 of the 22-byte gap between 1450 and 1472.
+  assert!(path.calls.borrow().len() <= DEFAULT_SIZES.len() + 5);
+}
+
+#[test]
+fn intermittent_loss_does_not_cap_the_largest_clean_size() {
+  let path = Path {
+    flaky_size: Some(256),
+    ..Path::new(1000)
+  };
+  let sweep = payload_sweep(&path, Platform::Linux, "10.0.0.1", &[64, 256, 512, 1024], &[], 2).unwrap();
+  assert_eq!(sweep.largest_clean, Some(1000));
+  assert_eq!(sweep.loss_starts_at(None), Some(1001));
+  assert!(sweep.steps.iter().any(|step| step.size == 256 && step.lossy()));
+}
+
+#[test]
+fn broken_pattern_loses_at_every_size() {
+  let path = Path {
+    broken_pattern: Some("55aa"),
+    ..Path::new(1472)
+  };
+  let sweep = payload_sweep(
+    &path,
+    Platform::Linux,
+    "10.0.0.1",
+    &[64, 512],
+    sweep::DEFAULT_PATTERNS,
+    2,
+  )
+  .unwrap();
+  assert_eq!(sweep.steps.len(), 2 + 2 * sweep::DEFAULT_PATTERNS.len());
+  assert_eq!(sweep.loss_starts_at(None), None);
+  assert_eq!(sweep.loss_starts_at(Some("ff")), None);
+  assert_eq!(sweep.loss_starts_at(Some("55aa")), Some(64));
+}
+
+#[test]
+fn windows_skips_patterns() {
+  let path = Path::new(1472);
+  let sweep = payload_sweep(
+    &path,
+    Platform::Windows,
+    "10.0.0.1",
+    &[64, 512],
+    sweep::DEFAULT_PATTERNS,
+    1,
+  )
+  .unwrap();
+  assert_eq!(sweep.steps.len(), 2);
+  assert!(path.calls.borrow().iter().all(|args| !args.contains(&"-p".to_string())));
+}
+
+#[test]
+fn validates_patterns() {
+  assert!(sweep::valid_pattern("55aa"));
+  assert!(sweep::valid_pattern("00112233445566778899aabbccddeeff"));
+  assert!(!sweep::valid_pattern("5"));
+  assert!(!sweep::valid_pattern("zz"));
+  assert!(!sweep::valid_pattern("00112233445566778899aabbccddeeff00"));
+}
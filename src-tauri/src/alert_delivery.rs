@@ -0,0 +1,282 @@
+// Sends an alert to its notification channels. Each channel sends from a thread of its own and
+// is judged against a deadline of its own, so an SMTP server that accepts the connection and
+// then hangs holds up neither the webhooks nor the SMS, and is reported as timed out instead of
+// just never arriving; the send itself is left to finish or fail in the background. Every
+// channel's outcome is logged as a `DELIVERY` line and kept with the alert in the alert history,
+// which answers "did the on-call actually get paged?" after the fact.
+
+use std::collections::VecDeque;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::Local;
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::alerts::AlertEvent;
+use crate::events;
+use crate::lock::LockExt;
+use crate::result_store::ResultStore;
+use crate::{push_log, LogBuffer};
+
+/// Alerts kept in the history, oldest dropped first.
+const HISTORY_LIMIT: usize = 200;
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Channel {
+  Email,
+  Sms,
+  Oncall,
+  Feishu,
+  Alertmanager,
+}
+
+impl Channel {
+  fn name(self) -> &'static str {
+    match self {
+      Channel::Email => "邮件",
+      Channel::Sms => "短信",
+      Channel::Oncall => "值班平台",
+      Channel::Feishu => "飞书",
+      Channel::Alertmanager => "Alertmanager",
+    }
+  }
+
+  /// The webhooks' clients give up after 10 seconds, which bounds a request that stalls but not
+  /// DNS or a proxy that hangs. Email also renders the chart and talks to the server several
+  /// times, each with its own 10-second timeout.
+  fn deadline(self) -> Duration {
+    match self {
+      Channel::Email => Duration::from_secs(60),
+      _ => Duration::from_secs(20),
+    }
+  }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+  Pending,
+  Sent,
+  Failed,
+  TimedOut,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ChannelOutcome {
+  pub channel: Channel,
+  pub status: Status,
+  pub error: Option<String>,
+  /// From the dispatch until the send finished or was given up on.
+  pub elapsed_ms: Option<u64>,
+}
+
+/// An alert and how each of its channels fared, emitted to the UI as `alert-delivery` whenever
+/// a channel settles.
+#[derive(Clone, Serialize)]
+pub struct AlertRecord {
+  pub id: u64,
+  pub event: AlertEvent,
+  pub channels: Vec<ChannelOutcome>,
+}
+
+#[derive(Default)]
+struct History {
+  next_id: u64,
+  records: VecDeque<AlertRecord>,
+}
+
+/// The alerts that went out on at least one channel, kept in memory for `get_alert_history`.
+#[derive(Default)]
+pub struct AlertHistory {
+  inner: Mutex<History>,
+}
+
+impl AlertHistory {
+  fn open(&self, event: &AlertEvent, channels: &[Channel]) -> u64 {
+    let mut inner = self.inner.lock_or_recover();
+    inner.next_id += 1;
+    let id = inner.next_id;
+    let channels = channels
+      .iter()
+      .map(|channel| ChannelOutcome {
+        channel: *channel,
+        status: Status::Pending,
+        error: None,
+        elapsed_ms: None,
+      })
+      .collect();
+    inner.records.push_back(AlertRecord {
+      id,
+      event: event.clone(),
+      channels,
+    });
+    while inner.records.len() > HISTORY_LIMIT {
+      inner.records.pop_front();
+    }
+    id
+  }
+
+  /// Records the outcome of `channel` and returns the updated alert, unless it has aged out.
+  fn settle(&self, id: u64, channel: Channel, outcome: ChannelOutcome) -> Option<AlertRecord> {
+    let mut inner = self.inner.lock_or_recover();
+    let record = inner.records.iter_mut().find(|record| record.id == id)?;
+    let slot = record
+      .channels
+      .iter_mut()
+      .find(|slot| slot.channel == channel && slot.status == Status::Pending)?;
+    *slot = outcome;
+    Some(record.clone())
+  }
+
+  /// The latest `limit` alerts, newest first.
+  pub fn recent(&self, limit: usize) -> Vec<AlertRecord> {
+    self
+      .inner
+      .lock_or_recover()
+      .records
+      .iter()
+      .rev()
+      .take(limit)
+      .cloned()
+      .collect()
+  }
+}
+
+type Job = Box<dyn FnOnce() -> Result<(), String> + Send>;
+
+/// The channels one alert goes out on, gathered before any of them is sent.
+pub struct Delivery {
+  event: AlertEvent,
+  sends: Vec<(Channel, Job)>,
+}
+
+impl Delivery {
+  pub fn new(event: &AlertEvent) -> Self {
+    Self {
+      event: event.clone(),
+      sends: Vec::new(),
+    }
+  }
+
+  pub fn add(&mut self, channel: Channel, send: impl FnOnce() -> Result<(), String> + Send + 'static) {
+    self.sends.push((channel, Box::new(send)));
+  }
+
+  /// Starts every channel at once and returns; the outcomes are logged as they come in.
+  pub fn dispatch(self, app: &AppHandle, store: &Arc<dyn ResultStore>, log_buffer: &Arc<Mutex<LogBuffer>>) {
+    if self.sends.is_empty() {
+      return;
+    }
+    let channels: Vec<Channel> = self.sends.iter().map(|(channel, _)| *channel).collect();
+    let id = app.state::<AlertHistory>().open(&self.event, &channels);
+    let started = Instant::now();
+    let (tx, rx) = mpsc::channel();
+    for (channel, send) in self.sends {
+      let tx = tx.clone();
+      thread::spawn(move || {
+        let result = send();
+        let _ = tx.send((channel, result, started.elapsed()));
+      });
+    }
+    drop(tx);
+    let watch = Watch {
+      app: app.clone(),
+      store: store.clone(),
+      log_buffer: log_buffer.clone(),
+      event: self.event,
+      id,
+    };
+    thread::spawn(move || watch.run(channels, started, rx));
+  }
+}
+
+/// Collects the outcomes of one delivery.
+struct Watch {
+  app: AppHandle,
+  store: Arc<dyn ResultStore>,
+  log_buffer: Arc<Mutex<LogBuffer>>,
+  event: AlertEvent,
+  id: u64,
+}
+
+impl Watch {
+  fn run(
+    self,
+    mut pending: Vec<Channel>,
+    started: Instant,
+    rx: mpsc::Receiver<(Channel, Result<(), String>, Duration)>,
+  ) {
+    while let Some(next) = pending.iter().map(|channel| channel.deadline()).min() {
+      match rx.recv_timeout(next.saturating_sub(started.elapsed())) {
+        Ok((channel, result, elapsed)) => {
+          // A send that already timed out has been settled; its late result is dropped.
+          let Some(at) = pending.iter().position(|pending| *pending == channel) else {
+            continue;
+          };
+          pending.remove(at);
+          let (status, error) = match result {
+            Ok(()) => (Status::Sent, None),
+            Err(err) => (Status::Failed, Some(err)),
+          };
+          self.settle(channel, status, error, elapsed);
+        }
+        Err(RecvTimeoutError::Timeout) => {
+          let elapsed = started.elapsed();
+          let (expired, waiting): (Vec<Channel>, Vec<Channel>) =
+            pending.into_iter().partition(|channel| channel.deadline() <= elapsed);
+          pending = waiting;
+          for channel in expired {
+            self.settle(channel, Status::TimedOut, None, channel.deadline());
+          }
+        }
+        // Only a send that panicked leaves without reporting.
+        Err(RecvTimeoutError::Disconnected) => {
+          for channel in pending.drain(..) {
+            self.settle(
+              channel,
+              Status::Failed,
+              Some("发送线程异常退出".to_string()),
+              started.elapsed(),
+            );
+          }
+        }
+      }
+    }
+  }
+
+  fn settle(&self, channel: Channel, status: Status, error: Option<String>, elapsed: Duration) {
+    let seconds = elapsed.as_secs_f64();
+    let result = match (status, &error) {
+      (Status::Sent, _) => format!("已送达（{seconds:.1} 秒）"),
+      (Status::TimedOut, _) => format!("超过 {seconds:.0} 秒未完成，按超时处理"),
+      (_, error) => format!("发送失败（{seconds:.1} 秒）：{}", error.as_deref().unwrap_or_default()),
+    };
+    let alert = match &self.event.incident_id {
+      Some(id) => id.clone(),
+      None => format!("{} 的告警", self.event.timestamp),
+    };
+    let now = Local::now();
+    let line = format!(
+      "[{}] DELIVERY | {} {result} | {alert}",
+      now.format("%Y-%m-%d %H:%M:%S"),
+      channel.name()
+    );
+    if let Err(e) = self.store.record_event(&now, &line) {
+      eprintln!("failed to write log: {e}");
+    }
+    let _ = push_log(&self.log_buffer, line);
+    let outcome = ChannelOutcome {
+      channel,
+      status,
+      error,
+      elapsed_ms: Some(elapsed.as_millis() as u64),
+    };
+    if let Some(record) = self.app.state::<AlertHistory>().settle(self.id, channel, outcome) {
+      events::emit(&self.app, "alert-delivery", record);
+    }
+  }
+}
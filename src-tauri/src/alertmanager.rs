@@ -0,0 +1,212 @@
+// Alerts in the Prometheus Alertmanager webhook format, so receivers written for Alertmanager
+// (and the routing, inhibition and silencing built around them) can take this tool's alerts as
+// they are. An outage is one alert named `PingTargetDown`: it fires when the outage is confirmed
+// and on every reminder, and resolves with `endsAt` set when the target answers again. A flapping
+// spell is likewise one `PingTargetFlapping` alert, firing with each summary. The fingerprint is
+// computed from the labels the same way Alertmanager does, so a receiver can pair the firing and
+// resolved notifications.
+
+use std::time::Duration;
+
+use chrono::{Local, NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::alerts::{AlertEvent, AlertKind, AlertSeverity};
+use crate::proxy;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+const OUTAGE_ALERT: &str = "PingTargetDown";
+const FLAPPING_ALERT: &str = "PingTargetFlapping";
+/// Alertmanager's `endsAt` for alerts that are still firing.
+const NOT_ENDED: &str = "0001-01-01T00:00:00Z";
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct AlertmanagerSettings {
+  #[serde(default)]
+  pub enabled: bool,
+  /// The receiver's webhook URL, as it would appear in an Alertmanager `webhook_configs` entry.
+  #[serde(default)]
+  pub url: String,
+  /// Sent as `Authorization: Bearer ...` when set.
+  #[serde(default)]
+  pub bearer_token: String,
+  /// Reported as the payload's `receiver`.
+  #[serde(default = "default_receiver")]
+  pub receiver: String,
+  /// Extra labels added to every alert, e.g. `site=office`, for routing on the receiver side.
+  #[serde(default)]
+  pub labels: Vec<String>,
+  /// Overrides the global alert proxy; an empty string connects directly.
+  #[serde(default)]
+  pub proxy: Option<String>,
+}
+
+impl Default for AlertmanagerSettings {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      url: String::new(),
+      bearer_token: String::new(),
+      receiver: default_receiver(),
+      labels: Vec::new(),
+      proxy: None,
+    }
+  }
+}
+
+fn default_receiver() -> String {
+  "ping-tool".to_string()
+}
+
+pub fn validate(settings: &AlertmanagerSettings) -> Result<(), String> {
+  if !settings.enabled {
+    return Ok(());
+  }
+  let url = settings.url.trim();
+  if !url.starts_with("http://") && !url.starts_with("https://") {
+    return Err("Alertmanager Webhook 地址必须以 http:// 或 https:// 开头".to_string());
+  }
+  if settings.receiver.trim().is_empty() {
+    return Err("Alertmanager 接收器名称不能为空".to_string());
+  }
+  for label in &settings.labels {
+    parse_label(label)?;
+  }
+  if let Some(proxy) = &settings.proxy {
+    proxy::validate_url(proxy)?;
+  }
+  Ok(())
+}
+
+/// `name=value`, with a name Prometheus accepts.
+fn parse_label(label: &str) -> Result<(&str, &str), String> {
+  let Some((name, value)) = label.split_once('=') else {
+    return Err(format!("标签格式应为 名称=值: {label}"));
+  };
+  let name = name.trim();
+  let valid = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+    && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+  if !valid || name.starts_with("__") {
+    return Err(format!("标签名无效: {name}"));
+  }
+  if matches!(name, "alertname" | "severity" | "instance" | "target" | "job" | "drill") {
+    return Err(format!("标签 {name} 由程序设置，不能覆盖"));
+  }
+  Ok((name, value.trim()))
+}
+
+fn rfc3339(local: &str) -> Option<String> {
+  let naive = NaiveDateTime::parse_from_str(local, "%Y-%m-%d %H:%M:%S").ok()?;
+  let local = Local.from_local_datetime(&naive).earliest()?;
+  Some(local.with_timezone(&Utc).format("%Y-%m-%dT%H:%M:%SZ").to_string())
+}
+
+fn severity_label(severity: AlertSeverity) -> &'static str {
+  match severity {
+    AlertSeverity::Critical => "critical",
+    AlertSeverity::Warning => "warning",
+    AlertSeverity::Info => "info",
+  }
+}
+
+/// Alertmanager's label-set fingerprint: FNV-1a over the sorted names and values, each followed
+/// by a 0xff separator.
+fn fingerprint(labels: &Map<String, Value>) -> String {
+  let mut names: Vec<&String> = labels.keys().collect();
+  names.sort();
+  let mut hash: u64 = 0xcbf29ce484222325;
+  for name in names {
+    let value = labels[name].as_str().unwrap_or_default();
+    for byte in name.bytes().chain([0xff]).chain(value.bytes()).chain([0xff]) {
+      hash ^= u64::from(byte);
+      hash = hash.wrapping_mul(0x100000001b3);
+    }
+  }
+  format!("{hash:016x}")
+}
+
+/// The webhook body for an outage or flapping alert; `None` for other alerts.
+pub fn payload(settings: &AlertmanagerSettings, event: &AlertEvent) -> Option<Value> {
+  let (alert_name, status) = match event.kind {
+    AlertKind::OutageStarted | AlertKind::OutageReminder => (OUTAGE_ALERT, "firing"),
+    AlertKind::OutageRecovered => (OUTAGE_ALERT, "resolved"),
+    AlertKind::FlappingStarted | AlertKind::FlappingSummary => (FLAPPING_ALERT, "firing"),
+    AlertKind::FlappingEnded => (FLAPPING_ALERT, "resolved"),
+    _ => return None,
+  };
+  let address = event.address.clone()?;
+
+  let mut labels = Map::new();
+  labels.insert("alertname".to_string(), json!(alert_name));
+  labels.insert("instance".to_string(), json!(address));
+  labels.insert("job".to_string(), json!("ping-tool"));
+  // The recovery is always `info`; the receiver should see the severity the alert fired with.
+  let severity = match status {
+    "resolved" => event.fired_severity.unwrap_or(AlertSeverity::Critical),
+    _ => event.severity,
+  };
+  labels.insert("severity".to_string(), json!(severity_label(severity)));
+  if !event.label.is_empty() {
+    labels.insert("target".to_string(), json!(event.label));
+  }
+  if event.drill {
+    labels.insert("drill".to_string(), json!("true"));
+  }
+  for (name, value) in settings.labels.iter().filter_map(|label| parse_label(label).ok()) {
+    labels.insert(name.to_string(), json!(value));
+  }
+
+  let mut annotations = Map::new();
+  annotations.insert("summary".to_string(), json!(event.message));
+  if let Some(id) = &event.incident_id {
+    annotations.insert("incident_id".to_string(), json!(id));
+  }
+
+  let starts_at = rfc3339(event.started.as_deref().unwrap_or(&event.timestamp));
+  let ends_at = match status {
+    "resolved" => rfc3339(event.ended.as_deref().unwrap_or(&event.timestamp)),
+    _ => None,
+  };
+  let alert = json!({
+    "status": status,
+    "labels": labels,
+    "annotations": annotations,
+    "startsAt": starts_at,
+    "endsAt": ends_at.as_deref().unwrap_or(NOT_ENDED),
+    "generatorURL": "",
+    "fingerprint": fingerprint(&labels),
+  });
+  Some(json!({
+    "version": "4",
+    "groupKey": format!("{{}}:{{alertname=\"{alert_name}\"}}"),
+    "truncatedAlerts": 0,
+    "status": status,
+    "receiver": settings.receiver.trim(),
+    "groupLabels": { "alertname": alert_name },
+    "commonLabels": labels,
+    "commonAnnotations": annotations,
+    "externalURL": "",
+    "alerts": [alert],
+  }))
+}
+
+pub fn send(settings: &AlertmanagerSettings, body: &Value) -> Result<(), String> {
+  let mut request = proxy::client(settings.proxy.as_deref(), TIMEOUT)?
+    .post(settings.url.trim())
+    .json(body);
+  let token = settings.bearer_token.trim();
+  if !token.is_empty() {
+    request = request.bearer_auth(token);
+  }
+  let response = request
+    .send()
+    .map_err(|e| format!("Alertmanager Webhook 请求失败: {e}"))?;
+  if response.status().is_success() {
+    Ok(())
+  } else {
+    let status = response.status();
+    let text: String = response.text().unwrap_or_default().chars().take(200).collect();
+    Err(format!("Alertmanager Webhook 返回 {status}: {text}"))
+  }
+}
@@ -0,0 +1,355 @@
+use std::time::Duration;
+
+use chrono::{Duration as ChronoDuration, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::captive_portal::Connectivity;
+use crate::feishu::CardColor;
+use crate::targets::{self, TargetConfig};
+use crate::{drill_tag, format_duration};
+
+pub const OUTAGE_CARD_TITLE: &str = "网络中断告警";
+
+/// What an email subject template can refer to as `{{name}}`.
+pub const SUBJECT_VARIABLES: &[&str] = &[
+  "severity", "event", "target", "label", "address", "start", "end", "time", "incident",
+];
+/// What an email body template can refer to: the subject's variables except `event`, plus the
+/// built-in body as `{{message}}` and the latency chart as `{{chart}}`.
+pub const BODY_VARIABLES: &[&str] = &[
+  "severity", "target", "label", "address", "start", "end", "time", "incident", "message", "chart",
+];
+
+/// Rendered texts for a recovered outage, one per channel format.
+pub struct RecoveryAlert {
+  pub subject: &'static str,
+  pub plain: String,
+  pub html: String,
+  pub card_lines: Vec<String>,
+}
+
+impl RecoveryAlert {
+  /// Adds a note on the state of the local network, e.g. an unreachable gateway, to every format.
+  pub fn note(&mut self, note: &str) {
+    self.plain.push_str(&format!("；注意：{note}"));
+    self.html.push_str(&format!("<br>注意：{note}"));
+    self.card_lines.push(format!("**注意**: {note}"));
+  }
+
+  /// Adds the outage's incident ID to every format.
+  pub fn incident(&mut self, id: &str) {
+    self.plain = with_incident(&self.plain, Some(id));
+    self.html.push_str(&format!("<br>事件编号：{id}"));
+    self.card_lines.push(format!("**事件编号**: {id}"));
+  }
+}
+
+/// `message` followed by the incident ID, so recipients can thread the alerts of one outage.
+pub fn with_incident(message: &str, incident_id: Option<&str>) -> String {
+  match incident_id {
+    Some(id) => format!("{message}（事件 {id}）"),
+    None => message.to_string(),
+  }
+}
+
+pub fn outage_message(
+  target: &TargetConfig,
+  failures: u32,
+  start_time: &str,
+  connectivity: Option<&Connectivity>,
+) -> String {
+  let target_name = target.display_name();
+  match connectivity {
+    Some(connectivity) => format!(
+      "{target_name} 连续 {failures} 次失败，开始时间 {start_time}，{}",
+      connectivity.describe()
+    ),
+    None => format!("{target_name} 连续 {failures} 次失败，开始时间 {start_time}"),
+  }
+}
+
+pub fn outage_card(target: &TargetConfig, message: &str, captive: bool) -> (CardColor, Vec<String>) {
+  let color = if captive { CardColor::Orange } else { CardColor::Red };
+  let mut lines = vec![message.to_string()];
+  if !target.description.trim().is_empty() {
+    lines.push(format!("**说明**: {}", target.description.trim()));
+  }
+  (color, lines)
+}
+
+/// `duration` is measured on the monotonic clock, so it stays right even if the wall clock
+/// jumped between `start_time` and `recover_time`.
+pub fn recovery(
+  target: &TargetConfig,
+  start_time: &str,
+  recover_time: &str,
+  duration: Duration,
+  captive: bool,
+) -> RecoveryAlert {
+  let (subject, problem) = if captive {
+    ("强制登录门户告警", "网络被强制登录门户（Captive Portal）拦截，需要登录认证")
+  } else {
+    ("网络丢包告警", "网络出现丢包")
+  };
+  recovery_with(target, start_time, recover_time, duration, subject, problem)
+}
+
+/// `recovery` with the subject and the closing description of the problem given.
+pub fn recovery_with(
+  target: &TargetConfig,
+  start_time: &str,
+  recover_time: &str,
+  duration: Duration,
+  subject: &'static str,
+  problem: &str,
+) -> RecoveryAlert {
+  let target_name = target.display_name();
+  let lasted = format_duration(duration);
+  let plain =
+    format!("目标: {target_name}，开始时间: {start_time}，恢复时间：{recover_time}，持续 {lasted} {problem}");
+  let mut html = format!(
+    "目标: {target_name}，<br>开始时间: {start_time}，<br>恢复时间：{recover_time}，<br>持续：{lasted} <br> {problem}"
+  );
+  if !target.description.trim().is_empty() {
+    html.push_str(&format!("<br>说明：{}", target.description.trim()));
+  }
+  let card_lines = vec![
+    format!("**目标**: {target_name}"),
+    format!("**开始时间**: {start_time}\n**恢复时间**: {recover_time}\n**持续**: {lasted}"),
+    problem.to_string(),
+  ];
+  RecoveryAlert {
+    subject,
+    plain,
+    html,
+    card_lines,
+  }
+}
+
+/// The exact message a channel would deliver, as returned by the test commands.
+#[derive(Serialize)]
+pub struct AlertPreview {
+  pub subject: Option<String>,
+  /// HTML for email, text for SMS, JSON for webhook-style channels.
+  pub body: String,
+}
+
+#[derive(Serialize)]
+pub struct ChannelTestResult {
+  /// Status of the real test send; says so when it was skipped for a dry run.
+  pub message: String,
+  pub preview: AlertPreview,
+}
+
+/// A five-minute outage of a documentation-range address, used to render previews.
+pub fn sample_outage() -> (TargetConfig, String, String) {
+  let now = Local::now();
+  let format = "%Y-%m-%d %H:%M:%S";
+  let target = TargetConfig {
+    address: "192.0.2.1".to_string(),
+    label: "示例目标".to_string(),
+    description: "预览用的模拟中断".to_string(),
+    ..TargetConfig::default()
+  };
+  let start = (now - ChronoDuration::minutes(5)).format(format).to_string();
+  (target, start, now.format(format).to_string())
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+  OutageStarted,
+  OutageRecovered,
+  OutageReminder,
+  LatencyAnomaly,
+  LatencyNormal,
+  SpeedtestBelowThreshold,
+  RouteChanged,
+  /// The app is running but no monitoring session has been for a while.
+  MonitoringIdle,
+  /// Too many outages in the last hour; per-outage alerts are damped from here on.
+  FlappingStarted,
+  FlappingSummary,
+  FlappingEnded,
+}
+
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertSeverity {
+  Critical,
+  Warning,
+  Info,
+}
+
+impl AlertSeverity {
+  /// The tag subject templates get, e.g. `CRITICAL`.
+  pub fn tag(self) -> &'static str {
+    match self {
+      AlertSeverity::Critical => "CRITICAL",
+      AlertSeverity::Warning => "WARNING",
+      AlertSeverity::Info => "INFO",
+    }
+  }
+}
+
+/// Structured counterpart of an `ALERT |` log line, emitted to the UI as `alert-event`.
+#[derive(Clone, Serialize)]
+pub struct AlertEvent {
+  pub kind: AlertKind,
+  pub severity: AlertSeverity,
+  pub timestamp: String,
+  pub address: Option<String>,
+  pub label: String,
+  pub incident_id: Option<String>,
+  pub started: Option<String>,
+  pub ended: Option<String>,
+  pub message: String,
+  pub drill: bool,
+  /// On a recovery, the severity the outage fired with.
+  pub fired_severity: Option<AlertSeverity>,
+}
+
+impl AlertEvent {
+  pub fn new(kind: AlertKind, severity: AlertSeverity, timestamp: &str, message: String) -> Self {
+    Self {
+      kind,
+      severity,
+      timestamp: timestamp.to_string(),
+      address: None,
+      label: String::new(),
+      incident_id: None,
+      started: None,
+      ended: None,
+      message,
+      drill: false,
+      fired_severity: None,
+    }
+  }
+
+  pub fn target(mut self, target: &TargetConfig) -> Self {
+    self.address = Some(targets::public_address(&target.address).into_owned());
+    self.label = target.label.clone();
+    self
+  }
+
+  pub fn span(mut self, started: &str, ended: Option<&str>) -> Self {
+    self.started = Some(started.to_string());
+    self.ended = ended.map(str::to_string);
+    self
+  }
+
+  pub fn incident(mut self, incident_id: Option<String>, drill: bool) -> Self {
+    self.incident_id = incident_id;
+    self.drill = drill;
+    self
+  }
+
+  pub fn fired_with(mut self, severity: AlertSeverity) -> Self {
+    self.fired_severity = Some(severity);
+    self
+  }
+}
+
+/// The subject of an alert email: `template` filled in for `event`, or `default_subject` when no
+/// template is set. `{{event}}` stands for the default subject, so a template can add tags
+/// around it, e.g. `[{{severity}}][{{label}}] {{event}} since {{start}}`.
+pub fn render_subject(template: &str, default_subject: &str, event: &AlertEvent) -> String {
+  let template = template.trim();
+  if template.is_empty() {
+    return drill_tag(event.drill, default_subject);
+  }
+  let subject = fill(template, |name| match name {
+    "event" => default_subject.to_string(),
+    name => variable(name, event),
+  });
+  // Header fields are one line; a label is free text.
+  let subject = subject.split_whitespace().collect::<Vec<_>>().join(" ");
+  drill_tag(event.drill, &subject)
+}
+
+/// The HTML body of an alert email: `template` filled in for `event`, or `message` followed by
+/// `chart` when no template is set. `message` and `chart` are HTML already and go in as they
+/// are; the other values are escaped, and empty without an event.
+pub fn render_body(template: &str, message: &str, event: Option<&AlertEvent>, chart: Option<&str>) -> String {
+  let template = template.trim();
+  if template.is_empty() {
+    return match chart {
+      Some(chart) => format!("{message}<br><br>{chart}"),
+      None => message.to_string(),
+    };
+  }
+  fill(template, |name| match name {
+    "message" => message.to_string(),
+    "chart" => chart.unwrap_or_default().to_string(),
+    name => event.map_or_else(String::new, |event| escape_html(&variable(name, event))),
+  })
+}
+
+/// The value of `{{name}}` for `event`; empty for names it doesn't know.
+fn variable(name: &str, event: &AlertEvent) -> String {
+  match name {
+    "severity" => event.severity.tag().to_string(),
+    "target" if event.label.is_empty() => event.address.clone().unwrap_or_default(),
+    "target" => event.label.clone(),
+    "label" => event.label.clone(),
+    "address" => event.address.clone().unwrap_or_default(),
+    "start" => event.started.clone().unwrap_or_default(),
+    "end" => event.ended.clone().unwrap_or_default(),
+    "time" => event.timestamp.clone(),
+    "incident" => event.incident_id.clone().unwrap_or_default(),
+    _ => String::new(),
+  }
+}
+
+/// `template` with every `{{name}}` replaced by `value(name)`.
+fn fill(template: &str, value: impl Fn(&str) -> String) -> String {
+  let mut out = String::new();
+  let mut rest = template;
+  while let Some((before, name, after)) = next_variable(rest) {
+    out.push_str(before);
+    out.push_str(&value(name));
+    rest = after;
+  }
+  out.push_str(rest);
+  out
+}
+
+fn escape_html(text: &str) -> String {
+  text
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+}
+
+pub fn validate_subject_template(template: &str) -> Result<(), String> {
+  validate_template(template, "邮件标题模板", SUBJECT_VARIABLES)
+}
+
+pub fn validate_body_template(template: &str) -> Result<(), String> {
+  validate_template(template, "邮件正文模板", BODY_VARIABLES)
+}
+
+fn validate_template(template: &str, what: &str, variables: &[&str]) -> Result<(), String> {
+  let mut rest = template;
+  while let Some(open) = rest.find("{{") {
+    let Some((_, name, after)) = next_variable(rest) else {
+      return Err(format!("{what}中的 {{{{ 没有闭合: {}", &rest[open..]));
+    };
+    if !variables.contains(&name) {
+      return Err(format!(
+        "{what}中有未知变量 {{{{{name}}}}}，可用变量: {}",
+        variables.join(", ")
+      ));
+    }
+    rest = after;
+  }
+  Ok(())
+}
+
+/// Splits `text` at its first `{{name}}` into the text before, the trimmed name and the rest.
+fn next_variable(text: &str) -> Option<(&str, &str, &str)> {
+  let open = text.find("{{")?;
+  let close = text[open + 2..].find("}}")? + open + 2;
+  Some((&text[..open], text[open + 2..close].trim(), &text[close + 2..]))
+}
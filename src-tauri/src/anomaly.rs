@@ -0,0 +1,162 @@
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// Alerts when latency stays well above the target's own learned baseline, catching slow
+/// degradations that a fixed threshold would miss.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct AnomalySettings {
+  #[serde(default)]
+  pub enabled: bool,
+  /// EWMA weight of each new sample; smaller learns slower.
+  #[serde(default = "default_alpha")]
+  pub alpha: f64,
+  /// How many standard deviations above the mean count as anomalous.
+  #[serde(default = "default_sigma")]
+  pub sigma: f64,
+  /// Minimum margin over the mean, so a very stable link doesn't alert on a few ms.
+  #[serde(default = "default_min_margin_ms")]
+  pub min_margin_ms: f64,
+  /// How long latency must stay high before alerting.
+  #[serde(default = "default_sustain_secs")]
+  pub sustain_secs: u64,
+  /// Samples to learn from before alerting at all.
+  #[serde(default = "default_warmup_samples")]
+  pub warmup_samples: u64,
+}
+
+impl Default for AnomalySettings {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      alpha: default_alpha(),
+      sigma: default_sigma(),
+      min_margin_ms: default_min_margin_ms(),
+      sustain_secs: default_sustain_secs(),
+      warmup_samples: default_warmup_samples(),
+    }
+  }
+}
+
+fn default_alpha() -> f64 {
+  0.01
+}
+
+fn default_sigma() -> f64 {
+  3.0
+}
+
+fn default_min_margin_ms() -> f64 {
+  20.0
+}
+
+fn default_sustain_secs() -> u64 {
+  60
+}
+
+fn default_warmup_samples() -> u64 {
+  300
+}
+
+pub fn validate(settings: &AnomalySettings) -> Result<(), String> {
+  if !(settings.alpha > 0.0 && settings.alpha < 1.0) {
+    return Err("平滑系数应在 0-1 之间（不含端点）".to_string());
+  }
+  if settings.sigma <= 0.0 || settings.min_margin_ms < 0.0 {
+    return Err("基线偏离阈值必须为正数".to_string());
+  }
+  Ok(())
+}
+
+#[derive(Clone, Copy, Serialize)]
+pub struct Baseline {
+  pub mean_ms: f64,
+  pub stddev_ms: f64,
+  pub samples: u64,
+}
+
+pub enum Transition {
+  /// Latency has been above `threshold_ms` for the sustain period.
+  Began { rtt_ms: f64, baseline: Baseline, threshold_ms: f64 },
+  Ended { rtt_ms: f64, baseline: Baseline },
+}
+
+pub struct LatencyBaseline {
+  settings: AnomalySettings,
+  mean: f64,
+  variance: f64,
+  samples: u64,
+  above_since: Option<Instant>,
+  anomalous: bool,
+}
+
+impl LatencyBaseline {
+  pub fn new(settings: AnomalySettings) -> Self {
+    Self {
+      settings,
+      mean: 0.0,
+      variance: 0.0,
+      samples: 0,
+      above_since: None,
+      anomalous: false,
+    }
+  }
+
+  pub fn baseline(&self) -> Option<Baseline> {
+    (self.samples > 0).then(|| Baseline {
+      mean_ms: self.mean,
+      stddev_ms: self.variance.sqrt(),
+      samples: self.samples,
+    })
+  }
+
+  /// Feeds a successful probe's RTT and reports when the anomaly state flips.
+  pub fn observe(&mut self, rtt_ms: f64) -> Option<Transition> {
+    let threshold = self.mean + (self.settings.sigma * self.variance.sqrt()).max(self.settings.min_margin_ms);
+    let warmed_up = self.samples >= self.settings.warmup_samples;
+    let high = warmed_up && rtt_ms > threshold;
+
+    let mut transition = None;
+    if high {
+      let since = *self.above_since.get_or_insert_with(Instant::now);
+      if self.settings.enabled
+        && !self.anomalous
+        && since.elapsed() >= Duration::from_secs(self.settings.sustain_secs)
+      {
+        self.anomalous = true;
+        transition = self.baseline().map(|baseline| Transition::Began {
+          rtt_ms,
+          baseline,
+          threshold_ms: threshold,
+        });
+      }
+    } else {
+      self.above_since = None;
+      if self.anomalous {
+        self.anomalous = false;
+        transition = self.baseline().map(|baseline| Transition::Ended { rtt_ms, baseline });
+      }
+    }
+
+    // Keep the baseline from chasing a degradation before it has been reported; afterwards
+    // keep learning so a lasting shift eventually becomes the new normal.
+    if !high || self.anomalous || !self.settings.enabled {
+      self.update(rtt_ms);
+    }
+    transition
+  }
+
+  fn update(&mut self, rtt_ms: f64) {
+    if self.samples == 0 {
+      self.mean = rtt_ms;
+      self.variance = 0.0;
+    } else {
+      let alpha = self.settings.alpha;
+      let diff = rtt_ms - self.mean;
+      let increment = alpha * diff;
+      self.mean += increment;
+      self.variance = (1.0 - alpha) * (self.variance + diff * increment);
+    }
+    self.samples += 1;
+  }
+}
@@ -0,0 +1,132 @@
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{Local, NaiveDate};
+use serde::Serialize;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::anomaly::AnomalySettings;
+use crate::rollup;
+use crate::targets::TargetConfig;
+use crate::{AppSettings, PingSettings};
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Describes what an archive holds. Alert channel settings are left out on purpose: they
+/// carry credentials, and the bundle is meant to be handed to other people.
+#[derive(Serialize)]
+struct Manifest {
+  created: String,
+  from: String,
+  to: String,
+  /// Addresses with hourly rollups in the range.
+  targets: Vec<String>,
+  target_configs: Vec<TargetConfig>,
+  ping: PingSettings,
+  anomaly: AnomalySettings,
+  files: usize,
+}
+
+#[derive(Serialize)]
+pub struct ArchiveReport {
+  pub path: String,
+  pub days: usize,
+  pub files: usize,
+  pub removed_originals: bool,
+}
+
+/// Zips the day directories in `[from, to]` plus a manifest into `dest`, then deletes the
+/// originals if asked to. Nothing is deleted unless the archive was written completely.
+pub fn create(
+  base_dir: &Path,
+  dest: &Path,
+  from: NaiveDate,
+  to: NaiveDate,
+  settings: &AppSettings,
+  remove_originals: bool,
+) -> Result<ArchiveReport, String> {
+  if remove_originals && to >= Local::now().date_naive() {
+    return Err("今天的日志仍在写入，不能归档后删除".to_string());
+  }
+  let days: Vec<PathBuf> = from
+    .iter_days()
+    .take_while(|day| *day <= to)
+    .map(|day| base_dir.join(day.format("%Y-%m-%d").to_string()))
+    .filter(|dir| dir.is_dir())
+    .collect();
+  if days.is_empty() {
+    return Err("所选日期范围内没有日志".to_string());
+  }
+
+  let file = File::create(dest).map_err(|e| format!("无法创建归档文件: {e}"))?;
+  let mut zip = ZipWriter::new(file);
+  let options = SimpleFileOptions::default()
+    .compression_method(CompressionMethod::Deflated)
+    .large_file(true);
+  let mut files = 0;
+  for dir in &days {
+    files += add_dir(&mut zip, base_dir, dir, options).map_err(|e| format!("写入归档失败: {e}"))?;
+  }
+
+  let targets: BTreeSet<String> =
+    rollup::load(base_dir, from, to, None).into_iter().map(|rollup| rollup.address).collect();
+  let manifest = Manifest {
+    created: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+    from: from.to_string(),
+    to: to.to_string(),
+    targets: targets.into_iter().collect(),
+    target_configs: settings.targets.clone(),
+    ping: settings.ping.clone(),
+    anomaly: settings.anomaly.clone(),
+    files,
+  };
+  let data = serde_json::to_vec_pretty(&manifest).map_err(|e| e.to_string())?;
+  zip
+    .start_file(MANIFEST_FILE, options)
+    .and_then(|_| zip.write_all(&data).map_err(Into::into))
+    .and_then(|_| zip.finish())
+    .map_err(|e| format!("写入归档失败: {e}"))?;
+
+  if remove_originals {
+    for dir in &days {
+      fs::remove_dir_all(dir).map_err(|e| format!("归档已完成，但删除 {} 失败: {e}", dir.display()))?;
+    }
+  }
+  Ok(ArchiveReport {
+    path: dest.to_string_lossy().to_string(),
+    days: days.len(),
+    files,
+    removed_originals: remove_originals,
+  })
+}
+
+/// Adds every file under `dir`, named relative to `base_dir` with `/` separators.
+fn add_dir(
+  zip: &mut ZipWriter<File>,
+  base_dir: &Path,
+  dir: &Path,
+  options: SimpleFileOptions,
+) -> zip::result::ZipResult<usize> {
+  let mut count = 0;
+  for entry in fs::read_dir(dir)?.flatten() {
+    let path = entry.path();
+    if path.is_dir() {
+      count += add_dir(zip, base_dir, &path, options)?;
+      continue;
+    }
+    let Ok(relative) = path.strip_prefix(base_dir) else {
+      continue;
+    };
+    let name: Vec<String> = relative
+      .components()
+      .map(|c| c.as_os_str().to_string_lossy().to_string())
+      .collect();
+    zip.start_file(name.join("/"), options)?;
+    io::copy(&mut File::open(&path)?, zip)?;
+    count += 1;
+  }
+  Ok(count)
+}
@@ -0,0 +1,190 @@
+// ARP liveness for hosts on the local subnet. Locked-down Windows laptops and a lot of IoT gear
+// drop ICMP echo requests, but anything that wants to stay on the network has to answer ARP,
+// so a failed ping followed by an ARP reply means the device is there and only ignores pings.
+//
+// Windows sends a real ARP request (SendARP). Elsewhere sending raw ARP needs CAP_NET_RAW or
+// root, so a datagram to the host makes the kernel resolve it and the neighbour table is read
+// back; a device that left within the last minute or so can still show up there.
+
+use std::net::{IpAddr, Ipv4Addr, ToSocketAddrs};
+
+use crate::{local_names, targets};
+
+/// Resolves `address` over ARP; the success line names the hardware address that answered.
+pub fn probe(address: &str) -> Result<String, String> {
+  targets::validate_host(address)?;
+  let ip = ipv4_of(address).ok_or_else(|| format!("ARP 仅支持 IPv4 地址: {address}"))?;
+  if !platform::on_link(ip) {
+    return Err(format!("{ip} 不在本机所在网段，无法 ARP 探测"));
+  }
+  match platform::resolve(ip) {
+    Some(mac) => Ok(format!("ARP reply from {ip}: {mac} (ICMP 无响应)")),
+    None => Err(format!("ARP 无应答: {ip}")),
+  }
+}
+
+fn ipv4_of(address: &str) -> Option<Ipv4Addr> {
+  if let Ok(ip) = address.parse::<Ipv4Addr>() {
+    return Some(ip);
+  }
+  if let Some(IpAddr::V4(ip)) = local_names::translate(address) {
+    return Some(ip);
+  }
+  (address, 0).to_socket_addrs().ok()?.find_map(|addr| match addr.ip() {
+    IpAddr::V4(ip) => Some(ip),
+    IpAddr::V6(_) => None,
+  })
+}
+
+/// Any datagram to an unresolved on-link address makes the kernel send an ARP request. Port 9
+/// is discard, so a host that does listen ignores it.
+#[cfg(unix)]
+fn trigger_resolution(ip: Ipv4Addr) {
+  if let Ok(socket) = std::net::UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)) {
+    let _ = socket.send_to(&[0], (ip, 9));
+  }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+  use std::mem;
+  use std::net::Ipv4Addr;
+
+  use windows_sys::Win32::NetworkManagement::IpHelper::{
+    GetBestRoute, SendARP, MIB_IPFORWARDROW, MIB_IPROUTE_TYPE_DIRECT,
+  };
+
+  pub fn on_link(ip: Ipv4Addr) -> bool {
+    let mut route: MIB_IPFORWARDROW = unsafe { mem::zeroed() };
+    if unsafe { GetBestRoute(u32::from_ne_bytes(ip.octets()), 0, &mut route) } != 0 {
+      return false;
+    }
+    let kind = unsafe { route.Anonymous1.ForwardType };
+    kind == MIB_IPROUTE_TYPE_DIRECT
+  }
+
+  pub fn resolve(ip: Ipv4Addr) -> Option<String> {
+    let mut mac = [0u8; 8];
+    let mut len = mac.len() as u32;
+    let status = unsafe { SendARP(u32::from_ne_bytes(ip.octets()), 0, mac.as_mut_ptr().cast(), &mut len) };
+    if status != 0 || len < 6 {
+      return None;
+    }
+    Some(mac[..6].iter().map(|b| format!("{b:02x}")).collect::<Vec<_>>().join(":"))
+  }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+  use std::fs;
+  use std::net::Ipv4Addr;
+  use std::thread;
+  use std::time::Duration;
+
+  /// ATF_COM: the entry holds a resolved hardware address.
+  const ATF_COM: u32 = 0x02;
+
+  /// Whether a route without a gateway covers `ip`. `/proc/net/route` prints addresses as the
+  /// hex of their in-memory (network order) value.
+  pub fn on_link(ip: Ipv4Addr) -> bool {
+    let Ok(routes) = fs::read_to_string("/proc/net/route") else {
+      return false;
+    };
+    let ip = u32::from_ne_bytes(ip.octets());
+    routes.lines().skip(1).any(|line| {
+      let fields: Vec<&str> = line.split_whitespace().collect();
+      let hex = |index: usize| fields.get(index).and_then(|field| u32::from_str_radix(field, 16).ok());
+      match (hex(1), hex(2), hex(7)) {
+        (Some(destination), Some(0), Some(mask)) if mask != 0 => ip & mask == destination,
+        _ => false,
+      }
+    })
+  }
+
+  pub fn resolve(ip: Ipv4Addr) -> Option<String> {
+    super::trigger_resolution(ip);
+    for _ in 0..10 {
+      if let Some(mac) = neighbour(ip) {
+        return Some(mac);
+      }
+      thread::sleep(Duration::from_millis(100));
+    }
+    None
+  }
+
+  /// `IP address  HW type  Flags  HW address  Mask  Device`, one header line.
+  fn neighbour(ip: Ipv4Addr) -> Option<String> {
+    let table = fs::read_to_string("/proc/net/arp").ok()?;
+    let ip = ip.to_string();
+    table.lines().skip(1).find_map(|line| {
+      let fields: Vec<&str> = line.split_whitespace().collect();
+      let flags = u32::from_str_radix(fields.get(2)?.trim_start_matches("0x"), 16).ok()?;
+      let mac = *fields.get(3)?;
+      (fields[0] == ip && flags & ATF_COM != 0 && mac != "00:00:00:00:00:00").then(|| mac.to_string())
+    })
+  }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+  use std::net::Ipv4Addr;
+  use std::process::Command;
+  use std::ptr;
+  use std::thread;
+  use std::time::Duration;
+
+  /// Whether `ip` is inside the subnet of an IPv4 address configured on an interface.
+  pub fn on_link(ip: Ipv4Addr) -> bool {
+    let mut addrs: *mut libc::ifaddrs = ptr::null_mut();
+    if unsafe { libc::getifaddrs(&mut addrs) } != 0 {
+      return false;
+    }
+    let ip = u32::from(ip);
+    let mut found = false;
+    let mut cursor = addrs;
+    while let Some(entry) = unsafe { cursor.as_ref() } {
+      cursor = entry.ifa_next;
+      let (addr, mask) = unsafe { (entry.ifa_addr.as_ref(), entry.ifa_netmask.as_ref()) };
+      let (Some(addr), Some(mask)) = (addr, mask) else {
+        continue;
+      };
+      if i32::from(addr.sa_family) != libc::AF_INET || entry.ifa_flags & libc::IFF_LOOPBACK as u32 != 0 {
+        continue;
+      }
+      let v4 = |sockaddr: &libc::sockaddr| {
+        let sockaddr = unsafe { &*(sockaddr as *const libc::sockaddr as *const libc::sockaddr_in) };
+        u32::from_be(sockaddr.sin_addr.s_addr)
+      };
+      let (local, mask) = (v4(addr), v4(mask));
+      if mask != 0 && local & mask == ip & mask {
+        found = true;
+        break;
+      }
+    }
+    unsafe { libc::freeifaddrs(addrs) };
+    found
+  }
+
+  pub fn resolve(ip: Ipv4Addr) -> Option<String> {
+    super::trigger_resolution(ip);
+    thread::sleep(Duration::from_millis(500));
+    // `? (192.168.1.20) at 3c:22:fb:1:2:3 on en0 ifscope [ethernet]`, or `(incomplete)`.
+    let output = Command::new("arp").args(["-n", &ip.to_string()]).output().ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let mac = text.split(" at ").nth(1)?.split_whitespace().next()?;
+    mac.contains(':').then(|| mac.to_string())
+  }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
+mod platform {
+  use std::net::Ipv4Addr;
+
+  pub fn on_link(_ip: Ipv4Addr) -> bool {
+    false
+  }
+
+  pub fn resolve(_ip: Ipv4Addr) -> Option<String> {
+    None
+  }
+}
@@ -0,0 +1,256 @@
+// Starting with the user session: a value under the registry Run key on Windows, a LaunchAgent
+// on macOS and an XDG autostart entry elsewhere. With background start the entry passes
+// `--background`, and such a launch keeps the window hidden behind a tray icon and resumes
+// monitoring the most recent target, so a reboot needs no one at the keyboard.
+
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+use tauri::menu::{Menu, MenuItem};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+use tauri::{AppHandle, Manager};
+
+use crate::{load_settings, start_session, viewer};
+
+pub const BACKGROUND_ARG: &str = "--background";
+const MAIN_WINDOW: &str = "main";
+
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct AutostartSettings {
+  /// Launch the app when the user logs in.
+  #[serde(default)]
+  pub enabled: bool,
+  /// Such launches start hidden in the tray and begin monitoring the last target.
+  #[serde(default)]
+  pub background: bool,
+}
+
+/// Adds or removes the login entry to match `settings`.
+pub fn apply(app: &AppHandle, settings: &AutostartSettings) -> Result<(), String> {
+  let name = app.config().identifier.clone();
+  if !settings.enabled {
+    return platform::unregister(&name);
+  }
+  let exe = std::env::current_exe().map_err(|e| format!("无法确定程序路径: {e}"))?;
+  let args: &[&str] = if settings.background { &[BACKGROUND_ARG] } else { &[] };
+  platform::register(&name, &exe, args)
+}
+
+pub fn launched_in_background() -> bool {
+  std::env::args().any(|arg| arg == BACKGROUND_ARG)
+}
+
+/// Hides the window behind a tray icon and resumes monitoring. Called from setup for launches
+/// with `--background`.
+pub fn start_in_background(app: &AppHandle) {
+  if let Some(window) = app.get_webview_window(MAIN_WINDOW) {
+    let _ = window.hide();
+  }
+  if let Err(e) = build_tray(app) {
+    eprintln!("failed to create tray icon: {e}");
+    show_main_window(app);
+  }
+  let Some(address) = load_settings(app).history.first().map(|entry| entry.address.clone()) else {
+    return;
+  };
+  // The preflight check resolves the address, which can take a while; setup must not wait.
+  let app = app.clone();
+  thread::spawn(move || {
+    if let Err(e) = start_session(&app, &address) {
+      eprintln!("failed to resume monitoring: {}", e.message);
+    }
+  });
+}
+
+fn build_tray(app: &AppHandle) -> tauri::Result<()> {
+  let show = MenuItem::with_id(app, "show", "显示窗口", true, None::<&str>)?;
+  let quit = MenuItem::with_id(app, "quit", "退出", true, None::<&str>)?;
+  let menu = Menu::with_items(app, &[&show, &quit])?;
+  let mut tray = TrayIconBuilder::new()
+    .tooltip(app.package_info().name.clone())
+    .menu(&menu)
+    .show_menu_on_left_click(false)
+    .on_menu_event(|app, event| match event.id().as_ref() {
+      "show" => show_main_window(app),
+      // Quitting stops monitoring, which viewer mode doesn't allow.
+      "quit" if !viewer::status().enabled => app.exit(0),
+      _ => {}
+    })
+    .on_tray_icon_event(|tray, event| {
+      if let TrayIconEvent::Click {
+        button: MouseButton::Left,
+        button_state: MouseButtonState::Up,
+        ..
+      } = event
+      {
+        show_main_window(tray.app_handle());
+      }
+    });
+  if let Some(icon) = app.default_window_icon() {
+    tray = tray.icon(icon.clone());
+  }
+  tray.build(app)?;
+  Ok(())
+}
+
+fn show_main_window(app: &AppHandle) {
+  if let Some(window) = app.get_webview_window(MAIN_WINDOW) {
+    let _ = window.show();
+    let _ = window.set_focus();
+  }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+  use std::path::Path;
+
+  use windows_sys::Win32::Foundation::ERROR_FILE_NOT_FOUND;
+  use windows_sys::Win32::System::Registry::{RegDeleteKeyValueW, RegSetKeyValueW, HKEY_CURRENT_USER, REG_SZ};
+
+  const RUN_KEY: &str = r"Software\Microsoft\Windows\CurrentVersion\Run";
+
+  fn wide(text: &str) -> Vec<u16> {
+    text.encode_utf16().chain([0]).collect()
+  }
+
+  pub fn register(name: &str, exe: &Path, args: &[&str]) -> Result<(), String> {
+    let mut command = format!("\"{}\"", exe.display());
+    for arg in args {
+      command.push(' ');
+      command.push_str(arg);
+    }
+    let (key, name, value) = (wide(RUN_KEY), wide(name), wide(&command));
+    let status = unsafe {
+      RegSetKeyValueW(
+        HKEY_CURRENT_USER,
+        key.as_ptr(),
+        name.as_ptr(),
+        REG_SZ,
+        value.as_ptr().cast(),
+        (value.len() * 2) as u32,
+      )
+    };
+    if status != 0 {
+      return Err(format!("写入开机启动项失败（错误 {status}）"));
+    }
+    Ok(())
+  }
+
+  pub fn unregister(name: &str) -> Result<(), String> {
+    let (key, name) = (wide(RUN_KEY), wide(name));
+    let status = unsafe { RegDeleteKeyValueW(HKEY_CURRENT_USER, key.as_ptr(), name.as_ptr()) };
+    if status != 0 && status != ERROR_FILE_NOT_FOUND {
+      return Err(format!("删除开机启动项失败（错误 {status}）"));
+    }
+    Ok(())
+  }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+  use std::path::{Path, PathBuf};
+
+  fn plist_path(name: &str) -> Result<PathBuf, String> {
+    Ok(super::home()?.join("Library/LaunchAgents").join(format!("{name}.plist")))
+  }
+
+  fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+  }
+
+  pub fn register(name: &str, exe: &Path, args: &[&str]) -> Result<(), String> {
+    let path = plist_path(name)?;
+    let arguments: String = std::iter::once(exe.to_string_lossy().to_string())
+      .chain(args.iter().map(|arg| arg.to_string()))
+      .map(|arg| format!("    <string>{}</string>\n", escape(&arg)))
+      .collect();
+    let plist = format!(
+      r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+  <key>Label</key>
+  <string>{label}</string>
+  <key>ProgramArguments</key>
+  <array>
+{arguments}  </array>
+  <key>RunAtLoad</key>
+  <true/>
+</dict>
+</plist>
+"#,
+      label = escape(name)
+    );
+    super::write_entry(&path, &plist)
+  }
+
+  pub fn unregister(name: &str) -> Result<(), String> {
+    super::remove_entry(&plist_path(name)?)
+  }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+mod platform {
+  use std::path::{Path, PathBuf};
+
+  fn desktop_path(name: &str) -> Result<PathBuf, String> {
+    let config = match std::env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+      Some(dir) => PathBuf::from(dir),
+      None => super::home()?.join(".config"),
+    };
+    Ok(config.join("autostart").join(format!("{name}.desktop")))
+  }
+
+  /// Exec quoting per the desktop entry spec: `"`, `` ` ``, `$` and `\` are escaped.
+  fn quote(arg: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in arg.chars() {
+      if matches!(c, '"' | '`' | '$' | '\\') {
+        quoted.push('\\');
+      }
+      quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+  }
+
+  pub fn register(name: &str, exe: &Path, args: &[&str]) -> Result<(), String> {
+    let mut exec = quote(&exe.to_string_lossy());
+    for arg in args {
+      exec.push(' ');
+      exec.push_str(arg);
+    }
+    let entry = format!(
+      "[Desktop Entry]\nType=Application\nName=Ping Tool\nExec={exec}\nX-GNOME-Autostart-enabled=true\nTerminal=false\n"
+    );
+    super::write_entry(&desktop_path(name)?, &entry)
+  }
+
+  pub fn unregister(name: &str) -> Result<(), String> {
+    super::remove_entry(&desktop_path(name)?)
+  }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn home() -> Result<std::path::PathBuf, String> {
+  std::env::var_os("HOME")
+    .filter(|home| !home.is_empty())
+    .map(std::path::PathBuf::from)
+    .ok_or_else(|| "无法确定用户主目录".to_string())
+}
+
+#[cfg(not(target_os = "windows"))]
+fn write_entry(path: &std::path::Path, contents: &str) -> Result<(), String> {
+  if let Some(dir) = path.parent() {
+    std::fs::create_dir_all(dir).map_err(|e| format!("无法创建 {}: {e}", dir.display()))?;
+  }
+  std::fs::write(path, contents).map_err(|e| format!("写入开机启动项失败: {e}"))
+}
+
+#[cfg(not(target_os = "windows"))]
+fn remove_entry(path: &std::path::Path) -> Result<(), String> {
+  match std::fs::remove_file(path) {
+    Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(format!("删除开机启动项失败: {e}")),
+    _ => Ok(()),
+  }
+}
@@ -0,0 +1,73 @@
+// Reports which probe engine this machine can use. Sending ICMP directly needs a raw socket
+// (root, CAP_NET_RAW or an administrator on Windows) or an unprivileged datagram ICMP socket
+// where the OS allows one; without either, probes go through the system ping binary.
+
+use std::io;
+
+use serde::Serialize;
+use socket2::{Domain, Protocol, Socket, Type};
+
+use crate::preflight;
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProbeEngine {
+  SystemPing,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum SocketStatus {
+  Available,
+  /// The OS refused for lack of privileges; `guidance` says how to grant them.
+  PermissionDenied { error: String },
+  Unsupported { error: String },
+}
+
+#[derive(Serialize)]
+pub struct Capabilities {
+  /// The engine sessions use.
+  pub engine: ProbeEngine,
+  pub raw_icmp: SocketStatus,
+  pub datagram_icmp: SocketStatus,
+  /// Path of the ping binary, if one is on PATH.
+  pub system_ping: Option<String>,
+  /// Whether ping probes can be DSCP-marked; Windows ping can't.
+  pub dscp_marking: bool,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub guidance: Option<String>,
+}
+
+pub fn detect() -> Capabilities {
+  let raw_icmp = open_icmp(Type::RAW);
+  let datagram_icmp = open_icmp(Type::DGRAM);
+  let denied = matches!(raw_icmp, SocketStatus::PermissionDenied { .. })
+    && !matches!(datagram_icmp, SocketStatus::Available);
+  Capabilities {
+    engine: ProbeEngine::SystemPing,
+    raw_icmp,
+    datagram_icmp,
+    system_ping: preflight::find_ping_binary().map(|path| path.to_string_lossy().to_string()),
+    dscp_marking: ping_core::supports_dscp(ping_core::Platform::current()),
+    guidance: denied.then(|| privilege_guidance().to_string()),
+  }
+}
+
+fn open_icmp(kind: Type) -> SocketStatus {
+  match Socket::new(Domain::IPV4, kind, Some(Protocol::ICMPV4)) {
+    Ok(_) => SocketStatus::Available,
+    Err(e) if e.kind() == io::ErrorKind::PermissionDenied => SocketStatus::PermissionDenied { error: e.to_string() },
+    Err(e) => SocketStatus::Unsupported { error: e.to_string() },
+  }
+}
+
+fn privilege_guidance() -> &'static str {
+  if cfg!(target_os = "windows") {
+    "直接发送 ICMP 需要以管理员身份运行；当前使用系统 ping"
+  } else if cfg!(target_os = "linux") {
+    "直接发送 ICMP 需要 root 或 CAP_NET_RAW（sudo setcap cap_net_raw+ep <程序路径>），\
+     或将当前用户组加入 net.ipv4.ping_group_range；当前使用系统 ping"
+  } else {
+    "直接发送 ICMP 需要管理员权限；当前使用系统 ping"
+  }
+}
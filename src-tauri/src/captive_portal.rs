@@ -0,0 +1,91 @@
+use std::time::Duration;
+
+use reqwest::blocking::Client;
+use reqwest::redirect::Policy;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct CaptivePortalSettings {
+  #[serde(default)]
+  pub enabled: bool,
+  /// An endpoint that answers `204 No Content` when the internet is reachable directly.
+  #[serde(default = "default_check_url")]
+  pub check_url: String,
+}
+
+impl Default for CaptivePortalSettings {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      check_url: default_check_url(),
+    }
+  }
+}
+
+fn default_check_url() -> String {
+  "http://connectivitycheck.gstatic.com/generate_204".to_string()
+}
+
+#[derive(Clone, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum Connectivity {
+  Online,
+  CaptivePortal { location: Option<String> },
+  Offline { error: String },
+}
+
+impl Connectivity {
+  pub fn is_captive(&self) -> bool {
+    matches!(self, Connectivity::CaptivePortal { .. })
+  }
+
+  pub fn describe(&self) -> String {
+    match self {
+      Connectivity::Online => "外网连通".to_string(),
+      Connectivity::CaptivePortal { location: Some(location) } => {
+        format!("检测到强制登录门户（Captive Portal）：{location}")
+      }
+      Connectivity::CaptivePortal { location: None } => "检测到强制登录门户（Captive Portal）".to_string(),
+      Connectivity::Offline { error } => format!("无网络连接（{error}）"),
+    }
+  }
+}
+
+pub fn check(settings: &CaptivePortalSettings) -> Connectivity {
+  let client = match Client::builder()
+    .redirect(Policy::none())
+    .timeout(Duration::from_secs(3))
+    .build()
+  {
+    Ok(client) => client,
+    Err(e) => return Connectivity::Offline { error: e.to_string() },
+  };
+
+  let response = match client.get(settings.check_url.trim()).send() {
+    Ok(response) => response,
+    Err(e) => return Connectivity::Offline { error: e.to_string() },
+  };
+
+  // A portal intercepts the request with a redirect to its login page, or serves that page in
+  // place of the empty answer. Any other status is the check URL's own trouble, not a portal.
+  let status = response.status();
+  if status.is_redirection() {
+    let location = response
+      .headers()
+      .get(reqwest::header::LOCATION)
+      .and_then(|value| value.to_str().ok())
+      .map(str::to_string);
+    return Connectivity::CaptivePortal { location };
+  }
+  match status {
+    StatusCode::NO_CONTENT => Connectivity::Online,
+    StatusCode::OK if response.bytes().is_ok_and(|body| !body.is_empty()) => {
+      Connectivity::CaptivePortal { location: None }
+    }
+    StatusCode::OK => Connectivity::Online,
+    _ => Connectivity::Offline {
+      error: format!("检测地址返回 {status}"),
+    },
+  }
+}
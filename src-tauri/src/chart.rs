@@ -0,0 +1,200 @@
+// A small latency chart for alert emails, drawn from the minute logs and encoded as PNG. Mail
+// clients don't run scripts and many block remote images, so the picture travels inline with
+// the message and the HTML body points at it by content ID. There is no font rendering; the
+// scale and time range go in a caption under the image.
+
+use std::path::Path;
+
+use chrono::{Duration, Local, NaiveDateTime};
+
+use crate::logfile::{self, Sample};
+
+pub const CONTENT_ID: &str = "latency-chart";
+pub const MAX_MINUTES: u32 = 24 * 60;
+const WIDTH: u32 = 640;
+const HEIGHT: u32 = 200;
+const MARGIN: u32 = 6;
+/// Failed probes are marked by a tick this tall along the bottom edge.
+const FAILURE_TICK: u32 = 12;
+/// RTT points further apart than this are not joined, so a gap in the log stays a gap.
+const MAX_JOIN_SECS: i64 = 30;
+const MIN_SCALE_MS: f64 = 10.0;
+
+const BACKGROUND: [u8; 3] = [255, 255, 255];
+const GRID: [u8; 3] = [225, 225, 225];
+const OUTAGE: [u8; 3] = [253, 226, 226];
+const FAILURE: [u8; 3] = [220, 38, 38];
+const LINE: [u8; 3] = [37, 99, 235];
+
+pub struct Chart {
+  pub png: Vec<u8>,
+  /// One line of HTML describing the axes.
+  pub caption: String,
+}
+
+impl Chart {
+  /// The chart and its caption, for the `{{chart}}` of an email body.
+  pub fn html(&self) -> String {
+    format!(
+      "<img src=\"cid:{CONTENT_ID}\" width=\"{WIDTH}\" height=\"{HEIGHT}\" alt=\"延迟曲线\"><br>\
+       <span style=\"color:#666;font-size:12px\">{}</span>",
+      self.caption
+    )
+  }
+}
+
+pub fn validate_minutes(minutes: u32) -> Result<(), String> {
+  if minutes > MAX_MINUTES {
+    return Err(format!("邮件延迟图的时长不能超过 {MAX_MINUTES} 分钟"));
+  }
+  Ok(())
+}
+
+/// The last `minutes` of `address`, stretched back to show the start of an outage that began
+/// earlier. `None` when there is nothing to draw.
+pub fn recent(base_dir: &Path, address: &str, minutes: u32, outage_start: Option<&str>) -> Option<Chart> {
+  let to = Local::now().naive_local();
+  let mut from = to - Duration::minutes(i64::from(minutes.clamp(1, MAX_MINUTES)));
+  let outage_start = outage_start.and_then(|start| NaiveDateTime::parse_from_str(start, "%Y-%m-%d %H:%M:%S").ok());
+  if let Some(start) = outage_start {
+    let floor = to - Duration::minutes(i64::from(MAX_MINUTES));
+    from = from.min(start - Duration::minutes(5)).max(floor);
+  }
+  let samples = logfile::load_window(base_dir, from, to, address);
+  if samples.is_empty() {
+    return None;
+  }
+  let outage = outage_start.map(|start| (start, to));
+  let (png, scale_ms) = render(&samples, from, to, outage)?;
+  let caption = format!(
+    "{} 至 {}；纵轴 0–{scale_ms:.0} ms；蓝线为延迟，底部红色刻度为失败，浅红底色为中断时段",
+    from.format("%m-%d %H:%M"),
+    to.format("%m-%d %H:%M")
+  );
+  Some(Chart { png, caption })
+}
+
+struct Canvas {
+  pixels: Vec<u8>,
+}
+
+impl Canvas {
+  fn new() -> Self {
+    Self {
+      pixels: BACKGROUND.repeat((WIDTH * HEIGHT) as usize),
+    }
+  }
+
+  fn set(&mut self, x: i64, y: i64, color: [u8; 3]) {
+    if x < 0 || y < 0 || x >= i64::from(WIDTH) || y >= i64::from(HEIGHT) {
+      return;
+    }
+    let at = ((y as u32 * WIDTH + x as u32) * 3) as usize;
+    self.pixels[at..at + 3].copy_from_slice(&color);
+  }
+
+  fn fill(&mut self, x0: i64, x1: i64, y0: i64, y1: i64, color: [u8; 3]) {
+    for y in y0..=y1 {
+      for x in x0..=x1 {
+        self.set(x, y, color);
+      }
+    }
+  }
+
+  /// Bresenham, two pixels thick so it survives downscaling in mail clients.
+  fn line(&mut self, (mut x0, mut y0): (i64, i64), (x1, y1): (i64, i64), color: [u8; 3]) {
+    let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+    let (sx, sy) = (if x0 < x1 { 1 } else { -1 }, if y0 < y1 { 1 } else { -1 });
+    let mut err = dx + dy;
+    loop {
+      self.set(x0, y0, color);
+      self.set(x0, y0 + 1, color);
+      if x0 == x1 && y0 == y1 {
+        break;
+      }
+      let e2 = 2 * err;
+      if e2 >= dy {
+        err += dy;
+        x0 += sx;
+      }
+      if e2 <= dx {
+        err += dx;
+        y0 += sy;
+      }
+    }
+  }
+
+  fn encode(&self) -> Option<Vec<u8>> {
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, WIDTH, HEIGHT);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().ok()?;
+    writer.write_image_data(&self.pixels).ok()?;
+    writer.finish().ok()?;
+    Some(png)
+  }
+}
+
+/// Draws the chart; returns the PNG and the top of the RTT scale.
+fn render(
+  samples: &[Sample],
+  from: NaiveDateTime,
+  to: NaiveDateTime,
+  outage: Option<(NaiveDateTime, NaiveDateTime)>,
+) -> Option<(Vec<u8>, f64)> {
+  let span_secs = (to - from).num_seconds().max(1) as f64;
+  let (left, right) = (i64::from(MARGIN), i64::from(WIDTH - MARGIN - 1));
+  let (top, bottom) = (i64::from(MARGIN), i64::from(HEIGHT - MARGIN - 1));
+  let x_of = |at: NaiveDateTime| left + ((at - from).num_seconds() as f64 / span_secs * (right - left) as f64) as i64;
+
+  // The scale follows the 99th percentile, so one 3-second reply doesn't flatten the rest.
+  let mut rtts: Vec<f64> = samples.iter().filter_map(|sample| sample.rtt_ms).collect();
+  rtts.sort_by(f64::total_cmp);
+  let p99 = rtts.get((rtts.len() * 99 / 100).min(rtts.len().saturating_sub(1))).copied();
+  let scale_ms = nice_ceiling(p99.unwrap_or(0.0).max(MIN_SCALE_MS) * 1.1);
+  let y_of = |rtt: f64| bottom - ((rtt / scale_ms).min(1.0) * (bottom - top) as f64) as i64;
+
+  let mut canvas = Canvas::new();
+  if let Some((start, end)) = outage {
+    canvas.fill(x_of(start.max(from)), x_of(end.min(to)), top, bottom, OUTAGE);
+  }
+  for quarter in 1..4 {
+    let y = bottom - (bottom - top) * quarter / 4;
+    canvas.fill(left, right, y, y, GRID);
+  }
+  canvas.fill(left, right, bottom, bottom, GRID);
+
+  let mut previous: Option<(NaiveDateTime, (i64, i64))> = None;
+  for sample in samples {
+    let x = x_of(sample.timestamp);
+    match sample.rtt_ms.filter(|_| sample.success) {
+      Some(rtt) => {
+        let point = (x, y_of(rtt));
+        match previous {
+          Some((at, last)) if (sample.timestamp - at).num_seconds() <= MAX_JOIN_SECS => {
+            canvas.line(last, point, LINE)
+          }
+          _ => canvas.fill(point.0, point.0 + 1, point.1, point.1 + 1, LINE),
+        }
+        previous = Some((sample.timestamp, point));
+      }
+      None if !sample.success => {
+        canvas.fill(x, x, bottom - i64::from(FAILURE_TICK), bottom, FAILURE);
+        previous = None;
+      }
+      None => {}
+    }
+  }
+  Some((canvas.encode()?, scale_ms))
+}
+
+/// Rounds up to 1, 2 or 5 times a power of ten.
+fn nice_ceiling(value: f64) -> f64 {
+  let magnitude = 10f64.powf(value.log10().floor());
+  [1.0, 2.0, 5.0, 10.0]
+    .iter()
+    .map(|step| step * magnitude)
+    .find(|candidate| *candidate >= value)
+    .unwrap_or(value)
+}
@@ -0,0 +1,21 @@
+use std::sync::Mutex;
+
+use arboard::Clipboard;
+
+use crate::error::AppError;
+use crate::lock::LockExt;
+
+// On X11/Wayland the copied text is served by the clipboard owner, so the handle lives for
+// the whole process instead of being dropped (and the text lost) after each copy.
+static CLIPBOARD: Mutex<Option<Clipboard>> = Mutex::new(None);
+
+pub fn set_text(text: String) -> Result<(), AppError> {
+  let mut guard = CLIPBOARD.lock_or_recover();
+  let clipboard = match guard.as_mut() {
+    Some(clipboard) => clipboard,
+    None => guard.insert(Clipboard::new().map_err(|e| AppError::from(format!("无法访问剪贴板: {e}")))?),
+  };
+  clipboard
+    .set_text(text)
+    .map_err(|e| AppError::from(format!("写入剪贴板失败: {e}")))
+}
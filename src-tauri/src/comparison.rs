@@ -0,0 +1,233 @@
+// Puts two periods of one target side by side, e.g. the week before and the week after the
+// provider claims to have fixed the line, so "did it get better" has a number attached.
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::logfile::{self, FailureRuns};
+use crate::{parse_date_range, rollup};
+
+/// Ranges up to this many days are read from the raw minute logs. Longer ones come from the
+/// hourly rollups, with only the hours that lost probes read in full for the outages.
+const MAX_RAW_DAYS: i64 = 31;
+
+/// Days as `YYYY-MM-DD`, both inclusive.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct DateRange {
+  pub from: String,
+  pub to: String,
+}
+
+#[derive(Serialize)]
+pub struct RangeStats {
+  pub range: DateRange,
+  pub probes: u64,
+  pub failures: u64,
+  pub loss_percent: Option<f64>,
+  pub avg_rtt_ms: Option<f64>,
+  pub p50_rtt_ms: Option<f64>,
+  pub p90_rtt_ms: Option<f64>,
+  pub p95_rtt_ms: Option<f64>,
+  pub p99_rtt_ms: Option<f64>,
+  pub max_rtt_ms: Option<f64>,
+  /// Runs of failures long enough to confirm an outage.
+  pub outages: u64,
+  pub outage_secs: i64,
+  /// Counted from the hourly rollups; the percentiles are those of the hourly averages.
+  pub from_rollups: bool,
+}
+
+/// Round-trip times counted per 0.1 ms, so a range of any length takes memory in proportion to
+/// the distinct values rather than the probes.
+#[derive(Default)]
+struct RttDistribution {
+  counts: BTreeMap<u64, u64>,
+  total: u64,
+  sum: f64,
+}
+
+impl RttDistribution {
+  fn record(&mut self, rtt_ms: f64) {
+    self.record_many(rtt_ms, 1);
+  }
+
+  fn record_many(&mut self, rtt_ms: f64, count: u64) {
+    *self.counts.entry((rtt_ms * 10.0).round() as u64).or_default() += count;
+    self.total += count;
+    self.sum += rtt_ms * count as f64;
+  }
+
+  fn average(&self) -> Option<f64> {
+    (self.total > 0).then(|| self.sum / self.total as f64)
+  }
+
+  fn max(&self) -> Option<f64> {
+    self.counts.keys().next_back().map(|tenths| *tenths as f64 / 10.0)
+  }
+
+  /// Nearest-rank percentile, like `rollup::percentile`.
+  fn percentile(&self, p: f64) -> Option<f64> {
+    if self.total == 0 {
+      return None;
+    }
+    let rank = ((p * self.total as f64).ceil() as u64).clamp(1, self.total);
+    let mut seen = 0;
+    self.counts.iter().find_map(|(tenths, count)| {
+      seen += count;
+      (seen >= rank).then_some(*tenths as f64 / 10.0)
+    })
+  }
+}
+
+/// `b` minus `a`; negative loss, RTT and outage numbers mean `b` is better.
+#[derive(Serialize)]
+pub struct RangeDelta {
+  pub loss_percent: Option<f64>,
+  pub avg_rtt_ms: Option<f64>,
+  pub p50_rtt_ms: Option<f64>,
+  pub p90_rtt_ms: Option<f64>,
+  pub p95_rtt_ms: Option<f64>,
+  pub p99_rtt_ms: Option<f64>,
+  pub max_rtt_ms: Option<f64>,
+  pub outages: i64,
+  pub outage_secs: i64,
+}
+
+#[derive(Serialize)]
+pub struct RangeComparison {
+  pub address: String,
+  pub a: RangeStats,
+  pub b: RangeStats,
+  pub delta: RangeDelta,
+}
+
+pub fn compare(
+  base_dir: &Path,
+  address: &str,
+  range_a: DateRange,
+  range_b: DateRange,
+  confirm_failures: u32,
+) -> Result<RangeComparison, String> {
+  let (a_from, a_to) = parse_date_range(&range_a.from, &range_a.to)?;
+  let (b_from, b_to) = parse_date_range(&range_b.from, &range_b.to)?;
+  let a = range_stats(base_dir, address, range_a, a_from, a_to, confirm_failures);
+  let b = range_stats(base_dir, address, range_b, b_from, b_to, confirm_failures);
+  let diff = |a: Option<f64>, b: Option<f64>| Some(b? - a?);
+  let delta = RangeDelta {
+    loss_percent: diff(a.loss_percent, b.loss_percent),
+    avg_rtt_ms: diff(a.avg_rtt_ms, b.avg_rtt_ms),
+    p50_rtt_ms: diff(a.p50_rtt_ms, b.p50_rtt_ms),
+    p90_rtt_ms: diff(a.p90_rtt_ms, b.p90_rtt_ms),
+    p95_rtt_ms: diff(a.p95_rtt_ms, b.p95_rtt_ms),
+    p99_rtt_ms: diff(a.p99_rtt_ms, b.p99_rtt_ms),
+    max_rtt_ms: diff(a.max_rtt_ms, b.max_rtt_ms),
+    outages: b.outages as i64 - a.outages as i64,
+    outage_secs: b.outage_secs - a.outage_secs,
+  };
+  Ok(RangeComparison {
+    address: address.to_string(),
+    a,
+    b,
+    delta,
+  })
+}
+
+fn range_stats(
+  base_dir: &Path,
+  address: &str,
+  range: DateRange,
+  from: NaiveDate,
+  to: NaiveDate,
+  confirm_failures: u32,
+) -> RangeStats {
+  if (to - from).num_days() >= MAX_RAW_DAYS {
+    return rollup_stats(base_dir, address, range, from, to, confirm_failures);
+  }
+  let (mut probes, mut failures) = (0u64, 0u64);
+  let mut rtts = RttDistribution::default();
+  let mut runs = FailureRuns::new(confirm_failures);
+  logfile::for_each_sample(base_dir, from, to, Some(address), |sample| {
+    probes += 1;
+    if !sample.success {
+      failures += 1;
+    }
+    if let Some(rtt_ms) = sample.rtt_ms {
+      rtts.record(rtt_ms);
+    }
+    runs.push(&sample);
+  });
+  let outages = runs.finish();
+  RangeStats {
+    range,
+    probes,
+    failures,
+    loss_percent: (probes > 0).then(|| failures as f64 * 100.0 / probes as f64),
+    avg_rtt_ms: rtts.average(),
+    p50_rtt_ms: rtts.percentile(0.50),
+    p90_rtt_ms: rtts.percentile(0.90),
+    p95_rtt_ms: rtts.percentile(0.95),
+    p99_rtt_ms: rtts.percentile(0.99),
+    max_rtt_ms: rtts.max(),
+    outages: outages.len() as u64,
+    outage_secs: outages.iter().map(|run| (run.ended - run.started).num_seconds()).sum(),
+    from_rollups: false,
+  }
+}
+
+/// `range_stats` of a long range: counts from the hourly rollups, and failure runs from the
+/// minute files of the hours that lost probes, plus the hour after each, where a run ends.
+fn rollup_stats(
+  base_dir: &Path,
+  address: &str,
+  range: DateRange,
+  from: NaiveDate,
+  to: NaiveDate,
+  confirm_failures: u32,
+) -> RangeStats {
+  let rollups = rollup::load(base_dir, from, to, Some(address));
+  let (mut probes, mut failures) = (0u64, 0u64);
+  let mut rtts = RttDistribution::default();
+  let mut max_rtt_ms: Option<f64> = None;
+  let mut lossy_hours = HashSet::new();
+  for hour in &rollups {
+    probes += hour.samples;
+    failures += hour.lost;
+    if let Some(avg) = hour.avg_rtt_ms {
+      rtts.record_many(avg, hour.samples - hour.lost);
+    }
+    if let Some(max) = hour.max_rtt_ms {
+      max_rtt_ms = Some(max_rtt_ms.map_or(max, |seen| seen.max(max)));
+    }
+    if hour.lost > 0 {
+      lossy_hours.insert(hour.hour.clone());
+    }
+  }
+
+  let hour_key = |minute: chrono::NaiveDateTime| minute.format("%Y-%m-%d %H:00").to_string();
+  let files = logfile::log_files(base_dir, from, to).into_iter().filter(|path| {
+    logfile::file_minute(path).is_some_and(|minute| {
+      lossy_hours.contains(&hour_key(minute)) || lossy_hours.contains(&hour_key(minute - Duration::hours(1)))
+    })
+  });
+  let mut runs = FailureRuns::new(confirm_failures);
+  logfile::for_each_sample_in(files, Some(address), |sample| runs.push(&sample));
+  let outages = runs.finish();
+  RangeStats {
+    range,
+    probes,
+    failures,
+    loss_percent: (probes > 0).then(|| failures as f64 * 100.0 / probes as f64),
+    avg_rtt_ms: rtts.average(),
+    p50_rtt_ms: rtts.percentile(0.50),
+    p90_rtt_ms: rtts.percentile(0.90),
+    p95_rtt_ms: rtts.percentile(0.95),
+    p99_rtt_ms: rtts.percentile(0.99),
+    max_rtt_ms,
+    outages: outages.len() as u64,
+    outage_secs: outages.iter().map(|run| (run.ended - run.started).num_seconds()).sum(),
+    from_rollups: true,
+  }
+}
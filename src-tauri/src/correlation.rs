@@ -0,0 +1,133 @@
+// Lines up failure runs of every target on one timeline. When several targets fail at the same
+// time the cause is usually shared (the local uplink, the gateway, an upstream provider) rather
+// than the individual hosts, and that is what the windows here point out.
+
+use std::path::Path;
+
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use serde::Serialize;
+
+use crate::logfile::{self, FailureRun, FailureRuns};
+
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+/// Targets are probed one after another, not in lockstep, so failures this close together
+/// still count as simultaneous.
+const SLACK_SECS: i64 = 5;
+/// Every raw minute log of the range is read, so the range is kept to a month.
+pub const MAX_DAYS: i64 = 31;
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventKind {
+  /// Enough consecutive failures to be an outage.
+  Outage,
+  /// Fewer failures: packet loss or a blip.
+  Degradation,
+}
+
+#[derive(Serialize)]
+pub struct TargetEvent {
+  pub target: String,
+  pub address: String,
+  pub kind: EventKind,
+  pub started: String,
+  pub ended: String,
+  pub failures: u32,
+  /// Index into `windows` when other targets failed at the same time.
+  pub window: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct CorrelatedWindow {
+  pub started: String,
+  pub ended: String,
+  /// Affected targets, in the order they started failing.
+  pub targets: Vec<String>,
+  /// Every target with results in the range was affected, which points at the local side.
+  pub all_targets: bool,
+  pub outages: usize,
+}
+
+#[derive(Serialize)]
+pub struct CorrelationReport {
+  /// Targets with results in the range.
+  pub targets: Vec<String>,
+  /// Oldest first.
+  pub events: Vec<TargetEvent>,
+  pub windows: Vec<CorrelatedWindow>,
+}
+
+pub fn validate_range(from: NaiveDate, to: NaiveDate) -> Result<(), String> {
+  if (to - from).num_days() >= MAX_DAYS {
+    return Err(format!("关联分析的日期范围最长 {MAX_DAYS} 天"));
+  }
+  Ok(())
+}
+
+pub fn correlate(base_dir: &Path, from: NaiveDate, to: NaiveDate, confirm_failures: u32) -> CorrelationReport {
+  let mut targets: Vec<String> = Vec::new();
+  let mut runs = FailureRuns::new(1);
+  logfile::for_each_sample(base_dir, from, to, None, |sample| {
+    if !targets.contains(&sample.target) {
+      targets.push(sample.target.clone());
+    }
+    runs.push(&sample);
+  });
+  let runs = runs.finish();
+  let kind = |run: &FailureRun| {
+    if run.failures >= confirm_failures {
+      EventKind::Outage
+    } else {
+      EventKind::Degradation
+    }
+  };
+
+  // Runs are sorted by start, so overlapping ones form consecutive groups.
+  let slack = Duration::seconds(SLACK_SECS);
+  let mut groups: Vec<(NaiveDateTime, Vec<&FailureRun>)> = Vec::new();
+  for run in &runs {
+    match groups.last_mut() {
+      Some((ended, group)) if run.started <= *ended + slack => {
+        *ended = (*ended).max(run.ended);
+        group.push(run);
+      }
+      _ => groups.push((run.ended, vec![run])),
+    }
+  }
+
+  let mut events = Vec::new();
+  let mut windows = Vec::new();
+  for (ended, group) in groups {
+    let mut affected: Vec<String> = Vec::new();
+    for run in &group {
+      if !affected.contains(&run.target) {
+        affected.push(run.target.clone());
+      }
+    }
+    let window = (affected.len() > 1).then(|| {
+      windows.push(CorrelatedWindow {
+        started: group[0].started.format(TIMESTAMP_FORMAT).to_string(),
+        ended: ended.format(TIMESTAMP_FORMAT).to_string(),
+        all_targets: affected.len() == targets.len(),
+        outages: group.iter().filter(|run| kind(run) == EventKind::Outage).count(),
+        targets: affected,
+      });
+      windows.len() - 1
+    });
+    events.extend(group.into_iter().map(|run| TargetEvent {
+      target: run.target.clone(),
+      address: logfile::address_of(&run.target).to_string(),
+      kind: kind(run),
+      started: run.started.format(TIMESTAMP_FORMAT).to_string(),
+      ended: run.ended.format(TIMESTAMP_FORMAT).to_string(),
+      failures: run.failures,
+      window,
+    }));
+  }
+
+  CorrelationReport {
+    targets,
+    events,
+    windows,
+  }
+}
@@ -0,0 +1,268 @@
+// A small manifest per day folder, `<log dir>/<YYYY-MM-DD>/index.json`: which minute logs the
+// day has, which targets were probed, the first and last result and how many outages began.
+// Readers use it to find a day's files without listing every hour folder. Days written before
+// the manifest existed have none and are listed as before.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::lock::LockExt;
+
+pub const INDEX_FILE: &str = "index.json";
+/// Probe counts and the last timestamp are written out at most this often.
+const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct DayIndex {
+  pub date: String,
+  /// Minute logs relative to the day folder, e.g. `09/ping_2024-05-01_09-30.log`, sorted.
+  #[serde(default)]
+  pub files: Vec<String>,
+  /// Targets with probe results, as they appear in the log.
+  #[serde(default)]
+  pub targets: Vec<String>,
+  #[serde(default)]
+  pub first: Option<String>,
+  #[serde(default)]
+  pub last: Option<String>,
+  #[serde(default)]
+  pub probes: u64,
+  #[serde(default)]
+  pub failures: u64,
+  #[serde(default)]
+  pub outages: u64,
+}
+
+/// Serializes read-modify-write of the manifests between the session and other log writers.
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+/// Minute files already in their day's manifest, so `note_file` only touches it once per file.
+static NOTED: Mutex<Option<(String, HashSet<PathBuf>)>> = Mutex::new(None);
+
+fn day_dir(base_dir: &Path, date: &str) -> PathBuf {
+  base_dir.join(date)
+}
+
+pub fn load(base_dir: &Path, day: NaiveDate) -> Option<DayIndex> {
+  let path = day_dir(base_dir, &day.format("%Y-%m-%d").to_string()).join(INDEX_FILE);
+  serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+/// The manifest of `day`, or one listing only its files when the day has none. `None` when
+/// nothing was logged that day.
+pub fn load_or_scan(base_dir: &Path, day: NaiveDate) -> Option<DayIndex> {
+  if let Some(index) = load(base_dir, day) {
+    return Some(index);
+  }
+  let date = day.format("%Y-%m-%d").to_string();
+  let dir = day_dir(base_dir, &date);
+  dir.is_dir().then(|| DayIndex {
+    files: scan_files(&dir),
+    date,
+    ..DayIndex::default()
+  })
+}
+
+/// Minute logs of `day` from its manifest, as full paths.
+pub fn files(base_dir: &Path, day: NaiveDate) -> Option<Vec<PathBuf>> {
+  let index = load(base_dir, day)?;
+  let dir = day_dir(base_dir, &index.date);
+  Some(index.files.iter().map(|file| dir.join(file)).collect())
+}
+
+/// Records that a minute log is about to be written. Called for every writer through
+/// `minute_log_path`, so the file list stays complete.
+pub fn note_file(base_dir: &Path, at: &DateTime<Local>, path: &Path) {
+  let date = at.format("%Y-%m-%d").to_string();
+  {
+    let mut noted = NOTED.lock_or_recover();
+    let (day, paths) = noted.get_or_insert_with(|| (date.clone(), HashSet::new()));
+    if *day != date {
+      *day = date.clone();
+      paths.clear();
+    }
+    if !paths.insert(path.to_path_buf()) {
+      return;
+    }
+  }
+  let Ok(relative) = path.strip_prefix(day_dir(base_dir, &date)) else {
+    return;
+  };
+  let relative = relative_name(relative);
+  let result = update(base_dir, &date, |index| {
+    if let Err(at) = index.files.binary_search(&relative) {
+      index.files.insert(at, relative);
+    }
+  });
+  if let Err(e) = result {
+    eprintln!("failed to update log index: {e}");
+  }
+}
+
+/// What a session adds to its days' manifests; kept in memory and merged in now and then.
+#[derive(Default)]
+pub struct DayIndexWriter {
+  pending: Option<Pending>,
+  /// Targets already in the current day's manifest.
+  known: Vec<String>,
+  saved: Option<Instant>,
+}
+
+struct Pending {
+  date: String,
+  targets: Vec<String>,
+  first: String,
+  last: String,
+  probes: u64,
+  failures: u64,
+  outages: u64,
+}
+
+impl DayIndexWriter {
+  pub fn record_probe(&mut self, base_dir: &Path, at: &DateTime<Local>, target: &str, success: bool) {
+    let new_target = !self.known.iter().any(|known| known == target);
+    let pending = self.pending_for(base_dir, at);
+    if new_target {
+      pending.targets.push(target.to_string());
+    }
+    pending.probes += 1;
+    pending.failures += u64::from(!success);
+    if new_target {
+      self.known.push(target.to_string());
+    }
+    self.save_if_due(base_dir, new_target);
+  }
+
+  pub fn record_outage(&mut self, base_dir: &Path, at: &DateTime<Local>) {
+    self.pending_for(base_dir, at).outages += 1;
+    self.save_if_due(base_dir, true);
+  }
+
+  pub fn flush(&mut self, base_dir: &Path) {
+    if let Some(pending) = self.pending.take() {
+      save(base_dir, pending);
+    }
+    self.saved = Some(Instant::now());
+  }
+
+  /// The pending changes for `at`'s day; those of an earlier day are written out first.
+  fn pending_for(&mut self, base_dir: &Path, at: &DateTime<Local>) -> &mut Pending {
+    let date = at.format("%Y-%m-%d").to_string();
+    let timestamp = at.format("%Y-%m-%d %H:%M:%S").to_string();
+    if self.pending.as_ref().is_some_and(|pending| pending.date != date) {
+      self.flush(base_dir);
+      self.known.clear();
+    }
+    let pending = self.pending.get_or_insert_with(|| Pending {
+      date,
+      targets: Vec::new(),
+      first: timestamp.clone(),
+      last: timestamp.clone(),
+      probes: 0,
+      failures: 0,
+      outages: 0,
+    });
+    pending.last = timestamp;
+    pending
+  }
+
+  fn save_if_due(&mut self, base_dir: &Path, urgent: bool) {
+    if urgent || self.saved.is_none_or(|saved| saved.elapsed() >= SAVE_INTERVAL) {
+      self.flush(base_dir);
+    }
+  }
+}
+
+fn save(base_dir: &Path, pending: Pending) {
+  let result = update(base_dir, &pending.date, |index| {
+    for target in &pending.targets {
+      if !index.targets.contains(target) {
+        index.targets.push(target.clone());
+      }
+    }
+    if index.first.as_ref().is_none_or(|first| pending.first < *first) {
+      index.first = Some(pending.first.clone());
+    }
+    if index.last.as_ref().is_none_or(|last| pending.last > *last) {
+      index.last = Some(pending.last.clone());
+    }
+    index.probes += pending.probes;
+    index.failures += pending.failures;
+    index.outages += pending.outages;
+  });
+  if let Err(e) = result {
+    eprintln!("failed to update log index: {e}");
+  }
+}
+
+/// Applies `change` to the day's manifest on disk. A day without one starts from a listing of
+/// its folder, so files written before the manifest existed are included.
+fn update(base_dir: &Path, date: &str, change: impl FnOnce(&mut DayIndex)) -> io::Result<()> {
+  let _guard = WRITE_LOCK.lock_or_recover();
+  let dir = day_dir(base_dir, date);
+  let path = dir.join(INDEX_FILE);
+  let existing = match fs::read_to_string(&path) {
+    Ok(contents) => serde_json::from_str(&contents).ok(),
+    Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+    Err(e) => return Err(e),
+  };
+  // A damaged manifest is rebuilt from the folder; its counts are lost.
+  let mut index = existing.unwrap_or_else(|| DayIndex {
+    files: scan_files(&dir),
+    ..DayIndex::default()
+  });
+  index.date = date.to_string();
+  change(&mut index);
+  write(&dir, &index)
+}
+
+/// Replaces the manifest of `index.date` outright, e.g. with one recomputed from the logs.
+pub fn replace(base_dir: &Path, index: &DayIndex) -> io::Result<()> {
+  let _guard = WRITE_LOCK.lock_or_recover();
+  write(&day_dir(base_dir, &index.date), index)
+}
+
+fn write(dir: &Path, index: &DayIndex) -> io::Result<()> {
+  let data = serde_json::to_string_pretty(index).map_err(io::Error::other)?;
+  // Written aside and renamed over, so a reader never sees half a manifest.
+  let temp = dir.join(format!("{INDEX_FILE}.tmp"));
+  fs::create_dir_all(dir)?;
+  fs::write(&temp, data)?;
+  fs::rename(&temp, dir.join(INDEX_FILE))
+}
+
+/// Minute logs under a day folder, relative to it and sorted.
+pub fn scan_files(dir: &Path) -> Vec<String> {
+  let mut files = Vec::new();
+  let Ok(hours) = fs::read_dir(dir) else {
+    return files;
+  };
+  for hour in hours.flatten().filter(|entry| entry.path().is_dir()) {
+    let Ok(minutes) = fs::read_dir(hour.path()) else {
+      continue;
+    };
+    files.extend(
+      minutes
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "log"))
+        .filter_map(|path| path.strip_prefix(dir).ok().map(relative_name)),
+    );
+  }
+  files.sort();
+  files
+}
+
+fn relative_name(path: &Path) -> String {
+  path
+    .components()
+    .map(|c| c.as_os_str().to_string_lossy().to_string())
+    .collect::<Vec<_>>()
+    .join("/")
+}
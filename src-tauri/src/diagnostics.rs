@@ -0,0 +1,178 @@
+// One zip to attach to a bug report: the settings with credentials blanked out, the minute logs
+// of the last hours, what the platform can probe with and which build on which OS produced it.
+// It is meant to leave the machine, so anything that looks like a secret is redacted, in the
+// logs as well: they name targets by address, and older ones may still carry an SNMP community.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+
+use chrono::{Duration, Local};
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+use url::Url;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::self_metrics::{SelfMetrics, SelfMetricsState};
+use crate::{capabilities, load_settings, logfile, settings_watch};
+
+pub const DEFAULT_HOURS: u32 = 24;
+pub const MAX_HOURS: u32 = 7 * 24;
+const REDACTED: &str = "***";
+/// Settings keys whose values are credentials, matched as substrings of the key.
+const SECRET_KEYS: &[&str] = &["password", "secret", "token", "key", "authorization", "webhook"];
+/// Credentials shorter than this are not searched for in the logs, where they would blank out
+/// unrelated text; the URL user info around them is still redacted.
+const MIN_SECRET_LEN: usize = 4;
+
+#[derive(Serialize)]
+struct SystemInfo {
+  created: String,
+  app_name: String,
+  app_version: String,
+  os: &'static str,
+  arch: &'static str,
+  family: &'static str,
+  log_dir: String,
+  log_hours: u32,
+  log_files: usize,
+  /// Problems `settings.json` has, as the settings watcher reports them.
+  settings_problems: Vec<String>,
+  self_metrics: SelfMetrics,
+}
+
+#[derive(Serialize)]
+pub struct DiagnosticsReport {
+  pub path: String,
+  pub log_files: usize,
+}
+
+/// Writes the bundle to `dest`, with the logs of the last `hours` from `base_dir`.
+pub fn create(app: &AppHandle, base_dir: &Path, dest: &Path, hours: u32) -> Result<DiagnosticsReport, String> {
+  let hours = hours.clamp(1, MAX_HOURS);
+  let now = Local::now();
+  let since = (now - Duration::hours(i64::from(hours))).naive_local();
+  let logs: Vec<_> = logfile::log_files(base_dir, since.date(), now.date_naive())
+    .into_iter()
+    .filter(|path| logfile::file_minute(path).is_some_and(|minute| minute >= since - Duration::minutes(1)))
+    .collect();
+
+  let mut settings = serde_json::to_value(load_settings(app)).map_err(|e| e.to_string())?;
+  let mut secrets = Vec::new();
+  redact(&mut settings, false, &mut secrets);
+  secrets.retain(|secret| secret.len() >= MIN_SECRET_LEN);
+  secrets.sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+  secrets.dedup();
+  let info = SystemInfo {
+    created: now.format("%Y-%m-%d %H:%M:%S").to_string(),
+    app_name: app.package_info().name.clone(),
+    app_version: app.package_info().version.to_string(),
+    os: std::env::consts::OS,
+    arch: std::env::consts::ARCH,
+    family: std::env::consts::FAMILY,
+    log_dir: base_dir.to_string_lossy().to_string(),
+    log_hours: hours,
+    log_files: logs.len(),
+    settings_problems: settings_watch::problems(app),
+    self_metrics: app.state::<SelfMetricsState>().snapshot(),
+  };
+
+  let file = File::create(dest).map_err(|e| format!("无法创建诊断包: {e}"))?;
+  let mut zip = ZipWriter::new(file);
+  let options = SimpleFileOptions::default()
+    .compression_method(CompressionMethod::Deflated)
+    .large_file(true);
+  let write = |zip: &mut ZipWriter<File>| -> zip::result::ZipResult<()> {
+    add_json(zip, "system.json", &info, options)?;
+    add_json(zip, "settings.json", &settings, options)?;
+    add_json(zip, "capabilities.json", &capabilities::detect(), options)?;
+    for path in &logs {
+      let Ok(relative) = path.strip_prefix(base_dir) else {
+        continue;
+      };
+      let name: Vec<String> = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect();
+      let text = fs::read(path)?;
+      zip.start_file(format!("logs/{}", name.join("/")), options)?;
+      zip.write_all(redact_log(&String::from_utf8_lossy(&text), &secrets).as_bytes())?;
+    }
+    Ok(())
+  };
+  write(&mut zip)
+    .and_then(|_| zip.finish().map(drop))
+    .map_err(|e| format!("写入诊断包失败: {e}"))?;
+  Ok(DiagnosticsReport {
+    path: dest.to_string_lossy().to_string(),
+    log_files: logs.len(),
+  })
+}
+
+fn add_json<T: Serialize>(
+  zip: &mut ZipWriter<File>,
+  name: &str,
+  value: &T,
+  options: SimpleFileOptions,
+) -> zip::result::ZipResult<()> {
+  let data = serde_json::to_vec_pretty(value).map_err(io::Error::other)?;
+  zip.start_file(name, options)?;
+  zip.write_all(&data)?;
+  Ok(())
+}
+
+/// Blanks credential values, and the user info of any URL (proxy and relay URLs, SNMP
+/// communities). Under a secret key, every string below is blanked, e.g. all headers. What was
+/// blanked is collected in `found`, to be blanked in the logs too.
+fn redact(value: &mut Value, secret: bool, found: &mut Vec<String>) {
+  match value {
+    Value::Object(map) => {
+      for (key, value) in map.iter_mut() {
+        let key = key.to_ascii_lowercase();
+        let secret = secret || key == "headers" || SECRET_KEYS.iter().any(|part| key.contains(part));
+        redact(value, secret, found);
+      }
+    }
+    Value::Array(items) => items.iter_mut().for_each(|item| redact(item, secret, found)),
+    Value::String(text) if secret && !text.is_empty() => found.push(std::mem::replace(text, REDACTED.to_string())),
+    Value::String(text) => {
+      if let Ok(mut url) = Url::parse(text) {
+        if !url.username().is_empty() || url.password().is_some() {
+          found.push(url.username().to_string());
+          found.extend(url.password().map(str::to_string));
+          let _ = url.set_username(REDACTED);
+          let _ = url.set_password(None);
+          *text = url.to_string();
+        }
+      }
+    }
+    _ => {}
+  }
+}
+
+/// Blanks the user info of every URL in a log file, e.g. `snmp://***@host/oid`, then every
+/// credential in `secrets`, longest first.
+fn redact_log(text: &str, secrets: &[String]) -> String {
+  let mut redacted = String::with_capacity(text.len());
+  let mut rest = text;
+  while let Some(at) = rest.find("://") {
+    let (head, tail) = rest.split_at(at + 3);
+    redacted.push_str(head);
+    let end = tail
+      .find(|c: char| c.is_whitespace() || matches!(c, '/' | '?' | '#' | ')' | '|'))
+      .unwrap_or(tail.len());
+    rest = match tail[..end].rfind('@') {
+      Some(user_end) => {
+        redacted.push_str(REDACTED);
+        &tail[user_end..]
+      }
+      None => tail,
+    };
+  }
+  redacted.push_str(rest);
+  secrets
+    .iter()
+    .fold(redacted, |text, secret| text.replace(secret.as_str(), REDACTED))
+}
@@ -0,0 +1,61 @@
+// File and folder selection for commands that read or write files the user picks. A frontend
+// that already asked (e.g. with the dialog plugin) passes the path in; otherwise a native dialog
+// is shown and awaited, so no runtime thread sits blocked while it is open. The file work itself
+// runs with `run_io` on the blocking pool, apart from the dialog.
+
+use std::path::PathBuf;
+
+use rfd::AsyncFileDialog;
+
+use crate::error::AppError;
+
+/// `None` when the dialog was cancelled.
+pub async fn save_file(given: Option<String>, dialog: AsyncFileDialog) -> Option<PathBuf> {
+  match given_path(given) {
+    Some(path) => Some(path),
+    None => dialog.save_file().await.map(|file| file.path().to_path_buf()),
+  }
+}
+
+pub async fn pick_file(given: Option<String>, dialog: AsyncFileDialog) -> Option<PathBuf> {
+  match given_path(given) {
+    Some(path) => Some(path),
+    None => dialog.pick_file().await.map(|file| file.path().to_path_buf()),
+  }
+}
+
+pub async fn pick_files(given: Option<Vec<String>>, dialog: AsyncFileDialog) -> Option<Vec<PathBuf>> {
+  let given: Vec<PathBuf> = given
+    .unwrap_or_default()
+    .into_iter()
+    .filter_map(|path| given_path(Some(path)))
+    .collect();
+  if !given.is_empty() {
+    return Some(given);
+  }
+  let files = dialog.pick_files().await?;
+  Some(files.iter().map(|file| file.path().to_path_buf()).collect())
+}
+
+pub async fn pick_folder(given: Option<String>, dialog: AsyncFileDialog) -> Option<PathBuf> {
+  match given_path(given) {
+    Some(path) => Some(path),
+    None => dialog.pick_folder().await.map(|folder| folder.path().to_path_buf()),
+  }
+}
+
+fn given_path(given: Option<String>) -> Option<PathBuf> {
+  given
+    .map(|path| path.trim().to_string())
+    .filter(|path| !path.is_empty())
+    .map(PathBuf::from)
+}
+
+/// Runs the file work of a command on the blocking pool.
+pub async fn run_io<T: Send + 'static>(
+  work: impl FnOnce() -> Result<T, AppError> + Send + 'static,
+) -> Result<T, AppError> {
+  tauri::async_runtime::spawn_blocking(work)
+    .await
+    .map_err(|_| AppError::cancelled("文件操作被取消"))?
+}
@@ -0,0 +1,107 @@
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::lock::LockExt;
+use crate::{load_settings, send_alert_email};
+
+const TICK: Duration = Duration::from_secs(60);
+const MAX_ENTRIES: usize = 500;
+
+/// Batches non-critical events (short blips, degradations) into one periodic email.
+/// Outages are never digested; they alert immediately.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct DigestSettings {
+  #[serde(default)]
+  pub enabled: bool,
+  #[serde(default = "default_digest_interval")]
+  pub interval_hours: u64,
+}
+
+impl Default for DigestSettings {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      interval_hours: default_digest_interval(),
+    }
+  }
+}
+
+fn default_digest_interval() -> u64 {
+  4
+}
+
+pub fn validate(settings: &DigestSettings) -> Result<(), String> {
+  if settings.enabled && settings.interval_hours == 0 {
+    return Err("摘要间隔必须大于 0".to_string());
+  }
+  Ok(())
+}
+
+struct DigestEntry {
+  timestamp: String,
+  message: String,
+}
+
+#[derive(Default)]
+pub struct DigestState {
+  entries: Mutex<Vec<DigestEntry>>,
+}
+
+impl DigestState {
+  pub fn push(&self, timestamp: &str, message: &str) {
+    let mut entries = self.entries.lock_or_recover();
+    if entries.len() >= MAX_ENTRIES {
+      entries.remove(0);
+    }
+    entries.push(DigestEntry {
+      timestamp: timestamp.to_string(),
+      message: message.to_string(),
+    });
+  }
+
+  fn take(&self) -> Vec<DigestEntry> {
+    std::mem::take(&mut *self.entries.lock_or_recover())
+  }
+}
+
+/// Queues the event for the next digest when digest mode is on; returns false otherwise
+/// so the caller can alert right away.
+pub fn queue(app: &AppHandle, timestamp: &str, message: &str) -> bool {
+  if !load_settings(app).digest.enabled {
+    return false;
+  }
+  app.state::<DigestState>().push(timestamp, message);
+  true
+}
+
+pub fn spawn_flusher(app: AppHandle) {
+  thread::spawn(move || {
+    let mut last_flush = Instant::now();
+    loop {
+      thread::sleep(TICK);
+      let settings = load_settings(&app);
+      let interval = Duration::from_secs(settings.digest.interval_hours.saturating_mul(3600));
+      // Turning digest mode off sends whatever was still queued.
+      if settings.digest.enabled && last_flush.elapsed() < interval {
+        continue;
+      }
+      last_flush = Instant::now();
+      let entries = app.state::<DigestState>().take();
+      if entries.is_empty() {
+        continue;
+      }
+      let lines: Vec<String> = entries
+        .iter()
+        .map(|entry| format!("[{}] {}", entry.timestamp, entry.message))
+        .collect();
+      let body = format!("共 {} 条非紧急事件：<br><br>{}", entries.len(), lines.join("<br>"));
+      if let Err(err) = send_alert_email(&settings.smtp.with_proxy(&settings.proxy), "网络告警摘要", &body, None) {
+        eprintln!("failed to send digest email: {err}");
+      }
+    }
+  });
+}
@@ -0,0 +1,228 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::Local;
+use serde::Serialize;
+
+use crate::error::{AppError, ErrorKind};
+use crate::lock::LockExt;
+use crate::{append_line, minute_log_path, parse_rtt_ms, ping_once, PingEncoding};
+
+const MAX_SAMPLES: usize = 3600;
+
+pub struct DualWanRunner {
+  stop_tx: mpsc::Sender<()>,
+  join: thread::JoinHandle<()>,
+}
+
+#[derive(Default)]
+pub struct DualWanState {
+  inner: Mutex<Option<DualWanRunner>>,
+  data: Arc<Mutex<DualWanData>>,
+}
+
+#[derive(Default)]
+struct DualWanData {
+  address: String,
+  timestamps: VecDeque<String>,
+  links: [LinkSeries; 2],
+}
+
+#[derive(Default)]
+struct LinkSeries {
+  source: String,
+  rtts: VecDeque<Option<f64>>,
+}
+
+#[derive(Serialize)]
+pub struct LinkStats {
+  pub source: String,
+  pub sent: usize,
+  pub lost: usize,
+  pub loss_percent: f64,
+  pub avg_rtt_ms: Option<f64>,
+  pub min_rtt_ms: Option<f64>,
+  pub max_rtt_ms: Option<f64>,
+  pub jitter_ms: Option<f64>,
+}
+
+/// Comparison of both links plus aligned per-second series for a side-by-side chart.
+#[derive(Serialize)]
+pub struct DualWanReport {
+  pub running: bool,
+  pub address: String,
+  pub links: Vec<LinkStats>,
+  /// Source of the link with lower loss (then lower average RTT), if they differ.
+  pub better: Option<String>,
+  pub timestamps: Vec<String>,
+  pub series: Vec<Vec<Option<f64>>>,
+}
+
+impl DualWanState {
+  pub fn start(
+    &self,
+    base_dir: PathBuf,
+    address: String,
+    sources: [String; 2],
+    encoding: PingEncoding,
+  ) -> Result<(), AppError> {
+    let mut guard = self.inner.lock_or_recover();
+    if guard.is_some() {
+      return Err(AppError::new(ErrorKind::AlreadyRunning, "Dual-WAN comparison is already running"));
+    }
+
+    {
+      let mut data = self.data.lock_or_recover();
+      *data = DualWanData {
+        address: address.clone(),
+        timestamps: VecDeque::new(),
+        links: sources.clone().map(|source| LinkSeries {
+          source,
+          rtts: VecDeque::new(),
+        }),
+      };
+    }
+
+    let (stop_tx, stop_rx) = mpsc::channel();
+    let data = self.data.clone();
+    let join = thread::spawn(move || compare_loop(base_dir, address, sources, encoding, stop_rx, data));
+    *guard = Some(DualWanRunner { stop_tx, join });
+    Ok(())
+  }
+
+  pub fn stop(&self) -> Result<(), AppError> {
+    let mut guard = self.inner.lock_or_recover();
+    let runner = guard
+      .take()
+      .ok_or_else(|| AppError::new(ErrorKind::NotRunning, "Dual-WAN comparison is not running"))?;
+    let _ = runner.stop_tx.send(());
+    thread::spawn(move || {
+      let _ = runner.join.join();
+    });
+    Ok(())
+  }
+
+  pub fn report(&self, limit: usize) -> Result<DualWanReport, AppError> {
+    let running = self
+      .inner
+      .lock_or_recover()
+      .is_some();
+    let data = self.data.lock_or_recover();
+    let skip = data.timestamps.len().saturating_sub(limit);
+    let links: Vec<LinkStats> = data.links.iter().map(link_stats).collect();
+    let better = match (&links[0], &links[1]) {
+      (a, b) if a.sent == 0 || b.sent == 0 => None,
+      (a, b) => {
+        let key = |l: &LinkStats| (l.loss_percent, l.avg_rtt_ms.unwrap_or(f64::MAX));
+        match key(a).partial_cmp(&key(b)) {
+          Some(std::cmp::Ordering::Less) => Some(a.source.clone()),
+          Some(std::cmp::Ordering::Greater) => Some(b.source.clone()),
+          _ => None,
+        }
+      }
+    };
+    Ok(DualWanReport {
+      running,
+      address: data.address.clone(),
+      links,
+      better,
+      timestamps: data.timestamps.iter().skip(skip).cloned().collect(),
+      series: data
+        .links
+        .iter()
+        .map(|link| link.rtts.iter().skip(skip).copied().collect())
+        .collect(),
+    })
+  }
+}
+
+fn link_stats(link: &LinkSeries) -> LinkStats {
+  let rtts: Vec<f64> = link.rtts.iter().flatten().copied().collect();
+  let sent = link.rtts.len();
+  let lost = sent - rtts.len();
+  let avg = (!rtts.is_empty()).then(|| rtts.iter().sum::<f64>() / rtts.len() as f64);
+  let jitter = (rtts.len() > 1).then(|| {
+    rtts.windows(2).map(|w| (w[1] - w[0]).abs()).sum::<f64>() / (rtts.len() - 1) as f64
+  });
+  LinkStats {
+    source: link.source.clone(),
+    sent,
+    lost,
+    loss_percent: if sent == 0 { 0.0 } else { lost as f64 * 100.0 / sent as f64 },
+    avg_rtt_ms: avg,
+    min_rtt_ms: rtts.iter().copied().reduce(f64::min),
+    max_rtt_ms: rtts.iter().copied().reduce(f64::max),
+    jitter_ms: jitter,
+  }
+}
+
+fn compare_loop(
+  base_dir: PathBuf,
+  address: String,
+  sources: [String; 2],
+  encoding: PingEncoding,
+  stop_rx: mpsc::Receiver<()>,
+  data: Arc<Mutex<DualWanData>>,
+) {
+  loop {
+    if stop_rx.try_recv().is_ok() {
+      break;
+    }
+    let loop_start = Instant::now();
+    let now = Local::now();
+    let timestamp = now.format("%Y-%m-%d %H:%M:%S").to_string();
+
+    // Probe both links at the same moment so the samples are comparable.
+    let target = address.as_str();
+    let results = thread::scope(|scope| {
+      let handles = sources
+        .each_ref()
+        .map(|source| scope.spawn(move || ping_once(target, encoding, Some(source.as_str()))));
+      handles.map(|handle| {
+        handle
+          .join()
+          .unwrap_or_else(|_| Err("probe thread panicked".to_string()))
+      })
+    });
+
+    if let Ok(path) = minute_log_path(&base_dir, &now) {
+      for (source, result) in sources.iter().zip(&results) {
+        let text = match result {
+          Ok(line) => line.clone(),
+          Err(err) => format!("error: {err}"),
+        };
+        let line = format!("[{timestamp}] {address} via {source} | {text}\n");
+        if let Err(e) = append_line(&path, &line) {
+          eprintln!("failed to write log: {e}");
+        }
+      }
+    }
+
+    {
+      let mut data = data.lock_or_recover();
+      data.timestamps.push_back(timestamp);
+      for (link, result) in data.links.iter_mut().zip(&results) {
+        let rtt = match result {
+          // A reply without a parsable time still counts as delivered.
+          Ok(line) => Some(parse_rtt_ms(line).unwrap_or(0.0)),
+          Err(_) => None,
+        };
+        link.rtts.push_back(rtt);
+        while link.rtts.len() > MAX_SAMPLES {
+          link.rtts.pop_front();
+        }
+      }
+      while data.timestamps.len() > MAX_SAMPLES {
+        data.timestamps.pop_front();
+      }
+    }
+
+    let elapsed = loop_start.elapsed();
+    if elapsed < Duration::from_secs(1) && stop_rx.recv_timeout(Duration::from_secs(1) - elapsed).is_ok() {
+      break;
+    }
+  }
+}
@@ -0,0 +1,77 @@
+use std::fmt;
+
+use serde::Serialize;
+
+/// Lets the UI react to a failure without matching on message text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+  AlreadyRunning,
+  NotRunning,
+  /// A bad argument or setting; the UI should point the user at the input.
+  InvalidInput,
+  NotFound,
+  Io,
+  Network,
+  /// A local firewall or policy drops outbound ICMP, so ping targets can't be monitored.
+  IcmpBlocked,
+  Cancelled,
+  /// Viewer mode is on and the command would change something.
+  ReadOnly,
+  Other,
+}
+
+/// The error type of every command, serialized as `{ kind, message, details? }`.
+#[derive(Debug, Serialize)]
+pub struct AppError {
+  pub kind: ErrorKind,
+  pub message: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub details: Option<String>,
+}
+
+impl AppError {
+  pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+    Self {
+      kind,
+      message: message.into(),
+      details: None,
+    }
+  }
+
+  pub fn with_details(mut self, details: impl Into<String>) -> Self {
+    self.details = Some(details.into());
+    self
+  }
+
+  pub fn invalid_input(message: impl Into<String>) -> Self {
+    Self::new(ErrorKind::InvalidInput, message)
+  }
+
+  pub fn not_found(message: impl Into<String>) -> Self {
+    Self::new(ErrorKind::NotFound, message)
+  }
+
+  pub fn cancelled(message: impl Into<String>) -> Self {
+    Self::new(ErrorKind::Cancelled, message)
+  }
+}
+
+impl fmt::Display for AppError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(&self.message)
+  }
+}
+
+/// Helpers still report plain strings; those surface as `other`.
+impl From<String> for AppError {
+  fn from(message: String) -> Self {
+    Self::new(ErrorKind::Other, message)
+  }
+}
+
+impl From<std::io::Error> for AppError {
+  fn from(err: std::io::Error) -> Self {
+    Self::new(ErrorKind::Io, err.to_string())
+  }
+}
@@ -0,0 +1,52 @@
+// Routes backend events to windows. A window that never subscribes gets every event, as the
+// main window always has; once a window subscribes, it only gets the events it asked for, so a
+// detached chart or a settings window isn't flooded with ping lines it ignores.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::lock::LockExt;
+
+#[derive(Default)]
+pub struct EventSubscriptions {
+  /// Window label to subscribed event names.
+  windows: Mutex<HashMap<String, HashSet<String>>>,
+}
+
+impl EventSubscriptions {
+  pub fn subscribe(&self, window: &str, events: Vec<String>) {
+    self.windows.lock_or_recover().entry(window.to_string()).or_default().extend(events);
+  }
+
+  /// Drops the given events, or all of them; the window then receives nothing it hasn't
+  /// subscribed to again.
+  pub fn unsubscribe(&self, window: &str, events: Option<Vec<String>>) {
+    let mut windows = self.windows.lock_or_recover();
+    let subscribed = windows.entry(window.to_string()).or_default();
+    match events {
+      Some(events) => subscribed.retain(|event| !events.contains(event)),
+      None => subscribed.clear(),
+    }
+  }
+
+  /// Called when a window closes, so a reused label starts out receiving everything.
+  pub fn forget(&self, window: &str) {
+    self.windows.lock_or_recover().remove(window);
+  }
+
+  fn wants(&self, window: &str, event: &str) -> bool {
+    self.windows.lock_or_recover().get(window).is_none_or(|events| events.contains(event))
+  }
+}
+
+pub fn emit<S: Serialize + Clone>(app: &AppHandle, event: &str, payload: S) {
+  let subscriptions = app.state::<EventSubscriptions>();
+  for label in app.webview_windows().into_keys() {
+    if subscriptions.wants(&label, event) {
+      let _ = app.emit_to(label.as_str(), event, payload.clone());
+    }
+  }
+}
@@ -0,0 +1,243 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use chrono::{NaiveDate, NaiveDateTime, TimeDelta};
+use rust_xlsxwriter::{ColNum, Format, RowNum, Workbook, Worksheet, XlsxError};
+
+use crate::health;
+use crate::logfile::{self, FailureRun, FailureRuns, Sample};
+use crate::rollup::HourRollup;
+
+/// Excel's row limit, minus the header row.
+const MAX_SAMPLE_ROWS: usize = 1_048_575;
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+const SLA_COLUMNS: [&str; 13] = [
+  "目标",
+  "开始",
+  "结束",
+  "样本数",
+  "丢失",
+  "丢包率 %",
+  "平均延迟 ms",
+  "中断次数",
+  "中断时长 秒",
+  "可用率 %",
+  "测量时长 秒",
+  "测量覆盖 %",
+  "健康评分（最后 24 小时）",
+];
+const ROLLUP_COLUMNS: [&str; 10] = [
+  "小时", "地址", "样本数", "丢失", "丢包率 %", "最小 ms", "平均 ms", "最大 ms", "P95 ms", "测量覆盖 %",
+];
+
+#[derive(Default)]
+struct TargetSla {
+  /// As last logged; an address logged under several labels is summarized once.
+  name: String,
+  first: Option<NaiveDateTime>,
+  last: Option<NaiveDateTime>,
+  samples: u64,
+  lost: u64,
+  rtt_sum: f64,
+  rtt_count: u64,
+  outages: u32,
+  outage_secs: i64,
+  /// From the hourly rollups, counting the wall-clock span for those written before it was
+  /// tracked; `None` without rollups.
+  measured_secs: Option<i64>,
+}
+
+/// The days and target a report covers.
+pub struct ReportScope<'a> {
+  pub base_dir: &'a Path,
+  pub from: NaiveDate,
+  pub to: NaiveDate,
+  pub address: Option<&'a str>,
+}
+
+/// Writes the raw samples, hourly rollups, outage list and an SLA summary as four sheets. The
+/// samples are read from the logs twice, once for the summary and once for the raw sheet, rather
+/// than held in memory for a range that may span a year. Outages are runs of at least
+/// `outage_failures` failures, the threshold the health score uses as well.
+pub fn write_report(
+  path: &Path,
+  scope: &ReportScope,
+  rollups: &[HourRollup],
+  outage_failures: u32,
+) -> Result<(), String> {
+  build(path, scope, rollups, outage_failures).map_err(|e| format!("写入 Excel 失败: {e}"))
+}
+
+fn build(path: &Path, scope: &ReportScope, rollups: &[HourRollup], outage_failures: u32) -> Result<(), XlsxError> {
+  let header = Format::new().set_bold();
+  let mut workbook = Workbook::new();
+
+  let mut total_samples = 0usize;
+  let mut targets: BTreeMap<String, TargetSla> = BTreeMap::new();
+  let mut runs = FailureRuns::new(outage_failures);
+  logfile::for_each_sample(scope.base_dir, scope.from, scope.to, scope.address, |sample| {
+    total_samples += 1;
+    runs.push(&sample);
+    add_sample(&mut targets, &sample);
+  });
+  let outages = runs.finish();
+  summarize(&mut targets, rollups, &outages);
+
+  let sheet = workbook.add_worksheet().set_name("SLA 汇总")?;
+  write_header(sheet, &header, &SLA_COLUMNS)?;
+  let mut row: RowNum = 1;
+  for (address, sla) in targets {
+    let (Some(first), Some(last)) = (sla.first, sla.last) else {
+      continue;
+    };
+    let span_secs = (last - first).num_seconds() + 1;
+    sheet.write_string(row, 0, &sla.name)?;
+    sheet.write_string(row, 1, first.format(TIME_FORMAT).to_string())?;
+    sheet.write_string(row, 2, last.format(TIME_FORMAT).to_string())?;
+    sheet.write_number(row, 3, sla.samples as f64)?;
+    sheet.write_number(row, 4, sla.lost as f64)?;
+    sheet.write_number(row, 5, sla.lost as f64 * 100.0 / sla.samples as f64)?;
+    write_optional(sheet, row, 6, (sla.rtt_count > 0).then(|| sla.rtt_sum / sla.rtt_count as f64))?;
+    sheet.write_number(row, 7, sla.outages)?;
+    sheet.write_number(row, 8, sla.outage_secs as f64)?;
+    // Availability over the time actually measured, so gaps in monitoring don't count as
+    // uptime; over the whole span for logs without rollups.
+    let measured_secs = sla.measured_secs.map(|secs| secs.min(span_secs)).filter(|secs| *secs > 0);
+    let basis = measured_secs.unwrap_or(span_secs);
+    sheet.write_number(row, 9, (basis - sla.outage_secs).max(0) as f64 * 100.0 / basis as f64)?;
+    write_optional(sheet, row, 10, measured_secs.map(|secs| secs as f64))?;
+    write_optional(sheet, row, 11, measured_secs.map(|secs| secs as f64 * 100.0 / span_secs as f64))?;
+    // Only the last day is read again, not the whole range.
+    let window_from = last - TimeDelta::hours(health::WINDOW_HOURS);
+    let mut window = logfile::load_window(scope.base_dir, window_from, last, &address);
+    window.retain(|sample| sample.timestamp >= first);
+    let health = health::score(&window, &address, None, outage_failures);
+    write_optional(sheet, row, 12, health.map(|health| f64::from(health.score)))?;
+    row += 1;
+  }
+  if total_samples > MAX_SAMPLE_ROWS {
+    sheet.write_string(
+      row + 1,
+      0,
+      format!("原始样本共 {total_samples} 条，超过 Excel 行数上限，仅导出前 {MAX_SAMPLE_ROWS} 条"),
+    )?;
+  }
+
+  let sheet = workbook.add_worksheet().set_name("中断列表")?;
+  write_header(sheet, &header, &["目标", "开始", "结束", "持续 秒", "失败次数"])?;
+  for (row, outage) in (1..).zip(&outages) {
+    sheet.write_string(row, 0, &outage.target)?;
+    sheet.write_string(row, 1, outage.started.format(TIME_FORMAT).to_string())?;
+    sheet.write_string(row, 2, outage.ended.format(TIME_FORMAT).to_string())?;
+    sheet.write_number(row, 3, (outage.ended - outage.started).num_seconds() as f64)?;
+    sheet.write_number(row, 4, outage.failures)?;
+  }
+
+  let sheet = workbook.add_worksheet().set_name("小时汇总")?;
+  write_header(sheet, &header, &ROLLUP_COLUMNS)?;
+  for (row, rollup) in (1..).zip(rollups) {
+    sheet.write_string(row, 0, &rollup.hour)?;
+    sheet.write_string(row, 1, &rollup.address)?;
+    sheet.write_number(row, 2, rollup.samples as f64)?;
+    sheet.write_number(row, 3, rollup.lost as f64)?;
+    sheet.write_number(row, 4, rollup.loss_percent)?;
+    write_optional(sheet, row, 5, rollup.min_rtt_ms)?;
+    write_optional(sheet, row, 6, rollup.avg_rtt_ms)?;
+    write_optional(sheet, row, 7, rollup.max_rtt_ms)?;
+    write_optional(sheet, row, 8, rollup.p95_rtt_ms)?;
+    write_optional(sheet, row, 9, rollup.completeness_percent)?;
+  }
+
+  // Raw samples can run into millions of rows; stream them instead of keeping cells in memory.
+  let sheet = workbook.add_worksheet_with_constant_memory().set_name("原始样本")?;
+  write_header(sheet, &header, &["时间", "目标", "结果", "延迟 ms", "详情"])?;
+  let mut row: RowNum = 1;
+  let mut written = Ok(());
+  logfile::for_each_sample(scope.base_dir, scope.from, scope.to, scope.address, |sample| {
+    if written.is_ok() && row as usize <= MAX_SAMPLE_ROWS {
+      written = write_sample(sheet, row, &sample);
+      row += 1;
+    }
+  });
+  written?;
+
+  workbook.save(path)
+}
+
+fn write_sample(sheet: &mut Worksheet, row: RowNum, sample: &Sample) -> Result<(), XlsxError> {
+  sheet.write_string(row, 0, sample.timestamp.format(TIME_FORMAT).to_string())?;
+  sheet.write_string(row, 1, &sample.target)?;
+  sheet.write_string(row, 2, if sample.success { "成功" } else { "失败" })?;
+  write_optional(sheet, row, 3, sample.rtt_ms)?;
+  sheet.write_string(row, 4, &sample.detail)?;
+  Ok(())
+}
+
+fn write_header(sheet: &mut Worksheet, format: &Format, titles: &[&str]) -> Result<(), XlsxError> {
+  sheet.write_row_with_format(0, 0, titles.iter().copied(), format)?;
+  sheet.set_freeze_panes(1, 0)?;
+  for col in 0..titles.len() as ColNum {
+    sheet.set_column_width(col, 18)?;
+  }
+  Ok(())
+}
+
+fn write_optional(sheet: &mut Worksheet, row: RowNum, col: ColNum, value: Option<f64>) -> Result<(), XlsxError> {
+  if let Some(value) = value {
+    sheet.write_number(row, col, value)?;
+  }
+  Ok(())
+}
+
+/// Counts a sample into the summary of its address. Samples come in time order.
+fn add_sample(targets: &mut BTreeMap<String, TargetSla>, sample: &Sample) {
+  let address = logfile::address_of(&sample.target);
+  let sla = match targets.get_mut(address) {
+    Some(sla) => sla,
+    None => targets.entry(address.to_string()).or_default(),
+  };
+  sla.name.clone_from(&sample.target);
+  sla.first.get_or_insert(sample.timestamp);
+  sla.last = Some(sample.timestamp);
+  sla.samples += 1;
+  if !sample.success {
+    sla.lost += 1;
+  }
+  if let Some(rtt) = sample.rtt_ms {
+    sla.rtt_sum += rtt;
+    sla.rtt_count += 1;
+  }
+}
+
+/// Adds the outages and measured time to the per-address summaries built by `add_sample`.
+fn summarize(targets: &mut BTreeMap<String, TargetSla>, rollups: &[HourRollup], outages: &[FailureRun]) {
+  for outage in outages {
+    if let Some(sla) = targets.get_mut(logfile::address_of(&outage.target)) {
+      sla.outages += 1;
+      sla.outage_secs += (outage.ended - outage.started).num_seconds();
+    }
+  }
+  for (address, sla) in targets.iter_mut() {
+    let (Some(first), Some(last)) = (sla.first, sla.last) else {
+      continue;
+    };
+    for rollup in rollups.iter().filter(|rollup| rollup.address == *address) {
+      let secs = match rollup.measured_total_secs() {
+        Some(secs) => secs as i64,
+        None => wall_clock_secs(&rollup.hour, first, last),
+      };
+      *sla.measured_secs.get_or_insert(0) += secs;
+    }
+  }
+}
+
+/// The part of the rollup hour `hour` between the first and last sample.
+fn wall_clock_secs(hour: &str, first: NaiveDateTime, last: NaiveDateTime) -> i64 {
+  let Ok(start) = NaiveDateTime::parse_from_str(hour, "%Y-%m-%d %H:%M") else {
+    return 0;
+  };
+  let from = start.max(first);
+  let to = (start + TimeDelta::hours(1)).min(last + TimeDelta::seconds(1));
+  (to - from).num_seconds().max(0)
+}
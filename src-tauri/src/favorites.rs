@@ -0,0 +1,41 @@
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use crate::targets::TargetConfig;
+
+/// A pinned target. `target` is a snapshot of its per-target options, restored on quick start.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Favorite {
+  pub id: String,
+  #[serde(default)]
+  pub name: String,
+  pub target: TargetConfig,
+}
+
+pub fn new_favorite_id() -> String {
+  format!("fav-{}", Local::now().format("%Y%m%d%H%M%S%3f"))
+}
+
+pub fn find<'a>(favorites: &'a [Favorite], id: &str) -> Result<&'a Favorite, String> {
+  favorites
+    .iter()
+    .find(|favorite| favorite.id == id)
+    .ok_or_else(|| format!("收藏 {id} 不存在"))
+}
+
+/// `ids` must list every favorite exactly once, in the new order.
+pub fn reorder(favorites: &mut Vec<Favorite>, ids: &[String]) -> Result<(), String> {
+  if ids.len() != favorites.len() {
+    return Err("排序列表与收藏数量不一致".to_string());
+  }
+  let mut reordered = Vec::with_capacity(favorites.len());
+  for id in ids {
+    let index = favorites
+      .iter()
+      .position(|favorite| &favorite.id == id)
+      .ok_or_else(|| format!("收藏 {id} 不存在或重复"))?;
+    reordered.push(favorites.remove(index));
+  }
+  *favorites = reordered;
+  Ok(())
+}
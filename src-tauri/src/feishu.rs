@@ -0,0 +1,113 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+
+use crate::proxy;
+
+/// Feishu (Lark) custom bot. The secret is only needed when signature verification is enabled on the bot.
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct FeishuSettings {
+  #[serde(default)]
+  pub enabled: bool,
+  /// `https://open.feishu.cn/open-apis/bot/v2/hook/...` (or the larksuite.com equivalent).
+  #[serde(default)]
+  pub webhook_url: String,
+  #[serde(default)]
+  pub secret: String,
+  /// Overrides the global alert proxy; an empty string connects directly.
+  #[serde(default)]
+  pub proxy: Option<String>,
+}
+
+#[derive(Clone, Copy)]
+pub enum CardColor {
+  Red,
+  Orange,
+  Green,
+  Blue,
+}
+
+impl CardColor {
+  fn template(self) -> &'static str {
+    match self {
+      CardColor::Red => "red",
+      CardColor::Orange => "orange",
+      CardColor::Green => "green",
+      CardColor::Blue => "blue",
+    }
+  }
+}
+
+pub fn validate(settings: &FeishuSettings) -> Result<(), String> {
+  if !settings.enabled {
+    return Ok(());
+  }
+  if !settings.webhook_url.trim().starts_with("https://") {
+    return Err("飞书机器人地址必须以 https:// 开头".to_string());
+  }
+  if let Some(proxy) = &settings.proxy {
+    proxy::validate_url(proxy)?;
+  }
+  Ok(())
+}
+
+/// Interactive card payload before signing; `lines` become separate lark_md paragraphs.
+pub fn card(title: &str, color: CardColor, lines: &[String]) -> Value {
+  let elements: Vec<Value> = lines
+    .iter()
+    .map(|line| json!({ "tag": "div", "text": { "tag": "lark_md", "content": line } }))
+    .collect();
+  json!({
+    "msg_type": "interactive",
+    "card": {
+      "header": {
+        "title": { "tag": "plain_text", "content": title },
+        "template": color.template(),
+      },
+      "elements": elements,
+    },
+  })
+}
+
+pub fn send_card(settings: &FeishuSettings, title: &str, color: CardColor, lines: &[String]) -> Result<(), String> {
+  let mut body = card(title, color, lines);
+
+  let secret = settings.secret.trim();
+  if !secret.is_empty() {
+    let timestamp = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map(|d| d.as_secs())
+      .unwrap_or_default();
+    body["timestamp"] = json!(timestamp.to_string());
+    body["sign"] = json!(sign(timestamp, secret)?);
+  }
+
+  let response: Value = proxy::client(settings.proxy.as_deref(), Duration::from_secs(10))?
+    .post(settings.webhook_url.trim())
+    .json(&body)
+    .send()
+    .map_err(|e| format!("飞书请求失败: {e}"))?
+    .json()
+    .map_err(|e| format!("飞书响应无效: {e}"))?;
+  // Older bots answer with StatusCode, newer ones with code.
+  let code = response["code"].as_i64().or_else(|| response["StatusCode"].as_i64()).unwrap_or(0);
+  if code == 0 {
+    Ok(())
+  } else {
+    Err(format!(
+      "飞书发送失败 ({code}): {}",
+      response["msg"].as_str().or_else(|| response["StatusMessage"].as_str()).unwrap_or("unknown error")
+    ))
+  }
+}
+
+/// Feishu signs with `timestamp + "\n" + secret` as the HMAC-SHA256 key over an empty message.
+fn sign(timestamp: u64, secret: &str) -> Result<String, String> {
+  let key = format!("{timestamp}\n{secret}");
+  let mac = Hmac::<Sha256>::new_from_slice(key.as_bytes()).map_err(|e| e.to_string())?;
+  Ok(base64::engine::general_purpose::STANDARD.encode(mac.finalize().into_bytes()))
+}
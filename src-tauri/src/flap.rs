@@ -0,0 +1,211 @@
+// Flap detection. A target that keeps dropping and coming back raises an outage and a recovery
+// alert for every bounce, which buries everything else and teaches people to ignore the pager.
+// Once its outages in the last hour reach the threshold it counts as flapping: one notification
+// says so, further outages and recoveries only go to the log and the UI, and a summary goes out
+// every `summary_minutes` until the outage rate has dropped below half the threshold.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::alerts::{AlertKind, AlertSeverity};
+use crate::format_duration;
+
+const WINDOW: Duration = Duration::from_secs(3600);
+const MAX_OUTAGES_PER_HOUR: u32 = 60;
+const MAX_SUMMARY_MINUTES: u64 = 24 * 60;
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct FlapSettings {
+  #[serde(default = "default_enabled")]
+  pub enabled: bool,
+  /// Outages within an hour that make a target count as flapping.
+  #[serde(default = "default_outages_per_hour")]
+  pub outages_per_hour: u32,
+  /// How often a flapping target's outages are summarized.
+  #[serde(default = "default_summary_minutes")]
+  pub summary_minutes: u64,
+}
+
+impl Default for FlapSettings {
+  fn default() -> Self {
+    Self {
+      enabled: default_enabled(),
+      outages_per_hour: default_outages_per_hour(),
+      summary_minutes: default_summary_minutes(),
+    }
+  }
+}
+
+fn default_enabled() -> bool {
+  true
+}
+
+fn default_outages_per_hour() -> u32 {
+  5
+}
+
+fn default_summary_minutes() -> u64 {
+  60
+}
+
+pub fn validate(settings: &FlapSettings) -> Result<(), String> {
+  if !(2..=MAX_OUTAGES_PER_HOUR).contains(&settings.outages_per_hour) {
+    return Err(format!("抖动判定的每小时中断次数应在 2-{MAX_OUTAGES_PER_HOUR} 之间"));
+  }
+  if !(5..=MAX_SUMMARY_MINUTES).contains(&settings.summary_minutes) {
+    return Err(format!("抖动汇总间隔应在 5-{MAX_SUMMARY_MINUTES} 分钟之间"));
+  }
+  Ok(())
+}
+
+/// Sent as `flap-state` when a target starts or stops flapping.
+#[derive(Clone, Serialize)]
+pub struct FlapState {
+  pub address: String,
+  pub timestamp: String,
+  pub flapping: bool,
+  pub outages_last_hour: u32,
+}
+
+pub enum FlapChange {
+  Started { outages: u32 },
+  Summary { outages: u32, minutes: u64 },
+  Ended { outages: u32, lasted: Duration },
+}
+
+impl FlapChange {
+  pub fn kind(&self) -> AlertKind {
+    match self {
+      FlapChange::Started { .. } => AlertKind::FlappingStarted,
+      FlapChange::Summary { .. } => AlertKind::FlappingSummary,
+      FlapChange::Ended { .. } => AlertKind::FlappingEnded,
+    }
+  }
+
+  pub fn severity(&self) -> AlertSeverity {
+    match self {
+      FlapChange::Started { .. } => AlertSeverity::Warning,
+      _ => AlertSeverity::Info,
+    }
+  }
+
+  pub fn subject(&self) -> &'static str {
+    match self {
+      FlapChange::Started { .. } => "目标频繁中断",
+      FlapChange::Summary { .. } => "抖动汇总",
+      FlapChange::Ended { .. } => "目标已稳定",
+    }
+  }
+
+  /// The `{event}` of an SMS, as in `网络{event}`.
+  pub fn sms_event(&self) -> &'static str {
+    match self {
+      FlapChange::Started { .. } => "频繁中断",
+      FlapChange::Summary { .. } => "持续抖动",
+      FlapChange::Ended { .. } => "恢复稳定",
+    }
+  }
+
+  pub fn message(&self, target_name: &str, summary_minutes: u64) -> String {
+    match self {
+      FlapChange::Started { outages } => format!(
+        "{target_name} 频繁中断，过去一小时中断 {outages} 次，已进入抖动模式：之后的中断和恢复只记录不逐条告警，\
+         每 {summary_minutes} 分钟汇总一次"
+      ),
+      FlapChange::Summary { outages, minutes } => {
+        format!("{target_name} 仍在抖动，过去 {minutes} 分钟中断 {outages} 次")
+      }
+      FlapChange::Ended { outages, lasted } => format!(
+        "{target_name} 已趋于稳定，退出抖动模式（持续 {}，期间中断 {outages} 次），恢复逐条告警",
+        format_duration(*lasted)
+      ),
+    }
+  }
+}
+
+pub struct FlapDetector {
+  settings: FlapSettings,
+  outages: VecDeque<Instant>,
+  flapping_since: Option<Instant>,
+  last_summary: Instant,
+  since_summary: u32,
+  total: u32,
+}
+
+impl FlapDetector {
+  pub fn new(settings: FlapSettings) -> Self {
+    Self {
+      settings,
+      outages: VecDeque::new(),
+      flapping_since: None,
+      last_summary: Instant::now(),
+      since_summary: 0,
+      total: 0,
+    }
+  }
+
+  pub fn is_flapping(&self) -> bool {
+    self.flapping_since.is_some()
+  }
+
+  pub fn summary_minutes(&self) -> u64 {
+    self.settings.summary_minutes
+  }
+
+  pub fn outages_last_hour(&self) -> u32 {
+    self.outages.len() as u32
+  }
+
+  fn prune(&mut self, now: Instant) {
+    while self.outages.front().is_some_and(|at| now.duration_since(*at) > WINDOW) {
+      self.outages.pop_front();
+    }
+  }
+
+  /// Counts a confirmed outage; returns `Started` when it makes the target flap.
+  pub fn record_outage(&mut self, now: Instant) -> Option<FlapChange> {
+    self.outages.push_back(now);
+    self.prune(now);
+    if self.is_flapping() {
+      self.since_summary += 1;
+      self.total += 1;
+      return None;
+    }
+    let outages = self.outages_last_hour();
+    if !self.settings.enabled || outages < self.settings.outages_per_hour {
+      return None;
+    }
+    self.flapping_since = Some(now);
+    self.last_summary = now;
+    self.since_summary = 0;
+    self.total = outages;
+    Some(FlapChange::Started { outages })
+  }
+
+  /// Called every cycle: a summary when one is due, or `Ended` once the target has calmed
+  /// down and isn't in an outage.
+  pub fn tick(&mut self, now: Instant, outage_open: bool) -> Option<FlapChange> {
+    self.prune(now);
+    let since = self.flapping_since?;
+    let calm = self.outages_last_hour() < self.settings.outages_per_hour.div_ceil(2);
+    if !outage_open && calm {
+      self.flapping_since = None;
+      return Some(FlapChange::Ended {
+        outages: self.total,
+        lasted: now.duration_since(since),
+      });
+    }
+    let interval = Duration::from_secs(self.settings.summary_minutes.saturating_mul(60));
+    if now.duration_since(self.last_summary) < interval {
+      return None;
+    }
+    self.last_summary = now;
+    let outages = std::mem::take(&mut self.since_summary);
+    (outages > 0).then_some(FlapChange::Summary {
+      outages,
+      minutes: self.settings.summary_minutes,
+    })
+  }
+}
@@ -0,0 +1,157 @@
+// One 0-100 number per target for people who don't read latency charts. It is built from the
+// last 24 hours of probe results: packet loss (40 points), latency against the baseline (25),
+// jitter (20) and short failure bursts that stayed below the outage threshold (15). Each part
+// scores full marks up to a level nobody would notice and falls linearly to zero at a level
+// that clearly hurts calls and remote desktops.
+
+use chrono::{NaiveDateTime, TimeDelta};
+use serde::Serialize;
+
+use crate::logfile::{self, Sample};
+use crate::rollup::percentile;
+
+pub const WINDOW_HOURS: i64 = 24;
+
+const LOSS_POINTS: f64 = 40.0;
+const LATENCY_POINTS: f64 = 25.0;
+const JITTER_POINTS: f64 = 20.0;
+const BLIP_POINTS: f64 = 15.0;
+
+#[derive(Clone, Serialize)]
+pub struct HealthParts {
+  pub loss: f64,
+  pub latency: f64,
+  pub jitter: f64,
+  pub blips: f64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct HealthScore {
+  pub address: String,
+  pub score: u8,
+  /// 良好, 一般 or 较差.
+  pub grade: &'static str,
+  pub from: String,
+  pub to: String,
+  pub samples: u64,
+  pub loss_percent: f64,
+  /// Median RTT of the window, or the session's learned baseline when there is one.
+  pub baseline_ms: Option<f64>,
+  /// Average RTT of the last hour of the window.
+  pub recent_avg_ms: Option<f64>,
+  /// Mean difference between consecutive RTTs.
+  pub jitter_ms: Option<f64>,
+  pub blips: u32,
+  pub parts: HealthParts,
+}
+
+impl HealthScore {
+  pub fn to_text(&self) -> String {
+    format!(
+      "健康评分（最近 {WINDOW_HOURS} 小时）: {}/100（{}），丢包 {:.2}%，抖动 {}，短暂丢包 {} 次",
+      self.score,
+      self.grade,
+      self.loss_percent,
+      self.jitter_ms.map_or("-".to_string(), |ms| format!("{ms:.1} ms")),
+      self.blips
+    )
+  }
+}
+
+/// Full marks at or below `good`, none at or beyond `bad`, linear in between.
+fn scale(value: f64, good: f64, bad: f64, points: f64) -> f64 {
+  (points * (bad - value) / (bad - good)).clamp(0.0, points)
+}
+
+/// Scores the samples of `address` in the `WINDOW_HOURS` ending at the newest one. Failure runs
+/// shorter than `outage_failures` count as blips. `None` when there are no samples.
+pub fn score(samples: &[Sample], address: &str, baseline_ms: Option<f64>, outage_failures: u32) -> Option<HealthScore> {
+  let to = samples
+    .iter()
+    .filter(|sample| logfile::matches_address(&sample.target, address))
+    .map(|sample| sample.timestamp)
+    .max()?;
+  let from = to - TimeDelta::hours(WINDOW_HOURS);
+  let window: Vec<Sample> = samples
+    .iter()
+    .filter(|sample| sample.timestamp > from && logfile::matches_address(&sample.target, address))
+    .cloned()
+    .collect();
+  Some(score_window(&window, address, from, to, baseline_ms, outage_failures))
+}
+
+fn score_window(
+  samples: &[Sample],
+  address: &str,
+  from: NaiveDateTime,
+  to: NaiveDateTime,
+  baseline_ms: Option<f64>,
+  outage_failures: u32,
+) -> HealthScore {
+  let lost = samples.iter().filter(|sample| !sample.success).count();
+  let loss_percent = lost as f64 * 100.0 / samples.len().max(1) as f64;
+
+  let rtts: Vec<f64> = samples.iter().filter_map(|sample| sample.rtt_ms).collect();
+  let mut sorted = rtts.clone();
+  sorted.sort_by(f64::total_cmp);
+  let baseline_ms = baseline_ms.or_else(|| percentile(&sorted, 0.5));
+  let recent_from = to - TimeDelta::hours(1);
+  let recent: Vec<f64> = samples
+    .iter()
+    .filter(|sample| sample.timestamp > recent_from)
+    .filter_map(|sample| sample.rtt_ms)
+    .collect();
+  let recent_avg_ms = (!recent.is_empty()).then(|| recent.iter().sum::<f64>() / recent.len() as f64);
+  let jitter_ms = (rtts.len() >= 2)
+    .then(|| rtts.windows(2).map(|pair| (pair[1] - pair[0]).abs()).sum::<f64>() / (rtts.len() - 1) as f64);
+  let blips = logfile::failure_runs(samples, 1)
+    .iter()
+    .filter(|run| run.failures < outage_failures.max(1))
+    .count() as u32;
+
+  // Latency and jitter are judged relative to the baseline, with a floor so a 1 ms LAN target
+  // isn't marked down for 2 ms of noise.
+  let reference = baseline_ms.unwrap_or(0.0).max(10.0);
+  let parts = HealthParts {
+    loss: scale(loss_percent, 0.1, 5.0, LOSS_POINTS),
+    latency: match (recent_avg_ms, baseline_ms) {
+      (Some(recent), Some(baseline)) => scale(recent / baseline.max(1.0), 1.2, 3.0, LATENCY_POINTS),
+      // Nothing answered; loss already takes the points.
+      _ => {
+        if rtts.is_empty() {
+          0.0
+        } else {
+          LATENCY_POINTS
+        }
+      }
+    },
+    jitter: jitter_ms.map_or(if rtts.is_empty() { 0.0 } else { JITTER_POINTS }, |jitter| {
+      scale(jitter / reference, 0.1, 1.0, JITTER_POINTS)
+    }),
+    // A target that never answered has no blips, just one long outage.
+    blips: if lost == samples.len() {
+      0.0
+    } else {
+      scale(f64::from(blips), 0.0, WINDOW_HOURS as f64, BLIP_POINTS)
+    },
+  };
+  let score = (parts.loss + parts.latency + parts.jitter + parts.blips).round() as u8;
+  HealthScore {
+    address: address.to_string(),
+    score,
+    grade: match score {
+      80.. => "良好",
+      60.. => "一般",
+      _ => "较差",
+    },
+    from: from.format("%Y-%m-%d %H:%M:%S").to_string(),
+    to: to.format("%Y-%m-%d %H:%M:%S").to_string(),
+    samples: samples.len() as u64,
+    loss_percent,
+    baseline_ms,
+    recent_avg_ms,
+    jitter_ms,
+    blips,
+    parts,
+  }
+}
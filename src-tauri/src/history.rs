@@ -0,0 +1,83 @@
+use serde::{Deserialize, Serialize};
+
+use crate::stats::StatsReport;
+use crate::targets;
+
+const MAX_ENTRIES: usize = 50;
+
+/// A previously monitored address, most recent first in the settings file.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct HistoryEntry {
+  /// Without credentials (`targets::public_address`); `targets::with_credentials` finds them again.
+  pub address: String,
+  #[serde(default)]
+  pub label: String,
+  pub last_used: String,
+  #[serde(default)]
+  pub sessions: u32,
+  #[serde(default)]
+  pub last_session: Option<SessionSummary>,
+}
+
+/// Quick stats of the most recent session, enough to show next to the address.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct SessionSummary {
+  pub started: String,
+  pub ended: String,
+  pub sent: u64,
+  pub loss_percent: f64,
+  pub avg_rtt_ms: Option<f64>,
+  pub outages: u32,
+}
+
+pub fn record_start(history: &mut Vec<HistoryEntry>, address: &str, label: &str, now: &str) {
+  let address = targets::public_address(address);
+  // Entries written before addresses were stored without credentials still match.
+  let mut entry = match history.iter().position(|entry| targets::public_address(&entry.address) == address) {
+    Some(index) => history.remove(index),
+    None => HistoryEntry {
+      address: String::new(),
+      label: String::new(),
+      last_used: String::new(),
+      sessions: 0,
+      last_session: None,
+    },
+  };
+  entry.address = address.into_owned();
+  entry.label = label.to_string();
+  entry.last_used = now.to_string();
+  entry.sessions = entry.sessions.saturating_add(1);
+  history.insert(0, entry);
+  history.truncate(MAX_ENTRIES);
+}
+
+pub fn record_end(history: &mut [HistoryEntry], address: &str, summary: SessionSummary) {
+  let address = targets::public_address(address);
+  if let Some(entry) = history.iter_mut().find(|entry| entry.address == address) {
+    entry.last_session = Some(summary);
+  }
+}
+
+/// Totals over every day of the session; the average RTT is weighted by replies.
+pub fn summarize(report: &StatsReport, started: &str, ended: &str) -> SessionSummary {
+  let (mut sent, mut lost, mut outages) = (0u64, 0u64, 0u32);
+  let (mut rtt_sum, mut replies) = (0.0, 0u64);
+  for day in &report.days {
+    sent += day.sent;
+    lost += day.lost;
+    outages += day.outages;
+    if let Some(avg) = day.avg_rtt_ms {
+      let day_replies = day.sent - day.lost;
+      rtt_sum += avg * day_replies as f64;
+      replies += day_replies;
+    }
+  }
+  SessionSummary {
+    started: started.to_string(),
+    ended: ended.to_string(),
+    sent,
+    loss_percent: if sent == 0 { 0.0 } else { lost as f64 * 100.0 / sent as f64 },
+    avg_rtt_ms: (replies > 0).then(|| rtt_sum / replies as f64),
+    outages,
+  }
+}
@@ -0,0 +1,214 @@
+use std::collections::VecDeque;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+use native_tls::TlsConnector;
+use serde::Serialize;
+use url::Url;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_SAMPLES: usize = 3600;
+
+/// Per-phase timings of one HTTP probe, all in milliseconds.
+#[derive(Clone, Serialize)]
+pub struct HttpTiming {
+  pub timestamp: String,
+  pub status: Option<u16>,
+  pub dns_ms: f64,
+  pub connect_ms: Option<f64>,
+  pub tls_ms: Option<f64>,
+  pub ttfb_ms: Option<f64>,
+  pub total_ms: f64,
+  pub error: Option<String>,
+}
+
+#[derive(Clone, Default, Serialize)]
+pub struct PhaseStats {
+  pub avg_ms: Option<f64>,
+  pub max_ms: Option<f64>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct HttpTimingReport {
+  pub samples: Vec<HttpTiming>,
+  pub dns: PhaseStats,
+  pub connect: PhaseStats,
+  pub tls: PhaseStats,
+  pub ttfb: PhaseStats,
+  pub total: PhaseStats,
+}
+
+pub struct HttpTimingBuffer {
+  samples: VecDeque<HttpTiming>,
+}
+
+impl Default for HttpTimingBuffer {
+  fn default() -> Self {
+    Self {
+      samples: VecDeque::with_capacity(MAX_SAMPLES),
+    }
+  }
+}
+
+impl HttpTimingBuffer {
+  pub fn clear(&mut self) {
+    self.samples.clear();
+  }
+
+  pub fn push(&mut self, timing: HttpTiming) {
+    self.samples.push_back(timing);
+    while self.samples.len() > MAX_SAMPLES {
+      self.samples.pop_front();
+    }
+  }
+
+  pub fn report(&self, limit: usize) -> HttpTimingReport {
+    let skip = self.samples.len().saturating_sub(limit);
+    let samples: Vec<HttpTiming> = self.samples.iter().skip(skip).cloned().collect();
+    HttpTimingReport {
+      dns: phase_stats(samples.iter().map(|s| Some(s.dns_ms))),
+      connect: phase_stats(samples.iter().map(|s| s.connect_ms)),
+      tls: phase_stats(samples.iter().map(|s| s.tls_ms)),
+      ttfb: phase_stats(samples.iter().map(|s| s.ttfb_ms)),
+      total: phase_stats(samples.iter().map(|s| Some(s.total_ms))),
+      samples,
+    }
+  }
+}
+
+fn phase_stats(values: impl Iterator<Item = Option<f64>>) -> PhaseStats {
+  let values: Vec<f64> = values.flatten().collect();
+  if values.is_empty() {
+    return PhaseStats::default();
+  }
+  PhaseStats {
+    avg_ms: Some(values.iter().sum::<f64>() / values.len() as f64),
+    max_ms: values.iter().copied().reduce(f64::max),
+  }
+}
+
+pub fn is_http_target(address: &str) -> bool {
+  let lower = address.to_ascii_lowercase();
+  lower.starts_with("http://") || lower.starts_with("https://")
+}
+
+/// Runs one GET request and times each phase. A 5xx status counts as a failed probe.
+pub fn probe(address: &str, timestamp: &str) -> HttpTiming {
+  let start = Instant::now();
+  let mut timing = HttpTiming {
+    timestamp: timestamp.to_string(),
+    status: None,
+    dns_ms: 0.0,
+    connect_ms: None,
+    tls_ms: None,
+    ttfb_ms: None,
+    total_ms: 0.0,
+    error: None,
+  };
+  if let Err(err) = run(address, &mut timing) {
+    timing.error = Some(err);
+  }
+  timing.total_ms = elapsed_ms(start);
+  timing
+}
+
+pub fn summarize(timing: &HttpTiming) -> Result<String, String> {
+  let fmt = |value: Option<f64>| value.map_or("-".to_string(), |v| format!("{v:.0}ms"));
+  let phases = format!(
+    "dns={} connect={} tls={} ttfb={} total={:.0}ms",
+    fmt(Some(timing.dns_ms)),
+    fmt(timing.connect_ms),
+    fmt(timing.tls_ms),
+    fmt(timing.ttfb_ms),
+    timing.total_ms
+  );
+  match (&timing.error, timing.status) {
+    (Some(err), _) => Err(format!("{err} ({phases})")),
+    (None, Some(status)) if status >= 500 => Err(format!("HTTP {status} {phases}")),
+    (None, Some(status)) => Ok(format!("HTTP {status} {phases}")),
+    (None, None) => Err(format!("no response ({phases})")),
+  }
+}
+
+fn run(address: &str, timing: &mut HttpTiming) -> Result<(), String> {
+  let url = Url::parse(address).map_err(|e| format!("invalid url: {e}"))?;
+  let host = url.host_str().ok_or_else(|| "url has no host".to_string())?;
+  let port = url
+    .port_or_known_default()
+    .ok_or_else(|| "url has no port".to_string())?;
+  let tls = url.scheme() == "https";
+
+  let phase = Instant::now();
+  let addr = (host, port)
+    .to_socket_addrs()
+    .map_err(|e| format!("dns failed: {e}"))?
+    .next()
+    .ok_or_else(|| "dns returned no address".to_string())?;
+  timing.dns_ms = elapsed_ms(phase);
+
+  let phase = Instant::now();
+  let stream = TcpStream::connect_timeout(&addr, TIMEOUT).map_err(|e| format!("connect failed: {e}"))?;
+  timing.connect_ms = Some(elapsed_ms(phase));
+  let _ = stream.set_read_timeout(Some(TIMEOUT));
+  let _ = stream.set_write_timeout(Some(TIMEOUT));
+
+  let path = match url.query() {
+    Some(query) => format!("{}?{query}", url.path()),
+    None => url.path().to_string(),
+  };
+  let request = format!(
+    "GET {path} HTTP/1.1\r\nHost: {host}\r\nUser-Agent: ping-tool/{}\r\nAccept: */*\r\nConnection: close\r\n\r\n",
+    env!("CARGO_PKG_VERSION")
+  );
+
+  if tls {
+    let phase = Instant::now();
+    let connector = TlsConnector::new().map_err(|e| e.to_string())?;
+    let mut stream = connector
+      .connect(host, stream)
+      .map_err(|e| format!("tls failed: {e}"))?;
+    timing.tls_ms = Some(elapsed_ms(phase));
+    exchange(&mut stream, &request, timing)
+  } else {
+    let mut stream = stream;
+    exchange(&mut stream, &request, timing)
+  }
+}
+
+fn exchange<S: Read + Write>(stream: &mut S, request: &str, timing: &mut HttpTiming) -> Result<(), String> {
+  let phase = Instant::now();
+  stream
+    .write_all(request.as_bytes())
+    .map_err(|e| format!("send failed: {e}"))?;
+
+  let mut buf = [0u8; 512];
+  let mut head = Vec::new();
+  loop {
+    let n = stream.read(&mut buf).map_err(|e| format!("read failed: {e}"))?;
+    if n == 0 {
+      break;
+    }
+    if head.is_empty() {
+      timing.ttfb_ms = Some(elapsed_ms(phase));
+    }
+    head.extend_from_slice(&buf[..n]);
+    if head.contains(&b'\n') {
+      break;
+    }
+  }
+
+  let line = String::from_utf8_lossy(&head);
+  let status = line
+    .lines()
+    .next()
+    .and_then(|status_line| status_line.split_whitespace().nth(1))
+    .and_then(|code| code.parse::<u16>().ok())
+    .ok_or_else(|| "invalid http response".to_string())?;
+  timing.status = Some(status);
+  Ok(())
+}
+
+fn elapsed_ms(since: Instant) -> f64 {
+  since.elapsed().as_secs_f64() * 1000.0
+}
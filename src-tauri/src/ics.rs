@@ -0,0 +1,106 @@
+// Outage history as an iCalendar (.ics) file, one event per outage, so downtime can be laid
+// over a team calendar next to maintenance windows and office events. Event UIDs are derived
+// from the target and start time, so importing an overlapping export again updates the events
+// instead of duplicating them. Events are marked transparent and don't show anyone as busy.
+
+use std::fs;
+use std::path::Path;
+
+use chrono::{Local, NaiveDateTime, TimeDelta, TimeZone, Utc};
+
+use crate::format_duration;
+use crate::logfile::{self, FailureRun};
+
+const UTC_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+/// Lines longer than this many octets are folded (RFC 5545 §3.1).
+const MAX_LINE_OCTETS: usize = 75;
+
+fn utc(local: NaiveDateTime) -> String {
+  Local
+    .from_local_datetime(&local)
+    .earliest()
+    .map_or_else(|| local.and_utc(), |at| at.with_timezone(&Utc))
+    .format(UTC_FORMAT)
+    .to_string()
+}
+
+/// Escapes a TEXT value.
+fn escape(text: &str) -> String {
+  text
+    .replace('\\', "\\\\")
+    .replace(';', "\\;")
+    .replace(',', "\\,")
+    .replace("\r\n", "\\n")
+    .replace('\n', "\\n")
+}
+
+/// Appends `line`, folded into CRLF-terminated lines of at most 75 octets without splitting a
+/// character.
+fn push_line(out: &mut String, line: &str) {
+  let mut octets = 0;
+  for c in line.chars() {
+    if octets + c.len_utf8() > MAX_LINE_OCTETS {
+      out.push_str("\r\n ");
+      // The leading space of a continuation line counts.
+      octets = 1;
+    }
+    out.push(c);
+    octets += c.len_utf8();
+  }
+  out.push_str("\r\n");
+}
+
+/// The calendar for `outages`, found with a confirmation threshold of `confirm_failures`.
+pub fn calendar(outages: &[FailureRun], confirm_failures: u32) -> String {
+  let mut out = String::new();
+  let stamp = Utc::now().format(UTC_FORMAT).to_string();
+  for line in [
+    "BEGIN:VCALENDAR",
+    "VERSION:2.0",
+    "PRODID:-//Ping Tool//Outage History//ZH",
+    "CALSCALE:GREGORIAN",
+    "METHOD:PUBLISH",
+    "X-WR-CALNAME:网络中断记录",
+  ] {
+    push_line(&mut out, line);
+  }
+  for run in outages {
+    // A run that ends with the log has its last failure as the end; show at least a second.
+    let ended = run.ended.max(run.started + TimeDelta::seconds(1));
+    let lasted = format_duration((ended - run.started).to_std().unwrap_or_default());
+    let address = logfile::address_of(&run.target);
+    let mut notes = vec![
+      format!("目标: {}", run.target),
+      format!("开始: {}", run.started.format("%Y-%m-%d %H:%M:%S")),
+      format!("结束: {}", run.ended.format("%Y-%m-%d %H:%M:%S")),
+      format!("持续: {lasted}"),
+      format!("连续失败: {} 次（中断判定阈值 {confirm_failures} 次）", run.failures),
+    ];
+    if !run.first_error.is_empty() {
+      notes.push(format!("首个错误: {}", run.first_error));
+    }
+    push_line(&mut out, "BEGIN:VEVENT");
+    push_line(
+      &mut out,
+      &format!("UID:{}-{}@ping-tool", address.replace(':', "-"), utc(run.started)),
+    );
+    push_line(&mut out, &format!("DTSTAMP:{stamp}"));
+    push_line(&mut out, &format!("DTSTART:{}", utc(run.started)));
+    push_line(&mut out, &format!("DTEND:{}", utc(ended)));
+    push_line(
+      &mut out,
+      &format!("SUMMARY:{}", escape(&format!("{} 中断 {lasted}", run.target))),
+    );
+    push_line(&mut out, &format!("DESCRIPTION:{}", escape(&notes.join("\n"))));
+    push_line(&mut out, "CATEGORIES:网络中断");
+    push_line(&mut out, "TRANSP:TRANSPARENT");
+    push_line(&mut out, "END:VEVENT");
+  }
+  push_line(&mut out, "END:VCALENDAR");
+  out
+}
+
+pub fn write(path: &Path, outages: &[FailureRun], confirm_failures: u32) -> Result<(), String> {
+  fs::write(path, calendar(outages, confirm_failures))
+    .map_err(|e| format!("无法写入日历文件 {}: {e}", path.display()))
+}
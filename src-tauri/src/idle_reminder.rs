@@ -0,0 +1,92 @@
+// Nags when the app is open but nothing is being monitored. After a reboot the app comes back
+// (autostart, restored session) without a session running, and nobody notices until they look
+// for last week's data. The reminder shows in the main window's status line, flashes the
+// window's taskbar entry and optionally goes by email, and repeats every `after_hours` for as long as monitoring stays stopped.
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, UserAttentionType};
+
+use crate::alerts::{self, AlertEvent, AlertKind, AlertSeverity};
+use crate::{events, format_duration, load_settings, send_alert_email, PingState};
+
+const TICK: Duration = Duration::from_secs(60);
+const MAX_AFTER_HOURS: u64 = 24 * 30;
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct IdleReminderSettings {
+  #[serde(default)]
+  pub enabled: bool,
+  /// Hours without a monitoring session before the first reminder, and between reminders.
+  #[serde(default = "default_after_hours")]
+  pub after_hours: u64,
+  /// Also email the reminder to the SMTP recipient.
+  #[serde(default)]
+  pub email: bool,
+}
+
+impl Default for IdleReminderSettings {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      after_hours: default_after_hours(),
+      email: false,
+    }
+  }
+}
+
+fn default_after_hours() -> u64 {
+  4
+}
+
+pub fn validate(settings: &IdleReminderSettings) -> Result<(), String> {
+  if !(1..=MAX_AFTER_HOURS).contains(&settings.after_hours) {
+    return Err(format!("未监控提醒间隔应在 1 到 {MAX_AFTER_HOURS} 小时之间"));
+  }
+  Ok(())
+}
+
+pub fn spawn_watcher(app: AppHandle) {
+  thread::spawn(move || {
+    // When the app started counts as the start of the idle stretch.
+    let mut idle_since = Some(Instant::now());
+    let mut last_reminder: Option<Instant> = None;
+    loop {
+      thread::sleep(TICK);
+      if app.state::<PingState>().is_monitoring() {
+        idle_since = None;
+        last_reminder = None;
+        continue;
+      }
+      let since = *idle_since.get_or_insert_with(Instant::now);
+      let settings = load_settings(&app);
+      let reminder = settings.idle_reminder;
+      let after = Duration::from_secs(reminder.after_hours.saturating_mul(3600));
+      if !reminder.enabled || last_reminder.unwrap_or(since).elapsed() < after {
+        continue;
+      }
+      last_reminder = Some(Instant::now());
+
+      let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+      let message = format!(
+        "程序正在运行，但已有 {} 没有进行任何监控，是否忘记点击开始？",
+        format_duration(since.elapsed())
+      );
+      let event = AlertEvent::new(AlertKind::MonitoringIdle, AlertSeverity::Info, &timestamp, message.clone());
+      events::emit(&app, "alert-event", &event);
+      if let Some(window) = app.get_webview_window("main") {
+        let _ = window.request_user_attention(Some(UserAttentionType::Informational));
+      }
+      if reminder.email {
+        let subject = alerts::render_subject(&settings.smtp.subject_template, "监控未运行提醒", &event);
+        let smtp = settings.smtp.with_proxy(&settings.proxy);
+        if let Err(err) = send_alert_email(&smtp, &subject, &message, Some(&event)) {
+          eprintln!("failed to send idle reminder email: {err}");
+        }
+      }
+    }
+  });
+}
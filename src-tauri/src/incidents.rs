@@ -0,0 +1,80 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+/// The ID of the outage of `address` that started at `started`, e.g. `INC-20240501-093012-6c1d2f`:
+/// the start time and a hash of the address. Derived rather than counted, so another system given
+/// the same address and start time computes the same ID, and targets that went down in the same
+/// second get different ones. An outage still open when the app restarts is detected anew, with a
+/// new start time and so a new ID.
+pub fn incident_id(address: &str, started: &str) -> Result<String, String> {
+  let time = NaiveDateTime::parse_from_str(started, "%Y-%m-%d %H:%M:%S")
+    .map_err(|e| format!("中断开始时间格式无效 {started}: {e}"))?
+    .format("%Y%m%d-%H%M%S");
+  // FNV-1a: stable across builds and platforms, unlike the std hashers.
+  let hash = address.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+    (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+  });
+  Ok(format!("INC-{time}-{:06x}", hash & 0xff_ffff))
+}
+
+/// An outage that has been alerted and not yet recovered.
+#[derive(Clone, Serialize)]
+pub struct Incident {
+  pub id: String,
+  pub address: String,
+  pub target: String,
+  pub started: String,
+  /// Set by `acknowledge_outage`; acknowledged incidents get no further reminders or escalation.
+  pub acknowledged_at: Option<String>,
+}
+
+#[derive(Default)]
+pub struct IncidentBoard {
+  active: Vec<Incident>,
+}
+
+impl IncidentBoard {
+  pub fn open(&mut self, address: &str, target: &str, started: &str) -> Result<String, String> {
+    let id = incident_id(address, started)?;
+    self.active.push(Incident {
+      id: id.clone(),
+      address: address.to_string(),
+      target: target.to_string(),
+      started: started.to_string(),
+      acknowledged_at: None,
+    });
+    Ok(id)
+  }
+
+  pub fn close(&mut self, id: &str) -> Option<Incident> {
+    let index = self.active.iter().position(|incident| incident.id == id)?;
+    Some(self.active.remove(index))
+  }
+
+  pub fn acknowledge(&mut self, id: &str, at: &str) -> Result<Incident, String> {
+    let incident = self
+      .active
+      .iter_mut()
+      .find(|incident| incident.id == id)
+      .ok_or_else(|| format!("没有进行中的中断事件 {id}"))?;
+    if incident.acknowledged_at.is_none() {
+      incident.acknowledged_at = Some(at.to_string());
+    }
+    Ok(incident.clone())
+  }
+
+  pub fn is_acknowledged(&self, id: &str) -> bool {
+    self
+      .active
+      .iter()
+      .any(|incident| incident.id == id && incident.acknowledged_at.is_some())
+  }
+
+  pub fn list(&self) -> Vec<Incident> {
+    self.active.clone()
+  }
+
+  pub fn clear(&mut self) {
+    self.active.clear();
+  }
+}
@@ -0,0 +1,233 @@
+// Throughput of the network interfaces from the OS byte counters, so latency spikes can be
+// lined up with someone saturating the uplink. A background thread samples once a second and
+// keeps the last five minutes per interface.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::Local;
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::lock::LockExt;
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+const HISTORY: usize = 300;
+
+#[derive(Clone, Serialize)]
+pub struct RatePoint {
+  pub timestamp: String,
+  pub rx_bytes_per_sec: f64,
+  pub tx_bytes_per_sec: f64,
+}
+
+#[derive(Serialize)]
+pub struct InterfaceStats {
+  pub name: String,
+  /// Counter values as reported by the OS, since boot or since the interface came up.
+  pub rx_bytes: u64,
+  pub tx_bytes: u64,
+  pub rx_bytes_per_sec: Option<f64>,
+  pub tx_bytes_per_sec: Option<f64>,
+  /// Oldest first.
+  pub history: Vec<RatePoint>,
+}
+
+struct Series {
+  at: Instant,
+  rx: u64,
+  tx: u64,
+  history: VecDeque<RatePoint>,
+}
+
+#[derive(Default)]
+pub struct InterfaceSampler {
+  interfaces: Mutex<BTreeMap<String, Series>>,
+}
+
+impl InterfaceSampler {
+  fn sample(&self) {
+    let now = Instant::now();
+    let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let counters = platform::counters();
+    let mut interfaces = self.interfaces.lock_or_recover();
+    // Interfaces that went away (a VPN disconnecting, a dongle unplugged) are dropped.
+    interfaces.retain(|name, _| counters.iter().any(|(other, _, _)| other == name));
+    for (name, rx, tx) in counters {
+      let Some(series) = interfaces.get_mut(&name) else {
+        interfaces.insert(
+          name,
+          Series {
+            at: now,
+            rx,
+            tx,
+            history: VecDeque::new(),
+          },
+        );
+        continue;
+      };
+      let secs = now.duration_since(series.at).as_secs_f64();
+      if secs > 0.0 {
+        series.history.push_back(RatePoint {
+          timestamp: timestamp.clone(),
+          rx_bytes_per_sec: counter_delta(series.rx, rx) as f64 / secs,
+          tx_bytes_per_sec: counter_delta(series.tx, tx) as f64 / secs,
+        });
+        while series.history.len() > HISTORY {
+          series.history.pop_front();
+        }
+      }
+      series.at = now;
+      series.rx = rx;
+      series.tx = tx;
+    }
+  }
+
+  /// Current rates plus the last `limit` points of history per interface.
+  pub fn report(&self, limit: usize) -> Vec<InterfaceStats> {
+    self
+      .interfaces
+      .lock_or_recover()
+      .iter()
+      .map(|(name, series)| {
+        let latest = series.history.back();
+        let skip = series.history.len().saturating_sub(limit);
+        InterfaceStats {
+          name: name.clone(),
+          rx_bytes: series.rx,
+          tx_bytes: series.tx,
+          rx_bytes_per_sec: latest.map(|point| point.rx_bytes_per_sec),
+          tx_bytes_per_sec: latest.map(|point| point.tx_bytes_per_sec),
+          history: series.history.iter().skip(skip).cloned().collect(),
+        }
+      })
+      .collect()
+  }
+}
+
+/// Bytes between two counter readings. A drop in a 32-bit counter is a wrap; in a 64-bit one it
+/// means the counter was reset, e.g. by the interface going down and up, and counts from zero.
+fn counter_delta(before: u64, after: u64) -> u64 {
+  if after >= before {
+    after - before
+  } else if platform::COUNTER_BITS == 32 {
+    (after + (1 << 32)).saturating_sub(before)
+  } else {
+    after
+  }
+}
+
+pub fn spawn_sampler(app: AppHandle) {
+  thread::spawn(move || loop {
+    app.state::<InterfaceSampler>().sample();
+    thread::sleep(SAMPLE_INTERVAL);
+  });
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+  use std::ptr;
+
+  use windows_sys::Win32::NetworkManagement::IpHelper::{
+    FreeMibTable, GetIfTable2, IF_TYPE_SOFTWARE_LOOPBACK, MIB_IF_TABLE2,
+  };
+  use windows_sys::Win32::NetworkManagement::Ndis::IfOperStatusUp;
+
+  /// Set in `InterfaceAndOperStatusFlags` for NDIS filter rows, which repeat their adapter's
+  /// counters.
+  const FILTER_INTERFACE: u8 = 0x02;
+
+  pub const COUNTER_BITS: u32 = 64;
+
+  pub fn counters() -> Vec<(String, u64, u64)> {
+    let mut table: *mut MIB_IF_TABLE2 = ptr::null_mut();
+    if unsafe { GetIfTable2(&mut table) } != 0 || table.is_null() {
+      return Vec::new();
+    }
+    let rows = unsafe { std::slice::from_raw_parts((*table).Table.as_ptr(), (*table).NumEntries as usize) };
+    let counters = rows
+      .iter()
+      .filter(|row| {
+        row.OperStatus == IfOperStatusUp
+          && row.Type != IF_TYPE_SOFTWARE_LOOPBACK
+          && row.InterfaceAndOperStatusFlags._bitfield & FILTER_INTERFACE == 0
+      })
+      .map(|row| {
+        let len = row.Alias.iter().position(|&c| c == 0).unwrap_or(row.Alias.len());
+        (String::from_utf16_lossy(&row.Alias[..len]), row.InOctets, row.OutOctets)
+      })
+      .collect();
+    unsafe { FreeMibTable(table.cast()) };
+    counters
+  }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+  use std::fs;
+
+  /// `unsigned long` in the kernel, so 32 bits on a 32-bit system.
+  pub const COUNTER_BITS: u32 = usize::BITS;
+
+  pub fn counters() -> Vec<(String, u64, u64)> {
+    let Ok(contents) = fs::read_to_string("/proc/net/dev") else {
+      return Vec::new();
+    };
+    // Two header lines, then `name: rx_bytes rx_packets ... (8 rx fields) tx_bytes ...`.
+    contents
+      .lines()
+      .skip(2)
+      .filter_map(|line| {
+        let (name, fields) = line.split_once(':')?;
+        let fields: Vec<u64> = fields.split_whitespace().filter_map(|f| f.parse().ok()).collect();
+        Some((name.trim().to_string(), *fields.first()?, *fields.get(8)?))
+      })
+      .filter(|(name, _, _)| name != "lo")
+      .collect()
+  }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+  use std::ffi::CStr;
+  use std::ptr;
+
+  /// `if_data` keeps 32-bit byte counters.
+  pub const COUNTER_BITS: u32 = 32;
+
+  pub fn counters() -> Vec<(String, u64, u64)> {
+    let mut addrs: *mut libc::ifaddrs = ptr::null_mut();
+    if unsafe { libc::getifaddrs(&mut addrs) } != 0 {
+      return Vec::new();
+    }
+    let mut counters = Vec::new();
+    let mut cursor = addrs;
+    while let Some(entry) = unsafe { cursor.as_ref() } {
+      cursor = entry.ifa_next;
+      // Each interface has one AF_LINK entry, whose data holds the counters.
+      let is_link =
+        unsafe { entry.ifa_addr.as_ref() }.is_some_and(|addr| i32::from(addr.sa_family) == libc::AF_LINK);
+      let up = entry.ifa_flags & libc::IFF_UP as u32 != 0;
+      let loopback = entry.ifa_flags & libc::IFF_LOOPBACK as u32 != 0;
+      if !is_link || !up || loopback || entry.ifa_data.is_null() {
+        continue;
+      }
+      let data = unsafe { &*(entry.ifa_data as *const libc::if_data) };
+      let name = unsafe { CStr::from_ptr(entry.ifa_name) }.to_string_lossy().into_owned();
+      counters.push((name, u64::from(data.ifi_ibytes), u64::from(data.ifi_obytes)));
+    }
+    unsafe { libc::freeifaddrs(addrs) };
+    counters
+  }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
+mod platform {
+  pub const COUNTER_BITS: u32 = 64;
+
+  pub fn counters() -> Vec<(String, u64, u64)> {
+    Vec::new()
+  }
+}
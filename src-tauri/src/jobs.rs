@@ -0,0 +1,258 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use cron::Schedule;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::lock::LockExt;
+use crate::{
+  alert_chart, append_line, load_settings, minute_log_path, parse_rtt_ms, ping_once, port_scan,
+  resolve_log_base, send_alert_email_with_chart, speedtest, targets, update_settings,
+};
+
+const TICK: Duration = Duration::from_secs(15);
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JobAction {
+  /// Sends `count` single-packet probes back to back and reports loss and RTT.
+  Burst { address: String, count: u32 },
+  PortScan { host: String, ports: String },
+  Speedtest,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct ProbeJob {
+  #[serde(default)]
+  pub id: String,
+  #[serde(default)]
+  pub name: String,
+  #[serde(default = "default_enabled")]
+  pub enabled: bool,
+  /// Cron expression, 5 fields (`0 9 * * *`) or 6 with seconds. Takes precedence over `run_at`.
+  #[serde(default)]
+  pub cron: Option<String>,
+  /// One-off run time, `YYYY-MM-DD HH:MM` local; the job disables itself after running.
+  #[serde(default)]
+  pub run_at: Option<String>,
+  pub action: JobAction,
+  /// Email the report to the configured alert recipient.
+  #[serde(default = "default_enabled")]
+  pub email: bool,
+}
+
+fn default_enabled() -> bool {
+  true
+}
+
+#[derive(Clone, Serialize)]
+pub struct JobReport {
+  pub job_id: String,
+  pub started: String,
+  pub success: bool,
+  pub summary: String,
+}
+
+pub fn validate(job: &ProbeJob) -> Result<(), String> {
+  match (&job.cron, &job.run_at) {
+    (Some(expr), _) => {
+      parse_cron(expr)?;
+    }
+    (None, Some(run_at)) => {
+      parse_run_at(run_at)?;
+    }
+    (None, None) => return Err("任务需要 cron 表达式或执行时间".to_string()),
+  }
+  match &job.action {
+    JobAction::Burst { address, count } => {
+      if address.trim().is_empty() {
+        return Err("Address cannot be empty".to_string());
+      }
+      targets::validate_host(address.trim())?;
+      if !(1..=1000).contains(count) {
+        return Err("突发测试包数应在 1-1000 之间".to_string());
+      }
+    }
+    JobAction::PortScan { host, ports } => {
+      if host.trim().is_empty() {
+        return Err("Address cannot be empty".to_string());
+      }
+      port_scan::parse_ports(ports)?;
+    }
+    JobAction::Speedtest => {}
+  }
+  Ok(())
+}
+
+pub fn new_job_id() -> String {
+  format!("job-{}", Local::now().format("%Y%m%d%H%M%S%3f"))
+}
+
+fn parse_cron(expr: &str) -> Result<Schedule, String> {
+  let expr = expr.trim();
+  // The cron crate wants a seconds field; accept the common 5-field form too.
+  let full = if expr.split_whitespace().count() == 5 {
+    format!("0 {expr}")
+  } else {
+    expr.to_string()
+  };
+  Schedule::from_str(&full).map_err(|e| format!("cron 表达式无效: {e}"))
+}
+
+fn parse_run_at(value: &str) -> Result<DateTime<Local>, String> {
+  let naive = NaiveDateTime::parse_from_str(value.trim(), "%Y-%m-%d %H:%M")
+    .map_err(|_| format!("执行时间格式应为 YYYY-MM-DD HH:MM: {value}"))?;
+  Local
+    .from_local_datetime(&naive)
+    .earliest()
+    .ok_or_else(|| format!("执行时间无效: {value}"))
+}
+
+/// Whether the job has an occurrence in `(since, now]`.
+fn is_due(job: &ProbeJob, since: &DateTime<Local>, now: &DateTime<Local>) -> bool {
+  if !job.enabled {
+    return false;
+  }
+  if let Some(expr) = &job.cron {
+    return parse_cron(expr)
+      .ok()
+      .and_then(|schedule| schedule.after(since).next())
+      .is_some_and(|next| next <= *now);
+  }
+  job
+    .run_at
+    .as_deref()
+    .and_then(|run_at| parse_run_at(run_at).ok())
+    .is_some_and(|at| at <= *now)
+}
+
+pub fn spawn_scheduler(app: AppHandle) {
+  thread::spawn(move || {
+    // Jobs still running from an earlier tick; a job that outlasts its interval is not started twice.
+    let running: Arc<Mutex<HashSet<String>>> = Arc::default();
+    let mut last_check = Local::now();
+    loop {
+      thread::sleep(TICK);
+      let now = Local::now();
+      let settings = load_settings(&app);
+      for job in settings.jobs.iter().filter(|job| is_due(job, &last_check, &now)) {
+        if !running.lock_or_recover().insert(job.id.clone()) {
+          continue;
+        }
+        // A one-off stays due until disabled, so it's disabled before it runs, not after.
+        if job.cron.is_none() && !disable_one_off(&app, &job.id) {
+          running.lock_or_recover().remove(&job.id);
+          continue;
+        }
+        let (app, job, running) = (app.clone(), job.clone(), running.clone());
+        thread::spawn(move || {
+          let report = run_job(&app, &job);
+          running.lock_or_recover().remove(&job.id);
+          if !report.success {
+            eprintln!("scheduled job {} failed: {}", job.id, report.summary);
+          }
+        });
+      }
+      last_check = now;
+    }
+  });
+}
+
+/// Turns the one-off job off, touching nothing else in the settings. `false` if that failed,
+/// in which case the job mustn't run or it would run again on every tick.
+fn disable_one_off(app: &AppHandle, id: &str) -> bool {
+  let disabled = update_settings(app, |settings| {
+    if let Some(job) = settings.jobs.iter_mut().find(|job| job.id == id) {
+      job.enabled = false;
+    }
+  });
+  if let Err(err) = &disabled {
+    eprintln!("failed to disable one-off job {id}: {err}");
+  }
+  disabled.is_ok()
+}
+
+/// Runs the job now, writes the report to the log directory and emails it if requested.
+pub fn run_job(app: &AppHandle, job: &ProbeJob) -> JobReport {
+  let started = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+  let settings = load_settings(app);
+  let (success, summary) = match execute(&job.action, &started, &settings) {
+    Ok(summary) => (true, summary),
+    Err(err) => (false, format!("error: {err}")),
+  };
+
+  let name = if job.name.trim().is_empty() { job.id.as_str() } else { job.name.trim() };
+  let now = Local::now();
+  if let Ok(base_dir) = resolve_log_base(app) {
+    if let Ok(path) = minute_log_path(&base_dir, &now) {
+      let timestamp = now.format("%Y-%m-%d %H:%M:%S");
+      let line = format!("[{timestamp}] JOB | {name} | {}\n", summary.replace('\n', "; "));
+      if let Err(e) = append_line(&path, &line) {
+        eprintln!("failed to write log: {e}");
+      }
+    }
+  }
+
+  if job.email {
+    let body = format!("任务: {name}<br>开始时间: {started}<br><br>{}", summary.replace('\n', "<br>"));
+    let chart = match &job.action {
+      JobAction::Burst { address, .. } => alert_chart(app, &settings.smtp, address, None),
+      _ => None,
+    };
+    let subject = format!("定时诊断报告 - {name}");
+    let smtp = settings.smtp.with_proxy(&settings.proxy);
+    if let Err(err) = send_alert_email_with_chart(&smtp, &subject, &body, None, chart.as_ref()) {
+      eprintln!("failed to send job report: {err}");
+    }
+  }
+
+  JobReport {
+    job_id: job.id.clone(),
+    started,
+    success,
+    summary,
+  }
+}
+
+fn execute(action: &JobAction, started: &str, settings: &crate::AppSettings) -> Result<String, String> {
+  match action {
+    JobAction::Burst { address, count } => {
+      let address = address.trim();
+      let mut rtts = Vec::new();
+      for _ in 0..*count {
+        if let Ok(line) = ping_once(address, settings.ping.encoding, None) {
+          rtts.push(parse_rtt_ms(&line).unwrap_or(0.0));
+        }
+        thread::sleep(Duration::from_millis(200));
+      }
+      let lost = *count as usize - rtts.len();
+      let loss = lost as f64 * 100.0 / f64::from(*count);
+      let mut summary = format!("{address}: 发送 {count}，丢失 {lost}，丢包率 {loss:.1}%");
+      if !rtts.is_empty() {
+        let avg = rtts.iter().sum::<f64>() / rtts.len() as f64;
+        let min = rtts.iter().copied().fold(f64::MAX, f64::min);
+        let max = rtts.iter().copied().fold(0.0, f64::max);
+        summary.push_str(&format!("\n延迟 最小 {min:.1} ms / 平均 {avg:.1} ms / 最大 {max:.1} ms"));
+      }
+      Ok(summary)
+    }
+    JobAction::PortScan { host, ports } => {
+      let ports = port_scan::parse_ports(ports)?;
+      let results = port_scan::scan(host.trim(), ports, Duration::from_secs(1))?;
+      let lines: Vec<String> = results
+        .iter()
+        .map(|r| match r.latency_ms {
+          Some(ms) => format!("{}: {} ({ms:.0} ms)", r.port, r.state.as_str()),
+          None => format!("{}: {}", r.port, r.state.as_str()),
+        })
+        .collect();
+      Ok(format!("{}:\n{}", host.trim(), lines.join("\n")))
+    }
+    JobAction::Speedtest => speedtest::run(&settings.speedtest, started).map(|result| result.summary()),
+  }
+}
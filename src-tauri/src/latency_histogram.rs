@@ -0,0 +1,163 @@
+// How RTTs are distributed, per target. Averages hide a bimodal link, e.g. Wi-Fi that answers
+// in 3 ms or, after link-layer retries, in 40 ms; bucket counts show both humps. Counts are kept
+// per minute for the last day, so any window up to that is a sum of minutes.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::lock::LockExt;
+
+/// Minutes of counts kept per target.
+pub const MAX_WINDOW_MINUTES: u32 = 24 * 60;
+const MAX_BOUNDS: usize = 32;
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct HistogramSettings {
+  /// Upper edges of the buckets in ms, increasing. Replies slower than the last edge fall in an
+  /// open-ended bucket.
+  #[serde(default = "default_bounds_ms")]
+  pub bounds_ms: Vec<f64>,
+}
+
+impl Default for HistogramSettings {
+  fn default() -> Self {
+    Self {
+      bounds_ms: default_bounds_ms(),
+    }
+  }
+}
+
+fn default_bounds_ms() -> Vec<f64> {
+  vec![1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 500.0, 1000.0]
+}
+
+pub fn validate(settings: &HistogramSettings) -> Result<(), String> {
+  let bounds = &settings.bounds_ms;
+  if bounds.is_empty() || bounds.len() > MAX_BOUNDS {
+    return Err(format!("直方图分桶边界应有 1 到 {MAX_BOUNDS} 个"));
+  }
+  if bounds.iter().any(|bound| !bound.is_finite() || *bound <= 0.0) {
+    return Err("直方图分桶边界必须为正数".to_string());
+  }
+  if bounds.windows(2).any(|pair| pair[0] >= pair[1]) {
+    return Err("直方图分桶边界必须严格递增".to_string());
+  }
+  Ok(())
+}
+
+#[derive(Serialize)]
+pub struct HistogramBucket {
+  /// Exclusive lower edge; `None` for the first bucket.
+  pub lower_ms: Option<f64>,
+  /// Inclusive upper edge; `None` for the open-ended last bucket.
+  pub upper_ms: Option<f64>,
+  pub count: u64,
+}
+
+#[derive(Serialize)]
+pub struct LatencyHistogram {
+  pub target: String,
+  pub window_minutes: u32,
+  pub buckets: Vec<HistogramBucket>,
+  /// Replies counted in the buckets.
+  pub samples: u64,
+  /// Probes without a reply, which have no RTT to bucket.
+  pub lost: u64,
+}
+
+struct Minute {
+  index: i64,
+  counts: Vec<u64>,
+  lost: u64,
+}
+
+#[derive(Default)]
+struct Inner {
+  bounds_ms: Vec<f64>,
+  targets: HashMap<String, VecDeque<Minute>>,
+}
+
+#[derive(Default)]
+pub struct LatencyHistograms {
+  inner: Mutex<Inner>,
+}
+
+impl LatencyHistograms {
+  /// Counts gathered with other edges can't be re-bucketed, so changing them starts over.
+  pub fn set_bounds(&self, bounds_ms: &[f64]) {
+    let mut inner = self.inner.lock_or_recover();
+    if inner.bounds_ms != bounds_ms {
+      inner.bounds_ms = bounds_ms.to_vec();
+      inner.targets.clear();
+    }
+  }
+
+  /// One probe result of `target`. A successful probe without a round-trip time, such as an
+  /// SNMP or HTTP check that didn't report one, is left out rather than counted as lost.
+  pub fn record(&self, target: &str, at: &DateTime<Local>, success: bool, rtt_ms: Option<f64>) {
+    let rtt_ms = match (success, rtt_ms) {
+      (true, None) => return,
+      (true, rtt) => rtt,
+      (false, _) => None,
+    };
+    let index = minute_index(at);
+    let mut inner = self.inner.lock_or_recover();
+    let buckets = inner.bounds_ms.len() + 1;
+    let bucket = rtt_ms.map(|rtt| inner.bounds_ms.partition_point(|bound| *bound < rtt));
+    let minutes = inner.targets.entry(target.to_string()).or_default();
+    if minutes.back().is_none_or(|minute| minute.index != index) {
+      minutes.push_back(Minute {
+        index,
+        counts: vec![0; buckets],
+        lost: 0,
+      });
+    }
+    while minutes.front().is_some_and(|minute| index - minute.index >= i64::from(MAX_WINDOW_MINUTES)) {
+      minutes.pop_front();
+    }
+    if let Some(minute) = minutes.back_mut() {
+      match bucket {
+        Some(bucket) => minute.counts[bucket] += 1,
+        None => minute.lost += 1,
+      }
+    }
+  }
+
+  /// The distribution of `target` over the last `window_minutes`, including the current one.
+  pub fn report(&self, target: &str, window_minutes: u32, now: &DateTime<Local>) -> LatencyHistogram {
+    let window_minutes = window_minutes.clamp(1, MAX_WINDOW_MINUTES);
+    let since = minute_index(now) - i64::from(window_minutes);
+    let inner = self.inner.lock_or_recover();
+    let mut counts = vec![0u64; inner.bounds_ms.len() + 1];
+    let mut lost = 0;
+    for minute in inner.targets.get(target).into_iter().flatten().filter(|minute| minute.index > since) {
+      for (total, count) in counts.iter_mut().zip(&minute.counts) {
+        *total += count;
+      }
+      lost += minute.lost;
+    }
+    let buckets = counts
+      .iter()
+      .enumerate()
+      .map(|(i, count)| HistogramBucket {
+        lower_ms: i.checked_sub(1).map(|below| inner.bounds_ms[below]),
+        upper_ms: inner.bounds_ms.get(i).copied(),
+        count: *count,
+      })
+      .collect();
+    LatencyHistogram {
+      target: target.to_string(),
+      window_minutes,
+      buckets,
+      samples: counts.iter().sum(),
+      lost,
+    }
+  }
+}
+
+fn minute_index(at: &DateTime<Local>) -> i64 {
+  at.timestamp().div_euclid(60)
+}
@@ -0,0 +1,192 @@
+// The small HTTP/1.1 server behind the relay mode and remote control: one request per
+// connection, bearer-token auth, short plain-text or JSON answers. Each connection is served on
+// a thread of its own, up to a cap; connections beyond it are answered 503 straight from the
+// accept loop, so a flood of unauthenticated clients can't pile up threads. `stop` waits for the
+// accept loop to drop its socket, so a listener restarted on the same address can bind it.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::error::{AppError, ErrorKind};
+
+const ACCEPT_POLL: Duration = Duration::from_millis(200);
+const SOCKET_TIMEOUT: Duration = Duration::from_secs(10);
+/// The busy answer is written from the accept loop, which mustn't wait on a slow client.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(1);
+const MAX_HEAD_BYTES: u64 = 8 * 1024;
+const MAX_BODY_BYTES: usize = 4 * 1024;
+
+pub struct Request {
+  pub method: String,
+  pub path: String,
+  pub authorization: Option<String>,
+  pub body: Vec<u8>,
+}
+
+impl Request {
+  /// Whether the request carries `Authorization: Bearer <token>`.
+  pub fn authorized(&self, token: &str) -> bool {
+    let presented = self
+      .authorization
+      .as_deref()
+      .and_then(|value| value.strip_prefix("Bearer "));
+    presented.is_some_and(|presented| constant_time_eq(presented.trim().as_bytes(), token.as_bytes()))
+  }
+}
+
+type Handler = dyn Fn(TcpStream, SocketAddr) -> io::Result<()> + Send + Sync;
+
+pub struct Listener {
+  bind: String,
+  stop_tx: mpsc::Sender<()>,
+  join: thread::JoinHandle<()>,
+}
+
+impl Listener {
+  /// Binds `bind` and serves each connection with `handle`, at most `max_connections` at once.
+  /// `name` prefixes the errors logged for failed requests.
+  pub fn start(
+    name: &'static str,
+    bind: &str,
+    max_connections: usize,
+    handle: impl Fn(TcpStream, SocketAddr) -> io::Result<()> + Send + Sync + 'static,
+  ) -> Result<Self, AppError> {
+    let listener = TcpListener::bind(bind)
+      .and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+      .map_err(|e| AppError::new(ErrorKind::Io, format!("无法监听 {bind}")).with_details(e.to_string()))?;
+    let (stop_tx, stop_rx) = mpsc::channel();
+    let handle: Arc<Handler> = Arc::new(handle);
+    let join = thread::spawn(move || accept_loop(name, listener, max_connections, handle, stop_rx));
+    Ok(Self {
+      bind: bind.to_string(),
+      stop_tx,
+      join,
+    })
+  }
+
+  pub fn bind(&self) -> &str {
+    &self.bind
+  }
+
+  /// Stops accepting and returns once the socket is closed; requests in progress finish.
+  pub fn stop(self) {
+    let _ = self.stop_tx.send(());
+    let _ = self.join.join();
+  }
+}
+
+fn accept_loop(
+  name: &'static str,
+  listener: TcpListener,
+  max_connections: usize,
+  handle: Arc<Handler>,
+  stop_rx: mpsc::Receiver<()>,
+) {
+  let open = Arc::new(AtomicUsize::new(0));
+  loop {
+    match stop_rx.try_recv() {
+      Ok(()) | Err(TryRecvError::Disconnected) => break,
+      Err(TryRecvError::Empty) => {}
+    }
+    match listener.accept() {
+      Ok((stream, peer)) => {
+        if open.fetch_add(1, Ordering::SeqCst) >= max_connections {
+          open.fetch_sub(1, Ordering::SeqCst);
+          let _ = refuse_busy(stream);
+          continue;
+        }
+        let (open, handle) = (open.clone(), handle.clone());
+        thread::spawn(move || {
+          if let Err(e) = prepare(&stream).and_then(|_| handle(stream, peer)) {
+            eprintln!("{name} request from {peer} failed: {e}");
+          }
+          open.fetch_sub(1, Ordering::SeqCst);
+        });
+      }
+      Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(ACCEPT_POLL),
+      Err(e) => {
+        eprintln!("{name} listener error: {e}");
+        thread::sleep(ACCEPT_POLL);
+      }
+    }
+  }
+}
+
+/// Accepted sockets inherit the listener's non-blocking mode.
+fn prepare(stream: &TcpStream) -> io::Result<()> {
+  stream.set_nonblocking(false)?;
+  stream.set_read_timeout(Some(SOCKET_TIMEOUT))?;
+  stream.set_write_timeout(Some(SOCKET_TIMEOUT))
+}
+
+fn refuse_busy(mut stream: TcpStream) -> io::Result<()> {
+  stream.set_nonblocking(false)?;
+  stream.set_write_timeout(Some(BUSY_TIMEOUT))?;
+  respond(&mut stream, "503 Service Unavailable", "too many connections")
+}
+
+pub fn read_request(reader: &mut BufReader<TcpStream>) -> io::Result<Request> {
+  let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+  let mut head = reader.by_ref().take(MAX_HEAD_BYTES);
+  let mut line = String::new();
+  head.read_line(&mut line)?;
+  let mut parts = line.split_whitespace();
+  let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+    return Err(invalid("malformed request line"));
+  };
+  let mut request = Request {
+    method: method.to_string(),
+    path: path.split('?').next().unwrap_or(path).to_string(),
+    authorization: None,
+    body: Vec::new(),
+  };
+
+  let mut content_length = 0;
+  loop {
+    line.clear();
+    if head.read_line(&mut line)? == 0 {
+      return Err(invalid("request head too large or truncated"));
+    }
+    let header = line.trim_end();
+    if header.is_empty() {
+      break;
+    }
+    let Some((name, value)) = header.split_once(':') else {
+      return Err(invalid("malformed header"));
+    };
+    let value = value.trim();
+    if name.eq_ignore_ascii_case("content-length") {
+      content_length = value.parse().map_err(|_| invalid("invalid content-length"))?;
+    } else if name.eq_ignore_ascii_case("authorization") {
+      request.authorization = Some(value.to_string());
+    }
+  }
+
+  if content_length > MAX_BODY_BYTES {
+    return Err(invalid("body too large"));
+  }
+  request.body = vec![0; content_length];
+  reader.read_exact(&mut request.body)?;
+  Ok(request)
+}
+
+pub fn respond(writer: &mut TcpStream, status: &str, message: &str) -> io::Result<()> {
+  let mut head = format!(
+    "HTTP/1.1 {status}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n",
+    message.len()
+  );
+  if status.starts_with("401") {
+    head.push_str("WWW-Authenticate: Bearer\r\n");
+  }
+  writer.write_all(format!("{head}\r\n{message}").as_bytes())
+}
+
+/// Compares without stopping at the first difference, so response timing doesn't leak the token.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+  a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
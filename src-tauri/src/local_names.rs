@@ -0,0 +1,192 @@
+// Resolves `.local` mDNS names and bare NetBIOS names that the system resolver can't, e.g.
+// on Linux without nss-mdns. Printers and NAS boxes are often reachable only by those names.
+// Such names are looked up here and the ping binary is handed the address instead.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::lock::LockExt;
+
+const QUERY_TIMEOUT: Duration = Duration::from_millis(1500);
+/// Answers (including "the system resolver handles it") are reused for this long so the
+/// per-second probe doesn't query the network every time.
+const CACHE_TTL: Duration = Duration::from_secs(60);
+const MDNS_GROUP: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(224, 0, 0, 251)), 5353);
+const NETBIOS_BROADCAST: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::BROADCAST), 137);
+const NETBIOS_NAME_LEN: usize = 15;
+
+struct CachedName {
+  name: String,
+  checked: Instant,
+  address: Option<IpAddr>,
+}
+
+static CACHE: Mutex<Vec<CachedName>> = Mutex::new(Vec::new());
+
+/// The address to ping instead of `host`, when `host` is an mDNS or NetBIOS name the system
+/// resolver doesn't know. `None` means `host` can be used as is.
+pub fn translate(host: &str) -> Option<IpAddr> {
+  let name = host.strip_suffix('.').unwrap_or(host).to_ascii_lowercase();
+  if !is_mdns_name(&name) && !is_netbios_name(&name) {
+    return None;
+  }
+  if let Some(cached) = CACHE
+    .lock_or_recover()
+    .iter()
+    .find(|cached| cached.name == name && cached.checked.elapsed() < CACHE_TTL)
+  {
+    return cached.address;
+  }
+
+  let address = if system_resolves(&name) { None } else { resolve(&name) };
+  let mut cache = CACHE.lock_or_recover();
+  cache.retain(|cached| cached.name != name && cached.checked.elapsed() < CACHE_TTL);
+  cache.push(CachedName {
+    name,
+    checked: Instant::now(),
+    address,
+  });
+  address
+}
+
+/// Looks `name` up over mDNS or NetBIOS, whichever fits it.
+pub fn resolve(name: &str) -> Option<IpAddr> {
+  if is_mdns_name(name) {
+    query_mdns(name).ok().flatten()
+  } else if is_netbios_name(name) {
+    query_netbios(name).ok().flatten()
+  } else {
+    None
+  }
+}
+
+fn is_mdns_name(name: &str) -> bool {
+  name.len() > ".local".len() && name.to_ascii_lowercase().ends_with(".local")
+}
+
+fn is_netbios_name(name: &str) -> bool {
+  !name.is_empty() && name.len() <= NETBIOS_NAME_LEN && !name.contains('.') && name.parse::<IpAddr>().is_err()
+}
+
+fn system_resolves(name: &str) -> bool {
+  (name, 0).to_socket_addrs().is_ok_and(|mut addrs| addrs.next().is_some())
+}
+
+fn query_mdns(name: &str) -> std::io::Result<Option<IpAddr>> {
+  let mut query = vec![0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+  for label in name.split('.') {
+    query.push(label.len() as u8);
+    query.extend_from_slice(label.as_bytes());
+  }
+  // Type A, class IN with the unicast-response bit set.
+  query.extend_from_slice(&[0, 0, 0, 1, 0x80, 0x01]);
+
+  let socket = UdpSocket::bind("0.0.0.0:0")?;
+  socket.send_to(&query, MDNS_GROUP)?;
+  receive(&socket, |packet| parse_dns_answer(packet, name))
+}
+
+fn query_netbios(name: &str) -> std::io::Result<Option<IpAddr>> {
+  let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().subsec_nanos();
+  let id = (nanos & 0xffff) as u16;
+  let mut query = id.to_be_bytes().to_vec();
+  // Broadcast, recursion desired; one question.
+  query.extend_from_slice(&[0x01, 0x10, 0, 1, 0, 0, 0, 0, 0, 0]);
+  query.push(32);
+  let mut padded = [b' '; 16];
+  padded[..name.len()].copy_from_slice(name.to_ascii_uppercase().as_bytes());
+  padded[15] = 0;
+  for byte in padded {
+    query.push(b'A' + (byte >> 4));
+    query.push(b'A' + (byte & 0x0f));
+  }
+  // Terminator, type NB, class IN.
+  query.extend_from_slice(&[0, 0, 0x20, 0, 1]);
+
+  let socket = UdpSocket::bind("0.0.0.0:0")?;
+  socket.set_broadcast(true)?;
+  socket.send_to(&query, NETBIOS_BROADCAST)?;
+  receive(&socket, |packet| {
+    (packet.get(..2) == Some(&id.to_be_bytes()[..]))
+      .then(|| parse_netbios_answer(packet))
+      .flatten()
+  })
+}
+
+/// Reads replies until one parses or the timeout passes; other hosts' traffic is skipped.
+fn receive(socket: &UdpSocket, parse: impl Fn(&[u8]) -> Option<IpAddr>) -> std::io::Result<Option<IpAddr>> {
+  let deadline = Instant::now() + QUERY_TIMEOUT;
+  let mut buf = [0u8; 1500];
+  while let Some(remaining) = deadline.checked_duration_since(Instant::now()).filter(|d| !d.is_zero()) {
+    socket.set_read_timeout(Some(remaining))?;
+    match socket.recv_from(&mut buf) {
+      Ok((len, _)) => {
+        if let Some(address) = parse(&buf[..len]) {
+          return Ok(Some(address));
+        }
+      }
+      Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => break,
+      Err(e) => return Err(e),
+    }
+  }
+  Ok(None)
+}
+
+/// The first A record for `name` in a DNS/mDNS response.
+fn parse_dns_answer(packet: &[u8], name: &str) -> Option<IpAddr> {
+  let count = |at: usize| -> Option<usize> {
+    Some(u16::from_be_bytes([*packet.get(at)?, *packet.get(at + 1)?]) as usize)
+  };
+  // Only responses; the query itself echoes back on the multicast group.
+  if packet.get(2)? & 0x80 == 0 {
+    return None;
+  }
+  let (questions, answers) = (count(4)?, count(6)? + count(8)? + count(10)?);
+  let mut pos = 12;
+  for _ in 0..questions {
+    pos = read_name(packet, pos)?.1 + 4;
+  }
+  for _ in 0..answers {
+    let (owner, end) = read_name(packet, pos)?;
+    let kind = count(end)?;
+    let len = count(end + 8)?;
+    let data = packet.get(end + 10..end + 10 + len)?;
+    if kind == 1 && len == 4 && owner.eq_ignore_ascii_case(name) {
+      return Some(IpAddr::V4(Ipv4Addr::new(data[0], data[1], data[2], data[3])));
+    }
+    pos = end + 10 + len;
+  }
+  None
+}
+
+/// Reads a possibly compressed name at `pos`; returns it and the position after it.
+fn read_name(packet: &[u8], mut pos: usize) -> Option<(String, usize)> {
+  let mut labels = Vec::new();
+  let mut end = None;
+  for _ in 0..128 {
+    let len = *packet.get(pos)? as usize;
+    if len == 0 {
+      return Some((labels.join("."), end.unwrap_or(pos + 1)));
+    }
+    if len & 0xc0 == 0xc0 {
+      end.get_or_insert(pos + 2);
+      pos = ((len & 0x3f) << 8) | *packet.get(pos + 1)? as usize;
+      continue;
+    }
+    labels.push(String::from_utf8_lossy(packet.get(pos + 1..pos + 1 + len)?).to_string());
+    pos += 1 + len;
+  }
+  None
+}
+
+/// The first address of a NetBIOS name query response.
+fn parse_netbios_answer(packet: &[u8]) -> Option<IpAddr> {
+  // Must be a positive response with one answer.
+  if packet.get(2)? & 0x80 == 0 || packet.get(3)? & 0x0f != 0 || packet.get(7)? == &0 {
+    return None;
+  }
+  let (_, end) = read_name(packet, 12)?;
+  let data = packet.get(end + 10..end + 16)?;
+  Some(IpAddr::V4(Ipv4Addr::new(data[2], data[3], data[4], data[5])))
+}
@@ -0,0 +1,18 @@
+use std::sync::{Mutex, MutexGuard};
+
+pub trait LockExt<T> {
+  /// Locks even if another thread panicked while holding the lock, and clears the poison
+  /// flag. Everything behind these mutexes is a buffer, counter or handle that stays
+  /// consistent enough after an interrupted update, so one panic shouldn't leave the
+  /// commands failing until the app is restarted.
+  fn lock_or_recover(&self) -> MutexGuard<'_, T>;
+}
+
+impl<T> LockExt<T> for Mutex<T> {
+  fn lock_or_recover(&self) -> MutexGuard<'_, T> {
+    self.lock().unwrap_or_else(|poisoned| {
+      self.clear_poison();
+      poisoned.into_inner()
+    })
+  }
+}
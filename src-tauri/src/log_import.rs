@@ -0,0 +1,198 @@
+// Imports ping logs recorded elsewhere — this tool's own text logs, or plain `ping -t` /
+// `ping -D` captures — into the log directory, so rollups, exports and outage lists cover
+// them like locally monitored history.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use serde::Serialize;
+
+use crate::logfile::{self, Sample};
+use crate::rollup::RollupWriter;
+use crate::{append_line, minute_log_path, parse_rtt_ms, ttl};
+
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Failure lines of Windows (English and Chinese) and Unix ping.
+const FAILURE_MARKERS: [&str; 9] = [
+  "request timed out",
+  "destination host unreachable",
+  "destination net unreachable",
+  "general failure",
+  "transmit failed",
+  "no answer yet",
+  "请求超时",
+  "无法访问目标",
+  "一般故障",
+];
+
+#[derive(Default, Serialize)]
+pub struct ImportReport {
+  pub files: usize,
+  pub imported: u64,
+  /// Samples already present in the log directory, e.g. from importing a file twice.
+  pub duplicates: u64,
+  /// Lines that were neither a probe result nor a recognised header.
+  pub skipped_lines: u64,
+  pub first: Option<String>,
+  pub last: Option<String>,
+}
+
+/// Parses one file. Lines without their own timestamp are spaced one second apart from
+/// `start`, or end at the file's modification time when no start is given.
+pub fn parse_file(
+  path: &Path,
+  address: Option<&str>,
+  start: Option<NaiveDateTime>,
+) -> Result<(Vec<Sample>, u64), String> {
+  let bytes = fs::read(path).map_err(|e| format!("读取 {} 失败: {e}", path.display()))?;
+  // Windows consoles save captures in the OEM codepage, which is GBK on Chinese systems.
+  let contents = match String::from_utf8(bytes) {
+    Ok(text) => text,
+    Err(err) => encoding_rs::GBK.decode(err.as_bytes()).0.into_owned(),
+  };
+
+  let mut samples = Vec::new();
+  let mut undated: Vec<(bool, String)> = Vec::new();
+  let mut header_target: Option<String> = None;
+  let mut skipped = 0;
+  for line in contents.lines().map(str::trim).filter(|line| !line.is_empty()) {
+    if let Some(sample) = logfile::parse_sample(line) {
+      samples.push(sample);
+    } else if let Some((timestamp, rest)) = parse_epoch_prefix(line) {
+      match classify(rest) {
+        Some(success) => {
+          let sample = undated_sample(address, &header_target, timestamp, success, rest).ok_or_else(missing_target)?;
+          samples.push(sample);
+        }
+        None => skipped += 1,
+      }
+    } else if let Some(target) = parse_header(line) {
+      header_target = Some(target);
+    } else if line.starts_with('[') {
+      // Alert and event lines of this tool's own logs.
+      skipped += 1;
+    } else if let Some(success) = classify(line) {
+      undated.push((success, line.to_string()));
+    } else {
+      skipped += 1;
+    }
+  }
+
+  if !undated.is_empty() {
+    let count = undated.len() as i64;
+    let start = match start {
+      Some(start) => start,
+      None => {
+        let modified: DateTime<Local> = fs::metadata(path)
+          .and_then(|meta| meta.modified())
+          .map_err(|e| format!("无法读取 {} 的修改时间: {e}", path.display()))?
+          .into();
+        modified.naive_local() - chrono::Duration::seconds(count - 1)
+      }
+    };
+    for (offset, (success, line)) in (0..).zip(undated) {
+      let timestamp = start + chrono::Duration::seconds(offset);
+      let sample =
+        undated_sample(address, &header_target, timestamp, success, &line).ok_or_else(missing_target)?;
+      samples.push(sample);
+    }
+  }
+  Ok((samples, skipped))
+}
+
+fn missing_target() -> String {
+  "无法从日志中确定目标地址，请指定地址".to_string()
+}
+
+fn undated_sample(
+  address: Option<&str>,
+  header_target: &Option<String>,
+  timestamp: NaiveDateTime,
+  success: bool,
+  line: &str,
+) -> Option<Sample> {
+  let target = address.map(str::to_string).or_else(|| header_target.clone())?;
+  Some(Sample {
+    timestamp,
+    target,
+    success,
+    rtt_ms: if success { parse_rtt_ms(line) } else { None },
+    detail: if success { line.to_string() } else { format!("error: {line}") },
+  })
+}
+
+/// `ping -D` prefixes each line with `[1700000000.123456]`.
+fn parse_epoch_prefix(line: &str) -> Option<(NaiveDateTime, &str)> {
+  let (epoch, rest) = line.strip_prefix('[')?.split_once("] ")?;
+  let seconds: f64 = epoch.parse().ok()?;
+  let timestamp = Local.timestamp_opt(seconds.trunc() as i64, 0).single()?;
+  Some((timestamp.naive_local(), rest))
+}
+
+/// `Pinging host [ip] with ...`, `正在 Ping host [ip] 具有 ...` or `PING host (ip) ...`.
+fn parse_header(line: &str) -> Option<String> {
+  let rest = ["Pinging ", "正在 Ping ", "PING "]
+    .iter()
+    .find_map(|prefix| line.strip_prefix(prefix))?;
+  rest.split_whitespace().next().map(str::to_string)
+}
+
+fn classify(line: &str) -> Option<bool> {
+  let lower = line.to_lowercase();
+  if FAILURE_MARKERS.iter().any(|marker| lower.contains(marker)) {
+    Some(false)
+  } else if ttl::parse(line).is_some() || lower.contains("bytes from") {
+    Some(true)
+  } else {
+    None
+  }
+}
+
+/// Writes samples into the minute logs and hourly rollups, skipping lines already logged.
+pub fn replay(base_dir: &Path, mut samples: Vec<Sample>, report: &mut ImportReport) {
+  samples.sort_by_key(|sample| sample.timestamp);
+  let mut files: BTreeMap<PathBuf, (HashSet<String>, Vec<String>)> = BTreeMap::new();
+  let mut rollups: HashMap<String, RollupWriter> = HashMap::new();
+
+  for sample in samples {
+    let Some(at) = Local.from_local_datetime(&sample.timestamp).earliest() else {
+      continue;
+    };
+    let Ok(path) = minute_log_path(base_dir, &at) else {
+      continue;
+    };
+    let line = format!("[{}] {} | {}", sample.timestamp.format(TIMESTAMP_FORMAT), sample.target, sample.detail);
+    let (existing, pending) = files.entry(path.clone()).or_insert_with(|| {
+      let existing = fs::read_to_string(&path).unwrap_or_default().lines().map(str::to_string).collect();
+      (existing, Vec::new())
+    });
+    if !existing.insert(line.clone()) {
+      report.duplicates += 1;
+      continue;
+    }
+    pending.push(line);
+
+    let address = logfile::address_of(&sample.target);
+    rollups
+      .entry(address.to_string())
+      .or_insert_with(|| RollupWriter::new(address))
+      .record(base_dir, &at, sample.success, sample.rtt_ms);
+    let timestamp = sample.timestamp.format(TIMESTAMP_FORMAT).to_string();
+    report.first.get_or_insert_with(|| timestamp.clone());
+    report.last = Some(timestamp);
+    report.imported += 1;
+  }
+
+  for (path, (_, lines)) in files.into_iter().filter(|(_, (_, lines))| !lines.is_empty()) {
+    let text: String = lines.iter().map(|line| format!("{line}\n")).collect();
+    if let Err(e) = append_line(&path, &text) {
+      eprintln!("failed to write imported log {}: {e}", path.display());
+    }
+  }
+  for writer in rollups.values_mut() {
+    writer.flush(base_dir);
+  }
+}
@@ -0,0 +1,182 @@
+// Checks the log directory at launch for damage left behind by crashes or power loss: empty
+// minute files, a last line cut off mid-write, and unreadable rollup lines are repaired, and
+// gaps in the minute files are listed. The report is saved next to the logs and sent to the UI.
+
+use std::fs::{self, File, Metadata, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use chrono::{Local, NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::rollup::{HourRollup, ROLLUP_FILE};
+
+const REPORT_FILE: &str = "integrity.json";
+/// Files written this recently may belong to a session that just started; leave them alone.
+const RECENT: Duration = Duration::from_secs(120);
+/// Shorter breaks between minute files are normal (a quick restart) and not reported.
+const MIN_GAP_MINUTES: i64 = 5;
+const MAX_GAPS: usize = 200;
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Gap {
+  /// Last minute with a log file before the gap.
+  pub after: String,
+  /// First minute with a log file after it.
+  pub before: String,
+  pub missing_minutes: i64,
+}
+
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct IntegrityReport {
+  pub checked: String,
+  pub scanned_files: u64,
+  pub removed_empty: u64,
+  pub repaired_partial: u64,
+  pub dropped_rollup_lines: u64,
+  /// Most recent first, at most 200.
+  pub gaps: Vec<Gap>,
+  pub errors: Vec<String>,
+}
+
+/// The report of the last scan, if one was saved.
+pub fn load_report(base_dir: &Path) -> Option<IntegrityReport> {
+  serde_json::from_str(&fs::read_to_string(base_dir.join(REPORT_FILE)).ok()?).ok()
+}
+
+pub fn scan(base_dir: &Path) -> IntegrityReport {
+  let mut report = IntegrityReport {
+    checked: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+    ..IntegrityReport::default()
+  };
+  let Ok(entries) = fs::read_dir(base_dir) else {
+    return report;
+  };
+  let mut days: Vec<_> = entries
+    .flatten()
+    .map(|entry| entry.path())
+    .filter(|path| {
+      path.is_dir()
+        && path
+          .file_name()
+          .and_then(|name| name.to_str())
+          .is_some_and(|name| NaiveDate::parse_from_str(name, "%Y-%m-%d").is_ok())
+    })
+    .collect();
+  days.sort();
+
+  let mut minutes = Vec::new();
+  for day in &days {
+    check_rollups(&day.join(ROLLUP_FILE), &mut report);
+    let Ok(hours) = fs::read_dir(day) else {
+      continue;
+    };
+    for hour in hours.flatten().filter(|entry| entry.path().is_dir()) {
+      let Ok(files) = fs::read_dir(hour.path()) else {
+        continue;
+      };
+      for path in files.flatten().map(|entry| entry.path()) {
+        if path.extension().is_none_or(|ext| ext != "log") {
+          continue;
+        }
+        report.scanned_files += 1;
+        match check_log_file(&path, &mut report) {
+          Ok(true) => minutes.extend(minute_of(&path)),
+          Ok(false) => {}
+          Err(e) => report.errors.push(format!("{}: {e}", path.display())),
+        }
+      }
+    }
+  }
+
+  minutes.sort();
+  report.gaps = minutes
+    .windows(2)
+    .filter_map(|pair| {
+      let missing = (pair[1] - pair[0]).num_minutes() - 1;
+      (missing >= MIN_GAP_MINUTES).then(|| Gap {
+        after: pair[0].format("%Y-%m-%d %H:%M").to_string(),
+        before: pair[1].format("%Y-%m-%d %H:%M").to_string(),
+        missing_minutes: missing,
+      })
+    })
+    .rev()
+    .take(MAX_GAPS)
+    .collect();
+
+  if let Ok(data) = serde_json::to_string_pretty(&report) {
+    if let Err(e) = fs::write(base_dir.join(REPORT_FILE), data) {
+      eprintln!("failed to write log integrity report: {e}");
+    }
+  }
+  report
+}
+
+/// Removes an empty file or cuts a partial last line; returns whether the file remains.
+fn check_log_file(path: &Path, report: &mut IntegrityReport) -> io::Result<bool> {
+  let meta = fs::metadata(path)?;
+  if recently_modified(&meta) {
+    return Ok(true);
+  }
+  if meta.len() == 0 {
+    fs::remove_file(path)?;
+    report.removed_empty += 1;
+    return Ok(false);
+  }
+
+  let mut file = File::open(path)?;
+  file.seek(SeekFrom::End(-1))?;
+  let mut last = [0u8; 1];
+  file.read_exact(&mut last)?;
+  if last[0] == b'\n' {
+    return Ok(true);
+  }
+  // Lines are small, so reading the whole file to find the last complete one is cheap.
+  let contents = fs::read(path)?;
+  let keep = contents.iter().rposition(|&b| b == b'\n').map_or(0, |pos| pos + 1);
+  report.repaired_partial += 1;
+  if keep == 0 {
+    fs::remove_file(path)?;
+    return Ok(false);
+  }
+  OpenOptions::new().write(true).open(path)?.set_len(keep as u64)?;
+  Ok(true)
+}
+
+fn check_rollups(path: &Path, report: &mut IntegrityReport) {
+  if fs::metadata(path).is_ok_and(|meta| recently_modified(&meta)) {
+    return;
+  }
+  let Ok(contents) = fs::read_to_string(path) else {
+    return;
+  };
+  let lines: Vec<&str> = contents.lines().filter(|line| !line.trim().is_empty()).collect();
+  let valid: Vec<&str> = lines
+    .iter()
+    .copied()
+    .filter(|line| serde_json::from_str::<HourRollup>(line).is_ok())
+    .collect();
+  if valid.len() == lines.len() && contents.ends_with('\n') {
+    return;
+  }
+  report.dropped_rollup_lines += (lines.len() - valid.len()) as u64;
+  let text: String = valid.iter().map(|line| format!("{line}\n")).collect();
+  if let Err(e) = fs::write(path, text) {
+    report.errors.push(format!("{}: {e}", path.display()));
+  }
+}
+
+fn recently_modified(meta: &Metadata) -> bool {
+  meta
+    .modified()
+    .ok()
+    .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+    .is_some_and(|age| age < RECENT)
+}
+
+/// `ping_2024-05-01_13-37.log` names the minute it covers.
+fn minute_of(path: &Path) -> Option<NaiveDateTime> {
+  let stem = path.file_stem()?.to_str()?.strip_prefix("ping_")?;
+  NaiveDateTime::parse_from_str(stem, "%Y-%m-%d_%H-%M").ok()
+}
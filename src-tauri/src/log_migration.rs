@@ -0,0 +1,169 @@
+// Moving or copying the log tree when the log directory changes. Everything the app keeps about
+// past sessions (minute logs, hourly rollups, day manifests) lives under the log directory, so
+// switching folders without bringing it along leaves months of history behind in the old one.
+// Files that already exist at the destination are never overwritten; they are reported and, when
+// moving, left in place in the old folder.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// Progress events are sent at most this often; a few months of minute logs are ~100k files.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+const MAX_REPORTED_CONFLICTS: usize = 20;
+
+#[derive(Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrationMode {
+  Copy,
+  Move,
+}
+
+/// What a log folder holds, so the UI can say what a move or copy would involve.
+#[derive(Serialize)]
+pub struct LogDirUsage {
+  pub path: String,
+  pub files: u64,
+  pub bytes: u64,
+}
+
+/// Sent as `log-migration-progress` while files are moved or copied.
+#[derive(Clone, Serialize)]
+pub struct MigrationProgress {
+  pub files_done: u64,
+  pub files_total: u64,
+  pub bytes_done: u64,
+  pub bytes_total: u64,
+}
+
+#[derive(Serialize)]
+pub struct MigrationReport {
+  pub from: String,
+  pub to: String,
+  pub mode: MigrationMode,
+  pub files: u64,
+  pub bytes: u64,
+  /// Files left alone because the destination already had a file of that name.
+  pub conflicts: u64,
+  /// The first few of them, relative to the log folder.
+  pub conflict_paths: Vec<String>,
+}
+
+fn files_under(dir: &Path, files: &mut Vec<(PathBuf, u64)>) -> io::Result<()> {
+  for entry in fs::read_dir(dir)? {
+    let entry = entry?;
+    let file_type = entry.file_type()?;
+    if file_type.is_dir() {
+      files_under(&entry.path(), files)?;
+    } else if file_type.is_file() {
+      files.push((entry.path(), entry.metadata()?.len()));
+    }
+  }
+  Ok(())
+}
+
+/// `None` when `dir` doesn't exist or holds no files.
+pub fn usage(dir: &Path) -> Option<LogDirUsage> {
+  let mut files = Vec::new();
+  files_under(dir, &mut files).ok()?;
+  (!files.is_empty()).then(|| LogDirUsage {
+    path: dir.to_string_lossy().to_string(),
+    files: files.len() as u64,
+    bytes: files.iter().map(|(_, len)| len).sum(),
+  })
+}
+
+/// Moves or copies every file under `from` to the same place under `to`, calling `progress`
+/// along the way. Moving renames where both folders are on one volume and falls back to copy
+/// and delete where they aren't; emptied folders are removed afterwards.
+pub fn migrate(
+  from: &Path,
+  to: &Path,
+  mode: MigrationMode,
+  mut progress: impl FnMut(MigrationProgress),
+) -> Result<MigrationReport, String> {
+  if from == to {
+    return Err("新旧日志目录相同，无需迁移".to_string());
+  }
+  if to.starts_with(from) || from.starts_with(to) {
+    return Err("新旧日志目录不能互相包含".to_string());
+  }
+  let mut files = Vec::new();
+  files_under(from, &mut files).map_err(|e| format!("无法读取原日志目录 {}: {e}", from.display()))?;
+
+  let mut state = MigrationProgress {
+    files_done: 0,
+    files_total: files.len() as u64,
+    bytes_done: 0,
+    bytes_total: files.iter().map(|(_, len)| len).sum(),
+  };
+  let mut conflict_paths = Vec::new();
+  let mut conflicts = 0;
+  let mut bytes = 0;
+  let mut last_report = Instant::now();
+  progress(state.clone());
+  for (source, len) in &files {
+    let relative = source.strip_prefix(from).unwrap_or(source);
+    let dest = to.join(relative);
+    if dest.exists() {
+      conflicts += 1;
+      if conflict_paths.len() < MAX_REPORTED_CONFLICTS {
+        conflict_paths.push(relative.to_string_lossy().to_string());
+      }
+    } else {
+      transfer(source, &dest, mode).map_err(|e| format!("无法迁移 {}: {e}", relative.display()))?;
+      bytes += len;
+    }
+    state.files_done += 1;
+    state.bytes_done += len;
+    if last_report.elapsed() >= PROGRESS_INTERVAL {
+      last_report = Instant::now();
+      progress(state.clone());
+    }
+  }
+  if matches!(mode, MigrationMode::Move) {
+    remove_empty_dirs(from);
+  }
+  progress(state.clone());
+
+  Ok(MigrationReport {
+    from: from.to_string_lossy().to_string(),
+    to: to.to_string_lossy().to_string(),
+    mode,
+    files: state.files_done - conflicts,
+    bytes,
+    conflicts,
+    conflict_paths,
+  })
+}
+
+fn transfer(source: &Path, dest: &Path, mode: MigrationMode) -> io::Result<()> {
+  if let Some(parent) = dest.parent() {
+    fs::create_dir_all(parent)?;
+  }
+  if matches!(mode, MigrationMode::Move) && fs::rename(source, dest).is_ok() {
+    return Ok(());
+  }
+  fs::copy(source, dest)?;
+  if matches!(mode, MigrationMode::Move) {
+    fs::remove_file(source)?;
+  }
+  Ok(())
+}
+
+/// Removes folders under `dir` (and `dir` itself) that are empty after a move, deepest first.
+/// Folders that still hold conflicting files stay.
+fn remove_empty_dirs(dir: &Path) {
+  if let Ok(entries) = fs::read_dir(dir) {
+    for entry in entries.flatten() {
+      if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+        remove_empty_dirs(&entry.path());
+      }
+    }
+  }
+  // Fails, as intended, when something is left inside.
+  let _ = fs::remove_dir(dir);
+}
@@ -0,0 +1,233 @@
+use std::io::Write;
+use std::net::{TcpStream, ToSocketAddrs, UdpSocket};
+use std::thread;
+use std::time::Duration;
+
+use chrono::{Local, SecondsFormat};
+use native_tls::TlsConnector;
+use serde::{Deserialize, Serialize};
+
+const APP_NAME: &str = "ping-tool";
+// severity warning (4); the facility part comes from the settings
+const SEVERITY_WARNING: u8 = 4;
+
+#[derive(Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyslogProtocol {
+  #[default]
+  Udp,
+  Tcp,
+  Tls,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct RemoteSyslogSettings {
+  #[serde(default)]
+  pub enabled: bool,
+  #[serde(default)]
+  pub host: String,
+  #[serde(default = "default_syslog_port")]
+  pub port: u16,
+  #[serde(default)]
+  pub protocol: SyslogProtocol,
+  /// Syslog facility code, 0-23 (1 = user, 16-23 = local0-local7).
+  #[serde(default = "default_syslog_facility")]
+  pub facility: u8,
+}
+
+impl Default for RemoteSyslogSettings {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      host: String::new(),
+      port: default_syslog_port(),
+      protocol: SyslogProtocol::default(),
+      facility: default_syslog_facility(),
+    }
+  }
+}
+
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct LogSinkSettings {
+  /// Forward ALERT entries to syslog/journald on Unix or the Application event log on Windows.
+  #[serde(default)]
+  pub system_log: bool,
+  #[serde(default)]
+  pub remote_syslog: RemoteSyslogSettings,
+}
+
+fn default_syslog_port() -> u16 {
+  514
+}
+
+fn default_syslog_facility() -> u8 {
+  1
+}
+
+pub fn validate(settings: &LogSinkSettings) -> Result<(), String> {
+  let remote = &settings.remote_syslog;
+  if !remote.enabled {
+    return Ok(());
+  }
+  if remote.host.trim().is_empty() {
+    return Err("Syslog 服务器地址不能为空".to_string());
+  }
+  if remote.port == 0 {
+    return Err("Syslog 端口不合法".to_string());
+  }
+  if remote.facility > 23 {
+    return Err("Syslog facility 必须在 0-23 之间".to_string());
+  }
+  Ok(())
+}
+
+pub fn forward_alert(settings: &LogSinkSettings, message: &str) {
+  if settings.system_log {
+    if let Err(err) = write_system_log(message) {
+      eprintln!("failed to forward alert to system log: {err}");
+    }
+  }
+
+  if settings.remote_syslog.enabled {
+    let remote = settings.remote_syslog.clone();
+    let message = message.to_string();
+    thread::spawn(move || {
+      if let Err(err) = send_remote_syslog(&remote, &message) {
+        eprintln!("failed to forward alert to remote syslog: {err}");
+      }
+    });
+  }
+}
+
+fn send_remote_syslog(remote: &RemoteSyslogSettings, message: &str) -> Result<(), String> {
+  let host = remote.host.trim();
+  let packet = format_rfc5424(remote.facility, message);
+  let timeout = Duration::from_secs(5);
+
+  match remote.protocol {
+    SyslogProtocol::Udp => {
+      let socket = UdpSocket::bind("0.0.0.0:0")
+        .or_else(|_| UdpSocket::bind("[::]:0"))
+        .map_err(|e| e.to_string())?;
+      socket
+        .send_to(packet.as_bytes(), (host, remote.port))
+        .map_err(|e| e.to_string())?;
+    }
+    SyslogProtocol::Tcp => {
+      let mut stream = connect_tcp(host, remote.port, timeout)?;
+      write_framed(&mut stream, &packet)?;
+    }
+    SyslogProtocol::Tls => {
+      let stream = connect_tcp(host, remote.port, timeout)?;
+      let connector = TlsConnector::new().map_err(|e| e.to_string())?;
+      let mut stream = connector
+        .connect(host, stream)
+        .map_err(|e| format!("TLS 握手失败: {e}"))?;
+      write_framed(&mut stream, &packet)?;
+      let _ = stream.shutdown();
+    }
+  }
+  Ok(())
+}
+
+fn connect_tcp(host: &str, port: u16, timeout: Duration) -> Result<TcpStream, String> {
+  let addrs = (host, port).to_socket_addrs().map_err(|e| e.to_string())?;
+  let mut last_err = format!("无法解析 {host}");
+  for addr in addrs {
+    match TcpStream::connect_timeout(&addr, timeout) {
+      Ok(stream) => {
+        let _ = stream.set_write_timeout(Some(timeout));
+        return Ok(stream);
+      }
+      Err(e) => last_err = e.to_string(),
+    }
+  }
+  Err(last_err)
+}
+
+// Octet-counting framing (RFC 6587 / RFC 5425).
+fn write_framed<W: Write>(stream: &mut W, packet: &str) -> Result<(), String> {
+  let frame = format!("{} {packet}", packet.len());
+  stream
+    .write_all(frame.as_bytes())
+    .and_then(|_| stream.flush())
+    .map_err(|e| e.to_string())
+}
+
+fn format_rfc5424(facility: u8, message: &str) -> String {
+  let priority = u16::from(facility) * 8 + u16::from(SEVERITY_WARNING);
+  let timestamp = Local::now().to_rfc3339_opts(SecondsFormat::Millis, false);
+  let hostname = hostname::get()
+    .ok()
+    .and_then(|name| name.into_string().ok())
+    .filter(|name| !name.is_empty() && name.is_ascii() && !name.contains(' '))
+    .unwrap_or_else(|| "-".to_string());
+  let pid = std::process::id();
+  // BOM marks the free-form message as UTF-8, as RFC 5424 section 6.4 asks.
+  format!("<{priority}>1 {timestamp} {hostname} {APP_NAME} {pid} ALERT - \u{feff}{message}")
+}
+
+#[cfg(unix)]
+fn write_system_log(message: &str) -> Result<(), String> {
+  use std::os::unix::net::UnixDatagram;
+
+  // facility user (1) * 8 + severity warning (4)
+  const PRIORITY: u8 = 12;
+
+  let socket = UnixDatagram::unbound().map_err(|e| e.to_string())?;
+  let packet = format!("<{PRIORITY}>{APP_NAME}[{}]: {message}", std::process::id());
+  let mut last_err = String::from("no syslog socket found");
+  for path in ["/dev/log", "/var/run/syslog", "/var/run/log"] {
+    match socket.send_to(packet.as_bytes(), path) {
+      Ok(_) => return Ok(()),
+      Err(e) => last_err = format!("{path}: {e}"),
+    }
+  }
+  Err(last_err)
+}
+
+#[cfg(target_os = "windows")]
+fn write_system_log(message: &str) -> Result<(), String> {
+  use std::ptr::{null, null_mut};
+  use windows_sys::Win32::System::EventLog::{
+    DeregisterEventSource, RegisterEventSourceW, ReportEventW, EVENTLOG_WARNING_TYPE,
+  };
+
+  let source = to_wide(APP_NAME);
+  let text = to_wide(message);
+  let strings = [text.as_ptr()];
+
+  unsafe {
+    let handle = RegisterEventSourceW(null(), source.as_ptr());
+    if handle.is_null() {
+      return Err(std::io::Error::last_os_error().to_string());
+    }
+    let ok = ReportEventW(
+      handle,
+      EVENTLOG_WARNING_TYPE,
+      0,
+      1,
+      null_mut(),
+      strings.len() as u16,
+      0,
+      strings.as_ptr(),
+      null(),
+    );
+    let err = std::io::Error::last_os_error();
+    DeregisterEventSource(handle);
+    if ok == 0 {
+      return Err(err.to_string());
+    }
+  }
+  Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn to_wide(value: &str) -> Vec<u16> {
+  value.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+#[cfg(not(any(unix, target_os = "windows")))]
+fn write_system_log(_message: &str) -> Result<(), String> {
+  Err("system log is not supported on this platform".to_string())
+}
@@ -0,0 +1,261 @@
+// Reads probe results back out of the per-minute text logs, for exports that need more
+// detail than the hourly rollups keep.
+
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::Serialize;
+
+use crate::{day_index, parse_rtt_ms, targets};
+
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+/// A follow request returns at most this much; the rest comes with the next call.
+const MAX_TAIL_BYTES: u64 = 256 * 1024;
+
+/// One probe result line: `[timestamp] target | reply-or-error`.
+#[derive(Clone)]
+pub struct Sample {
+  pub timestamp: NaiveDateTime,
+  pub target: String,
+  pub success: bool,
+  pub rtt_ms: Option<f64>,
+  pub detail: String,
+}
+
+/// A run of consecutive failures of one target.
+pub struct FailureRun {
+  pub target: String,
+  pub started: NaiveDateTime,
+  /// The first successful probe afterwards, or the last failure if the log ends first.
+  pub ended: NaiveDateTime,
+  pub failures: u32,
+  /// What the first failure logged, e.g. `请求超时。`.
+  pub first_error: String,
+}
+
+/// Returns `None` for untargeted lines (`ALERT | ...`, `JOB | ...`), event lines
+/// (`target | ROUTE | ...`) and drill probes.
+pub fn parse_sample(line: &str) -> Option<Sample> {
+  let rest = line.strip_prefix('[')?;
+  let (timestamp, rest) = rest.split_once("] ")?;
+  let timestamp = NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT).ok()?;
+  let (target, detail) = rest.split_once(" | ")?;
+  if is_tag(target) || is_event(detail) || detail.contains("[DRILL]") {
+    return None;
+  }
+  let success = !detail.starts_with("error: ");
+  Some(Sample {
+    timestamp,
+    target: target.to_string(),
+    success,
+    rtt_ms: if success { parse_rtt_ms(detail) } else { None },
+    detail: detail.to_string(),
+  })
+}
+
+fn is_tag(text: &str) -> bool {
+  !text.is_empty() && text.chars().all(|c| c.is_ascii_uppercase() || c == '-')
+}
+
+/// Event lines carry a tag such as `ROUTE` or `MICRO-OUTAGE` before another ` | `.
+fn is_event(detail: &str) -> bool {
+  detail.split_once(" | ").is_some_and(|(tag, _)| is_tag(tag))
+}
+
+/// The address in a logged target name: a label with the address in parentheses, or the
+/// bare address.
+pub fn address_of(target: &str) -> &str {
+  target
+    .strip_suffix(')')
+    .and_then(|rest| rest.rsplit_once(" ("))
+    .map_or(target, |(_, address)| address)
+}
+
+/// `address` may carry credentials; the logs name it by `targets::public_address`.
+pub fn matches_address(target: &str, address: &str) -> bool {
+  address_of(target) == targets::public_address(address)
+}
+
+/// Minute log files of every day in `[from, to]`, oldest first. Days with a manifest
+/// (`day_index`) are not listed.
+pub fn log_files(base_dir: &Path, from: NaiveDate, to: NaiveDate) -> Vec<PathBuf> {
+  let mut files = Vec::new();
+  for day in from.iter_days().take_while(|day| *day <= to) {
+    if let Some(indexed) = day_index::files(base_dir, day) {
+      files.extend(indexed);
+      continue;
+    }
+    let Ok(hours) = fs::read_dir(base_dir.join(day.format("%Y-%m-%d").to_string())) else {
+      continue;
+    };
+    for hour in hours.flatten().filter(|entry| entry.path().is_dir()) {
+      let Ok(minutes) = fs::read_dir(hour.path()) else {
+        continue;
+      };
+      files.extend(
+        minutes
+          .flatten()
+          .map(|entry| entry.path())
+          .filter(|path| path.extension().is_some_and(|ext| ext == "log")),
+      );
+    }
+  }
+  files.sort();
+  files
+}
+
+/// The minute a log file covers, from its name `ping_YYYY-MM-DD_HH-MM.log`.
+pub fn file_minute(path: &Path) -> Option<NaiveDateTime> {
+  let stem = path.file_stem()?.to_str()?.strip_prefix("ping_")?;
+  NaiveDateTime::parse_from_str(stem, "%Y-%m-%d_%H-%M").ok()
+}
+
+/// Samples of `address` between `from` and `to`, reading only the minute files in between.
+pub fn load_window(base_dir: &Path, from: NaiveDateTime, to: NaiveDateTime, address: &str) -> Vec<Sample> {
+  let first_minute = from - chrono::Duration::minutes(1);
+  let mut samples: Vec<Sample> = log_files(base_dir, from.date(), to.date())
+    .into_iter()
+    .filter(|path| file_minute(path).is_some_and(|minute| minute >= first_minute && minute <= to))
+    .filter_map(|path| fs::read_to_string(path).ok())
+    .flat_map(|contents| contents.lines().filter_map(parse_sample).collect::<Vec<_>>())
+    .filter(|sample| matches_address(&sample.target, address) && sample.timestamp >= from && sample.timestamp <= to)
+    .collect();
+  samples.sort_by_key(|sample| sample.timestamp);
+  samples
+}
+
+/// Calls `visit` with the samples of every day in `[from, to]`, optionally of one address only,
+/// reading one minute file at a time: a year of results is never held in memory at once. The
+/// files are read oldest first, so samples arrive in time order.
+pub fn for_each_sample(
+  base_dir: &Path,
+  from: NaiveDate,
+  to: NaiveDate,
+  address: Option<&str>,
+  visit: impl FnMut(Sample),
+) {
+  for_each_sample_in(log_files(base_dir, from, to), address, visit);
+}
+
+/// `for_each_sample` over the given minute files only.
+pub fn for_each_sample_in(
+  files: impl IntoIterator<Item = PathBuf>,
+  address: Option<&str>,
+  mut visit: impl FnMut(Sample),
+) {
+  for path in files {
+    let Ok(contents) = fs::read_to_string(&path) else {
+      continue;
+    };
+    contents
+      .lines()
+      .filter_map(parse_sample)
+      .filter(|sample| address.is_none_or(|address| matches_address(&sample.target, address)))
+      .for_each(&mut visit);
+  }
+}
+
+/// Collects runs of at least `min_failures` consecutive failures, per target, from samples fed
+/// in time order.
+pub struct FailureRuns {
+  min_failures: u32,
+  open: Vec<FailureRun>,
+  runs: Vec<FailureRun>,
+}
+
+impl FailureRuns {
+  pub fn new(min_failures: u32) -> Self {
+    Self {
+      min_failures,
+      open: Vec::new(),
+      runs: Vec::new(),
+    }
+  }
+
+  pub fn push(&mut self, sample: &Sample) {
+    let index = self.open.iter().position(|run| run.target == sample.target);
+    match (sample.success, index) {
+      (false, Some(index)) => {
+        self.open[index].ended = sample.timestamp;
+        self.open[index].failures += 1;
+      }
+      (false, None) => self.open.push(FailureRun {
+        target: sample.target.clone(),
+        started: sample.timestamp,
+        ended: sample.timestamp,
+        failures: 1,
+        first_error: sample.detail.trim().to_string(),
+      }),
+      (true, Some(index)) => {
+        let mut run = self.open.swap_remove(index);
+        run.ended = sample.timestamp;
+        if run.failures >= self.min_failures {
+          self.runs.push(run);
+        }
+      }
+      (true, None) => {}
+    }
+  }
+
+  /// The runs in order of their start, including those still open when the samples ended.
+  pub fn finish(self) -> Vec<FailureRun> {
+    let min_failures = self.min_failures;
+    let mut runs = self.runs;
+    runs.extend(self.open.into_iter().filter(|run| run.failures >= min_failures));
+    runs.sort_by_key(|run| run.started);
+    runs
+  }
+}
+
+/// Runs of at least `min_failures` consecutive failures, per target, in time order.
+pub fn failure_runs(samples: &[Sample], min_failures: u32) -> Vec<FailureRun> {
+  let mut runs = FailureRuns::new(min_failures);
+  samples.iter().for_each(|sample| runs.push(sample));
+  runs.finish()
+}
+
+/// Lines appended to a log file since an offset, for following a file live.
+#[derive(Serialize)]
+pub struct LogTail {
+  pub lines: Vec<String>,
+  /// Where the next request should continue: after the last complete line returned.
+  pub next_offset: u64,
+  pub size: u64,
+  /// The file got shorter than the offset (rewritten or replaced), so reading started over.
+  pub restarted: bool,
+  /// More complete lines are waiting beyond `next_offset`.
+  pub more: bool,
+}
+
+/// Complete lines of `path` from byte `from_offset` on. A line still being written is left for
+/// the next call, so the writer is never read mid-line.
+pub fn tail(path: &Path, from_offset: u64) -> io::Result<LogTail> {
+  let mut file = File::open(path)?;
+  let size = file.metadata()?.len();
+  let restarted = from_offset > size;
+  let start = if restarted { 0 } else { from_offset };
+  let mut buf = Vec::new();
+  file.seek(SeekFrom::Start(start))?;
+  file.take(MAX_TAIL_BYTES).read_to_end(&mut buf)?;
+  let full = buf.len() as u64 == MAX_TAIL_BYTES;
+  let complete = match buf.iter().rposition(|b| *b == b'\n') {
+    Some(end) => end + 1,
+    // A single line longer than the limit is returned in pieces rather than never.
+    None if full => buf.len(),
+    None => 0,
+  };
+  let lines = String::from_utf8_lossy(&buf[..complete])
+    .lines()
+    .map(|line| line.trim_end_matches('\r').to_string())
+    .collect();
+  let next_offset = start + complete as u64;
+  Ok(LogTail {
+    lines,
+    next_offset,
+    size,
+    restarted,
+    more: full && size > next_offset,
+  })
+}
@@ -2,33 +2,246 @@
 
 use std::collections::VecDeque;
 use std::fs::{create_dir_all, read_to_string, OpenOptions};
-use std::io::Write;
+use std::io::{self, Read, Write};
+use std::panic::{self, AssertUnwindSafe};
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Child, Command, Output, Stdio};
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
 
-use chrono::Local;
-use lettre::message::{header::ContentType, Mailbox, Message};
+use chrono::{DateTime, Local};
+use lettre::message::{header::ContentType, Attachment, Mailbox, Message, MultiPart, SinglePart};
 use lettre::transport::smtp::authentication::Credentials;
+use lettre::transport::smtp::client::{Tls, TlsParameters};
 use lettre::{SmtpTransport, Transport};
+use ping_core::parse::{parse_duplicate_count, parse_marker_count, parse_rtt_ms, LATE_MARKER, REORDERED_MARKER};
+use ping_core::ttl::{self, TtlTracker};
+use ping_core::{
+  Burst, CommandOutput, CommandRunner, CustomPing, DscpMarked, OutageConfig, OutageDetector, OutageEvent, Platform,
+  SuccessCriterion, Sweep,
+};
 use serde::{Deserialize, Serialize};
 use tauri::path::BaseDirectory;
-use tauri::{AppHandle, Emitter, Manager, State};
+use tauri::{AppHandle, Manager, State, Window, WindowEvent};
 use url::Url;
 
+mod alert_delivery;
+mod alertmanager;
+mod alerts;
+mod anomaly;
+mod archive;
+mod arp;
+mod autostart;
+mod capabilities;
+mod captive_portal;
+mod chart;
+mod clipboard;
+mod comparison;
+mod correlation;
+mod day_index;
+mod diagnostics;
+mod dialogs;
+mod digest;
+mod dual_wan;
+mod error;
+mod events;
+mod excel;
+mod favorites;
+mod feishu;
+mod flap;
+mod health;
+mod history;
+mod http_probe;
+mod ics;
+mod idle_reminder;
+mod incidents;
+mod interfaces;
+mod jobs;
+mod latency_histogram;
+mod local_http;
+mod local_names;
+mod lock;
+mod log_import;
+mod log_integrity;
+mod log_migration;
+mod log_sinks;
+mod logfile;
+mod ntp_probe;
+mod oncall;
+mod otlp;
+mod outage_checks;
+mod payload_sweep;
+mod ping_binary;
+mod port_scan;
+mod portable;
+mod preflight;
+mod probe_budget;
+mod proxy;
+mod quic_probe;
+mod quiet_networks;
+mod relay;
+mod relay_server;
+mod remote_control;
+mod result_store;
+mod rollup;
+mod route_snapshot;
+mod scheduler;
+mod self_check;
+mod self_metrics;
+mod service_probe;
+mod settings_watch;
+mod sms;
+mod snmp;
+mod speech;
+mod speedtest;
+mod stats;
+mod stats_rebuild;
+mod summary;
+mod targets;
+mod tunnel;
+mod udp_probe;
+mod viewer;
+
+use alert_delivery::{AlertHistory, AlertRecord, Channel, Delivery};
+use alertmanager::AlertmanagerSettings;
+use alerts::{AlertEvent, AlertKind, AlertPreview, AlertSeverity, ChannelTestResult};
+use anomaly::{AnomalySettings, LatencyBaseline, Transition};
+use autostart::AutostartSettings;
+use archive::ArchiveReport;
+use capabilities::Capabilities;
+use captive_portal::{CaptivePortalSettings, Connectivity};
+use chart::Chart;
+use comparison::{DateRange, RangeComparison};
+use correlation::CorrelationReport;
+use day_index::DayIndex;
+use diagnostics::DiagnosticsReport;
+use digest::{DigestSettings, DigestState};
+use error::{AppError, ErrorKind};
+use events::EventSubscriptions;
+use dual_wan::{DualWanReport, DualWanState};
+use favorites::Favorite;
+use feishu::{CardColor, FeishuSettings};
+use flap::{FlapChange, FlapDetector, FlapSettings, FlapState};
+use history::HistoryEntry;
+use health::HealthScore;
+use http_probe::{HttpTimingBuffer, HttpTimingReport};
+use idle_reminder::IdleReminderSettings;
+use incidents::{Incident, IncidentBoard};
+use interfaces::{InterfaceSampler, InterfaceStats};
+use jobs::{JobReport, ProbeJob};
+use latency_histogram::{HistogramSettings, LatencyHistogram, LatencyHistograms};
+use lock::LockExt;
+use log_import::ImportReport;
+use log_integrity::IntegrityReport;
+use log_migration::{LogDirUsage, MigrationMode, MigrationReport};
+use log_sinks::LogSinkSettings;
+use logfile::LogTail;
+use oncall::OnCallSettings;
+use outage_checks::OutageChecks;
+use otlp::{OtlpExporter, OtlpSettings};
+use payload_sweep::SweepReport;
+use ping_binary::PingBinary;
+use port_scan::PortScanResult;
+use proxy::ProxySettings;
+use relay::{GeoReport, RelayAgent};
+use relay_server::{RelayServerSettings, RelayServerState, RelayServerStatus};
+use remote_control::{RemoteControlSettings, RemoteControlState, RemoteControlStatus};
+use result_store::{ProbeRecord, ResultStore};
+use rollup::HourRollup;
+use self_check::SelfCheck;
+use self_metrics::{SelfMetrics, SelfMetricsState, DRIFT_WARNING_INTERVAL, PROBE_INTERVAL};
+use sms::SmsSettings;
+use speech::SpeechSettings;
+use speedtest::{SpeedtestResult, SpeedtestSettings};
+use stats::{Blip, SessionStats, StatsReport};
+use stats_rebuild::RebuildReport;
+use summary::{ActiveOutage, LinkStatus, LinkTransition, PingSummary, RollingLoss, SUMMARY_INTERVAL};
+use targets::TargetConfig;
+use viewer::ViewerMode;
+
 struct PingRunner {
+  address: String,
   stop_tx: mpsc::Sender<()>,
+  probe: ProbeSlot,
   join: thread::JoinHandle<()>,
 }
 
+/// The ping process a session is currently waiting on, so stopping can kill it instead of
+/// waiting out the probe timeout.
+type ProbeSlot = Arc<Mutex<Option<Child>>>;
+
+impl PingRunner {
+  fn spawn(
+    app: &AppHandle,
+    base_dir: PathBuf,
+    address: String,
+    encoding: PingEncoding,
+    handles: SessionHandles,
+  ) -> Self {
+    let (stop_tx, stop_rx) = mpsc::channel();
+    let probe = ProbeSlot::default();
+    let app_handle = app.clone();
+    let worker_address = address.clone();
+    let worker_probe = probe.clone();
+    let join = thread::spawn(move || {
+      run_session(app_handle, base_dir, worker_address, encoding, stop_rx, worker_probe, handles)
+    });
+    Self {
+      address,
+      stop_tx,
+      probe,
+      join,
+    }
+  }
+
+  /// Signals the worker and kills its in-flight probe without waiting for it to exit.
+  fn interrupt(&self) {
+    let _ = self.stop_tx.send(());
+    if let Some(child) = self.probe.lock_or_recover().as_mut() {
+      let _ = child.kill();
+    }
+  }
+
+  /// Interrupts the worker; the thread is joined in the background.
+  fn stop(self) {
+    self.interrupt();
+    thread::spawn(move || {
+      let _ = self.join.join();
+    });
+  }
+}
+
+/// Why a monitoring session ended, sent with `monitoring-stopped`.
+#[derive(Clone, Serialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+enum StopReason {
+  User,
+  LogDirFailed { error: String },
+  Panicked { error: String },
+}
+
+#[derive(Clone, Serialize)]
+struct MonitoringStopped {
+  address: String,
+  label: String,
+  timestamp: String,
+  #[serde(flatten)]
+  reason: StopReason,
+}
+
 #[derive(Clone, Serialize)]
 struct PingEvent {
   seq: u64,
   line: String,
+  address: String,
+  label: String,
+  color: Option<String>,
+  /// TTL of the echo reply, when the probe produced one.
+  ttl: Option<u8>,
 }
 
 #[derive(Clone, Deserialize, Serialize)]
@@ -63,6 +276,20 @@ struct SmtpSettings {
   tls_mode: Option<TlsMode>,
   #[serde(default)]
   use_tls: bool,
+  /// Subject line of alert emails with `{{variables}}` (see `alerts::SUBJECT_VARIABLES`);
+  /// empty keeps the built-in subjects.
+  #[serde(default)]
+  subject_template: String,
+  /// Minutes of latency history drawn as a chart into outage and report emails; 0 sends none.
+  #[serde(default)]
+  chart_minutes: u32,
+  /// HTML body of alert emails with `{{variables}}` (see `alerts::BODY_VARIABLES`); empty keeps
+  /// the built-in body with the chart below it.
+  #[serde(default)]
+  body_template: String,
+  /// Overrides the global alert proxy; an empty string connects directly.
+  #[serde(default)]
+  proxy: Option<String>,
 }
 
 impl Default for SmtpSettings {
@@ -76,6 +303,20 @@ impl Default for SmtpSettings {
       to: String::new(),
       tls_mode: Some(TlsMode::Ssl),
       use_tls: false,
+      subject_template: String::new(),
+      chart_minutes: 0,
+      body_template: String::new(),
+      proxy: None,
+    }
+  }
+}
+
+impl SmtpSettings {
+  /// These settings with the proxy resolved against the global one.
+  fn with_proxy(&self, proxy: &ProxySettings) -> SmtpSettings {
+    SmtpSettings {
+      proxy: proxy::effective(proxy, &self.proxy),
+      ..self.clone()
     }
   }
 }
@@ -92,6 +333,188 @@ struct AlertSettings {
   smtp: SmtpSettings,
   #[serde(default)]
   wechat: WechatSettings,
+  #[serde(default)]
+  reminders: ReminderSettings,
+  /// Reminding that no monitoring session has been running for a while.
+  #[serde(default)]
+  idle_reminder: IdleReminderSettings,
+  /// Damping the alerts of targets that keep going down and up.
+  #[serde(default)]
+  flap: FlapSettings,
+  #[serde(default)]
+  digest: DigestSettings,
+  #[serde(default)]
+  sms: SmsSettings,
+  #[serde(default)]
+  oncall: OnCallSettings,
+  #[serde(default)]
+  feishu: FeishuSettings,
+  #[serde(default)]
+  proxy: ProxySettings,
+  /// Wi-Fi networks (SSIDs) on which alerts pause while logging goes on.
+  #[serde(default)]
+  quiet_networks: Vec<String>,
+  /// Spoken announcements through the OS text-to-speech.
+  #[serde(default)]
+  speech: SpeechSettings,
+  #[serde(default)]
+  alertmanager: AlertmanagerSettings,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+struct ReminderSettings {
+  #[serde(default)]
+  enabled: bool,
+  #[serde(default = "default_reminder_interval")]
+  interval_minutes: u64,
+}
+
+impl Default for ReminderSettings {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      interval_minutes: default_reminder_interval(),
+    }
+  }
+}
+
+#[derive(Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum PingEncoding {
+  #[default]
+  Auto,
+  /// Reads output as UTF-8 when it is valid UTF-8, for consoles switched to UTF-8 system-wide,
+  /// and falls back to the console codepage otherwise. Ping's own encoding is left alone.
+  #[serde(rename = "prefer_utf8", alias = "utf8")]
+  PreferUtf8,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+struct PingSettings {
+  #[serde(default)]
+  encoding: PingEncoding,
+  /// Consecutive failures before a run of them can be an outage.
+  #[serde(default = "default_confirm_failures")]
+  outage_confirm_failures: u32,
+  /// An outage must also last this long, counted from the first failure, before it alerts.
+  /// Shorter ones are kept as micro-outages.
+  #[serde(default)]
+  outage_grace_secs: u64,
+  /// Consecutive successful probes that end an outage.
+  #[serde(default = "default_recover_successes")]
+  outage_recover_successes: u32,
+  /// Send a low-priority alert (digest or email) when the reply TTL changes, i.e. the route likely did.
+  #[serde(default)]
+  ttl_change_alert: bool,
+  /// After monitoring starts and after the machine resumes from sleep, failures are logged but
+  /// don't count towards an outage for this long, e.g. while Wi-Fi reconnects.
+  #[serde(default)]
+  startup_grace_secs: u64,
+  /// Echo requests sent per probe cycle.
+  #[serde(default = "default_packets_per_cycle")]
+  packets_per_cycle: u32,
+  /// How many of them must be answered for the cycle to count as up.
+  #[serde(default)]
+  success_criterion: CycleCriterion,
+  /// Milliseconds a probe cycle may take; 0 means no limit. A ping still running when it is used
+  /// up is killed and the cycle counts as failed.
+  #[serde(default)]
+  probe_budget_ms: u64,
+  /// Executable used instead of the system ping; targets can set their own.
+  #[serde(default)]
+  ping_binary: PingBinary,
+}
+
+#[derive(Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum CycleCriterion {
+  #[default]
+  Any,
+  All,
+  AtLeast {
+    replies: u32,
+  },
+}
+
+impl From<CycleCriterion> for SuccessCriterion {
+  fn from(criterion: CycleCriterion) -> Self {
+    match criterion {
+      CycleCriterion::Any => SuccessCriterion::Any,
+      CycleCriterion::All => SuccessCriterion::All,
+      CycleCriterion::AtLeast { replies } => SuccessCriterion::AtLeast(replies),
+    }
+  }
+}
+
+impl Default for PingSettings {
+  fn default() -> Self {
+    Self {
+      encoding: PingEncoding::default(),
+      outage_confirm_failures: default_confirm_failures(),
+      outage_grace_secs: 0,
+      outage_recover_successes: default_recover_successes(),
+      ttl_change_alert: false,
+      startup_grace_secs: 0,
+      packets_per_cycle: default_packets_per_cycle(),
+      success_criterion: CycleCriterion::default(),
+      probe_budget_ms: 0,
+      ping_binary: PingBinary::default(),
+    }
+  }
+}
+
+impl PingSettings {
+  fn outage_config(&self) -> OutageConfig {
+    OutageConfig {
+      confirm_failures: self.outage_confirm_failures,
+      grace: Duration::from_secs(self.outage_grace_secs),
+      recover_successes: self.outage_recover_successes,
+    }
+  }
+
+  /// Packets per cycle and the criterion they are judged by, when more than one is sent.
+  fn burst(&self) -> Option<(u32, SuccessCriterion)> {
+    (self.packets_per_cycle > 1).then(|| (self.packets_per_cycle, self.success_criterion.into()))
+  }
+
+  fn validate(&self) -> Result<(), String> {
+    if self.startup_grace_secs > MAX_STARTUP_GRACE_SECS {
+      return Err(format!("启动静默期不能超过 {MAX_STARTUP_GRACE_SECS} 秒"));
+    }
+    if !(1..=MAX_PACKETS_PER_CYCLE).contains(&self.packets_per_cycle) {
+      return Err(format!("每轮发送的包数应在 1 到 {MAX_PACKETS_PER_CYCLE} 之间"));
+    }
+    if let CycleCriterion::AtLeast { replies } = self.success_criterion {
+      if replies == 0 || replies > self.packets_per_cycle {
+        return Err(format!("成功所需的回复数应在 1 到 {} 之间", self.packets_per_cycle));
+      }
+    }
+    ping_binary::validate(&self.ping_binary)?;
+    probe_budget::validate(self.probe_budget_ms)
+  }
+}
+
+/// The ping executable `address` is probed with, `None` for the system ping.
+fn ping_binary_for(settings: &AppSettings, address: &str) -> Option<PingBinary> {
+  let target = targets::find(&settings.targets, address);
+  ping_binary::effective(&settings.ping.ping_binary, target.ping_binary.as_ref())
+}
+
+const MAX_STARTUP_GRACE_SECS: u64 = 3600;
+
+/// Windows and macOS send one packet a second, so a larger burst would stretch the cycle a lot.
+const MAX_PACKETS_PER_CYCLE: u32 = 10;
+
+fn default_packets_per_cycle() -> u32 {
+  1
+}
+
+fn default_confirm_failures() -> u32 {
+  ping_core::CONFIRM_FAILURES
+}
+
+fn default_recover_successes() -> u32 {
+  1
 }
 
 #[derive(Default, Deserialize, Serialize)]
@@ -99,9 +522,74 @@ struct AppSettings {
   #[serde(default)]
   log_dir: Option<String>,
   #[serde(default)]
+  ping: PingSettings,
+  #[serde(default)]
+  sinks: LogSinkSettings,
+  #[serde(default)]
+  otlp: OtlpSettings,
+  #[serde(default)]
+  speedtest: SpeedtestSettings,
+  #[serde(default)]
+  captive_portal: CaptivePortalSettings,
+  #[serde(default)]
+  anomaly: AnomalySettings,
+  #[serde(default)]
+  histogram: HistogramSettings,
+  #[serde(default)]
+  targets: Vec<TargetConfig>,
+  #[serde(default)]
+  jobs: Vec<ProbeJob>,
+  /// Previously monitored addresses, most recent first.
+  #[serde(default)]
+  history: Vec<HistoryEntry>,
+  #[serde(default)]
+  favorites: Vec<Favorite>,
+  #[serde(default)]
   smtp: SmtpSettings,
   #[serde(default)]
   wechat: WechatSettings,
+  #[serde(default)]
+  reminders: ReminderSettings,
+  /// Reminding that no monitoring session has been running for a while.
+  #[serde(default)]
+  idle_reminder: IdleReminderSettings,
+  /// Damping the alerts of targets that keep going down and up.
+  #[serde(default)]
+  flap: FlapSettings,
+  #[serde(default)]
+  digest: DigestSettings,
+  #[serde(default)]
+  sms: SmsSettings,
+  #[serde(default)]
+  oncall: OnCallSettings,
+  #[serde(default)]
+  feishu: FeishuSettings,
+  /// Wi-Fi networks (SSIDs) on which alerts pause while logging goes on.
+  #[serde(default)]
+  quiet_networks: Vec<String>,
+  #[serde(default)]
+  proxy: ProxySettings,
+  /// Spoken announcements through the OS text-to-speech.
+  #[serde(default)]
+  speech: SpeechSettings,
+  /// Outage alerts in the Prometheus Alertmanager webhook format.
+  #[serde(default)]
+  alertmanager: AlertmanagerSettings,
+  /// Remote copies of the app that probe targets on our behalf.
+  #[serde(default)]
+  relays: Vec<RelayAgent>,
+  /// Answering probe requests from other copies of the app.
+  #[serde(default)]
+  relay_server: RelayServerSettings,
+  /// Starting and stopping sessions from orchestration tools over HTTP.
+  #[serde(default)]
+  remote_control: RemoteControlSettings,
+  /// Read-only UI for shared displays; see `viewer`.
+  #[serde(default)]
+  viewer_mode: bool,
+  /// Launching at login.
+  #[serde(default)]
+  autostart: AutostartSettings,
 }
 
 #[derive(Clone, Serialize)]
@@ -118,6 +606,21 @@ struct LogBuffer {
 struct PingState {
   inner: Mutex<Option<PingRunner>>,
   logs: Arc<Mutex<LogBuffer>>,
+  http_timings: Arc<Mutex<HttpTimingBuffer>>,
+  incidents: Arc<Mutex<IncidentBoard>>,
+  drill: Arc<Mutex<Option<Instant>>>,
+  stats: Arc<Mutex<SessionStats>>,
+}
+
+/// The shared buffers a monitoring session writes into.
+#[derive(Clone)]
+struct SessionHandles {
+  logs: Arc<Mutex<LogBuffer>>,
+  http_timings: Arc<Mutex<HttpTimingBuffer>>,
+  incidents: Arc<Mutex<IncidentBoard>>,
+  /// End of a running outage drill; probes fail synthetically until then.
+  drill: Arc<Mutex<Option<Instant>>>,
+  stats: Arc<Mutex<SessionStats>>,
 }
 
 impl Default for PingState {
@@ -128,203 +631,1623 @@ impl Default for PingState {
         next_seq: 1,
         entries: VecDeque::with_capacity(100),
       })),
+      http_timings: Arc::new(Mutex::new(HttpTimingBuffer::default())),
+      incidents: Arc::new(Mutex::new(IncidentBoard::default())),
+      drill: Arc::new(Mutex::new(None)),
+      stats: Arc::new(Mutex::new(SessionStats::default())),
     }
   }
 }
 
-#[tauri::command]
-fn start_ping(app: AppHandle, state: State<PingState>, address: String) -> Result<String, String> {
-  let address = address.trim().to_string();
-  if address.is_empty() {
-    return Err("Address cannot be empty".to_string());
+impl SessionHandles {
+  fn clear(&self) {
+    let mut logs = self.logs.lock_or_recover();
+    logs.entries.clear();
+    logs.next_seq = 1;
+    self.http_timings.lock_or_recover().clear();
+    self.incidents.lock_or_recover().clear();
+    *self.drill.lock_or_recover() = None;
+    *self.stats.lock_or_recover() = SessionStats::default();
   }
+}
 
-  let mut guard = state.inner.lock().map_err(|_| "State lock poisoned".to_string())?;
-  if guard.is_some() {
-    return Err("Ping is already running".to_string());
+impl PingState {
+  fn is_monitoring(&self) -> bool {
+    self.inner.lock_or_recover().is_some()
   }
 
-  let base_dir = resolve_log_base(&app)?;
-  let base_dir_clone = base_dir.clone();
-  let log_buffer = state.logs.clone();
+  fn current_address(&self) -> Option<String> {
+    self.inner.lock_or_recover().as_ref().map(|runner| runner.address.clone())
+  }
 
-  if let Ok(mut logs) = log_buffer.lock() {
-    logs.entries.clear();
-    logs.next_seq = 1;
+  fn handles(&self) -> SessionHandles {
+    SessionHandles {
+      logs: self.logs.clone(),
+      http_timings: self.http_timings.clone(),
+      incidents: self.incidents.clone(),
+      drill: self.drill.clone(),
+      stats: self.stats.clone(),
+    }
   }
+}
+
+/// The preflight check resolves the address, which can take a few seconds, so it runs off the
+/// main thread.
+#[tauri::command]
+async fn start_ping(app: AppHandle, address: String) -> Result<String, AppError> {
+  tauri::async_runtime::spawn_blocking(move || start_session(&app, &address))
+    .await
+    .map_err(|_| AppError::cancelled("启动监控被取消"))?
+}
+
+/// Starts monitoring `address`, blocking while the preflight check runs. Callers on the main
+/// thread go through `start_ping`.
+fn start_session(app: &AppHandle, address: &str) -> Result<String, AppError> {
+  let settings = load_settings(app);
+  let address = targets::with_credentials(&settings.targets, address.trim());
+  targets::validate_address(&address).map_err(AppError::invalid_input)?;
 
-  let (stop_tx, stop_rx) = mpsc::channel();
-  let app_handle = app.clone();
-  let join = thread::spawn(move || ping_loop(app_handle, base_dir_clone, address, stop_rx, log_buffer));
+  // Outside the state lock: resolving the address can take a few seconds.
+  let base_dir = resolve_log_base(app)?;
+  let binary = ping_binary_for(&settings, &address);
+  preflight::check(&address, settings.ping.encoding, binary.as_ref(), &base_dir)?;
 
-  *guard = Some(PingRunner { stop_tx, join });
+  let state = app.state::<PingState>();
+  let mut guard = state.inner.lock_or_recover();
+  if guard.is_some() {
+    return Err(AppError::new(ErrorKind::AlreadyRunning, "Ping is already running"));
+  }
+  let handles = state.handles();
+  handles.clear();
+  handles.stats.lock_or_recover().begin(&Local::now());
+  *guard = Some(PingRunner::spawn(app, base_dir.clone(), address.clone(), settings.ping.encoding, handles));
 
+  record_history_start(app, &address);
   Ok(base_dir.to_string_lossy().to_string())
 }
 
-#[tauri::command]
-fn stop_ping(state: State<PingState>) -> Result<(), String> {
-  let mut guard = state.inner.lock().map_err(|_| "State lock poisoned".to_string())?;
-  let runner = guard.take().ok_or_else(|| "Ping is not running".to_string())?;
-
-  let _ = runner.stop_tx.send(());
-  thread::spawn(move || {
-    let _ = runner.join.join();
+fn record_history_start(app: &AppHandle, address: &str) {
+  let now = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+  let recorded = update_settings(app, |settings| {
+    let label = targets::find(&settings.targets, address).label;
+    history::record_start(&mut settings.history, address, &label, &now);
   });
+  if let Err(err) = recorded {
+    eprintln!("failed to save target history: {err}");
+  }
+}
 
-  Ok(())
+/// Changes applied by `restart_ping`; anything left out keeps its current value.
+#[derive(Deserialize)]
+struct RestartOptions {
+  #[serde(default)]
+  address: Option<String>,
+  #[serde(default)]
+  ping: Option<PingSettings>,
+  #[serde(default)]
+  anomaly: Option<AnomalySettings>,
 }
 
+/// How long `restart_ping` waits for the old worker; an HTTP or SNMP probe can't be killed
+/// and has to run into its own timeout.
+const RESTART_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Replaces the running session with one using the given address and settings. The session
+/// slot is never empty in between, so a concurrent start or stop can't slip in. The log buffer
+/// is kept, a RESTART line marks the switch in the log, and statistics carry on when the
+/// address is unchanged or link back to the previous session when it isn't.
 #[tauri::command]
-fn get_recent_logs(state: State<PingState>) -> Result<Vec<LogEntry>, String> {
-  let logs = state.logs.lock().map_err(|_| "State lock poisoned".to_string())?;
-  Ok(logs.entries.iter().cloned().collect())
+async fn restart_ping(app: AppHandle, options: RestartOptions) -> Result<String, AppError> {
+  tauri::async_runtime::spawn_blocking(move || restart_session(&app, options))
+    .await
+    .map_err(|_| AppError::cancelled("重新启动监控被取消"))?
 }
 
-fn resolve_log_base(app: &AppHandle) -> Result<PathBuf, String> {
-  let settings = load_settings(app);
-  if let Some(dir) = settings.log_dir {
-    return Ok(PathBuf::from(dir));
+/// `restart_ping` on the blocking pool: the preflight check and the wait for the old worker
+/// can take seconds.
+fn restart_session(app: &AppHandle, options: RestartOptions) -> Result<String, AppError> {
+  let state = app.state::<PingState>();
+  let not_running = || AppError::new(ErrorKind::NotRunning, "Ping is not running");
+  let previous = state
+    .inner
+    .lock_or_recover()
+    .as_ref()
+    .map(|runner| runner.address.clone())
+    .ok_or_else(not_running)?;
+  let address = match options.address.as_deref().map(str::trim) {
+    Some(address) if !address.is_empty() => targets::with_credentials(&load_settings(app).targets, address),
+    _ => previous.clone(),
+  };
+  targets::validate_address(&address).map_err(AppError::invalid_input)?;
+  if let Some(anomaly) = &options.anomaly {
+    anomaly::validate(anomaly).map_err(AppError::invalid_input)?;
+  }
+  if let Some(ping) = &options.ping {
+    ping.validate().map_err(AppError::invalid_input)?;
+  }
+  let base_dir = resolve_log_base(app)?;
+  let mut current = load_settings(app);
+  if let Some(ping) = &options.ping {
+    current.ping = ping.clone();
+  }
+  let binary = ping_binary_for(&current, &address);
+  preflight::check(&address, current.ping.encoding, binary.as_ref(), &base_dir)?;
+  if options.ping.is_some() || options.anomaly.is_some() {
+    update_settings(app, |settings| {
+      if let Some(ping) = options.ping {
+        settings.ping = ping;
+      }
+      if let Some(anomaly) = options.anomaly {
+        settings.anomaly = anomaly;
+      }
+    })?;
   }
-  app
-    .path()
-    .resolve("ping-logs", BaseDirectory::AppLog)
-    .map_err(|e| e.to_string())
-}
 
-fn settings_path(app: &AppHandle) -> Result<PathBuf, String> {
-  app
-    .path()
-    .resolve("settings.json", BaseDirectory::AppConfig)
-    .map_err(|e| e.to_string())
-}
+  let worker = {
+    let guard = state.inner.lock_or_recover();
+    let runner = guard.as_ref().filter(|runner| runner.address == previous).ok_or_else(not_running)?;
+    runner.interrupt();
+    runner.join.thread().id()
+  };
+  // Wait without holding the lock: a worker that fails while stopping locks it to clear itself.
+  let deadline = Instant::now() + RESTART_TIMEOUT;
+  loop {
+    let guard = state.inner.lock_or_recover();
+    match guard.as_ref() {
+      Some(runner) if runner.join.thread().id() == worker => {
+        if runner.join.is_finished() {
+          break;
+        }
+      }
+      _ => return Err(AppError::new(ErrorKind::NotRunning, "监控已被停止，未重新启动")),
+    }
+    drop(guard);
+    if Instant::now() >= deadline {
+      return Err(AppError::new(ErrorKind::Other, "等待当前监控结束超时，未重新启动"));
+    }
+    thread::sleep(Duration::from_millis(50));
+  }
 
-fn load_settings(app: &AppHandle) -> AppSettings {
-  let path = match settings_path(app) {
-    Ok(path) => path,
-    Err(_) => return AppSettings::default(),
+  let mut guard = state.inner.lock_or_recover();
+  let Some(old) = guard.take().filter(|runner| runner.join.thread().id() == worker) else {
+    return Err(AppError::new(ErrorKind::NotRunning, "监控已被停止，未重新启动"));
   };
+  let _ = old.join.join();
 
-  match read_to_string(&path) {
-    Ok(contents) => serde_json::from_str(&contents).unwrap_or_default(),
-    Err(_) => AppSettings::default(),
-  }
-}
+  let settings = load_settings(app);
 
-fn save_settings(app: &AppHandle, settings: &AppSettings) -> Result<(), String> {
-  let path = settings_path(app)?;
-  if let Some(parent) = path.parent() {
-    create_dir_all(parent).map_err(|e| e.to_string())?;
+  let handles = state.handles();
+  handles.http_timings.lock_or_recover().clear();
+  handles.incidents.lock_or_recover().clear();
+  *handles.drill.lock_or_recover() = None;
+  let now = Local::now();
+  let session_id = {
+    let mut stats = handles.stats.lock_or_recover();
+    if address != previous {
+      stats.begin_after(&now);
+    }
+    stats.report().session_id
+  };
+  let target_name = targets::find(&settings.targets, &address).display_name();
+  let changed = if address == previous {
+    "设置已更新".to_string()
+  } else {
+    format!("原目标 {}", targets::public_address(&previous))
+  };
+  let line = format!(
+    "[{}] {target_name} | RESTART | 以新设置重新开始监控（{changed}，会话 {session_id}）",
+    now.format("%Y-%m-%d %H:%M:%S")
+  );
+  match minute_log_path(&base_dir, &now) {
+    Ok(path) => {
+      if let Err(e) = append_line(&path, &format!("{line}\n")) {
+        eprintln!("failed to write log: {e}");
+      }
+    }
+    Err(e) => eprintln!("failed to create log dir: {e}"),
   }
-  let data = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
-  std::fs::write(path, data).map_err(|e| e.to_string())
-}
+  let _ = push_log(&handles.logs, line);
 
-fn default_smtp_port() -> u16 {
-  465
+  record_history_start(app, &address);
+  *guard = Some(PingRunner::spawn(app, base_dir.clone(), address, settings.ping.encoding, handles));
+  Ok(base_dir.to_string_lossy().to_string())
 }
 
 #[tauri::command]
-fn get_log_dir(app: AppHandle) -> Result<String, String> {
-  let path = resolve_log_base(&app)?;
-  Ok(path.to_string_lossy().to_string())
+fn stop_ping(state: State<PingState>) -> Result<(), AppError> {
+  let mut guard = state.inner.lock_or_recover();
+  let runner = guard.take().ok_or_else(|| AppError::new(ErrorKind::NotRunning, "Ping is not running"))?;
+  runner.stop();
+  Ok(())
 }
 
+/// Last resort after something went wrong internally: stops every running session and
+/// clears the session buffers, without restarting the app. Settings and logs on disk are
+/// left alone.
 #[tauri::command]
-fn select_log_dir(app: AppHandle) -> Result<String, String> {
-  let current = resolve_log_base(&app)?;
-  let selected = rfd::FileDialog::new()
-    .set_title("选择日志保存目录")
-    .set_directory(&current)
-    .pick_folder();
+fn reset_state(state: State<PingState>, dual_wan: State<DualWanState>) -> Result<(), AppError> {
+  if let Some(runner) = state.inner.lock_or_recover().take() {
+    runner.stop();
+  }
+  // Not running is fine here.
+  let _ = dual_wan.stop();
+  state.handles().clear();
+  Ok(())
+}
 
-  let Some(path) = selected else {
-    return Ok(current.to_string_lossy().to_string());
-  };
+#[tauri::command]
+fn get_recent_logs(state: State<PingState>) -> Result<Vec<LogEntry>, AppError> {
+  let logs = state.logs.lock_or_recover();
+  Ok(logs.entries.iter().cloned().collect())
+}
 
-  let mut settings = load_settings(&app);
-  settings.log_dir = Some(path.to_string_lossy().to_string());
-  save_settings(&app, &settings)?;
-  Ok(path.to_string_lossy().to_string())
+/// Limits the calling window to the given events; windows that never subscribe get them all.
+#[tauri::command]
+fn subscribe_events(
+  window: Window,
+  subscriptions: State<EventSubscriptions>,
+  events: Vec<String>,
+) -> Result<(), AppError> {
+  subscriptions.subscribe(window.label(), events);
+  Ok(())
 }
 
+/// Stops the given events, or all of them when `events` is omitted, for the calling window.
 #[tauri::command]
-fn get_alert_settings(app: AppHandle) -> Result<AlertSettings, String> {
-  let settings = load_settings(&app);
-  Ok(AlertSettings {
-    smtp: settings.smtp,
-    wechat: settings.wechat,
-  })
+fn unsubscribe_events(
+  window: Window,
+  subscriptions: State<EventSubscriptions>,
+  events: Option<Vec<String>>,
+) -> Result<(), AppError> {
+  subscriptions.unsubscribe(window.label(), events);
+  Ok(())
 }
 
 #[tauri::command]
-fn save_alert_settings(app: AppHandle, settings: AlertSettings) -> Result<(), String> {
-  let mut existing = load_settings(&app);
-  existing.smtp = settings.smtp;
-  existing.wechat = settings.wechat;
-  save_settings(&app, &existing)
+fn get_active_incidents(state: State<PingState>) -> Result<Vec<Incident>, AppError> {
+  let incidents = state.incidents.lock_or_recover();
+  Ok(incidents.list())
 }
 
 #[tauri::command]
-fn export_alert_settings(app: AppHandle) -> Result<Option<String>, String> {
-  let settings = load_settings(&app);
-  let alert = AlertSettings {
-    smtp: settings.smtp,
-    wechat: settings.wechat,
-  };
+fn acknowledge_outage(app: AppHandle, state: State<PingState>, id: String) -> Result<Incident, AppError> {
+  let now = Local::now();
+  let timestamp = now.format("%Y-%m-%d %H:%M:%S").to_string();
+  let incident = state
+    .incidents
+    .lock_or_recover()
+    .acknowledge(id.trim(), &timestamp).map_err(AppError::not_found)?;
+
+  let line = format!("[{timestamp}] {} | ACK | 中断 {} 已确认，停止后续提醒", incident.target, incident.id);
+  let file_path = minute_log_path(&resolve_log_base(&app)?, &now).map_err(|e| e.to_string())?;
+  if let Err(e) = append_line(&file_path, &format!("{line}\n")) {
+    eprintln!("failed to write log: {e}");
+  }
+  let _ = push_log(&state.logs, line);
+  Ok(incident)
+}
 
-  let file_path = rfd::FileDialog::new()
-    .set_title("导出告警配置")
-    .add_filter("JSON", &["json"])
-    .set_file_name("alert-settings.json")
-    .save_file();
+/// Makes the running session's probes fail for `duration_secs` so the whole detection and
+/// alert pipeline runs end to end. Everything it produces is marked `[DRILL]`.
+#[tauri::command]
+fn simulate_outage(
+  app: AppHandle,
+  state: State<PingState>,
+  target: String,
+  duration_secs: u64,
+) -> Result<(), AppError> {
+  if !(5..=3600).contains(&duration_secs) {
+    return Err(AppError::invalid_input("演练时长应在 5-3600 秒之间"));
+  }
+  let target = target.trim();
+  {
+    let guard = state.inner.lock_or_recover();
+    match guard.as_ref() {
+      Some(runner) if runner.address == target => {}
+      Some(_) => return Err(AppError::new(ErrorKind::NotRunning, format!("当前未在监控 {target}"))),
+      None => return Err(AppError::new(ErrorKind::NotRunning, "Ping is not running")),
+    }
+  }
+  *state.drill.lock_or_recover() =
+    Some(Instant::now() + Duration::from_secs(duration_secs));
+
+  let now = Local::now();
+  let timestamp = now.format("%Y-%m-%d %H:%M:%S").to_string();
+  let line = format!("[{timestamp}] {target} | DRILL | 开始中断演练，持续 {duration_secs} 秒");
+  let file_path = minute_log_path(&resolve_log_base(&app)?, &now).map_err(|e| e.to_string())?;
+  if let Err(e) = append_line(&file_path, &format!("{line}\n")) {
+    eprintln!("failed to write log: {e}");
+  }
+  let _ = push_log(&state.logs, line);
+  Ok(())
+}
 
-  let Some(path) = file_path else {
-    return Ok(None);
+/// Copies the last `count` buffered log lines (all of them by default) and returns how many.
+#[tauri::command]
+fn copy_recent_logs_to_clipboard(state: State<PingState>, count: Option<usize>) -> Result<usize, AppError> {
+  let lines: Vec<String> = {
+    let logs = state.logs.lock_or_recover();
+    let skip = logs.entries.len().saturating_sub(count.unwrap_or(logs.entries.len()));
+    logs.entries.iter().skip(skip).map(|entry| entry.line.clone()).collect()
   };
-
-  let data = serde_json::to_string_pretty(&alert).map_err(|e| e.to_string())?;
-  if let Some(parent) = path.parent() {
-    create_dir_all(parent).map_err(|e| e.to_string())?;
+  if lines.is_empty() {
+    return Err(AppError::not_found("没有可复制的日志"));
   }
-  std::fs::write(&path, data).map_err(|e| e.to_string())?;
-  Ok(Some(path.to_string_lossy().to_string()))
+  let copied = lines.len();
+  clipboard::set_text(lines.join("\n"))?;
+  Ok(copied)
 }
 
+/// Copies a text summary of the session statistics and returns it.
 #[tauri::command]
-fn import_alert_settings(app: AppHandle) -> Result<Option<AlertSettings>, String> {
-  let file_path = rfd::FileDialog::new()
-    .set_title("导入告警配置")
-    .add_filter("JSON", &["json"])
-    .pick_file();
+fn copy_statistics_summary(app: AppHandle, state: State<PingState>) -> Result<String, AppError> {
+  let address = state.current_address();
+  let report = state.stats.lock_or_recover().report();
+  if report.days.is_empty() {
+    return Err(AppError::not_found("暂无统计数据"));
+  }
+  let settings = load_settings(&app);
+  let target_name = address
+    .as_ref()
+    .map(|address| targets::find(&settings.targets, address).display_name())
+    .unwrap_or_else(|| "-".to_string());
+  let mut text = report.to_text(&target_name);
+  let baseline_ms = report.baseline.map(|baseline| baseline.mean_ms);
+  let health = address.and_then(|address| {
+    health_score(&app, &address, baseline_ms, settings.ping.outage_confirm_failures)
+  });
+  if let Some(health) = health {
+    text.push_str(&health.to_text());
+    text.push('\n');
+  }
+  clipboard::set_text(text.clone())?;
+  Ok(text)
+}
+
+/// A 0-100 health score of `address` from the last 24 hours of logs; the session target when
+/// no address is given. `None` when there are no results in that time.
+#[tauri::command]
+fn get_health_score(
+  app: AppHandle,
+  state: State<PingState>,
+  address: Option<String>,
+) -> Result<Option<HealthScore>, AppError> {
+  let current = state.current_address();
+  let address = address
+    .map(|address| address.trim().to_string())
+    .filter(|address| !address.is_empty())
+    .or_else(|| current.clone())
+    .ok_or_else(|| AppError::invalid_input("请指定目标地址"))?;
+  // The learned baseline only describes the session target.
+  let baseline_ms = if current.as_deref() == Some(address.as_str()) {
+    state.stats.lock_or_recover().report().baseline.map(|baseline| baseline.mean_ms)
+  } else {
+    None
+  };
+  let confirm_failures = load_settings(&app).ping.outage_confirm_failures;
+  Ok(health_score(&app, &address, baseline_ms, confirm_failures))
+}
+
+fn health_score(app: &AppHandle, address: &str, baseline_ms: Option<f64>, confirm_failures: u32) -> Option<HealthScore> {
+  let base_dir = resolve_log_base(app).ok()?;
+  let now = Local::now().naive_local();
+  let samples = logfile::load_window(&base_dir, now - chrono::Duration::hours(health::WINDOW_HOURS), now, address);
+  health::score(&samples, address, baseline_ms, confirm_failures)
+}
+
+#[tauri::command]
+fn get_target_history(app: AppHandle) -> Result<Vec<HistoryEntry>, AppError> {
+  let mut history = load_settings(&app).history;
+  for entry in &mut history {
+    entry.address = targets::public_address(&entry.address).into_owned();
+  }
+  Ok(history)
+}
+
+#[tauri::command]
+fn get_statistics(state: State<PingState>) -> Result<StatsReport, AppError> {
+  let stats = state.stats.lock_or_recover();
+  Ok(stats.report())
+}
+
+#[tauri::command]
+fn get_blips(state: State<PingState>, limit: Option<usize>) -> Result<Vec<Blip>, AppError> {
+  let stats = state.stats.lock_or_recover();
+  Ok(stats.blips(limit.unwrap_or(200)))
+}
+
+#[tauri::command]
+fn reset_statistics(app: AppHandle, state: State<PingState>, session_id: String) -> Result<StatsReport, AppError> {
+  let now = Local::now();
+  let report = {
+    let mut stats = state.stats.lock_or_recover();
+    stats.reset(session_id.trim(), &now).map_err(AppError::not_found)?;
+    stats.report()
+  };
+
+  let timestamp = now.format("%Y-%m-%d %H:%M:%S");
+  let line = format!("[{timestamp}] STATS | 会话 {} 的统计已重置", report.session_id);
+  let file_path = minute_log_path(&resolve_log_base(&app)?, &now).map_err(|e| e.to_string())?;
+  if let Err(e) = append_line(&file_path, &format!("{line}\n")) {
+    eprintln!("failed to write log: {e}");
+  }
+  let _ = push_log(&state.logs, line);
+  Ok(report)
+}
+
+/// Hourly rollups for `from..=to` (`YYYY-MM-DD`), read from disk without touching the raw logs.
+#[tauri::command]
+fn get_hourly_rollups(
+  app: AppHandle,
+  from: String,
+  to: String,
+  address: Option<String>,
+) -> Result<Vec<HourRollup>, AppError> {
+  let (from, to) = parse_date_range(&from, &to).map_err(AppError::invalid_input)?;
+  let address = address.as_deref().map(str::trim).filter(|a| !a.is_empty());
+  Ok(rollup::load(&resolve_log_base(&app)?, from, to, address))
+}
+
+/// Failure runs of all targets in `from..=to` (`YYYY-MM-DD`) on one timeline, with the windows
+/// where several targets failed together.
+#[tauri::command]
+async fn get_correlated_events(app: AppHandle, from: String, to: String) -> Result<CorrelationReport, AppError> {
+  let (from, to) = parse_date_range(&from, &to).map_err(AppError::invalid_input)?;
+  correlation::validate_range(from, to).map_err(AppError::invalid_input)?;
+  let confirm_failures = load_settings(&app).ping.outage_confirm_failures;
+  let base_dir = resolve_log_base(&app)?;
+  dialogs::run_io(move || Ok(correlation::correlate(&base_dir, from, to, confirm_failures))).await
+}
+
+/// Loss, RTT percentiles and outages of `address` in two date ranges, with the change from
+/// `range_a` to `range_b`.
+#[tauri::command]
+async fn compare_ranges(
+  app: AppHandle,
+  address: String,
+  range_a: DateRange,
+  range_b: DateRange,
+) -> Result<RangeComparison, AppError> {
+  let confirm_failures = load_settings(&app).ping.outage_confirm_failures;
+  let base_dir = resolve_log_base(&app)?;
+  dialogs::run_io(move || {
+    comparison::compare(&base_dir, &address, range_a, range_b, confirm_failures).map_err(AppError::invalid_input)
+  })
+  .await
+}
+
+/// Exports samples, hourly rollups, outages and an SLA summary of `[from, to]` to an .xlsx
+/// workbook at `path`, or chosen in a save dialog.
+#[tauri::command]
+async fn export_statistics_xlsx(
+  app: AppHandle,
+  from: String,
+  to: String,
+  address: Option<String>,
+  path: Option<String>,
+) -> Result<Option<String>, AppError> {
+  let (from, to) = parse_date_range(&from, &to).map_err(AppError::invalid_input)?;
+  let address = address.map(|a| a.trim().to_string()).filter(|a| !a.is_empty());
+
+  let dialog = rfd::AsyncFileDialog::new()
+    .set_title("导出统计报表")
+    .add_filter("Excel", &["xlsx"])
+    .set_file_name(format!("ping-report_{from}_{to}.xlsx"));
+  let Some(path) = dialogs::save_file(path, dialog).await else {
+    return Ok(None);
+  };
+
+  let base_dir = resolve_log_base(&app)?;
+  let confirm_failures = load_settings(&app).ping.outage_confirm_failures;
+  dialogs::run_io(move || {
+    let address = address.as_deref();
+    let rollups = rollup::load(&base_dir, from, to, address);
+    let scope = excel::ReportScope {
+      base_dir: &base_dir,
+      from,
+      to,
+      address,
+    };
+    // Same threshold as the monitoring loop uses before it raises an outage.
+    excel::write_report(&path, &scope, &rollups, confirm_failures)?;
+    Ok(Some(path.to_string_lossy().to_string()))
+  })
+  .await
+}
+
+/// Exports the outages of `[from, to]` as an iCalendar file at `path`, or chosen in a save
+/// dialog, one event per outage.
+#[tauri::command]
+async fn export_outages_ics(
+  app: AppHandle,
+  from: String,
+  to: String,
+  address: Option<String>,
+  path: Option<String>,
+) -> Result<Option<String>, AppError> {
+  let (from, to) = parse_date_range(&from, &to).map_err(AppError::invalid_input)?;
+  let address = address.map(|a| a.trim().to_string()).filter(|a| !a.is_empty());
+
+  let dialog = rfd::AsyncFileDialog::new()
+    .set_title("导出中断日历")
+    .add_filter("iCalendar", &["ics"])
+    .set_file_name(format!("ping-outages_{from}_{to}.ics"));
+  let Some(path) = dialogs::save_file(path, dialog).await else {
+    return Ok(None);
+  };
+
+  let confirm_failures = load_settings(&app).ping.outage_confirm_failures;
+  let base_dir = resolve_log_base(&app)?;
+  dialogs::run_io(move || {
+    let mut outages = logfile::FailureRuns::new(confirm_failures);
+    logfile::for_each_sample(&base_dir, from, to, address.as_deref(), |sample| outages.push(&sample));
+    ics::write(&path, &outages.finish(), confirm_failures)?;
+    Ok(Some(path.to_string_lossy().to_string()))
+  })
+  .await
+}
+
+/// Imports log files given in `paths` or picked by the user. `address` names the target of
+/// captures that don't say; `start_time` dates plain `ping -t` output, which has no timestamps
+/// of its own.
+#[tauri::command]
+async fn import_ping_logs(
+  app: AppHandle,
+  address: Option<String>,
+  start_time: Option<String>,
+  paths: Option<Vec<String>>,
+) -> Result<Option<ImportReport>, AppError> {
+  let address = address.map(|a| a.trim().to_string()).filter(|a| !a.is_empty());
+  if let Some(address) = &address {
+    targets::validate_address(address).map_err(AppError::invalid_input)?;
+  }
+  let start = start_time
+    .as_deref()
+    .map(str::trim)
+    .filter(|s| !s.is_empty())
+    .map(|s| {
+      chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S")
+        .map_err(|_| format!("时间格式应为 YYYY-MM-DD HH:MM:SS: {s}"))
+    })
+    .transpose()?;
+
+  let dialog = rfd::AsyncFileDialog::new()
+    .set_title("导入 ping 日志")
+    .add_filter("日志", &["log", "txt"]);
+  let Some(paths) = dialogs::pick_files(paths, dialog).await else {
+    return Ok(None);
+  };
+
+  let base_dir = resolve_log_base(&app)?;
+  dialogs::run_io(move || {
+    let mut report = ImportReport::default();
+    let mut samples = Vec::new();
+    for path in &paths {
+      let (parsed, skipped) = log_import::parse_file(path, address.as_deref(), start)?;
+      samples.extend(parsed);
+      report.skipped_lines += skipped;
+      report.files += 1;
+    }
+    log_import::replay(&base_dir, samples, &mut report);
+    Ok(Some(report))
+  })
+  .await
+}
+
+/// Zips the logs of `[from, to]` with a manifest into `path` or a file chosen in a save
+/// dialog, optionally deleting the originals afterwards.
+#[tauri::command]
+async fn archive_logs(
+  app: AppHandle,
+  from: String,
+  to: String,
+  remove_originals: bool,
+  path: Option<String>,
+) -> Result<Option<ArchiveReport>, AppError> {
+  let (from, to) = parse_date_range(&from, &to).map_err(AppError::invalid_input)?;
+  let dialog = rfd::AsyncFileDialog::new()
+    .set_title("归档日志")
+    .add_filter("ZIP", &["zip"])
+    .set_file_name(format!("ping-logs_{from}_{to}.zip"));
+  let Some(path) = dialogs::save_file(path, dialog).await else {
+    return Ok(None);
+  };
+  let base_dir = resolve_log_base(&app)?;
+  if path.starts_with(&base_dir) {
+    return Err(AppError::invalid_input("归档文件不能保存在日志目录中"));
+  }
+  let settings = load_settings(&app);
+  dialogs::run_io(move || Ok(Some(archive::create(&base_dir, &path, from, to, &settings, remove_originals)?))).await
+}
+
+/// Zips redacted settings, the logs of the last `hours` (default 24), the capability report
+/// and version and OS details into `path` or a file chosen in a save dialog, for bug reports.
+#[tauri::command]
+async fn export_diagnostics_bundle(
+  app: AppHandle,
+  hours: Option<u32>,
+  path: Option<String>,
+) -> Result<Option<DiagnosticsReport>, AppError> {
+  let hours = hours.unwrap_or(diagnostics::DEFAULT_HOURS);
+  if !(1..=diagnostics::MAX_HOURS).contains(&hours) {
+    return Err(AppError::invalid_input(format!("日志时长应在 1 到 {} 小时之间", diagnostics::MAX_HOURS)));
+  }
+  let dialog = rfd::AsyncFileDialog::new()
+    .set_title("导出诊断包")
+    .add_filter("ZIP", &["zip"])
+    .set_file_name(format!("ping-diagnostics_{}.zip", Local::now().format("%Y-%m-%d_%H-%M")));
+  let Some(path) = dialogs::save_file(path, dialog).await else {
+    return Ok(None);
+  };
+  let base_dir = resolve_log_base(&app)?;
+  dialogs::run_io(move || Ok(Some(diagnostics::create(&app, &base_dir, &path, hours)?))).await
+}
+
+fn parse_date_range(from: &str, to: &str) -> Result<(chrono::NaiveDate, chrono::NaiveDate), String> {
+  let parse = |value: &str| {
+    chrono::NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d").map_err(|_| format!("日期格式应为 YYYY-MM-DD: {value}"))
+  };
+  let (from, to) = (parse(from)?, parse(to)?);
+  if to < from || (to - from).num_days() > 366 {
+    return Err("日期范围无效（最长 366 天）".to_string());
+  }
+  Ok((from, to))
+}
+
+/// New lines of any `.log` file since `from_offset`, for a "follow this file" view; the file
+/// may belong to another session or instance.
+#[tauri::command]
+fn tail_log_file(path: String, from_offset: u64) -> Result<LogTail, AppError> {
+  let path = PathBuf::from(path);
+  if path.extension().is_none_or(|ext| ext != "log") {
+    return Err(AppError::invalid_input("只能跟踪 .log 日志文件"));
+  }
+  if !path.is_file() {
+    return Err(AppError::not_found(format!("日志文件不存在: {}", path.display())));
+  }
+  Ok(logfile::tail(&path, from_offset)?)
+}
+
+/// What each day in `[from, to]` holds, from the day manifests; days logged before they existed
+/// list only their files.
+#[tauri::command]
+fn get_day_indexes(app: AppHandle, from: String, to: String) -> Result<Vec<DayIndex>, AppError> {
+  let (from, to) = parse_date_range(&from, &to).map_err(AppError::invalid_input)?;
+  let base_dir = resolve_log_base(&app)?;
+  Ok(
+    from
+      .iter_days()
+      .take_while(|day| *day <= to)
+      .filter_map(|day| day_index::load_or_scan(&base_dir, day))
+      .collect(),
+  )
+}
+
+#[tauri::command]
+fn get_http_timings(state: State<PingState>, limit: Option<usize>) -> Result<HttpTimingReport, AppError> {
+  let timings = state.http_timings.lock_or_recover();
+  Ok(timings.report(limit.unwrap_or(300)))
+}
+
+fn resolve_log_base(app: &AppHandle) -> Result<PathBuf, String> {
+  let settings = load_settings(app);
+  if let Some(dir) = settings.log_dir.map(PathBuf::from) {
+    // A relative folder follows a portable install around instead of the working directory.
+    return Ok(match portable::data_dir() {
+      Some(data_dir) if dir.is_relative() => data_dir.join(dir),
+      _ => dir,
+    });
+  }
+  if let Some(data_dir) = portable::data_dir() {
+    return Ok(data_dir.join("ping-logs"));
+  }
+  app
+    .path()
+    .resolve("ping-logs", BaseDirectory::AppLog)
+    .map_err(|e| e.to_string())
+}
+
+fn settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+  if let Some(data_dir) = portable::data_dir() {
+    return Ok(data_dir.join("settings.json"));
+  }
+  app
+    .path()
+    .resolve("settings.json", BaseDirectory::AppConfig)
+    .map_err(|e| e.to_string())
+}
+
+fn load_settings(app: &AppHandle) -> AppSettings {
+  let path = match settings_path(app) {
+    Ok(path) => path,
+    Err(_) => return AppSettings::default(),
+  };
+
+  match read_to_string(&path) {
+    Ok(contents) => serde_json::from_str(&contents).unwrap_or_default(),
+    Err(_) => AppSettings::default(),
+  }
+}
+
+/// Held across a read-modify-write of the settings file, so two updates of different sections
+/// can't overwrite each other.
+static SETTINGS_LOCK: Mutex<()> = Mutex::new(());
+
+/// Loads the settings, applies `change` and saves them, all under `SETTINGS_LOCK`.
+fn update_settings<T>(app: &AppHandle, change: impl FnOnce(&mut AppSettings) -> T) -> Result<T, AppError> {
+  let _guard = SETTINGS_LOCK.lock_or_recover();
+  let mut settings = load_settings(app);
+  let value = change(&mut settings);
+  save_settings(app, &settings)?;
+  Ok(value)
+}
+
+fn save_settings(app: &AppHandle, settings: &AppSettings) -> Result<(), AppError> {
+  let path = settings_path(app)?;
+  if let Some(parent) = path.parent() {
+    create_dir_all(parent)?;
+  }
+  let data = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
+  settings_watch::note_write(&data);
+  std::fs::write(path, data)?;
+  Ok(())
+}
+
+fn default_smtp_port() -> u16 {
+  465
+}
+
+fn default_reminder_interval() -> u64 {
+  60
+}
+
+/// Scans the log directory once at launch; see `log_integrity`.
+fn spawn_integrity_check(app: AppHandle) {
+  thread::spawn(move || {
+    let Ok(base_dir) = resolve_log_base(&app) else {
+      return;
+    };
+    let report = log_integrity::scan(&base_dir);
+    events::emit(&app, "log-integrity", report);
+  });
+}
+
+/// The report of the launch-time log directory check, for windows that missed the event.
+#[tauri::command]
+fn get_log_integrity_report(app: AppHandle) -> Result<Option<IntegrityReport>, AppError> {
+  Ok(log_integrity::load_report(&resolve_log_base(&app)?))
+}
+
+#[tauri::command]
+fn get_autostart_settings(app: AppHandle) -> Result<AutostartSettings, AppError> {
+  Ok(load_settings(&app).autostart)
+}
+
+/// Saves the login launch settings and adds, updates or removes the OS entry to match.
+#[tauri::command]
+fn save_autostart_settings(app: AppHandle, settings: AutostartSettings) -> Result<(), AppError> {
+  autostart::apply(&app, &settings)?;
+  update_settings(&app, |existing| existing.autostart = settings)
+}
+
+#[tauri::command]
+fn get_log_dir(app: AppHandle) -> Result<String, AppError> {
+  let path = resolve_log_base(&app)?;
+  Ok(path.to_string_lossy().to_string())
+}
+
+/// The log folder after `select_log_dir`, and what was left in the previous one so the UI
+/// can offer to bring it along with `migrate_log_dir`.
+#[derive(Serialize)]
+struct LogDirChange {
+  path: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  previous: Option<LogDirUsage>,
+}
+
+/// The folder `select_log_dir` switched away from while it still held logs: the only folder
+/// `migrate_log_dir` moves or copies from.
+static PREVIOUS_LOG_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Switches the log folder to `path`, or one chosen in a folder dialog.
+#[tauri::command]
+async fn select_log_dir(app: AppHandle, path: Option<String>) -> Result<LogDirChange, AppError> {
+  let current = resolve_log_base(&app)?;
+  let dialog = rfd::AsyncFileDialog::new()
+    .set_title("选择日志保存目录")
+    .set_directory(&current);
+  let selected = dialogs::pick_folder(path, dialog).await;
+
+  let Some(path) = selected.filter(|path| *path != current) else {
+    return Ok(LogDirChange {
+      path: current.to_string_lossy().to_string(),
+      previous: None,
+    });
+  };
+
+  update_settings(&app, |settings| settings.log_dir = Some(path.to_string_lossy().to_string()))?;
+  // Counting the old folder's files can take a while on a slow or network drive.
+  let old = current.clone();
+  let previous = dialogs::run_io(move || Ok(log_migration::usage(&old))).await?;
+  *PREVIOUS_LOG_DIR.lock_or_recover() = previous.is_some().then_some(current);
+  Ok(LogDirChange {
+    path: path.to_string_lossy().to_string(),
+    previous,
+  })
+}
+
+/// Moves or copies the log tree of the folder last switched away from into the current log
+/// folder, sending `log-migration-progress` events. Not while a session is writing logs.
+#[tauri::command]
+async fn migrate_log_dir(app: AppHandle, mode: MigrationMode) -> Result<MigrationReport, AppError> {
+  if app.state::<PingState>().is_monitoring() {
+    return Err(AppError::new(ErrorKind::AlreadyRunning, "监控进行中，请先停止再迁移日志"));
+  }
+  let from = PREVIOUS_LOG_DIR
+    .lock_or_recover()
+    .clone()
+    .ok_or_else(|| AppError::not_found("没有可迁移的原日志目录"))?;
+  let to = resolve_log_base(&app)?;
+  let report = tauri::async_runtime::spawn_blocking(move || {
+    log_migration::migrate(&from, &to, mode, |progress| {
+      events::emit(&app, "log-migration-progress", progress)
+    })
+  })
+  .await
+  .map_err(|_| AppError::cancelled("日志迁移被取消"))?
+  .map_err(AppError::from)?;
+  *PREVIOUS_LOG_DIR.lock_or_recover() = None;
+  Ok(report)
+}
+
+/// Recomputes the hourly rollups and day manifests of `range` from the minute logs, e.g. after
+/// importing old logs or changing the outage threshold. Reports `statistics-rebuild-progress`.
+#[tauri::command]
+async fn rebuild_statistics(app: AppHandle, range: DateRange) -> Result<RebuildReport, AppError> {
+  let (from, to) = parse_date_range(&range.from, &range.to).map_err(AppError::invalid_input)?;
+  // The running session keeps appending to today's rollups and manifest.
+  if app.state::<PingState>().is_monitoring() && to >= Local::now().date_naive() {
+    return Err(AppError::new(
+      ErrorKind::AlreadyRunning,
+      "监控进行中，不能重建今天的统计，请先停止监控或选择更早的日期",
+    ));
+  }
+  let confirm_failures = load_settings(&app).ping.outage_confirm_failures;
+  let base_dir = resolve_log_base(&app)?;
+  tauri::async_runtime::spawn_blocking(move || {
+    stats_rebuild::rebuild(&base_dir, from, to, confirm_failures, |progress| {
+      events::emit(&app, "statistics-rebuild-progress", progress)
+    })
+  })
+  .await
+  .map_err(|_| AppError::cancelled("统计重建被取消"))?
+  .map_err(AppError::from)
+}
+
+/// Per-interface throughput, now and over the last `limit` seconds (default 300).
+#[tauri::command]
+fn get_interface_stats(
+  sampler: State<InterfaceSampler>,
+  limit: Option<usize>,
+) -> Result<Vec<InterfaceStats>, AppError> {
+  Ok(sampler.report(limit.unwrap_or(300)))
+}
+
+/// The app's CPU and memory use and how closely the probe loop keeps its schedule.
+#[tauri::command]
+fn get_self_metrics(metrics: State<SelfMetricsState>) -> Result<SelfMetrics, AppError> {
+  Ok(metrics.snapshot())
+}
+
+/// Which probe engine is in use and, if direct ICMP is unavailable, why and how to enable it.
+#[tauri::command]
+fn get_capabilities() -> Result<Capabilities, AppError> {
+  Ok(capabilities::detect())
+}
+
+#[tauri::command]
+fn get_ping_settings(app: AppHandle) -> Result<PingSettings, AppError> {
+  Ok(load_settings(&app).ping)
+}
+
+#[tauri::command]
+fn save_ping_settings(app: AppHandle, settings: PingSettings) -> Result<(), AppError> {
+  settings.validate().map_err(AppError::invalid_input)?;
+  update_settings(&app, |existing| existing.ping = settings)
+}
+
+#[tauri::command]
+fn get_log_sink_settings(app: AppHandle) -> Result<LogSinkSettings, AppError> {
+  Ok(load_settings(&app).sinks)
+}
+
+#[tauri::command]
+fn save_log_sink_settings(app: AppHandle, settings: LogSinkSettings) -> Result<(), AppError> {
+  log_sinks::validate(&settings).map_err(AppError::invalid_input)?;
+  update_settings(&app, |existing| existing.sinks = settings)
+}
+
+#[tauri::command]
+fn get_otlp_settings(app: AppHandle) -> Result<OtlpSettings, AppError> {
+  Ok(load_settings(&app).otlp)
+}
+
+#[tauri::command]
+fn save_otlp_settings(app: AppHandle, settings: OtlpSettings) -> Result<(), AppError> {
+  otlp::validate(&settings).map_err(AppError::invalid_input)?;
+  update_settings(&app, |existing| existing.otlp = settings)
+}
+
+#[tauri::command]
+fn get_speedtest_settings(app: AppHandle) -> Result<SpeedtestSettings, AppError> {
+  Ok(load_settings(&app).speedtest)
+}
+
+#[tauri::command]
+fn save_speedtest_settings(app: AppHandle, settings: SpeedtestSettings) -> Result<(), AppError> {
+  speedtest::validate(&settings).map_err(AppError::invalid_input)?;
+  update_settings(&app, |existing| existing.speedtest = settings)
+}
+
+#[tauri::command]
+async fn run_speedtest(app: AppHandle) -> Result<SpeedtestResult, AppError> {
+  let settings = load_settings(&app).speedtest;
+  let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+  tauri::async_runtime::spawn_blocking(move || speedtest::run(&settings, &timestamp))
+    .await
+    .map_err(|_| AppError::cancelled("测速任务被取消"))?
+    .map_err(AppError::from)
+}
+
+#[tauri::command]
+fn get_captive_portal_settings(app: AppHandle) -> Result<CaptivePortalSettings, AppError> {
+  Ok(load_settings(&app).captive_portal)
+}
+
+#[tauri::command]
+fn save_captive_portal_settings(app: AppHandle, settings: CaptivePortalSettings) -> Result<(), AppError> {
+  if settings.enabled && settings.check_url.trim().is_empty() {
+    return Err(AppError::invalid_input("检测地址不能为空"));
+  }
+  update_settings(&app, |existing| existing.captive_portal = settings)
+}
+
+#[tauri::command]
+async fn check_captive_portal(app: AppHandle) -> Result<Connectivity, AppError> {
+  let settings = load_settings(&app).captive_portal;
+  tauri::async_runtime::spawn_blocking(move || captive_portal::check(&settings))
+    .await
+    .map_err(|_| AppError::cancelled("检测任务被取消"))
+}
+
+#[tauri::command]
+fn start_dual_wan(
+  app: AppHandle,
+  state: State<DualWanState>,
+  address: String,
+  source_a: String,
+  source_b: String,
+) -> Result<(), AppError> {
+  let address = address.trim().to_string();
+  targets::validate_host(&address).map_err(AppError::invalid_input)?;
+  let sources = [source_a.trim().to_string(), source_b.trim().to_string()];
+  if sources.iter().any(String::is_empty) {
+    return Err(AppError::invalid_input("Source interface cannot be empty"));
+  }
+  if sources[0] == sources[1] {
+    return Err(AppError::invalid_input("The two sources must differ"));
+  }
+  let base_dir = resolve_log_base(&app)?;
+  let encoding = load_settings(&app).ping.encoding;
+  state.start(base_dir, address, sources, encoding)
+}
+
+#[tauri::command]
+fn stop_dual_wan(state: State<DualWanState>) -> Result<(), AppError> {
+  state.stop()
+}
+
+#[tauri::command]
+fn get_dual_wan_report(state: State<DualWanState>, limit: Option<usize>) -> Result<DualWanReport, AppError> {
+  state.report(limit.unwrap_or(300))
+}
+
+#[tauri::command]
+fn get_targets(app: AppHandle) -> Result<Vec<TargetConfig>, AppError> {
+  Ok(load_settings(&app).targets)
+}
+
+#[tauri::command]
+fn save_target(app: AppHandle, target: TargetConfig) -> Result<(), AppError> {
+  let target = TargetConfig {
+    address: target.address.trim().to_string(),
+    ..target
+  };
+  targets::validate(&target).map_err(AppError::invalid_input)?;
+  update_settings(&app, |existing| targets::upsert(&mut existing.targets, target))
+}
+
+#[tauri::command]
+fn remove_target(app: AppHandle, address: String) -> Result<(), AppError> {
+  update_settings(&app, |existing| existing.targets.retain(|target| target.address != address.trim()))
+}
+
+#[tauri::command]
+fn get_favorites(app: AppHandle) -> Result<Vec<Favorite>, AppError> {
+  Ok(load_settings(&app).favorites)
+}
+
+/// Pins `address` together with its current per-target options.
+#[tauri::command]
+fn add_favorite(app: AppHandle, address: String, name: Option<String>) -> Result<Favorite, AppError> {
+  let address = address.trim();
+  targets::validate_address(address).map_err(AppError::invalid_input)?;
+  update_settings(&app, |existing| {
+    let favorite = Favorite {
+      id: favorites::new_favorite_id(),
+      name: name.map(|name| name.trim().to_string()).unwrap_or_default(),
+      target: targets::find(&existing.targets, address),
+    };
+    existing.favorites.push(favorite.clone());
+    favorite
+  })
+}
+
+#[tauri::command]
+fn remove_favorite(app: AppHandle, id: String) -> Result<(), AppError> {
+  update_settings(&app, |existing| existing.favorites.retain(|favorite| favorite.id != id))
+}
+
+#[tauri::command]
+fn reorder_favorites(app: AppHandle, ids: Vec<String>) -> Result<(), AppError> {
+  let _guard = SETTINGS_LOCK.lock_or_recover();
+  let mut existing = load_settings(&app);
+  favorites::reorder(&mut existing.favorites, &ids).map_err(AppError::invalid_input)?;
+  save_settings(&app, &existing)
+}
+
+/// Restores the favorite's target options and starts monitoring it.
+#[tauri::command]
+async fn quick_start(app: AppHandle, favorite_id: String) -> Result<String, AppError> {
+  tauri::async_runtime::spawn_blocking(move || quick_start_session(&app, &favorite_id))
+    .await
+    .map_err(|_| AppError::cancelled("启动监控被取消"))?
+}
+
+fn quick_start_session(app: &AppHandle, favorite_id: &str) -> Result<String, AppError> {
+  let address = {
+    let _guard = SETTINGS_LOCK.lock_or_recover();
+    let mut existing = load_settings(app);
+    let target = favorites::find(&existing.favorites, favorite_id)
+      .map_err(AppError::not_found)?
+      .target
+      .clone();
+    let address = target.address.clone();
+    targets::upsert(&mut existing.targets, target);
+    save_settings(app, &existing)?;
+    address
+  };
+  start_session(app, &address)
+}
+
+#[tauri::command]
+fn get_jobs(app: AppHandle) -> Result<Vec<ProbeJob>, AppError> {
+  Ok(load_settings(&app).jobs)
+}
+
+#[tauri::command]
+fn save_job(app: AppHandle, job: ProbeJob) -> Result<ProbeJob, AppError> {
+  jobs::validate(&job).map_err(AppError::invalid_input)?;
+  let mut job = job;
+  if job.id.trim().is_empty() {
+    job.id = jobs::new_job_id();
+  }
+  update_settings(&app, |existing| match existing.jobs.iter_mut().find(|j| j.id == job.id) {
+    Some(slot) => *slot = job.clone(),
+    None => existing.jobs.push(job.clone()),
+  })?;
+  Ok(job)
+}
+
+#[tauri::command]
+fn remove_job(app: AppHandle, id: String) -> Result<(), AppError> {
+  update_settings(&app, |existing| existing.jobs.retain(|job| job.id != id))
+}
+
+#[tauri::command]
+async fn run_job_now(app: AppHandle, id: String) -> Result<JobReport, AppError> {
+  let job = load_settings(&app)
+    .jobs
+    .into_iter()
+    .find(|job| job.id == id)
+    .ok_or_else(|| "任务不存在".to_string())?;
+  tauri::async_runtime::spawn_blocking(move || jobs::run_job(&app, &job))
+    .await
+    .map_err(|_| AppError::cancelled("任务被取消"))
+}
+
+#[tauri::command]
+fn get_relay_agents(app: AppHandle) -> Result<Vec<RelayAgent>, AppError> {
+  Ok(load_settings(&app).relays)
+}
+
+#[tauri::command]
+fn save_relay_agent(app: AppHandle, agent: RelayAgent) -> Result<(), AppError> {
+  let agent = RelayAgent {
+    name: agent.name.trim().to_string(),
+    url: agent.url.trim().to_string(),
+    ..agent
+  };
+  relay::validate(&agent).map_err(AppError::invalid_input)?;
+  update_settings(&app, |existing| relay::upsert(&mut existing.relays, agent))
+}
+
+#[tauri::command]
+fn remove_relay_agent(app: AppHandle, name: String) -> Result<(), AppError> {
+  update_settings(&app, |existing| existing.relays.retain(|agent| agent.name != name.trim()))
+}
+
+/// Pings `address` `count` times (default 5) from here and from every enabled relay agent,
+/// and says whether it is down everywhere or only from here.
+#[tauri::command]
+async fn probe_from_relays(app: AppHandle, address: String, count: Option<u32>) -> Result<GeoReport, AppError> {
+  let address = address.trim().to_string();
+  targets::validate_host(&address).map_err(AppError::invalid_input)?;
+  let count = count.unwrap_or(relay::DEFAULT_COUNT);
+  if !(1..=relay::MAX_COUNT).contains(&count) {
+    return Err(AppError::invalid_input(format!("探测次数应在 1-{} 之间", relay::MAX_COUNT)));
+  }
+  let settings = load_settings(&app);
+  tauri::async_runtime::spawn_blocking(move || {
+    relay::probe_all(&settings.relays, &settings.proxy, &address, count, settings.ping.encoding)
+  })
+  .await
+  .map_err(|_| AppError::cancelled("探测被取消"))
+}
+
+#[tauri::command]
+fn get_relay_server_settings(app: AppHandle) -> Result<RelayServerSettings, AppError> {
+  Ok(load_settings(&app).relay_server)
+}
+
+/// Saves the relay mode settings and starts, restarts or stops the listener to match.
+#[tauri::command]
+fn save_relay_server_settings(
+  app: AppHandle,
+  state: State<RelayServerState>,
+  settings: RelayServerSettings,
+) -> Result<RelayServerStatus, AppError> {
+  relay_server::validate(&settings).map_err(AppError::invalid_input)?;
+  state.apply(&settings, load_settings(&app).ping.encoding)?;
+  update_settings(&app, |existing| existing.relay_server = settings)?;
+  Ok(state.status())
+}
+
+#[tauri::command]
+fn get_relay_server_status(state: State<RelayServerState>) -> Result<RelayServerStatus, AppError> {
+  Ok(state.status())
+}
+
+#[tauri::command]
+fn get_viewer_mode() -> Result<ViewerMode, AppError> {
+  Ok(viewer::status())
+}
+
+/// Turns the viewer mode setting on or off. Refused while viewer mode is on, so from the UI it
+/// can only be switched on.
+#[tauri::command]
+fn set_viewer_mode(app: AppHandle, enabled: bool) -> Result<ViewerMode, AppError> {
+  update_settings(&app, |settings| settings.viewer_mode = enabled)?;
+  viewer::set_setting(enabled);
+  Ok(viewer::status())
+}
+
+#[tauri::command]
+fn get_remote_control_settings(app: AppHandle) -> Result<RemoteControlSettings, AppError> {
+  Ok(load_settings(&app).remote_control)
+}
+
+/// Saves the remote control settings and starts, restarts or stops the listener to match.
+#[tauri::command]
+fn save_remote_control_settings(
+  app: AppHandle,
+  state: State<RemoteControlState>,
+  settings: RemoteControlSettings,
+) -> Result<RemoteControlStatus, AppError> {
+  remote_control::validate(&settings).map_err(AppError::invalid_input)?;
+  state.apply(&app, &settings)?;
+  update_settings(&app, |existing| existing.remote_control = settings)?;
+  Ok(state.status())
+}
+
+#[tauri::command]
+fn get_remote_control_status(state: State<RemoteControlState>) -> Result<RemoteControlStatus, AppError> {
+  Ok(state.status())
+}
+
+/// RTT distribution of the session target `address` over the last `window_minutes` (default 60).
+#[tauri::command]
+fn get_latency_histogram(
+  histograms: State<LatencyHistograms>,
+  address: String,
+  window_minutes: Option<u32>,
+) -> Result<LatencyHistogram, AppError> {
+  Ok(histograms.report(&address, window_minutes.unwrap_or(60), &Local::now()))
+}
+
+/// The latest `limit` alerts (default 50), newest first, with the outcome of each channel.
+#[tauri::command]
+fn get_alert_history(history: State<AlertHistory>, limit: Option<usize>) -> Result<Vec<AlertRecord>, AppError> {
+  Ok(history.recent(limit.unwrap_or(50)))
+}
+
+#[tauri::command]
+fn get_histogram_settings(app: AppHandle) -> Result<HistogramSettings, AppError> {
+  Ok(load_settings(&app).histogram)
+}
+
+#[tauri::command]
+fn save_histogram_settings(
+  app: AppHandle,
+  histograms: State<LatencyHistograms>,
+  settings: HistogramSettings,
+) -> Result<(), AppError> {
+  latency_histogram::validate(&settings).map_err(AppError::invalid_input)?;
+  histograms.set_bounds(&settings.bounds_ms);
+  update_settings(&app, |existing| existing.histogram = settings)
+}
+
+#[tauri::command]
+fn get_anomaly_settings(app: AppHandle) -> Result<AnomalySettings, AppError> {
+  Ok(load_settings(&app).anomaly)
+}
+
+#[tauri::command]
+fn save_anomaly_settings(app: AppHandle, settings: AnomalySettings) -> Result<(), AppError> {
+  anomaly::validate(&settings).map_err(AppError::invalid_input)?;
+  update_settings(&app, |existing| existing.anomaly = settings)
+}
+
+#[tauri::command]
+fn get_alert_settings(app: AppHandle) -> Result<AlertSettings, AppError> {
+  let settings = load_settings(&app);
+  Ok(AlertSettings {
+    smtp: settings.smtp,
+    wechat: settings.wechat,
+    reminders: settings.reminders,
+    idle_reminder: settings.idle_reminder,
+    flap: settings.flap,
+    digest: settings.digest,
+    sms: settings.sms,
+    oncall: settings.oncall,
+    feishu: settings.feishu,
+    proxy: settings.proxy,
+    quiet_networks: settings.quiet_networks,
+    speech: settings.speech,
+    alertmanager: settings.alertmanager,
+  })
+}
+
+#[tauri::command]
+fn save_alert_settings(app: AppHandle, settings: AlertSettings) -> Result<(), AppError> {
+  if settings.reminders.enabled && settings.reminders.interval_minutes == 0 {
+    return Err(AppError::invalid_input("提醒间隔必须大于 0"));
+  }
+  alerts::validate_subject_template(&settings.smtp.subject_template).map_err(AppError::invalid_input)?;
+  alerts::validate_body_template(&settings.smtp.body_template).map_err(AppError::invalid_input)?;
+  chart::validate_minutes(settings.smtp.chart_minutes).map_err(AppError::invalid_input)?;
+  proxy::validate_url(settings.smtp.proxy.as_deref().unwrap_or_default()).map_err(AppError::invalid_input)?;
+  idle_reminder::validate(&settings.idle_reminder).map_err(AppError::invalid_input)?;
+  flap::validate(&settings.flap).map_err(AppError::invalid_input)?;
+  digest::validate(&settings.digest).map_err(AppError::invalid_input)?;
+  sms::validate(&settings.sms).map_err(AppError::invalid_input)?;
+  oncall::validate(&settings.oncall).map_err(AppError::invalid_input)?;
+  feishu::validate(&settings.feishu).map_err(AppError::invalid_input)?;
+  proxy::validate_url(&settings.proxy.url).map_err(AppError::invalid_input)?;
+  quiet_networks::validate(&settings.quiet_networks).map_err(AppError::invalid_input)?;
+  speech::validate(&settings.speech).map_err(AppError::invalid_input)?;
+  alertmanager::validate(&settings.alertmanager).map_err(AppError::invalid_input)?;
+  let _guard = SETTINGS_LOCK.lock_or_recover();
+  let mut existing = load_settings(&app);
+  existing.smtp = settings.smtp;
+  existing.wechat = settings.wechat;
+  existing.reminders = settings.reminders;
+  existing.idle_reminder = settings.idle_reminder;
+  existing.flap = settings.flap;
+  existing.digest = settings.digest;
+  existing.sms = settings.sms;
+  existing.oncall = settings.oncall;
+  existing.feishu = settings.feishu;
+  existing.proxy = settings.proxy;
+  existing.quiet_networks = settings.quiet_networks;
+  existing.speech = settings.speech;
+  existing.alertmanager = settings.alertmanager;
+  save_settings(&app, &existing)
+}
+
+/// Writes the alert settings to `path` or a file chosen in a save dialog.
+#[tauri::command]
+async fn export_alert_settings(app: AppHandle, path: Option<String>) -> Result<Option<String>, AppError> {
+  let settings = load_settings(&app);
+  let alert = AlertSettings {
+    smtp: settings.smtp,
+    wechat: settings.wechat,
+    reminders: settings.reminders,
+    idle_reminder: settings.idle_reminder,
+    flap: settings.flap,
+    digest: settings.digest,
+    sms: settings.sms,
+    oncall: settings.oncall,
+    feishu: settings.feishu,
+    proxy: settings.proxy,
+    quiet_networks: settings.quiet_networks,
+    speech: settings.speech,
+    alertmanager: settings.alertmanager,
+  };
+
+  let data = serde_json::to_string_pretty(&alert).map_err(|e| e.to_string())?;
+
+  let dialog = rfd::AsyncFileDialog::new()
+    .set_title("导出告警配置")
+    .add_filter("JSON", &["json"])
+    .set_file_name("alert-settings.json");
+  let Some(path) = dialogs::save_file(path, dialog).await else {
+    return Ok(None);
+  };
+
+  dialogs::run_io(move || {
+    if let Some(parent) = path.parent() {
+      create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    std::fs::write(&path, data).map_err(|e| e.to_string())?;
+    Ok(Some(path.to_string_lossy().to_string()))
+  })
+  .await
+}
 
-  let Some(path) = file_path else {
+/// Reads alert settings from `path` or a file chosen in an open dialog and saves them.
+#[tauri::command]
+async fn import_alert_settings(app: AppHandle, path: Option<String>) -> Result<Option<AlertSettings>, AppError> {
+  let dialog = rfd::AsyncFileDialog::new()
+    .set_title("导入告警配置")
+    .add_filter("JSON", &["json"]);
+  let Some(path) = dialogs::pick_file(path, dialog).await else {
     return Ok(None);
   };
 
-  let contents = read_to_string(&path).map_err(|e| e.to_string())?;
+  let contents = dialogs::run_io(move || read_to_string(&path).map_err(|e| AppError::from(e.to_string()))).await?;
   let alert: AlertSettings = serde_json::from_str(&contents).map_err(|e| e.to_string())?;
 
+  let _guard = SETTINGS_LOCK.lock_or_recover();
   let mut existing = load_settings(&app);
   existing.smtp = alert.smtp.clone();
   existing.wechat = alert.wechat.clone();
+  existing.reminders = alert.reminders.clone();
+  existing.idle_reminder = alert.idle_reminder.clone();
+  existing.flap = alert.flap.clone();
+  existing.digest = alert.digest.clone();
+  existing.sms = alert.sms.clone();
+  existing.oncall = alert.oncall.clone();
+  existing.feishu = alert.feishu.clone();
+  existing.proxy = alert.proxy.clone();
+  existing.quiet_networks = alert.quiet_networks.clone();
+  existing.speech = alert.speech.clone();
+  existing.alertmanager = alert.alertmanager.clone();
   save_settings(&app, &existing)?;
 
   Ok(Some(alert))
 }
 
 #[tauri::command]
-async fn test_smtp(smtp: SmtpSettings) -> Result<String, String> {
+async fn test_sms(app: AppHandle, sms: SmsSettings, dry_run: Option<bool>) -> Result<ChannelTestResult, AppError> {
+  sms::validate(&SmsSettings { enabled: true, ..sms.clone() }).map_err(AppError::invalid_input)?;
+  let (target, start, _) = alerts::sample_outage();
+  let incident_id = incidents::incident_id(&target.address, &start)?;
+  let preview = AlertPreview {
+    subject: None,
+    body: sms::render(&sms, "中断", &target.display_name(), &start, &incident_id),
+  };
+  if dry_run.unwrap_or(false) {
+    return Ok(ChannelTestResult {
+      message: "仅预览，未发送".to_string(),
+      preview,
+    });
+  }
+  let proxy = proxy::effective(&load_settings(&app).proxy, &sms.proxy);
+  let sms = SmsSettings { proxy, ..sms };
+  let now = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+  tauri::async_runtime::spawn_blocking(move || sms::send(&sms, "测试", "Ping Tool", &now, ""))
+    .await
+    .map_err(|_| AppError::cancelled("测试任务被取消"))??;
+  Ok(ChannelTestResult {
+    message: "测试短信已发送".to_string(),
+    preview,
+  })
+}
+
+#[tauri::command]
+async fn test_feishu(
+  app: AppHandle,
+  feishu: FeishuSettings,
+  dry_run: Option<bool>,
+) -> Result<ChannelTestResult, AppError> {
+  feishu::validate(&FeishuSettings {
+    enabled: true,
+    ..feishu.clone()
+  })
+  .map_err(AppError::invalid_input)?;
+  let (target, start, _) = alerts::sample_outage();
+  let incident_id = incidents::incident_id(&target.address, &start)?;
+  let message = alerts::with_incident(&alerts::outage_message(&target, 3, &start, None), Some(&incident_id));
+  let (color, lines) = alerts::outage_card(&target, &message, false);
+  let payload = feishu::card(alerts::OUTAGE_CARD_TITLE, color, &lines);
+  let preview = AlertPreview {
+    subject: None,
+    body: serde_json::to_string_pretty(&payload).map_err(|e| e.to_string())?,
+  };
+  if dry_run.unwrap_or(false) {
+    return Ok(ChannelTestResult {
+      message: "仅预览，未发送".to_string(),
+      preview,
+    });
+  }
+  let proxy = proxy::effective(&load_settings(&app).proxy, &feishu.proxy);
+  let feishu = FeishuSettings { proxy, ..feishu };
+  let line = format!("这是一条测试消息，发送时间: {}", Local::now().format("%Y-%m-%d %H:%M:%S"));
+  tauri::async_runtime::spawn_blocking(move || {
+    feishu::send_card(&feishu, "Ping Tool 测试消息", CardColor::Blue, &[line])
+  })
+  .await
+  .map_err(|_| AppError::cancelled("测试任务被取消"))??;
+  Ok(ChannelTestResult {
+    message: "测试消息已发送".to_string(),
+    preview,
+  })
+}
+
+#[tauri::command]
+async fn test_alertmanager(
+  app: AppHandle,
+  alertmanager: AlertmanagerSettings,
+  dry_run: Option<bool>,
+) -> Result<ChannelTestResult, AppError> {
+  alertmanager::validate(&AlertmanagerSettings {
+    enabled: true,
+    ..alertmanager.clone()
+  })
+  .map_err(AppError::invalid_input)?;
+  let (target, start, timestamp) = alerts::sample_outage();
+  let incident_id = incidents::incident_id(&target.address, &start)?;
+  let message = alerts::with_incident(&alerts::outage_message(&target, 3, &start, None), Some(&incident_id));
+  let event = AlertEvent::new(AlertKind::OutageStarted, AlertSeverity::Critical, &timestamp, message)
+    .target(&target)
+    .span(&start, None)
+    .incident(Some(incident_id), false);
+  let payload =
+    alertmanager::payload(&alertmanager, &event).ok_or_else(|| "无法生成 Alertmanager 消息".to_string())?;
+  let preview = AlertPreview {
+    subject: None,
+    body: serde_json::to_string_pretty(&payload).map_err(|e| e.to_string())?,
+  };
+  if dry_run.unwrap_or(false) {
+    return Ok(ChannelTestResult {
+      message: "仅预览，未发送".to_string(),
+      preview,
+    });
+  }
+  let proxy = proxy::effective(&load_settings(&app).proxy, &alertmanager.proxy);
+  let alertmanager = AlertmanagerSettings { proxy, ..alertmanager };
+  tauri::async_runtime::spawn_blocking(move || alertmanager::send(&alertmanager, &payload))
+    .await
+    .map_err(|_| AppError::cancelled("测试任务被取消"))??;
+  Ok(ChannelTestResult {
+    message: "测试告警已发送".to_string(),
+    preview,
+  })
+}
+
+/// Speaks a sample outage announcement with the given voice and returns the text.
+#[tauri::command]
+async fn test_speech(speech: SpeechSettings) -> Result<String, AppError> {
+  speech::validate(&speech).map_err(AppError::invalid_input)?;
+  let (target, start, _) = alerts::sample_outage();
+  let event = AlertEvent::new(AlertKind::OutageStarted, AlertSeverity::Critical, &start, String::new()).target(&target);
+  let text = speech::announcement(&event, Some(Duration::from_secs(120)));
+  let spoken = text.clone();
+  tauri::async_runtime::spawn_blocking(move || speech::speak(&speech.voice, &spoken))
+    .await
+    .map_err(|_| AppError::cancelled("测试任务被取消"))??;
+  Ok(text)
+}
+
+#[tauri::command]
+async fn test_smtp(app: AppHandle, smtp: SmtpSettings, dry_run: Option<bool>) -> Result<ChannelTestResult, AppError> {
+  let (target, start, recover) = alerts::sample_outage();
+  let incident_id = incidents::incident_id(&target.address, &start)?;
+  let mut recovery = alerts::recovery(&target, &start, &recover, Duration::from_secs(300), false);
+  recovery.incident(&incident_id);
+  let event = AlertEvent::new(AlertKind::OutageRecovered, AlertSeverity::Info, &recover, recovery.plain)
+    .target(&target)
+    .span(&start, Some(&recover))
+    .incident(Some(incident_id), false);
+  let preview = AlertPreview {
+    subject: Some(alerts::render_subject(&smtp.subject_template, recovery.subject, &event)),
+    body: alerts::render_body(&smtp.body_template, &recovery.html, Some(&event), None),
+  };
+  if dry_run.unwrap_or(false) {
+    return Ok(ChannelTestResult {
+      message: "仅预览，未发送".to_string(),
+      preview,
+    });
+  }
+  let smtp = smtp.with_proxy(&load_settings(&app).proxy);
   let mut handle = tauri::async_runtime::spawn_blocking(move || test_smtp_sync(smtp));
   let result = match tokio::time::timeout(Duration::from_secs(15), &mut handle).await {
-    Ok(result) => result.map_err(|_| "测试任务被取消".to_string())?,
+    Ok(result) => result.map_err(|_| AppError::cancelled("测试任务被取消"))?,
     Err(_) => {
       // Best-effort abort: blocking task may continue in background.
       handle.abort();
-      return Err("连接超时（15 秒）".to_string());
+      return Err(AppError::new(ErrorKind::Network, "连接超时（15 秒）"));
     }
   };
-  result
+  Ok(ChannelTestResult {
+    message: result?,
+    preview,
+  })
+}
+
+#[tauri::command]
+async fn scan_ports(
+  host: String,
+  ports: String,
+  timeout_ms: Option<u64>,
+) -> Result<Vec<PortScanResult>, AppError> {
+  let host = host.trim().to_string();
+  if host.is_empty() {
+    return Err(AppError::invalid_input("Address cannot be empty"));
+  }
+  let ports = port_scan::parse_ports(&ports).map_err(AppError::invalid_input)?;
+  let timeout = Duration::from_millis(timeout_ms.unwrap_or(1000).clamp(100, 10_000));
+  tauri::async_runtime::spawn_blocking(move || port_scan::scan(&host, ports, timeout))
+    .await
+    .map_err(|_| AppError::cancelled("扫描任务被取消"))?
+    .map_err(AppError::from)
+}
+
+/// Pings `address` with growing payloads, don't-fragment set, and with fill patterns, to find
+/// where loss begins. Sizes default to 64-1472 bytes, patterns to zeros, ones and alternating bits.
+#[tauri::command]
+async fn run_payload_sweep(
+  app: AppHandle,
+  address: String,
+  sizes: Option<Vec<u32>>,
+  patterns: Option<Vec<String>>,
+  count: Option<u32>,
+) -> Result<SweepReport, AppError> {
+  let address = address.trim().to_string();
+  targets::validate_host(&address).map_err(AppError::invalid_input)?;
+  let sizes = sizes.unwrap_or_else(|| ping_core::sweep::DEFAULT_SIZES.to_vec());
+  let patterns: Vec<String> = patterns.map_or_else(
+    || ping_core::sweep::DEFAULT_PATTERNS.iter().map(|p| p.to_string()).collect(),
+    |patterns| patterns.iter().map(|p| p.trim().to_ascii_lowercase()).collect(),
+  );
+  let count = count.unwrap_or(payload_sweep::DEFAULT_COUNT);
+  payload_sweep::validate(&sizes, &patterns, count).map_err(AppError::invalid_input)?;
+  let settings = load_settings(&app);
+  let (encoding, binary) = (settings.ping.encoding, ping_binary_for(&settings, &address));
+  tauri::async_runtime::spawn_blocking(move || {
+    let ping = PingOptions {
+      encoding,
+      dscp: None,
+      binary: binary.as_ref(),
+    };
+    let result = payload_sweep_in(&address, &ping, &sizes, &patterns, count)?;
+    let supported = ping_core::sweep::supports_patterns(Platform::current());
+    Ok::<_, String>(payload_sweep::report(&address, result, &patterns, supported))
+  })
+  .await
+  .map_err(|_| AppError::cancelled("载荷扫描被取消"))?
+  .map_err(AppError::from)
 }
 
 fn test_smtp_sync(smtp: SmtpSettings) -> Result<String, String> {
@@ -350,41 +2273,7 @@ fn test_smtp_sync(smtp: SmtpSettings) -> Result<String, String> {
     .parse::<Mailbox>()
     .map_err(|_| "测试收件人邮箱格式不正确".to_string())?;
 
-  let tls_mode = smtp
-    .tls_mode
-    .clone()
-    .unwrap_or_else(|| if smtp.use_tls { TlsMode::Ssl } else { TlsMode::None });
-
-  let base_scheme = match tls_mode {
-    TlsMode::Ssl => "smtps",
-    _ => "smtp",
-  };
-
-  let mut url = Url::parse(&format!("{base_scheme}://localhost")).map_err(|e| e.to_string())?;
-  url
-    .set_host(Some(host))
-    .map_err(|_| "SMTP 主机不合法".to_string())?;
-  url
-    .set_port(Some(smtp.port))
-    .map_err(|_| "SMTP 端口不合法".to_string())?;
-  if matches!(tls_mode, TlsMode::Starttls) {
-    url
-      .query_pairs_mut()
-      .append_pair("tls", "required");
-  }
-
-  let mut builder = SmtpTransport::from_url(url.as_str())
-    .map_err(|e| format!("SMTP 配置无效: {e}\n{:?}", e))?
-    .timeout(Some(Duration::from_secs(10)));
-
-  if !smtp.username.is_empty() {
-    builder = builder.credentials(Credentials::new(
-      smtp.username.clone(),
-      smtp.password.clone(),
-    ));
-  }
-
-  let mailer = builder.build();
+  let mailer = smtp_transport(&smtp, host)?;
 
   let subject = "Ping Tool 测试邮件";
   let body = format!(
@@ -407,7 +2296,80 @@ fn test_smtp_sync(smtp: SmtpSettings) -> Result<String, String> {
   Ok("测试邮件已发送。".to_string())
 }
 
-fn send_alert_email(smtp: &SmtpSettings, message: &str) -> Result<(), String> {
+/// A transport for `host`, connecting through `smtp.proxy` when one is set.
+fn smtp_transport(smtp: &SmtpSettings, host: &str) -> Result<SmtpTransport, String> {
+  let tls_mode = smtp
+    .tls_mode
+    .clone()
+    .unwrap_or_else(|| if smtp.use_tls { TlsMode::Ssl } else { TlsMode::None });
+  let timeout = Duration::from_secs(10);
+
+  let builder = match smtp.proxy.as_deref().map(str::trim).filter(|proxy| !proxy.is_empty()) {
+    // lettre can't use a proxy itself, so it talks to the local end of a tunnel; certificates
+    // are still checked against the real host.
+    Some(proxy) => {
+      let local = proxy::tunnel(proxy, host, smtp.port, timeout)?;
+      let parameters = || TlsParameters::new(host.to_string()).map_err(|e| format!("SMTP TLS 配置无效: {e}"));
+      let tls = match tls_mode {
+        TlsMode::Ssl => Tls::Wrapper(parameters()?),
+        TlsMode::Starttls => Tls::Required(parameters()?),
+        TlsMode::None => Tls::None,
+      };
+      SmtpTransport::builder_dangerous(local.ip().to_string())
+        .port(local.port())
+        .tls(tls)
+    }
+    None => {
+      let base_scheme = match tls_mode {
+        TlsMode::Ssl => "smtps",
+        _ => "smtp",
+      };
+      let mut url = Url::parse(&format!("{base_scheme}://localhost")).map_err(|e| e.to_string())?;
+      url
+        .set_host(Some(host))
+        .map_err(|_| "SMTP 主机不合法".to_string())?;
+      url
+        .set_port(Some(smtp.port))
+        .map_err(|_| "SMTP 端口不合法".to_string())?;
+      if matches!(tls_mode, TlsMode::Starttls) {
+        url
+          .query_pairs_mut()
+          .append_pair("tls", "required");
+      }
+      SmtpTransport::from_url(url.as_str()).map_err(|e| format!("SMTP 配置无效: {e}"))?
+    }
+  };
+
+  let mut builder = builder.timeout(Some(timeout));
+  if !smtp.username.is_empty() {
+    builder = builder.credentials(Credentials::new(
+      smtp.username.clone(),
+      smtp.password.clone(),
+    ));
+  }
+  Ok(builder.build())
+}
+
+/// Sends `message` as the body of an alert email, laid out by the body template with the details
+/// of `event`; digests and reports have none.
+fn send_alert_email(
+  smtp: &SmtpSettings,
+  subject: &str,
+  message: &str,
+  event: Option<&AlertEvent>,
+) -> Result<(), String> {
+  send_alert_email_with_chart(smtp, subject, message, event, None)
+}
+
+/// `send_alert_email`, with a latency chart shown inline: below the message, or where the body
+/// template puts `{{chart}}`.
+fn send_alert_email_with_chart(
+  smtp: &SmtpSettings,
+  subject: &str,
+  message: &str,
+  event: Option<&AlertEvent>,
+  chart: Option<&Chart>,
+) -> Result<(), String> {
   let host = smtp.host.trim();
   if host.is_empty() {
     return Err("SMTP 主机未配置".to_string());
@@ -423,55 +2385,32 @@ fn send_alert_email(smtp: &SmtpSettings, message: &str) -> Result<(), String> {
     .from
     .parse::<Mailbox>()
     .map_err(|_| "发件人邮箱格式不正确".to_string())?;
-  let to = smtp
+  // Several recipients may be given, separated by commas or semicolons.
+  let recipients = smtp
     .to
-    .parse::<Mailbox>()
-    .map_err(|_| "收件人邮箱格式不正确".to_string())?;
-
-  let tls_mode = smtp
-    .tls_mode
-    .clone()
-    .unwrap_or_else(|| if smtp.use_tls { TlsMode::Ssl } else { TlsMode::None });
-
-  let base_scheme = match tls_mode {
-    TlsMode::Ssl => "smtps",
-    _ => "smtp",
-  };
-
-  let mut url = Url::parse(&format!("{base_scheme}://localhost")).map_err(|e| e.to_string())?;
-  url
-    .set_host(Some(host))
-    .map_err(|_| "SMTP 主机不合法".to_string())?;
-  url
-    .set_port(Some(smtp.port))
-    .map_err(|_| "SMTP 端口不合法".to_string())?;
-
-  if matches!(tls_mode, TlsMode::Starttls) {
-    url
-      .query_pairs_mut()
-      .append_pair("tls", "required");
+    .split([',', ';'])
+    .map(str::trim)
+    .filter(|to| !to.is_empty())
+    .map(|to| to.parse::<Mailbox>().map_err(|_| format!("收件人邮箱格式不正确: {to}")))
+    .collect::<Result<Vec<_>, _>>()?;
+
+  let mailer = smtp_transport(smtp, host)?;
+  let mut email = Message::builder().from(from);
+  for to in recipients {
+    email = email.to(to);
   }
-
-  let mut builder = SmtpTransport::from_url(url.as_str())
-    .map_err(|e| format!("SMTP 配置无效: {e}"))?
-    .timeout(Some(Duration::from_secs(10)));
-
-  if !smtp.username.is_empty() {
-    builder = builder.credentials(Credentials::new(
-      smtp.username.clone(),
-      smtp.password.clone(),
-    ));
+  let email = email.subject(subject);
+  let body = alerts::render_body(&smtp.body_template, message, event, chart.map(Chart::html).as_deref());
+  let email = match chart {
+    // A template without `{{chart}}` doesn't show it; don't attach it either.
+    Some(chart) if body.contains(chart::CONTENT_ID) => {
+      let image = Attachment::new_inline(chart::CONTENT_ID.to_string())
+        .body(chart.png.clone(), ContentType::parse("image/png").map_err(|e| e.to_string())?);
+      email.multipart(MultiPart::related().singlepart(SinglePart::html(body)).singlepart(image))
+    }
+    _ => email.header(ContentType::TEXT_HTML).body(body),
   }
-
-  let mailer = builder.build();
-  let subject = "网络丢包告警";
-  let email = Message::builder()
-    .from(from)
-    .to(to)
-    .subject(subject)
-    .header(ContentType::TEXT_HTML)
-    .body(message.to_string())
-    .map_err(|e| format!("构建告警邮件失败: {e}"))?;
+  .map_err(|e| format!("构建告警邮件失败: {e}"))?;
 
   mailer
     .send(&email)
@@ -482,271 +2421,1163 @@ fn send_alert_email(smtp: &SmtpSettings, message: &str) -> Result<(), String> {
 
 fn ping_loop(
   app: AppHandle,
-  base_dir: PathBuf,
+  store: Arc<dyn ResultStore>,
   address: String,
+  encoding: PingEncoding,
   stop_rx: mpsc::Receiver<()>,
-  log_buffer: Arc<Mutex<LogBuffer>>,
-) {
-  if let Err(e) = create_dir_all(&base_dir) {
-    eprintln!("failed to create log base dir: {e}");
-    return;
-  }
-
-  let mut fail_count: u32 = 0;
-  let mut first_fail_time: Option<String> = None;
-  let mut outage_start: Option<String> = None;
-
-  loop {
+  probe: ProbeSlot,
+  handles: SessionHandles,
+) -> StopReason {
+  let SessionHandles {
+    logs: log_buffer,
+    http_timings,
+    incidents,
+    drill,
+    stats,
+  } = handles;
+  let initial_settings = load_settings(&app);
+  let target = targets::find(&initial_settings.targets, &address);
+  let target_name = target.display_name();
+  // What gets logged and sent; `address` keeps the credentials a probe URL may carry.
+  let public_address = targets::public_address(&address).into_owned();
+  let binary = ping_binary_for(&initial_settings, &address);
+  let mut exporter = OtlpExporter::new(initial_settings.otlp, &public_address);
+  let mut latency = LatencyBaseline::new(initial_settings.anomaly.clone());
+  let mut ttl_tracker = TtlTracker::default();
+  let self_check = SelfCheck::start(encoding);
+  let speedtest = initial_settings.speedtest;
+  let speedtest_running = Arc::new(AtomicBool::new(false));
+  let mut last_speedtest = Instant::now();
+  let mut detector = OutageDetector::new(initial_settings.ping.outage_config());
+  let burst = initial_settings.ping.burst();
+  let probe_budget = Duration::from_millis(initial_settings.ping.probe_budget_ms);
+  let startup_grace = Duration::from_secs(initial_settings.ping.startup_grace_secs);
+  let mut quiet_until = (!startup_grace.is_zero()).then(|| Instant::now() + startup_grace);
+  let mut outage_captive = false;
+  let mut outage_checks: Option<OutageChecks> = None;
+  let mut outage_severity = AlertSeverity::Critical;
+  let mut outage_tunnel = false;
+  let mut outage_drill = false;
+  let mut outage_damped = false;
+  let mut flap = FlapDetector::new(initial_settings.flap);
+  let mut incident_id: Option<String> = None;
+  let reminders = initial_settings.reminders;
+  let quiet_list = initial_settings.quiet_networks;
+  let mut quiet_network: Option<String> = None;
+  let mut last_reminder = Instant::now();
+  let mut in_window = true;
+  let mut last_tick: Option<(Instant, DateTime<Local>)> = None;
+  let self_metrics = app.state::<SelfMetricsState>();
+  let histograms = app.state::<LatencyHistograms>();
+  histograms.set_bounds(&initial_settings.histogram.bounds_ms);
+  let mut last_drift_warning: Option<Instant> = None;
+  let mut rolling = RollingLoss::default();
+  let mut last_rtt: Option<f64> = None;
+  let mut last_summary: Option<Instant> = None;
+
+  let reason = loop {
     if stop_rx.try_recv().is_ok() {
-      break;
+      break StopReason::User;
     }
 
     let loop_start = Instant::now();
     let now = Local::now();
 
-    let date_folder = now.format("%Y-%m-%d").to_string();
-    let hour_folder = now.format("%H").to_string();
-    let minute_stamp = now.format("%Y-%m-%d_%H-%M").to_string();
     let timestamp = now.format("%Y-%m-%d %H:%M:%S").to_string();
-
-    let dir = base_dir.join(date_folder).join(hour_folder);
-    if let Err(e) = create_dir_all(&dir) {
+    if let Err(e) = store.ready(&now) {
       eprintln!("failed to create log dir: {e}");
-      break;
+      break StopReason::LogDirFailed { error: e.to_string() };
+    }
+
+    // Wall time should advance in step with the monotonic clock; anything else is an NTP
+    // correction, a manual change or a resume from sleep. Durations use `Instant` regardless,
+    // but wall-clock based reports need to know where the discontinuity is.
+    if let Some((tick_instant, tick_wall)) = last_tick {
+      self_metrics.record_tick(loop_start.duration_since(tick_instant));
+      let monotonic = loop_start.duration_since(tick_instant).as_secs_f64();
+      let wall = (now - tick_wall).num_milliseconds() as f64 / 1000.0;
+      let skew = wall - monotonic;
+      // Suspended time passes on the wall clock, and on some systems on the monotonic clock too.
+      if !startup_grace.is_zero() && (skew >= RESUME_GAP_SECS || monotonic >= RESUME_GAP_SECS) {
+        quiet_until = Some(loop_start + startup_grace);
+        let line = format!(
+          "[{timestamp}] {target_name} | GRACE | 从睡眠中恢复，{} 内的失败只记录不告警",
+          format_duration(startup_grace)
+        );
+        if let Err(e) = store.record_event(&now, &line) {
+          eprintln!("failed to write log: {e}");
+        }
+        let _ = push_log(&log_buffer, line);
+      }
+      if skew.abs() >= CLOCK_JUMP_THRESHOLD_SECS {
+        let line = format!(
+          "[{timestamp}] {target_name} | CLOCK | 系统时间跳变 {skew:+.0} 秒（上次 {}），期间统计以单调时钟为准",
+          tick_wall.format("%Y-%m-%d %H:%M:%S")
+        );
+        if let Err(e) = store.record_event(&now, &line) {
+          eprintln!("failed to write log: {e}");
+        }
+        let _ = push_log(&log_buffer, line);
+      }
+    }
+    last_tick = Some((loop_start, now));
+
+    let window_open = target.schedule.as_ref().is_none_or(|schedule| schedule.is_active(&now));
+    if window_open != in_window {
+      in_window = window_open;
+      let mut line = if window_open {
+        format!("[{timestamp}] {target_name} | SCHEDULE | 进入监控时段，恢复探测")
+      } else {
+        format!("[{timestamp}] {target_name} | SCHEDULE | 离开监控时段，暂停探测")
+      };
+      // Off-hours downtime is not tracked, so an open outage ends without a recovery alert.
+      if let Some(start_time) = detector.outage_started().filter(|_| !window_open) {
+        line.push_str(&format!("（未结束的中断自 {start_time} 起停止跟踪）"));
+        if let Some(id) = &incident_id {
+          line.push_str(&format!(" | {id}"));
+        }
+        outage_captive = false;
+        outage_drill = false;
+        if let Some(id) = incident_id.take() {
+          incidents.lock_or_recover().close(&id);
+        }
+      }
+      detector.reset();
+      outage_checks = None;
+      if let Err(e) = store.record_event(&now, &line) {
+        eprintln!("failed to write log: {e}");
+      }
+      let seq = push_log(&log_buffer, line.clone());
+      events::emit(
+        &app,
+        "ping-log",
+        PingEvent {
+          seq,
+          line,
+          address: address.clone(),
+          label: target.label.clone(),
+          color: target.color.clone(),
+          ttl: None,
+        },
+      );
+    }
+    if !in_window {
+      if last_summary.is_none_or(|at| at.elapsed() >= SUMMARY_INTERVAL) {
+        last_summary = Some(Instant::now());
+        events::emit(
+          &app,
+          "ping-summary",
+          PingSummary {
+            address: address.clone(),
+            label: target.label.clone(),
+            status: LinkStatus::Paused,
+            last_rtt_ms: None,
+            loss_1m_percent: None,
+            loss_5m_percent: None,
+            outage: None,
+            flapping: flap.is_flapping(),
+          },
+        );
+      }
+      if stop_rx.recv_timeout(Duration::from_secs(1)).is_ok() {
+        break StopReason::User;
+      }
+      continue;
+    }
+
+    let speedtest_interval = Duration::from_secs(speedtest.interval_minutes.saturating_mul(60));
+    if speedtest.enabled
+      && last_speedtest.elapsed() >= speedtest_interval
+      && !speedtest_running.swap(true, Ordering::SeqCst)
+    {
+      last_speedtest = Instant::now();
+      let app = app.clone();
+      let store = store.clone();
+      let log_buffer = log_buffer.clone();
+      let settings = speedtest.clone();
+      let running = speedtest_running.clone();
+      thread::spawn(move || {
+        run_scheduled_speedtest(&app, &store, &log_buffer, &settings);
+        running.store(false, Ordering::SeqCst);
+      });
     }
 
-    let file_path = dir.join(format!("ping_{minute_stamp}.log"));
-    let ping_result = ping_once(&address);
+    let drilling = drill.lock_or_recover().is_some_and(|until| Instant::now() < until);
+    let watchdog = (!drilling && !probe_budget.is_zero()).then(|| probe_budget::Watchdog::arm(&probe, probe_budget));
+    let (ping_result, rtt_ms, cycle_loss) = if drilling {
+      (Err("[DRILL] 模拟故障".to_string()), None, None)
+    } else {
+      let ping = PingOptions {
+        encoding,
+        dscp: target.dscp,
+        binary: binary.as_ref(),
+      };
+      match probe_target(&address, &ping, burst, target.quic, &timestamp, &http_timings, &probe) {
+        (Err(err), _, loss) if target.arp_fallback && loss.is_none_or(|loss| loss >= 1.0) => {
+          (arp::probe(&address).map_err(|_| err), None, None)
+        }
+        outcome => outcome,
+      }
+    };
+    // A stop kills the probe; don't log its aborted result as a failure.
+    if stop_rx.try_recv().is_ok() {
+      break StopReason::User;
+    }
+    let (ping_result, rtt_ms, cycle_loss) = match watchdog {
+      Some(watchdog) if watchdog.expired() => {
+        self_metrics.record_over_budget();
+        let error = format!("超出本轮探测时间预算 {} 毫秒", probe_budget.as_millis());
+        (Err(error), None, cycle_loss.map(|_| 1.0))
+      }
+      _ => (ping_result, rtt_ms, cycle_loss),
+    };
     let result = match &ping_result {
       Ok(line) => line.clone(),
       Err(err) => format!("error: {err}"),
     };
 
-    let summary = format!("{address} | {result}");
+    // Synthetic failures must not skew the exported metrics or statistics.
+    if let Some(exporter) = exporter.as_mut().filter(|_| !drilling) {
+      exporter.record(ping_result.is_ok(), rtt_ms);
+      exporter.maybe_export();
+    }
+    if !drilling {
+      histograms.record(&address, &now, ping_result.is_ok(), rtt_ms);
+    }
+    if !drilling {
+      let mut stats = stats.lock_or_recover();
+      stats.record(&now, ping_result.is_ok(), rtt_ms);
+      if let Ok(line) = &ping_result {
+        stats.record_duplicates(&now, parse_duplicate_count(line));
+        let (late, reordered) = (parse_marker_count(line, LATE_MARKER), parse_marker_count(line, REORDERED_MARKER));
+        stats.record_late_replies(&now, late, reordered);
+      }
+    }
+    let on_quiet_network = quiet_networks::matching(&quiet_list);
+    if on_quiet_network != quiet_network {
+      let message = match &on_quiet_network {
+        Some(name) => format!("已连接静默网络 {name}，暂停告警（继续记录）"),
+        None => format!("已离开静默网络 {}，恢复告警", quiet_network.as_deref().unwrap_or_default()),
+      };
+      let line = format!("[{timestamp}] {target_name} | QUIET | {message}");
+      if let Err(e) = store.record_event(&now, &line) {
+        eprintln!("failed to write log: {e}");
+      }
+      let _ = push_log(&log_buffer, line);
+      quiet_network = on_quiet_network;
+    }
+    let alert_target = if quiet_network.is_some() { target.muted() } else { target.clone() };
+
+    if let (Ok(_), Some(rtt)) = (&ping_result, rtt_ms) {
+      let transition = latency.observe(rtt);
+      stats.lock_or_recover().set_baseline(latency.baseline());
+      match transition {
+        Some(Transition::Began {
+          rtt_ms,
+          baseline,
+          threshold_ms,
+        }) => {
+          let message = format!(
+            "{target_name} 延迟持续高于基线：当前 {rtt_ms:.1} ms，基线 {:.1} ± {:.1} ms（阈值 {threshold_ms:.1} ms）",
+            baseline.mean_ms, baseline.stddev_ms
+          );
+          let settings = load_settings(&app);
+          let event = AlertEvent::new(AlertKind::LatencyAnomaly, AlertSeverity::Warning, &timestamp, message.clone())
+            .target(&target);
+          write_alert(&app, store.as_ref(), &now, &log_buffer, &settings, &event);
+          // A degradation is not an outage: batch it when digest mode is on.
+          if quiet_network.is_none() && !digest::queue(&app, &timestamp, &message) {
+            if let Some(smtp) = alert_target.alert_smtp(&settings.smtp, &settings.proxy) {
+              let subject = alerts::render_subject(&smtp.subject_template, "网络延迟异常", &event);
+              let mut delivery = Delivery::new(&event);
+              let alert = event.clone();
+              delivery.add(Channel::Email, move || send_alert_email(&smtp, &subject, &message, Some(&alert)));
+              delivery.dispatch(&app, &store, &log_buffer);
+            }
+          }
+        }
+        Some(Transition::Ended { rtt_ms, baseline }) => {
+          let message = format!(
+            "{target_name} 延迟回落到基线附近：当前 {rtt_ms:.1} ms，基线 {:.1} ms",
+            baseline.mean_ms
+          );
+          let event =
+            AlertEvent::new(AlertKind::LatencyNormal, AlertSeverity::Info, &timestamp, message).target(&target);
+          write_alert(&app, store.as_ref(), &now, &log_buffer, &load_settings(&app), &event);
+        }
+        None => {}
+      }
+    }
+
+    let mut summary = format!("{target_name} | {result}");
+    // The probes of an open outage carry its ID, so its lines can be found in the logs by it.
+    if let Some(id) = incident_id.as_deref().filter(|_| detector.outage_started().is_some()) {
+      summary.push_str(&format!(" | {id}"));
+    }
     let display_line = format!("[{timestamp}] {summary}");
-    let file_line = format!("{display_line}\n");
-    if let Err(e) = append_line(&file_path, &file_line) {
+    let record = ProbeRecord {
+      at: &now,
+      line: &display_line,
+      success: ping_result.is_ok(),
+      rtt_ms,
+      synthetic: drilling,
+    };
+    if let Err(e) = store.record_probe(&record) {
       eprintln!("failed to write log: {e}");
     }
 
     let seq = push_log(&log_buffer, display_line.clone());
 
-    let _ = app.emit(
+    let reply_ttl = ping_result.as_deref().ok().and_then(ttl::parse);
+    events::emit(
+      &app,
       "ping-log",
       PingEvent {
         seq,
         line: display_line,
+        address: address.clone(),
+        label: target.label.clone(),
+        color: target.color.clone(),
+        ttl: reply_ttl,
       },
     );
 
-    match ping_result {
-      Ok(_) => {
-        if let Some(start_time) = outage_start.take() {
-          let recover_time = timestamp.clone();
-          let alert_message_plain = format!(
-            "开始时间: {start_time}，恢复时间：{recover_time} 网络出现丢包"
-          );
-          let alert_message_html = format!(
-            "开始时间: {start_time}，<br>恢复时间：{recover_time} <br> 网络出现丢包"
-          );
-          let alert_line = format!("[{timestamp}] ALERT | {alert_message_plain}\n");
-          if let Err(e) = append_line(&file_path, &alert_line) {
-            eprintln!("failed to write alert log: {e}");
-          } else {
-            let _ = push_log(&log_buffer, alert_line.trim_end().to_string());
-          }
+    if let Some((old, new)) = reply_ttl.and_then(|ttl| ttl_tracker.observe(ttl)) {
+      let message = format!("{target_name} 回复 TTL 由 {old} 变为 {new}，路由可能发生变化");
+      let line = format!("[{timestamp}] {target_name} | ROUTE | {message}");
+      if let Err(e) = store.record_event(&now, &line) {
+        eprintln!("failed to write log: {e}");
+      }
+      let _ = push_log(&log_buffer, line);
+      let settings = load_settings(&app);
+      if settings.ping.ttl_change_alert
+        && quiet_network.is_none()
+        && !digest::queue(&app, &timestamp, &message)
+      {
+        if let Some(smtp) = alert_target.alert_smtp(&settings.smtp, &settings.proxy) {
+          let event = AlertEvent::new(AlertKind::RouteChanged, AlertSeverity::Info, &timestamp, message.clone())
+            .target(&target);
+          let subject = alerts::render_subject(&smtp.subject_template, "路由变化提示", &event);
+          let mut delivery = Delivery::new(&event);
+          let alert = event.clone();
+          delivery.add(Channel::Email, move || send_alert_email(&smtp, &subject, &message, Some(&alert)));
+          delivery.dispatch(&app, &store, &log_buffer);
+        }
+      }
+    }
 
-          let settings = load_settings(&app);
-          let smtp = settings.smtp.clone();
-          let email_body = alert_message_html.clone();
+    let ping_result_ok = ping_result.is_ok();
+    let quiet = quiet_until.is_some_and(|until| Instant::now() < until);
+    let transition = match cycle_loss {
+      // Failures right after startup or a resume don't start an outage.
+      _ if quiet && !ping_result_ok => None,
+      Some(loss) => detector.record_cycle(&timestamp, ping_result_ok, loss),
+      None if ping_result_ok => detector.record_success(),
+      None => detector.record_failure(&timestamp),
+    };
+    if let Some(transition) = &transition {
+      events::emit(
+        &app,
+        "link-state",
+        LinkTransition {
+          address: address.clone(),
+          timestamp: timestamp.clone(),
+          from: transition.from.into(),
+          to: transition.to.into(),
+          loss_ratio: detector.loss_ratio(),
+        },
+      );
+    }
+    match transition.as_ref().map(|transition| &transition.event) {
+      Some(OutageEvent::Degraded) => {
+        let portal = load_settings(&app).captive_portal;
+        outage_checks = Some(OutageChecks::start(&portal, target.tunnel.as_ref(), encoding));
+      }
+      Some(OutageEvent::Cleared(_)) => outage_checks = None,
+      _ => {}
+    }
+    match transition.map(|transition| transition.event) {
+      Some(OutageEvent::Recovered {
+        started: start_time,
+        duration: lasted,
+      }) => {
+        // Outages that began while the target was flapping recover without alerts too.
+        let outage_target = if outage_damped { alert_target.muted() } else { alert_target.clone() };
+        outage_damped = false;
+        let recover_time = timestamp.clone();
+        let mut recovery = match target.tunnel.as_ref().filter(|_| outage_tunnel) {
+          Some(tunnel) => tunnel::recovery(&target, tunnel, &start_time, &recover_time, lasted),
+          None => alerts::recovery(&target, &start_time, &recover_time, lasted, outage_captive),
+        };
+        outage_tunnel = false;
+        if let Some(problem) = self_check.problem() {
+          recovery.note(&problem);
+        }
+        if let Some(id) = &incident_id {
+          recovery.incident(id);
+        }
+        let subject = drill_tag(outage_drill, recovery.subject);
+        if outage_drill {
+          recovery.plain = drill_tag(true, &recovery.plain);
+          recovery.html = drill_tag(true, &recovery.html);
+        }
+        let sms_event = if outage_drill { "演练恢复" } else { "恢复" };
+        let dedup_key = incident_id.clone().unwrap_or_else(|| format!("{public_address}-{start_time}"));
+        let event = AlertEvent::new(
+          AlertKind::OutageRecovered,
+          AlertSeverity::Info,
+          &timestamp,
+          recovery.plain.clone(),
+        )
+        .target(&target)
+        .span(&start_time, Some(&recover_time))
+        .incident(incident_id.clone(), outage_drill)
+        .fired_with(outage_severity);
+        outage_captive = false;
+        if let Some(id) = incident_id.take() {
+          incidents.lock_or_recover().close(&id);
+        }
+        outage_drill = false;
+        let settings = load_settings(&app);
+        write_alert(&app, store.as_ref(), &now, &log_buffer, &settings, &event);
+        announce_alert(&outage_target, &settings, &event, Some(lasted));
+        let mut delivery = Delivery::new(&event);
+        forward_alertmanager(&mut delivery, &outage_target, &settings, &event);
+
+        // Resolved even while alerts are held back: the trigger may have gone out before the
+        // machine joined a quiet network, and resolving an incident that was never opened does no harm.
+        if let Some(oncall) = target.alert_oncall(&settings.oncall, &settings.proxy) {
+          let note = recovery.plain.clone();
+          delivery.add(Channel::Oncall, move || oncall::resolve(&oncall, &dedup_key, &note));
+        }
+        if let Some(feishu) = outage_target.alert_feishu(&settings.feishu, &settings.proxy) {
+          let lines = recovery.card_lines.clone();
+          let subject = subject.clone();
+          delivery.add(Channel::Feishu, move || feishu::send_card(&feishu, &subject, CardColor::Green, &lines));
+        }
+        if let Some(sms) = outage_target.alert_sms(&settings.sms, &settings.proxy) {
+          let (target_name, recover_time) = (target_name.clone(), recover_time.clone());
+          let incident = event.incident_id.clone().unwrap_or_default();
+          delivery.add(Channel::Sms, move || sms::send(&sms, sms_event, &target_name, &recover_time, &incident));
+        }
+        if let Some(smtp) = outage_target.alert_smtp(&settings.smtp, &settings.proxy) {
+          let email_body = recovery.html.clone();
+          let subject = alerts::render_subject(&smtp.subject_template, recovery.subject, &event);
+          let (app, address, alert) = (app.clone(), address.clone(), event.clone());
+          delivery.add(Channel::Email, move || {
+            let chart = alert_chart(&app, &smtp, &address, Some(&start_time));
+            send_alert_email_with_chart(&smtp, &subject, &email_body, Some(&alert), chart.as_ref())
+          });
+        }
+        delivery.dispatch(&app, &store, &log_buffer);
+      }
+      Some(OutageEvent::Cleared(run)) => {
+        let (since, fail_count) = (&run.started, run.failures);
+        if !drilling {
+          let blip = Blip {
+            started: since.clone(),
+            ended: timestamp.clone(),
+            failures: fail_count,
+            duration_ms: run.duration.as_millis() as u64,
+            micro_outage: run.micro_outage,
+          };
+          stats.lock_or_recover().record_blip(&now, blip);
+        }
+        let message = if run.micro_outage {
+          let lasted = format_duration(run.duration);
+          let message = format!("{target_name} 短暂中断 {lasted}，连续失败 {fail_count} 次（{since}），未达告警宽限期");
+          let line = format!("[{timestamp}] {target_name} | MICRO-OUTAGE | {message}");
+          if let Err(e) = store.record_event(&now, &line) {
+            eprintln!("failed to write log: {e}");
+          }
+          let _ = push_log(&log_buffer, line);
+          message
+        } else {
+          format!("{target_name} 短暂丢包 {fail_count} 次（{since}）后恢复")
+        };
+        if quiet_network.is_none() {
+          digest::queue(&app, &timestamp, &message);
+        }
+      }
+      Some(OutageEvent::Confirmed {
+        started: start_time,
+        failures: fail_count,
+      }) => {
+        match incidents.lock_or_recover().open(&public_address, &target_name, &start_time) {
+          Ok(id) => incident_id = Some(id),
+          Err(e) => eprintln!("failed to open incident: {e}"),
+        }
+        last_reminder = Instant::now();
+        let settings = load_settings(&app);
+        // Distinguish "no connectivity" from "logged out of a hotel/guest portal".
+        let mut checks = outage_checks
+          .take()
+          .unwrap_or_else(|| OutageChecks::start(&settings.captive_portal, target.tunnel.as_ref(), encoding));
+        let connectivity = checks.connectivity();
+        outage_captive = connectivity.as_ref().is_some_and(Connectivity::is_captive);
+        outage_drill = drilling;
+        let flap_change = if drilling { None } else { flap.record_outage(Instant::now()) };
+        outage_damped = flap.is_flapping() && !drilling;
+        let outage_target = if outage_damped { alert_target.muted() } else { alert_target.clone() };
+        if !drilling {
+          stats.lock_or_recover().record_outage(&now);
+        }
+        if !drilling {
+          store.record_outage(&now);
+          // `route print` can take a second or two on Windows; the alert must not wait for it.
+          let (store, log_buffer) = (store.clone(), log_buffer.clone());
+          let (timestamp, target_name) = (timestamp.clone(), target_name.clone());
+          let incident = incident_id.clone().unwrap_or_default();
           thread::spawn(move || {
-            if let Err(err) = send_alert_email(&smtp, &email_body) {
-              eprintln!("failed to send alert email: {err}");
+            for entry in route_snapshot::capture().log_lines() {
+              let line = format!("[{timestamp}] {target_name} | ROUTES | {incident} | {entry}");
+              if let Err(e) = store.record_event(&now, &line) {
+                eprintln!("failed to write log: {e}");
+              }
+              let _ = push_log(&log_buffer, line);
             }
           });
         }
-        fail_count = 0;
-        first_fail_time = None;
-      }
-      Err(_) => {
-        fail_count = fail_count.saturating_add(1);
-        if fail_count == 1 {
-          first_fail_time = Some(timestamp.clone());
+        // Whether the peer still answers outside the tunnel tells a broken tunnel from a broken link.
+        let tunnel_verdict = target.tunnel.as_ref().zip(checks.tunnel_verdict());
+        outage_tunnel = tunnel_verdict.is_some_and(|(_, verdict)| verdict == tunnel::Verdict::TunnelDown);
+        let mut outage_message = match tunnel_verdict {
+          Some((tunnel, verdict)) => tunnel::message(&target, tunnel, verdict, fail_count, &start_time),
+          None => alerts::outage_message(&target, fail_count, &start_time, connectivity.as_ref()),
+        };
+        let local_problem = self_check.problem();
+        if let Some(problem) = &local_problem {
+          outage_message.push_str(&format!("；{problem}，故障可能在本机而非远端"));
+        }
+        let message = drill_tag(outage_drill, &alerts::with_incident(&outage_message, incident_id.as_deref()));
+        let underlay_down = tunnel_verdict.is_some() && !outage_tunnel;
+        outage_severity = if outage_captive || local_problem.is_some() || underlay_down {
+          AlertSeverity::Warning
+        } else {
+          AlertSeverity::Critical
+        };
+        let event = AlertEvent::new(AlertKind::OutageStarted, outage_severity, &timestamp, message.clone())
+          .target(&target)
+          .span(&start_time, None)
+          .incident(incident_id.clone(), outage_drill);
+        write_alert(&app, store.as_ref(), &now, &log_buffer, &settings, &event);
+        announce_alert(&outage_target, &settings, &event, detector.outage_duration());
+        let mut delivery = Delivery::new(&event);
+        forward_alertmanager(&mut delivery, &outage_target, &settings, &event);
+        if let Some(change) = flap_change {
+          report_flap(&app, &store, &log_buffer, &alert_target, &flap, change, &self_check);
+        }
+        if let Some(oncall) = outage_target.alert_oncall(&settings.oncall, &settings.proxy) {
+          let dedup_key = incident_id.clone().unwrap_or_else(|| format!("{public_address}-{start_time}"));
+          let (summary, source, captive) = (message.clone(), public_address.clone(), outage_captive);
+          delivery.add(Channel::Oncall, move || oncall::trigger(&oncall, &dedup_key, &summary, &source, captive));
+        }
+        if let Some(feishu) = outage_target.alert_feishu(&settings.feishu, &settings.proxy) {
+          let (color, lines) = alerts::outage_card(&target, &message, outage_captive);
+          let title = if outage_tunnel { tunnel::CARD_TITLE } else { alerts::OUTAGE_CARD_TITLE };
+          let title = drill_tag(outage_drill, title);
+          delivery.add(Channel::Feishu, move || feishu::send_card(&feishu, &title, color, &lines));
         }
-        if fail_count == 3 && outage_start.is_none() {
-          let start_time = first_fail_time.clone().unwrap_or_else(|| timestamp.clone());
-          outage_start = Some(start_time.clone());
-          let alert_line = format!("[{timestamp}] ALERT | 连续 3 次失败，开始时间 {start_time}\n");
-          if let Err(e) = append_line(&file_path, &alert_line) {
-            eprintln!("failed to write alert log: {e}");
-          } else {
-            let _ = push_log(&log_buffer, alert_line.trim_end().to_string());
+        if let Some(sms) = outage_target.alert_sms(&settings.sms, &settings.proxy) {
+          let target_name = target_name.clone();
+          let incident = incident_id.clone().unwrap_or_default();
+          let event = match (drilling, outage_tunnel) {
+            (true, _) => "演练中断",
+            (false, true) => "隧道中断",
+            (false, false) => "中断",
+          };
+          delivery.add(Channel::Sms, move || sms::send(&sms, event, &target_name, &start_time, &incident));
+        }
+        delivery.dispatch(&app, &store, &log_buffer);
+      }
+      _ if !ping_result_ok => {
+        if let (Some(start_time), Some(lasted)) = (detector.outage_started(), detector.outage_duration()) {
+          let reminder_interval = Duration::from_secs(reminders.interval_minutes.saturating_mul(60));
+          let acknowledged = incident_id
+            .as_deref()
+            .is_some_and(|id| incidents.lock_or_recover().is_acknowledged(id));
+          if reminders.enabled && !acknowledged && !outage_damped && last_reminder.elapsed() >= reminder_interval {
+            last_reminder = Instant::now();
+            let mut reminder = format!(
+              "{target_name} 仍未恢复，已持续 {}（开始时间 {start_time}）",
+              format_duration(lasted)
+            );
+            if let Some(problem) = self_check.problem() {
+              reminder.push_str(&format!("；注意：{problem}"));
+            }
+            let message = drill_tag(outage_drill, &alerts::with_incident(&reminder, incident_id.as_deref()));
+            let settings = load_settings(&app);
+            let event = AlertEvent::new(AlertKind::OutageReminder, outage_severity, &timestamp, message.clone())
+              .target(&target)
+              .span(start_time, None)
+              .incident(incident_id.clone(), outage_drill);
+            write_alert(&app, store.as_ref(), &now, &log_buffer, &settings, &event);
+            announce_alert(&alert_target, &settings, &event, Some(lasted));
+            let mut delivery = Delivery::new(&event);
+            forward_alertmanager(&mut delivery, &alert_target, &settings, &event);
+            if let Some(smtp) = alert_target.alert_smtp(&settings.smtp, &settings.proxy) {
+              let subject = alerts::render_subject(&smtp.subject_template, "网络中断提醒", &event);
+              let (app, address, start_time) = (app.clone(), address.clone(), start_time.to_string());
+              let alert = event.clone();
+              delivery.add(Channel::Email, move || {
+                let chart = alert_chart(&app, &smtp, &address, Some(&start_time));
+                send_alert_email_with_chart(&smtp, &subject, &message, Some(&alert), chart.as_ref())
+              });
+            }
+            delivery.dispatch(&app, &store, &log_buffer);
           }
         }
       }
+      _ => {}
+    }
+
+    if let Some(change) = flap.tick(Instant::now(), detector.outage_started().is_some()) {
+      report_flap(&app, &store, &log_buffer, &alert_target, &flap, change, &self_check);
+    }
+
+    rolling.push(loop_start, ping_result_ok);
+    if ping_result_ok {
+      last_rtt = rtt_ms;
+    }
+    if last_summary.is_none_or(|at| at.elapsed() >= SUMMARY_INTERVAL) {
+      last_summary = Some(Instant::now());
+      let status = LinkStatus::from(detector.state());
+      let outage = detector.outage_started().map(|started| ActiveOutage {
+        incident_id: incident_id.clone(),
+        started: started.to_string(),
+        duration_secs: detector.outage_duration().unwrap_or_default().as_secs(),
+      });
+      let now_instant = Instant::now();
+      events::emit(
+        &app,
+        "ping-summary",
+        PingSummary {
+          address: address.clone(),
+          label: target.label.clone(),
+          status,
+          last_rtt_ms: last_rtt,
+          loss_1m_percent: rolling.loss_percent(now_instant, Duration::from_secs(60)),
+          loss_5m_percent: rolling.loss_percent(now_instant, Duration::from_secs(300)),
+          outage,
+          flapping: flap.is_flapping(),
+        },
+      );
     }
 
     let elapsed = loop_start.elapsed();
-    if elapsed < Duration::from_secs(1) {
-      let wait = Duration::from_secs(1) - elapsed;
+    if elapsed < PROBE_INTERVAL {
+      let wait = PROBE_INTERVAL - elapsed;
+      let sleep_start = Instant::now();
       if stop_rx.recv_timeout(wait).is_ok() {
-        break;
+        break StopReason::User;
+      }
+      let late = self_metrics.record_wakeup(wait, sleep_start.elapsed());
+      let warn_again = last_drift_warning.is_none_or(|at| at.elapsed() >= DRIFT_WARNING_INTERVAL);
+      if let (Some(late), true) = (late, warn_again) {
+        last_drift_warning = Some(Instant::now());
+        let line = format!(
+          "[{}] {target_name} | SELF | 探测调度延迟 {} ms，系统可能负载过高或刚从休眠中恢复",
+          Local::now().format("%Y-%m-%d %H:%M:%S"),
+          late.as_millis()
+        );
+        if let Err(e) = store.record_event(&now, &line) {
+          eprintln!("failed to write log: {e}");
+        }
+        let _ = push_log(&log_buffer, line);
       }
     }
-  }
+  };
+  store.flush();
+  reason
 }
 
-fn push_log(logs: &Arc<Mutex<LogBuffer>>, entry: String) -> u64 {
-  if let Ok(mut logs) = logs.lock() {
-    let seq = logs.next_seq;
-    logs.next_seq = logs.next_seq.saturating_add(1);
-    logs.entries.push_back(LogEntry { seq, line: entry });
-    while logs.entries.len() > 100 {
-      logs.entries.pop_front();
+/// Runs a session's worker and reports how it ended. A session that ended on its own is
+/// removed from the state so the UI can start a new one.
+fn run_session(
+  app: AppHandle,
+  base_dir: PathBuf,
+  address: String,
+  encoding: PingEncoding,
+  stop_rx: mpsc::Receiver<()>,
+  probe: ProbeSlot,
+  handles: SessionHandles,
+) {
+  let label = targets::find(&load_settings(&app).targets, &address).label;
+  let started = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+  let stats = handles.stats.clone();
+  let worker_app = app.clone();
+  let worker_address = address.clone();
+  let reason = panic::catch_unwind(AssertUnwindSafe(move || {
+    let store = result_store::open(base_dir, &targets::public_address(&worker_address));
+    ping_loop(worker_app, store, worker_address, encoding, stop_rx, probe, handles)
+  }))
+  .unwrap_or_else(|payload| {
+    let error = payload
+      .downcast_ref::<&str>()
+      .map(|s| s.to_string())
+      .or_else(|| payload.downcast_ref::<String>().cloned())
+      .unwrap_or_else(|| "unknown panic".to_string());
+    StopReason::Panicked { error }
+  });
+  let ended = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+
+  let summary = history::summarize(&stats.lock_or_recover().report(), &started, &ended);
+  let recorded = update_settings(&app, |settings| history::record_end(&mut settings.history, &address, summary));
+  if let Err(err) = recorded {
+    eprintln!("failed to save target history: {err}");
+  }
+
+  if !matches!(reason, StopReason::User) {
+    let state = app.state::<PingState>();
+    let mut guard = state.inner.lock_or_recover();
+    if guard.as_ref().is_some_and(|runner| runner.join.thread().id() == thread::current().id()) {
+      *guard = None;
     }
-    return seq;
   }
-  0
+  events::emit(
+    &app,
+    "monitoring-stopped",
+    MonitoringStopped {
+      address,
+      label,
+      timestamp: ended,
+      reason,
+    },
+  );
 }
 
-fn ping_once(address: &str) -> Result<String, String> {
-  let output = ping_command(address)
-    .output()
-    .map_err(|e| format!("failed to spawn ping: {e}"))?;
+/// A loop iteration this long means the machine was asleep; well above the longest probe (a
+/// multi-packet ping that times out throughout).
+const RESUME_GAP_SECS: f64 = 120.0;
 
-  let stdout = decode_ping_output(&output.stdout);
-  let stderr = decode_ping_output(&output.stderr);
+/// Wall-clock drift against the monotonic clock, per loop iteration, that counts as a jump.
+/// Probes can take several seconds, but both clocks advance through them equally.
+const CLOCK_JUMP_THRESHOLD_SECS: f64 = 5.0;
 
-  let success = output.status.success();
-  let text: &str = if success {
-    stdout.as_str()
-  } else if !stderr.trim().is_empty() {
-    stderr.as_str()
+fn drill_tag(drill: bool, text: &str) -> String {
+  if drill {
+    format!("[DRILL] {text}")
   } else {
-    stdout.as_str()
+    text.to_string()
+  }
+}
+
+/// `2 小时 15 分` style, rounded down to whole minutes; under a minute shows seconds.
+fn format_duration(duration: Duration) -> String {
+  let secs = duration.as_secs();
+  let minutes = secs / 60;
+  match (minutes / 60, minutes % 60) {
+    (0, 0) => format!("{secs} 秒"),
+    (0, m) => format!("{m} 分"),
+    (h, m) => format!("{h} 小时 {m} 分"),
+  }
+}
+
+fn minute_log_path(base_dir: &Path, now: &DateTime<Local>) -> std::io::Result<PathBuf> {
+  let dir = base_dir
+    .join(now.format("%Y-%m-%d").to_string())
+    .join(now.format("%H").to_string());
+  create_dir_all(&dir)?;
+  let path = dir.join(format!("ping_{}.log", now.format("%Y-%m-%d_%H-%M")));
+  day_index::note_file(base_dir, now, &path);
+  Ok(path)
+}
+
+fn run_scheduled_speedtest(
+  app: &AppHandle,
+  store: &Arc<dyn ResultStore>,
+  log_buffer: &Arc<Mutex<LogBuffer>>,
+  settings: &SpeedtestSettings,
+) {
+  let started = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+  let outcome = speedtest::run(settings, &started);
+
+  let now = Local::now();
+  let timestamp = now.format("%Y-%m-%d %H:%M:%S").to_string();
+
+  let (line, violation) = match &outcome {
+    Ok(result) => (result.summary(), result.threshold_violation(settings)),
+    Err(err) => (format!("SPEEDTEST | error: {err}"), None),
   };
+  let display_line = format!("[{timestamp}] {line}");
+  if let Err(e) = store.record_event(&now, &display_line) {
+    eprintln!("failed to write log: {e}");
+  }
+  let _ = push_log(log_buffer, display_line);
+
+  if let Some(violation) = violation {
+    let app_settings = load_settings(app);
+    let message = format!("测速结果低于阈值：{violation}");
+    let event = AlertEvent::new(
+      AlertKind::SpeedtestBelowThreshold,
+      AlertSeverity::Warning,
+      &timestamp,
+      message.clone(),
+    );
+    write_alert(app, store.as_ref(), &now, log_buffer, &app_settings, &event);
+    let quiet = quiet_networks::matching(&app_settings.quiet_networks).is_some();
+    if !quiet && !digest::queue(app, &timestamp, &message) {
+      let subject = alerts::render_subject(&app_settings.smtp.subject_template, "网络测速告警", &event);
+      let (smtp, alert) = (app_settings.smtp.with_proxy(&app_settings.proxy), event.clone());
+      let mut delivery = Delivery::new(&event);
+      delivery.add(Channel::Email, move || send_alert_email(&smtp, &subject, &message, Some(&alert)));
+      delivery.dispatch(app, store, log_buffer);
+    }
+  }
+}
 
-  let lines: Vec<&str> = text
-    .lines()
-    .map(str::trim)
-    .filter(|line| !line.is_empty())
-    .collect();
+/// The latency chart of `address` for an alert email, when the SMTP settings ask for one and
+/// the logs have something to draw.
+fn alert_chart(app: &AppHandle, smtp: &SmtpSettings, address: &str, outage_start: Option<&str>) -> Option<Chart> {
+  if smtp.chart_minutes == 0 {
+    return None;
+  }
+  let base_dir = resolve_log_base(app).ok()?;
+  chart::recent(&base_dir, address, smtp.chart_minutes, outage_start)
+}
 
-  let preferred = if success {
-    select_success_line(&lines)
-      .or_else(|| select_non_header_line(&lines))
-      .or_else(|| lines.first().copied())
+fn write_alert(
+  app: &AppHandle,
+  store: &dyn ResultStore,
+  at: &DateTime<Local>,
+  log_buffer: &Arc<Mutex<LogBuffer>>,
+  settings: &AppSettings,
+  event: &AlertEvent,
+) {
+  let alert_line = format!("[{}] ALERT | {}", event.timestamp, event.message);
+  if let Err(e) = store.record_event(at, &alert_line) {
+    eprintln!("failed to write alert log: {e}");
   } else {
-    select_error_line(&lines)
-      .or_else(|| select_non_header_line(&lines))
-      .or_else(|| lines.first().copied())
+    let _ = push_log(log_buffer, alert_line);
+  }
+  log_sinks::forward_alert(&settings.sinks, &event.message);
+  events::emit(app, "alert-event", event);
+}
+
+/// Adds the Alertmanager webhook receiver to an outage alert's delivery, if the target's alerts go there.
+fn forward_alertmanager(delivery: &mut Delivery, target: &TargetConfig, settings: &AppSettings, event: &AlertEvent) {
+  let Some(alertmanager) = target.alert_alertmanager(&settings.alertmanager, &settings.proxy) else {
+    return;
   };
+  let Some(payload) = alertmanager::payload(&alertmanager, event) else {
+    return;
+  };
+  delivery.add(Channel::Alertmanager, move || alertmanager::send(&alertmanager, &payload));
+}
+
+/// Speaks `event` unless the target's alerts are muted or speech doesn't cover its severity.
+fn announce_alert(target: &TargetConfig, settings: &AppSettings, event: &AlertEvent, lasted: Option<Duration>) {
+  if let Some(speech) = target.alert_speech(&settings.speech, event.severity) {
+    speech::announce(speech, speech::announcement(event, lasted));
+  }
+}
+
+/// Logs a target starting, continuing or stopping to flap, sends it on the target's channels and
+/// tells the UI which targets to badge.
+fn report_flap(
+  app: &AppHandle,
+  store: &Arc<dyn ResultStore>,
+  log_buffer: &Arc<Mutex<LogBuffer>>,
+  target: &TargetConfig,
+  flap: &FlapDetector,
+  change: FlapChange,
+  self_check: &SelfCheck,
+) {
+  let at = &Local::now();
+  let timestamp = at.format("%Y-%m-%d %H:%M:%S").to_string();
+  let mut message = change.message(&target.display_name(), flap.summary_minutes());
+  if let Some(problem) = self_check.problem() {
+    message.push_str(&format!("；注意：{problem}"));
+  }
+  let settings = load_settings(app);
+  let mut event = AlertEvent::new(change.kind(), change.severity(), &timestamp, message.clone()).target(target);
+  if let FlapChange::Ended { .. } = change {
+    event = event.fired_with(AlertSeverity::Warning);
+  }
+  write_alert(app, store.as_ref(), at, log_buffer, &settings, &event);
+  if !matches!(change, FlapChange::Summary { .. }) {
+    events::emit(
+      app,
+      "flap-state",
+      FlapState {
+        address: target.address.clone(),
+        timestamp: timestamp.clone(),
+        flapping: flap.is_flapping(),
+        outages_last_hour: flap.outages_last_hour(),
+      },
+    );
+  }
+  let mut delivery = Delivery::new(&event);
+  forward_alertmanager(&mut delivery, target, &settings, &event);
+  if let Some(oncall) = target.alert_oncall(&settings.oncall, &settings.proxy) {
+    // One on-call alert per flapping spell: opened when it starts, updated by the summaries.
+    let source = targets::public_address(&target.address).into_owned();
+    let dedup_key = format!("{source}-flapping");
+    let note = message.clone();
+    match change {
+      FlapChange::Ended { .. } => delivery.add(Channel::Oncall, move || oncall::resolve(&oncall, &dedup_key, &note)),
+      _ => delivery.add(Channel::Oncall, move || oncall::trigger(&oncall, &dedup_key, &note, &source, true)),
+    }
+  }
+  if let Some(feishu) = target.alert_feishu(&settings.feishu, &settings.proxy) {
+    let color = match change {
+      FlapChange::Started { .. } => CardColor::Orange,
+      FlapChange::Summary { .. } => CardColor::Blue,
+      FlapChange::Ended { .. } => CardColor::Green,
+    };
+    let (title, lines) = (change.subject(), vec![message.clone()]);
+    delivery.add(Channel::Feishu, move || feishu::send_card(&feishu, title, color, &lines));
+  }
+  if let Some(sms) = target.alert_sms(&settings.sms, &settings.proxy) {
+    let (sms_event, target_name, time) = (change.sms_event(), target.display_name(), timestamp.clone());
+    delivery.add(Channel::Sms, move || sms::send(&sms, sms_event, &target_name, &time, ""));
+  }
+  if let Some(smtp) = target.alert_smtp(&settings.smtp, &settings.proxy) {
+    let subject = alerts::render_subject(&smtp.subject_template, change.subject(), &event);
+    let alert = event.clone();
+    delivery.add(Channel::Email, move || send_alert_email(&smtp, &subject, &message, Some(&alert)));
+  }
+  delivery.dispatch(app, store, log_buffer);
+}
 
-  let summary = preferred
-    .unwrap_or("");
+/// How a session runs ping: the console encoding, the DSCP marking and the executable.
+struct PingOptions<'a> {
+  encoding: PingEncoding,
+  dscp: Option<u8>,
+  /// `None` for the system ping.
+  binary: Option<&'a PingBinary>,
+}
 
-  let summary = if summary.is_empty() {
-    format!("ping {address} {}", if success { "ok" } else { "failed" })
+/// Runs one probe of whichever kind the address names and returns the summary line, the RTT
+/// and, for a multi-packet ping, the share of packets lost. `quic` adds a QUIC check to HTTPS
+/// probes.
+fn probe_target(
+  address: &str,
+  ping: &PingOptions,
+  burst: Option<(u32, SuccessCriterion)>,
+  quic: bool,
+  timestamp: &str,
+  http_timings: &Arc<Mutex<HttpTimingBuffer>>,
+  probe: &ProbeSlot,
+) -> (Result<String, String>, Option<f64>, Option<f64>) {
+  if http_probe::is_http_target(address) {
+    let timing = http_probe::probe(address, timestamp);
+    let mut result = http_probe::summarize(&timing);
+    if quic {
+      result = result.and_then(|line| match quic_probe::probe(address) {
+        Ok(quic) => Ok(format!("{line} {quic}")),
+        Err(err) => Err(format!("{line}; {err}")),
+      });
+    }
+    let total_ms = timing.total_ms;
+    http_timings.lock_or_recover().push(timing);
+    (result, Some(total_ms), None)
+  } else if snmp::is_snmp_target(address) {
+    let (result, rtt_ms) = snmp::probe(address);
+    (result, rtt_ms, None)
+  } else if service_probe::is_service_target(address) {
+    let (result, rtt_ms) = service_probe::probe(address, timestamp);
+    (result, rtt_ms, None)
+  } else if udp_probe::is_udp_target(address) {
+    let (result, rtt_ms) = udp_probe::probe(address);
+    (result, rtt_ms, None)
+  } else if ntp_probe::is_ntp_target(address) {
+    let (result, rtt_ms) = ntp_probe::probe(address);
+    (result, rtt_ms, None)
+  } else if let Some((count, criterion)) = burst {
+    match ping_burst_in(address, ping, count, criterion, probe) {
+      Ok(burst) => {
+        let (rtt_ms, loss) = (burst.avg_rtt_ms(), burst.loss_ratio());
+        (burst.into_result(), rtt_ms, Some(loss))
+      }
+      Err(e) => (Err(e), None, Some(1.0)),
+    }
   } else {
-    summary.to_string()
+    let result = ping_once_in(address, ping, None, Some(probe));
+    let rtt_ms = result.as_deref().ok().and_then(parse_rtt_ms);
+    (result, rtt_ms, None)
+  }
+}
+
+fn push_log(logs: &Arc<Mutex<LogBuffer>>, entry: String) -> u64 {
+  let mut logs = logs.lock_or_recover();
+  let seq = logs.next_seq;
+  logs.next_seq = logs.next_seq.saturating_add(1);
+  logs.entries.push_back(LogEntry { seq, line: entry });
+  while logs.entries.len() > 100 {
+    logs.entries.pop_front();
+  }
+  seq
+}
+
+/// Runs the system ping once; `source` pins the probe to an interface or source address.
+fn ping_once(address: &str, encoding: PingEncoding, source: Option<&str>) -> Result<String, String> {
+  let ping = PingOptions {
+    encoding,
+    dscp: None,
+    binary: None,
   };
+  ping_once_in(address, &ping, source, None)
+}
 
-  if success {
-    Ok(summary)
-  } else {
-    Err(summary)
-  }
-}
-
-fn select_success_line<'a>(lines: &'a [&'a str]) -> Option<&'a str> {
-  lines.iter().copied().find(|line| {
-    let lower = line.to_ascii_lowercase();
-    line.contains("Reply from")
-      || line.contains("bytes from")
-      || line.contains("bytes=")
-      || lower.contains("time=")
-      || lower.contains("time<")
-      || lower.contains("ttl=")
-      || lower.contains("ms")
-      || line.contains("时间")
-      || line.contains("字节=")
+/// `ping_once` as `ping` says, parking the child in `slot` while it runs so it can be killed.
+fn ping_once_in(
+  address: &str,
+  ping: &PingOptions,
+  source: Option<&str>,
+  slot: Option<&ProbeSlot>,
+) -> Result<String, String> {
+  let host = ping_host(address)?;
+  with_ping_runner(ping, slot, |runner| {
+    ping_core::ping_once(runner, Platform::current(), &host, source)
+  })
+}
+
+/// Sends `count` echo requests in one ping run, judged by `criterion`.
+fn ping_burst_in(
+  address: &str,
+  ping: &PingOptions,
+  count: u32,
+  criterion: SuccessCriterion,
+  slot: &ProbeSlot,
+) -> Result<Burst, String> {
+  let host = ping_host(address)?;
+  with_ping_runner(ping, Some(slot), |runner| {
+    ping_core::ping_burst(runner, Platform::current(), &host, None, count, criterion)
   })
 }
 
-fn select_error_line<'a>(lines: &'a [&'a str]) -> Option<&'a str> {
-  lines.iter().copied().find(|line| {
-    let lower = line.to_ascii_lowercase();
-    lower.contains("timed out")
-      || lower.contains("timeout")
-      || lower.contains("unreachable")
-      || lower.contains("general failure")
-      || lower.contains("could not find host")
-      || lower.contains("name or service not known")
-      || line.contains("请求超时")
-      || line.contains("无法访问")
-      || line.contains("一般故障")
-      || line.contains("找不到主机")
-      || line.contains("无法解析")
+/// Runs a payload size and pattern sweep against `address`.
+fn payload_sweep_in(
+  address: &str,
+  ping: &PingOptions,
+  sizes: &[u32],
+  patterns: &[String],
+  count: u32,
+) -> Result<Sweep, String> {
+  let host = ping_host(address)?;
+  let patterns: Vec<&str> = patterns.iter().map(String::as_str).collect();
+  with_ping_runner(ping, None, |runner| {
+    ping_core::payload_sweep(runner, Platform::current(), &host, sizes, &patterns, count)
   })
 }
 
-fn select_non_header_line<'a>(lines: &'a [&'a str]) -> Option<&'a str> {
-  lines.iter().copied().find(|line| !is_header_line(line))
+/// Calls `run` with the system ping, swapped for `ping.binary` when one is set and DSCP-marked
+/// when `ping.dscp` is.
+fn with_ping_runner<T>(ping: &PingOptions, slot: Option<&ProbeSlot>, run: impl FnOnce(&dyn CommandRunner) -> T) -> T {
+  let system = SystemPing {
+    encoding: ping.encoding,
+    slot,
+  };
+  let custom = ping.binary.map(|binary| CustomPing {
+    runner: &system,
+    platform: Platform::current(),
+    program: binary.program.trim(),
+    template: &binary.args,
+  });
+  let base: &dyn CommandRunner = custom.as_ref().map_or(&system, |custom| custom);
+  let marked = ping.dscp.map(|dscp| DscpMarked {
+    runner: base,
+    platform: Platform::current(),
+    dscp,
+  });
+  run(marked.as_ref().map_or(base, |marked| marked))
+}
+
+/// What to hand the ping binary for `address`: the address itself, or what an mDNS or NetBIOS
+/// lookup found for it.
+fn ping_host(address: &str) -> Result<String, String> {
+  targets::validate_host(address)?;
+  Ok(local_names::translate(address).map_or_else(|| address.to_string(), |ip| ip.to_string()))
+}
+
+/// Runs ping as a hidden child process and decodes its output from the console codepage.
+struct SystemPing<'a> {
+  encoding: PingEncoding,
+  slot: Option<&'a ProbeSlot>,
 }
 
-fn is_header_line(line: &str) -> bool {
-  let lower = line.to_ascii_lowercase();
-  lower.starts_with("pinging ")
-    || lower.starts_with("ping ")
-    || line.contains("正在 Ping")
-    || line.contains("正在ping")
+impl CommandRunner for SystemPing<'_> {
+  fn run(&self, program: &str, args: &[String]) -> io::Result<CommandOutput> {
+    let output = run_probe_command(probe_command(program, args), self.slot)?;
+    Ok(CommandOutput {
+      success: output.status.success(),
+      stdout: decode_ping_output(&output.stdout, self.encoding),
+      stderr: decode_ping_output(&output.stderr, self.encoding),
+    })
+  }
+}
+
+fn run_probe_command(mut cmd: Command, slot: Option<&ProbeSlot>) -> io::Result<Output> {
+  let Some(slot) = slot else {
+    return cmd.output();
+  };
+  let mut child = cmd
+    .stdin(Stdio::null())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()?;
+  let stdout_pipe = child.stdout.take();
+  let stderr_pipe = child.stderr.take();
+  *slot.lock_or_recover() = Some(child);
+
+  // Ping writes a few hundred bytes, far below the pipe buffer, so reading the pipes one
+  // after the other can't stall the child. EOF arrives when it exits or is killed.
+  let mut stdout = Vec::new();
+  let mut stderr = Vec::new();
+  if let Some(mut pipe) = stdout_pipe {
+    pipe.read_to_end(&mut stdout)?;
+  }
+  if let Some(mut pipe) = stderr_pipe {
+    pipe.read_to_end(&mut stderr)?;
+  }
+  let status = match slot.lock_or_recover().take() {
+    Some(mut child) => child.wait()?,
+    None => return Err(io::Error::new(io::ErrorKind::Interrupted, "probe cancelled")),
+  };
+  Ok(Output { status, stdout, stderr })
 }
 
+/// Runs `program` itself, never through `cmd`, which would parse the target and the other
+/// arguments as a command line of their own.
 #[cfg(target_os = "windows")]
-fn ping_command(address: &str) -> Command {
+fn probe_command(program: &str, args: &[String]) -> Command {
   const CREATE_NO_WINDOW: u32 = 0x08000000;
-  let mut cmd = Command::new("ping");
-  cmd.args(["-n", "1", address]);
+  let mut cmd = Command::new(program);
+  cmd.args(args);
   cmd.creation_flags(CREATE_NO_WINDOW);
   cmd
 }
 
 #[cfg(not(target_os = "windows"))]
-fn ping_command(address: &str) -> Command {
-  let mut cmd = Command::new("ping");
-  cmd.args(["-c", "1", address]);
+fn probe_command(program: &str, args: &[String]) -> Command {
+  let mut cmd = Command::new(program);
+  cmd.args(args);
   cmd
 }
 
 #[cfg(target_os = "windows")]
-fn decode_ping_output(bytes: &[u8]) -> String {
+fn decode_ping_output(bytes: &[u8], encoding: PingEncoding) -> String {
   use windows_sys::Win32::Globalization::GetOEMCP;
+  use windows_sys::Win32::System::Console::GetConsoleOutputCP;
 
-  let cp = unsafe { GetOEMCP() };
-  match cp {
-    65001 => String::from_utf8_lossy(bytes).into_owned(),
-    936 => {
-      let (cow, _, _) = encoding_rs::GBK.decode(bytes);
-      cow.into_owned()
-    }
-    950 => {
-      let (cow, _, _) = encoding_rs::BIG5.decode(bytes);
-      cow.into_owned()
+  // For consoles set to UTF-8 system-wide; output that isn't UTF-8 is still read as OEM.
+  if matches!(encoding, PingEncoding::PreferUtf8) {
+    if let Ok(text) = std::str::from_utf8(bytes) {
+      return text.to_string();
     }
-    932 => {
-      let (cow, _, _) = encoding_rs::SHIFT_JIS.decode(bytes);
-      cow.into_owned()
-    }
-    949 => {
-      let (cow, _, _) = encoding_rs::EUC_KR.decode(bytes);
-      cow.into_owned()
-    }
-    _ => String::from_utf8_lossy(bytes).into_owned(),
   }
+
+  // GUI builds have no console and get 0 here; ping then inherits the OEM codepage.
+  let cp = match unsafe { GetConsoleOutputCP() } {
+    0 => unsafe { GetOEMCP() },
+    cp => cp,
+  };
+  ping_core::codepage::decode_oem(bytes, cp)
 }
 
 #[cfg(not(target_os = "windows"))]
-fn decode_ping_output(bytes: &[u8]) -> String {
+fn decode_ping_output(bytes: &[u8], _encoding: PingEncoding) -> String {
   String::from_utf8_lossy(bytes).into_owned()
 }
 
@@ -758,18 +3589,136 @@ fn append_line(path: &Path, line: &str) -> std::io::Result<()> {
 fn main() {
   tauri::Builder::default()
     .manage(PingState::default())
-    .invoke_handler(tauri::generate_handler![
+    .manage(DualWanState::default())
+    .manage(DigestState::default())
+    .manage(EventSubscriptions::default())
+    .manage(SelfMetricsState::default())
+    .manage(RelayServerState::default())
+    .manage(RemoteControlState::default())
+    .manage(InterfaceSampler::default())
+    .manage(LatencyHistograms::default())
+    .manage(AlertHistory::default())
+    .setup(|app| {
+      viewer::refresh(app.handle());
+      jobs::spawn_scheduler(app.handle().clone());
+      digest::spawn_flusher(app.handle().clone());
+      settings_watch::spawn_watcher(app.handle().clone());
+      spawn_integrity_check(app.handle().clone());
+      interfaces::spawn_sampler(app.handle().clone());
+      idle_reminder::spawn_watcher(app.handle().clone());
+      if autostart::launched_in_background() {
+        autostart::start_in_background(app.handle());
+      }
+      let settings = load_settings(app.handle());
+      if let Err(e) = app.state::<RelayServerState>().apply(&settings.relay_server, settings.ping.encoding) {
+        eprintln!("failed to start relay server: {}", e.message);
+      }
+      if let Err(e) = app.state::<RemoteControlState>().apply(app.handle(), &settings.remote_control) {
+        eprintln!("failed to start remote control: {}", e.message);
+      }
+      Ok(())
+    })
+    .on_window_event(|window, event| {
+      if let WindowEvent::Destroyed = event {
+        window.state::<EventSubscriptions>().forget(window.label());
+      }
+    })
+    .invoke_handler(viewer::guard(tauri::generate_handler![
       start_ping,
+      restart_ping,
       stop_ping,
+      reset_state,
       get_recent_logs,
+      subscribe_events,
+      unsubscribe_events,
+      get_active_incidents,
+      acknowledge_outage,
+      simulate_outage,
+      copy_recent_logs_to_clipboard,
+      copy_statistics_summary,
+      get_target_history,
+      get_statistics,
+      get_health_score,
+      reset_statistics,
+      get_blips,
+      get_hourly_rollups,
+      get_correlated_events,
+      compare_ranges,
+      get_day_indexes,
+      tail_log_file,
+      export_diagnostics_bundle,
+      export_statistics_xlsx,
+      export_outages_ics,
+      import_ping_logs,
+      archive_logs,
+      get_latency_histogram,
+      get_alert_history,
+      get_histogram_settings,
+      save_histogram_settings,
+      get_anomaly_settings,
+      save_anomaly_settings,
+      get_http_timings,
+      get_autostart_settings,
+      save_autostart_settings,
       get_log_dir,
+      get_log_integrity_report,
       select_log_dir,
+      migrate_log_dir,
+      rebuild_statistics,
+      get_capabilities,
+      get_self_metrics,
+      get_interface_stats,
+      get_ping_settings,
+      save_ping_settings,
+      get_log_sink_settings,
+      save_log_sink_settings,
+      get_otlp_settings,
+      save_otlp_settings,
+      get_speedtest_settings,
+      save_speedtest_settings,
+      run_speedtest,
+      get_captive_portal_settings,
+      save_captive_portal_settings,
+      check_captive_portal,
+      start_dual_wan,
+      stop_dual_wan,
+      get_dual_wan_report,
+      get_targets,
+      save_target,
+      remove_target,
+      get_favorites,
+      add_favorite,
+      remove_favorite,
+      reorder_favorites,
+      quick_start,
+      get_jobs,
+      save_job,
+      remove_job,
+      run_job_now,
+      get_relay_agents,
+      save_relay_agent,
+      remove_relay_agent,
+      probe_from_relays,
+      get_relay_server_settings,
+      save_relay_server_settings,
+      get_relay_server_status,
+      get_remote_control_settings,
+      save_remote_control_settings,
+      get_remote_control_status,
       get_alert_settings,
       save_alert_settings,
       export_alert_settings,
       import_alert_settings,
-      test_smtp
-    ])
+      test_smtp,
+      test_sms,
+      test_feishu,
+      test_speech,
+      test_alertmanager,
+      scan_ports,
+      run_payload_sweep,
+      get_viewer_mode,
+      set_viewer_mode
+    ]))
     .run(tauri::generate_context!())
     .expect("error while running tauri application");
 }
@@ -0,0 +1,176 @@
+// NTP targets: `ntp://host[:port]?max_offset=500` queries a time server with one SNTP request
+// (RFC 4330) and logs the local clock's offset from it, the round-trip delay and the server's
+// stratum. An offset beyond `max_offset` milliseconds fails the probe, so clock drift that
+// breaks Kerberos, TOTP or certificate checks raises an outage alert like an unreachable host.
+
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use url::Url;
+
+const TIMEOUT: Duration = Duration::from_secs(3);
+const DEFAULT_PORT: u16 = 123;
+const DEFAULT_MAX_OFFSET_MS: f64 = 500.0;
+/// Seconds from the NTP epoch (1900) to the Unix epoch.
+const UNIX_OFFSET: u64 = 2_208_988_800;
+const PACKET_LEN: usize = 48;
+/// Leap indicator 0, version 4, mode 3 (client).
+const CLIENT_REQUEST: u8 = 0x23;
+const MODE_SERVER: u8 = 4;
+
+struct NtpTarget {
+  /// As it appears in a URL: IPv6 literals keep their brackets.
+  host: String,
+  port: u16,
+  max_offset_ms: f64,
+}
+
+/// What one exchange measured, in milliseconds.
+struct Measurement {
+  offset_ms: f64,
+  delay_ms: f64,
+  stratum: u8,
+}
+
+pub fn is_ntp_target(address: &str) -> bool {
+  address.to_ascii_lowercase().starts_with("ntp://")
+}
+
+pub fn validate(address: &str) -> Result<(), String> {
+  parse_target(address).map(drop)
+}
+
+fn parse_target(address: &str) -> Result<NtpTarget, String> {
+  let url = Url::parse(address).map_err(|e| format!("地址格式无效: {e}"))?;
+  let host = url
+    .host_str()
+    .ok_or_else(|| format!("地址 {address} 缺少主机名"))?
+    .to_string();
+  let mut max_offset_ms = DEFAULT_MAX_OFFSET_MS;
+  for (key, value) in url.query_pairs() {
+    match key.as_ref() {
+      "max_offset" => {
+        max_offset_ms = value
+          .parse::<f64>()
+          .ok()
+          .filter(|ms| ms.is_finite() && *ms > 0.0)
+          .ok_or_else(|| format!("max_offset 应为大于 0 的毫秒数: {value}"))?;
+      }
+      other => return Err(format!("未知的 NTP 参数: {other}")),
+    }
+  }
+  Ok(NtpTarget {
+    host,
+    port: url.port().unwrap_or(DEFAULT_PORT),
+    max_offset_ms,
+  })
+}
+
+/// Queries the server and checks the offset. Returns the summary line and the round-trip delay.
+pub fn probe(address: &str) -> (Result<String, String>, Option<f64>) {
+  let target = match parse_target(address) {
+    Ok(target) => target,
+    Err(err) => return (Err(err), None),
+  };
+  let measurement = match query(&target) {
+    Ok(measurement) => measurement,
+    Err(err) => return (Err(format!("NTP {} {err}", target.host)), None),
+  };
+  let Measurement {
+    offset_ms,
+    delay_ms,
+    stratum,
+  } = measurement;
+  let line = format!(
+    "NTP {} delay={delay_ms:.1}ms offset={offset_ms:+.1}ms stratum {stratum}",
+    target.host
+  );
+  if offset_ms.abs() > target.max_offset_ms {
+    (
+      Err(format!("{line} (offset exceeds {:.0}ms)", target.max_offset_ms)),
+      Some(delay_ms),
+    )
+  } else {
+    (Ok(line), Some(delay_ms))
+  }
+}
+
+fn query(target: &NtpTarget) -> Result<Measurement, String> {
+  let host = target.host.trim_matches(['[', ']']);
+  let addr: SocketAddr = (host, target.port)
+    .to_socket_addrs()
+    .map_err(|e| format!("dns failed: {e}"))?
+    .next()
+    .ok_or_else(|| "dns returned no address".to_string())?;
+  let local = if addr.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+  let socket = UdpSocket::bind(local).map_err(|e| format!("bind failed: {e}"))?;
+  socket.connect(addr).map_err(|e| format!("connect failed: {e}"))?;
+  socket
+    .set_read_timeout(Some(TIMEOUT))
+    .map_err(|e| format!("socket setup failed: {e}"))?;
+
+  let mut request = [0u8; PACKET_LEN];
+  request[0] = CLIENT_REQUEST;
+  let sent_at = now();
+  let transmit = to_ntp(sent_at);
+  request[40..48].copy_from_slice(&transmit);
+  socket.send(&request).map_err(|e| format!("send failed: {e}"))?;
+  let mut response = [0u8; PACKET_LEN];
+  let bytes = socket.recv(&mut response).map_err(|e| format!("no response: {e}"))?;
+  let received_at = now();
+
+  if bytes < PACKET_LEN || response[0] & 0x07 != MODE_SERVER {
+    return Err("invalid response".to_string());
+  }
+  // Stratum 0 is a kiss-o'-death; the reference ID says why, e.g. `RATE`.
+  let stratum = response[1];
+  if stratum == 0 {
+    let code = String::from_utf8_lossy(&response[12..16])
+      .trim_end_matches('\0')
+      .to_string();
+    return Err(format!("server refused the request ({code})"));
+  }
+  // The origin timestamp echoes ours; anything else answers a different request.
+  if response[24..32] != transmit {
+    return Err("response does not match the request".to_string());
+  }
+  let server_received = from_ntp(&response[32..40]);
+  let server_sent = from_ntp(&response[40..48]);
+  let offset = ((server_received - sent_at) + (server_sent - received_at)) / 2.0;
+  let delay = (received_at - sent_at) - (server_sent - server_received);
+  Ok(Measurement {
+    offset_ms: offset * 1000.0,
+    delay_ms: delay.max(0.0) * 1000.0,
+    stratum,
+  })
+}
+
+/// Seconds since the Unix epoch.
+fn now() -> f64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .unwrap_or_default()
+    .as_secs_f64()
+}
+
+fn to_ntp(unix: f64) -> [u8; 8] {
+  let seconds = unix.trunc() as u64 + UNIX_OFFSET;
+  let fraction = (unix.fract() * 4_294_967_296.0) as u32;
+  let mut bytes = [0u8; 8];
+  bytes[..4].copy_from_slice(&(seconds as u32).to_be_bytes());
+  bytes[4..].copy_from_slice(&fraction.to_be_bytes());
+  bytes
+}
+
+/// An NTP timestamp as seconds since the Unix epoch. Seconds below 2^31 are taken to be in era 1,
+/// which starts in 2036.
+fn from_ntp(bytes: &[u8]) -> f64 {
+  let seconds = u64::from(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+  let fraction = f64::from(u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]));
+  let seconds = if seconds < 1 << 31 {
+    seconds + (1 << 32)
+  } else {
+    seconds
+  };
+  (seconds as f64 - UNIX_OFFSET as f64) + fraction / 4_294_967_296.0
+}
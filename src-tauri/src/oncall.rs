@@ -0,0 +1,171 @@
+use std::time::Duration;
+
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::proxy;
+
+const PAGERDUTY_EVENTS_URL: &str = "https://events.pagerduty.com/v2/enqueue";
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OnCallProvider {
+  #[default]
+  PagerDuty,
+  Opsgenie,
+}
+
+/// PagerDuty Events v2 severities; Opsgenie priorities are derived from them.
+#[derive(Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+  #[default]
+  Critical,
+  Error,
+  Warning,
+  Info,
+}
+
+impl Severity {
+  fn as_str(self) -> &'static str {
+    match self {
+      Severity::Critical => "critical",
+      Severity::Error => "error",
+      Severity::Warning => "warning",
+      Severity::Info => "info",
+    }
+  }
+
+  fn opsgenie_priority(self) -> &'static str {
+    match self {
+      Severity::Critical => "P1",
+      Severity::Error => "P2",
+      Severity::Warning => "P3",
+      Severity::Info => "P5",
+    }
+  }
+}
+
+/// Opens an incident on outage start and resolves it on recovery, keyed by the incident ID.
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct OnCallSettings {
+  #[serde(default)]
+  pub enabled: bool,
+  #[serde(default)]
+  pub provider: OnCallProvider,
+  /// PagerDuty integration (routing) key.
+  #[serde(default)]
+  pub routing_key: String,
+  /// Opsgenie API integration key.
+  #[serde(default)]
+  pub api_key: String,
+  /// Use `api.eu.opsgenie.com` instead of the US endpoint.
+  #[serde(default)]
+  pub opsgenie_eu: bool,
+  /// Severity for outages; captive-portal interruptions are always sent as `warning`.
+  #[serde(default)]
+  pub severity: Severity,
+  /// Overrides the global alert proxy; an empty string connects directly.
+  #[serde(default)]
+  pub proxy: Option<String>,
+}
+
+pub fn validate(settings: &OnCallSettings) -> Result<(), String> {
+  if !settings.enabled {
+    return Ok(());
+  }
+  if let Some(proxy) = &settings.proxy {
+    proxy::validate_url(proxy)?;
+  }
+  match settings.provider {
+    OnCallProvider::PagerDuty if settings.routing_key.trim().is_empty() => {
+      Err("PagerDuty Routing Key 不能为空".to_string())
+    }
+    OnCallProvider::Opsgenie if settings.api_key.trim().is_empty() => Err("Opsgenie API Key 不能为空".to_string()),
+    _ => Ok(()),
+  }
+}
+
+/// `warning` sends at warning severity instead of the configured one, e.g. for a captive portal
+/// or a flapping target.
+pub fn trigger(
+  settings: &OnCallSettings,
+  dedup_key: &str,
+  summary: &str,
+  source: &str,
+  warning: bool,
+) -> Result<(), String> {
+  let severity = if warning { Severity::Warning } else { settings.severity };
+  let client = proxy::client(settings.proxy.as_deref(), TIMEOUT)?;
+  match settings.provider {
+    OnCallProvider::PagerDuty => {
+      let body = json!({
+        "routing_key": settings.routing_key.trim(),
+        "event_action": "trigger",
+        "dedup_key": dedup_key,
+        "payload": {
+          "summary": summary,
+          "source": source,
+          "severity": severity.as_str(),
+          "component": "ping-tool",
+        },
+      });
+      post(&client, PAGERDUTY_EVENTS_URL, None, &body)
+    }
+    OnCallProvider::Opsgenie => {
+      let body = json!({
+        "message": truncate(summary, 130),
+        "alias": dedup_key,
+        "description": summary,
+        "source": source,
+        "priority": severity.opsgenie_priority(),
+      });
+      post(&client, &opsgenie_url(settings, "alerts"), Some(settings), &body)
+    }
+  }
+}
+
+pub fn resolve(settings: &OnCallSettings, dedup_key: &str, note: &str) -> Result<(), String> {
+  let client = proxy::client(settings.proxy.as_deref(), TIMEOUT)?;
+  match settings.provider {
+    OnCallProvider::PagerDuty => {
+      let body = json!({
+        "routing_key": settings.routing_key.trim(),
+        "event_action": "resolve",
+        "dedup_key": dedup_key,
+      });
+      post(&client, PAGERDUTY_EVENTS_URL, None, &body)
+    }
+    OnCallProvider::Opsgenie => {
+      let path = format!("alerts/{dedup_key}/close?identifierType=alias");
+      let body = json!({ "source": "ping-tool", "note": note });
+      post(&client, &opsgenie_url(settings, &path), Some(settings), &body)
+    }
+  }
+}
+
+fn opsgenie_url(settings: &OnCallSettings, path: &str) -> String {
+  let host = if settings.opsgenie_eu { "api.eu.opsgenie.com" } else { "api.opsgenie.com" };
+  format!("https://{host}/v2/{path}")
+}
+
+fn post(client: &Client, url: &str, opsgenie: Option<&OnCallSettings>, body: &serde_json::Value) -> Result<(), String> {
+  let mut request = client.post(url).json(body);
+  if let Some(settings) = opsgenie {
+    request = request.header("Authorization", format!("GenieKey {}", settings.api_key.trim()));
+  }
+  let response = request.send().map_err(|e| format!("值班平台请求失败: {e}"))?;
+  if response.status().is_success() {
+    Ok(())
+  } else {
+    let status = response.status();
+    let text = response.text().unwrap_or_default();
+    Err(format!("值班平台返回 {status}: {}", truncate(&text, 200)))
+  }
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+  text.chars().take(max_chars).collect()
+}
@@ -0,0 +1,218 @@
+use std::collections::BTreeMap;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+const RTT_BOUNDS_MS: [f64; 9] = [1.0, 5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 500.0, 1000.0];
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct OtlpSettings {
+  #[serde(default)]
+  pub enabled: bool,
+  /// Full OTLP/HTTP metrics URL, e.g. `http://localhost:4318/v1/metrics`.
+  #[serde(default)]
+  pub endpoint: String,
+  #[serde(default)]
+  pub headers: BTreeMap<String, String>,
+  #[serde(default = "default_export_interval")]
+  pub interval_secs: u64,
+}
+
+impl Default for OtlpSettings {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      endpoint: String::new(),
+      headers: BTreeMap::new(),
+      interval_secs: default_export_interval(),
+    }
+  }
+}
+
+fn default_export_interval() -> u64 {
+  60
+}
+
+pub fn validate(settings: &OtlpSettings) -> Result<(), String> {
+  if !settings.enabled {
+    return Ok(());
+  }
+  let endpoint = settings.endpoint.trim();
+  if !(endpoint.starts_with("http://") || endpoint.starts_with("https://")) {
+    return Err("OTLP 地址必须以 http:// 或 https:// 开头".to_string());
+  }
+  if settings.interval_secs == 0 {
+    return Err("OTLP 上报间隔必须大于 0".to_string());
+  }
+  Ok(())
+}
+
+/// Cumulative per-target metrics, pushed to the collector every `interval_secs`.
+pub struct OtlpExporter {
+  settings: OtlpSettings,
+  target: String,
+  start_nanos: u128,
+  last_export: Instant,
+  probes: u64,
+  lost: u64,
+  up: bool,
+  rtt_count: u64,
+  rtt_sum: f64,
+  rtt_min: f64,
+  rtt_max: f64,
+  bucket_counts: [u64; RTT_BOUNDS_MS.len() + 1],
+}
+
+impl OtlpExporter {
+  pub fn new(settings: OtlpSettings, target: &str) -> Option<Self> {
+    if !settings.enabled || validate(&settings).is_err() {
+      return None;
+    }
+    Some(Self {
+      settings,
+      target: target.to_string(),
+      start_nanos: unix_nanos(),
+      last_export: Instant::now(),
+      probes: 0,
+      lost: 0,
+      up: false,
+      rtt_count: 0,
+      rtt_sum: 0.0,
+      rtt_min: f64::MAX,
+      rtt_max: 0.0,
+      bucket_counts: [0; RTT_BOUNDS_MS.len() + 1],
+    })
+  }
+
+  pub fn record(&mut self, success: bool, rtt_ms: Option<f64>) {
+    self.probes += 1;
+    self.up = success;
+    if !success {
+      self.lost += 1;
+      return;
+    }
+    if let Some(rtt) = rtt_ms {
+      self.rtt_count += 1;
+      self.rtt_sum += rtt;
+      self.rtt_min = self.rtt_min.min(rtt);
+      self.rtt_max = self.rtt_max.max(rtt);
+      let bucket = RTT_BOUNDS_MS
+        .iter()
+        .position(|bound| rtt <= *bound)
+        .unwrap_or(RTT_BOUNDS_MS.len());
+      self.bucket_counts[bucket] += 1;
+    }
+  }
+
+  /// Sends a snapshot on a background thread once the export interval has elapsed.
+  pub fn maybe_export(&mut self) {
+    if self.last_export.elapsed() < Duration::from_secs(self.settings.interval_secs) {
+      return;
+    }
+    self.last_export = Instant::now();
+
+    let payload = self.payload();
+    let settings = self.settings.clone();
+    thread::spawn(move || {
+      if let Err(err) = send(&settings, &payload) {
+        eprintln!("failed to export OTLP metrics: {err}");
+      }
+    });
+  }
+
+  fn payload(&self) -> Value {
+    let now = unix_nanos().to_string();
+    let start = self.start_nanos.to_string();
+    let attributes = json!([{ "key": "ping.target", "value": { "stringValue": self.target } }]);
+
+    let mut histogram_point = json!({
+      "attributes": attributes,
+      "startTimeUnixNano": start,
+      "timeUnixNano": now,
+      "count": self.rtt_count.to_string(),
+      "sum": self.rtt_sum,
+      "bucketCounts": self.bucket_counts.iter().map(u64::to_string).collect::<Vec<_>>(),
+      "explicitBounds": RTT_BOUNDS_MS,
+    });
+    if self.rtt_count > 0 {
+      histogram_point["min"] = json!(self.rtt_min);
+      histogram_point["max"] = json!(self.rtt_max);
+    }
+
+    let counter = |name: &str, description: &str, value: u64| {
+      json!({
+        "name": name,
+        "description": description,
+        "unit": "1",
+        "sum": {
+          "aggregationTemporality": 2,
+          "isMonotonic": true,
+          "dataPoints": [{
+            "attributes": attributes,
+            "startTimeUnixNano": start,
+            "timeUnixNano": now,
+            "asInt": value.to_string(),
+          }],
+        },
+      })
+    };
+
+    json!({
+      "resourceMetrics": [{
+        "resource": {
+          "attributes": [{ "key": "service.name", "value": { "stringValue": "ping-tool" } }],
+        },
+        "scopeMetrics": [{
+          "scope": { "name": "ping-tool", "version": env!("CARGO_PKG_VERSION") },
+          "metrics": [
+            {
+              "name": "ping.rtt",
+              "description": "Round-trip time of successful probes",
+              "unit": "ms",
+              "histogram": { "aggregationTemporality": 2, "dataPoints": [histogram_point] },
+            },
+            counter("ping.probes", "Probes sent", self.probes),
+            counter("ping.lost", "Probes without reply", self.lost),
+            {
+              "name": "ping.up",
+              "description": "1 if the last probe succeeded",
+              "unit": "1",
+              "gauge": {
+                "dataPoints": [{
+                  "attributes": attributes,
+                  "timeUnixNano": now,
+                  "asInt": if self.up { "1" } else { "0" },
+                }],
+              },
+            },
+          ],
+        }],
+      }],
+    })
+  }
+}
+
+fn send(settings: &OtlpSettings, payload: &Value) -> Result<(), String> {
+  let client = reqwest::blocking::Client::builder()
+    .timeout(Duration::from_secs(10))
+    .build()
+    .map_err(|e| e.to_string())?;
+  let mut request = client.post(settings.endpoint.trim()).json(payload);
+  for (name, value) in &settings.headers {
+    request = request.header(name, value);
+  }
+  let response = request.send().map_err(|e| e.to_string())?;
+  if !response.status().is_success() {
+    return Err(format!("collector returned {}", response.status()));
+  }
+  Ok(())
+}
+
+fn unix_nanos() -> u128 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_nanos())
+    .unwrap_or_default()
+}
@@ -0,0 +1,60 @@
+// Side checks that tell what kind of outage a target is in: whether a captive portal intercepts
+// web traffic, and for tunnel targets whether the peer answers outside the tunnel. They start on
+// the first failure of a run, off the probe loop, so their answers are usually in by the time the
+// outage is confirmed. One still out then is waited for briefly and otherwise left out of the
+// alert, which must not wait on a network that is down.
+
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::captive_portal::{self, CaptivePortalSettings, Connectivity};
+use crate::tunnel::{self, TunnelCheck, Verdict};
+use crate::PingEncoding;
+
+/// How long after the checks started the outage alert waits for them at most.
+const DEADLINE: Duration = Duration::from_secs(2);
+
+pub struct OutageChecks {
+  deadline: Instant,
+  portal: Option<Receiver<Connectivity>>,
+  tunnel: Option<Receiver<Verdict>>,
+}
+
+impl OutageChecks {
+  pub fn start(portal: &CaptivePortalSettings, tunnel: Option<&TunnelCheck>, encoding: PingEncoding) -> Self {
+    let portal = portal.enabled.then(|| {
+      let settings = portal.clone();
+      spawn(move || captive_portal::check(&settings))
+    });
+    let tunnel = tunnel.cloned().map(|tunnel| spawn(move || tunnel::check(&tunnel, encoding)));
+    Self {
+      deadline: Instant::now() + DEADLINE,
+      portal,
+      tunnel,
+    }
+  }
+
+  /// The captive portal check's answer; `None` when it is off or didn't answer in time.
+  pub fn connectivity(&mut self) -> Option<Connectivity> {
+    receive(self.portal.take()?, self.deadline)
+  }
+
+  /// Whether the tunnel or the link under it is down; `None` for targets without a tunnel or
+  /// when the outside endpoint didn't answer in time either way.
+  pub fn tunnel_verdict(&mut self) -> Option<Verdict> {
+    receive(self.tunnel.take()?, self.deadline)
+  }
+}
+
+fn spawn<T: Send + 'static>(check: impl FnOnce() -> T + Send + 'static) -> Receiver<T> {
+  let (tx, rx) = mpsc::channel();
+  thread::spawn(move || {
+    let _ = tx.send(check());
+  });
+  rx
+}
+
+fn receive<T>(rx: Receiver<T>, deadline: Instant) -> Option<T> {
+  rx.recv_timeout(deadline.saturating_duration_since(Instant::now())).ok()
+}
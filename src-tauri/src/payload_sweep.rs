@@ -0,0 +1,139 @@
+// The payload sweep diagnostic: validates the plan, runs ping-core's sweep with the system ping
+// and says in one sentence what the result points at.
+
+use ping_core::sweep::{self, MAX_SIZE};
+use ping_core::Sweep;
+use serde::Serialize;
+
+pub const DEFAULT_COUNT: u32 = 3;
+pub const MAX_COUNT: u32 = 10;
+const MAX_SIZES: usize = 32;
+/// IPv4 and ICMP headers on top of the payload.
+const HEADER_BYTES: u32 = 28;
+
+#[derive(Serialize)]
+pub struct SweepStepReport {
+  pub size: u32,
+  pub pattern: Option<String>,
+  pub sent: u32,
+  pub received: u32,
+  pub avg_rtt_ms: Option<f64>,
+  pub line: String,
+}
+
+#[derive(Serialize)]
+pub struct PatternSummary {
+  /// `None` is ping's default payload.
+  pub pattern: Option<String>,
+  pub loss_starts_at: Option<u32>,
+}
+
+#[derive(Serialize)]
+pub struct SweepReport {
+  pub address: String,
+  pub steps: Vec<SweepStepReport>,
+  pub largest_clean: Option<u32>,
+  pub patterns: Vec<PatternSummary>,
+  /// Windows ping can't fill the payload, so only sizes were swept.
+  pub patterns_supported: bool,
+  pub conclusion: String,
+}
+
+pub fn validate(sizes: &[u32], patterns: &[String], count: u32) -> Result<(), String> {
+  if sizes.is_empty() || sizes.len() > MAX_SIZES {
+    return Err(format!("载荷大小应有 1 到 {MAX_SIZES} 个"));
+  }
+  if let Some(size) = sizes.iter().find(|size| !(1..=MAX_SIZE).contains(*size)) {
+    return Err(format!("载荷大小 {size} 无效，应在 1-{MAX_SIZE} 字节之间"));
+  }
+  if let Some(pattern) = patterns.iter().find(|pattern| !sweep::valid_pattern(pattern)) {
+    return Err(format!(
+      "填充模式 {pattern} 无效，应为 1 到 {} 字节的十六进制，如 55aa",
+      sweep::MAX_PATTERN_BYTES
+    ));
+  }
+  if !(1..=MAX_COUNT).contains(&count) {
+    return Err(format!("每个大小的探测次数应在 1-{MAX_COUNT} 之间"));
+  }
+  Ok(())
+}
+
+pub fn report(address: &str, result: Sweep, patterns: &[String], patterns_supported: bool) -> SweepReport {
+  let mut summaries = vec![PatternSummary {
+    pattern: None,
+    loss_starts_at: result.loss_starts_at(None),
+  }];
+  if patterns_supported {
+    summaries.extend(patterns.iter().map(|pattern| PatternSummary {
+      pattern: Some(pattern.clone()),
+      loss_starts_at: result.loss_starts_at(Some(pattern)),
+    }));
+  }
+  let conclusion = conclusion(&result, &summaries);
+  SweepReport {
+    address: address.to_string(),
+    steps: result
+      .steps
+      .into_iter()
+      .map(|step| SweepStepReport {
+        avg_rtt_ms: step.burst.avg_rtt_ms(),
+        size: step.size,
+        pattern: step.pattern,
+        sent: step.burst.sent,
+        received: step.burst.received,
+        line: step.burst.line,
+      })
+      .collect(),
+    largest_clean: result.largest_clean,
+    patterns: summaries,
+    patterns_supported,
+    conclusion,
+  }
+}
+
+fn conclusion(result: &Sweep, summaries: &[PatternSummary]) -> String {
+  let smallest = result.steps.first().map_or(0, |step| step.size);
+  let mut findings = Vec::new();
+  match (summaries[0].loss_starts_at, result.largest_clean) {
+    (None, _) => {}
+    (Some(_), Some(clean)) => findings.push(format!(
+      "载荷超过 {clean} 字节开始丢包，路径 MTU 约为 {} 字节（IPv4），大包需要分片或被丢弃",
+      clean + HEADER_BYTES
+    )),
+    (Some(_), None) => findings.push(format!("最小的 {smallest} 字节载荷也有丢包，丢包与包大小无关")),
+  }
+  // Loss at sizes below where it starts for good comes and goes; it's no size limit.
+  let intermittent: Vec<String> = result
+    .steps
+    .iter()
+    .filter(|step| step.pattern.is_none() && step.lossy())
+    .filter(|step| summaries[0].loss_starts_at.is_none_or(|start| step.size < start))
+    .map(|step| step.size.to_string())
+    .collect();
+  if !intermittent.is_empty() {
+    findings.push(format!(
+      "载荷 {} 字节出现间歇丢包，这部分丢包与包大小无关",
+      intermittent.join("、")
+    ));
+  }
+  let broken: Vec<&str> = summaries[1..]
+    .iter()
+    .filter(|summary| {
+      summary
+        .loss_starts_at
+        .is_some_and(|size| summaries[0].loss_starts_at.is_none_or(|d| size < d))
+    })
+    .filter_map(|summary| summary.pattern.as_deref())
+    .collect();
+  if !broken.is_empty() {
+    findings.push(format!(
+      "填充模式 {} 比默认载荷更早丢包，可能是网卡、调制解调器或线路对特定比特序列处理异常",
+      broken.join("、")
+    ));
+  }
+  if findings.is_empty() {
+    "所有载荷大小和填充模式均无丢包".to_string()
+  } else {
+    findings.join("；")
+  }
+}
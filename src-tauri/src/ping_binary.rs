@@ -0,0 +1,92 @@
+// A ping executable to use instead of the system `ping`, globally or for one target: busybox
+// ping on a stripped-down box, a vendored iputils where the system one lacks a feature, or a
+// wrapper on a machine where `ping` is locked down. The argument template is expanded by
+// `ping_core::custom_args`; the program's output is parsed as ping output.
+
+use std::env;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+const MAX_TEMPLATE_LEN: usize = 500;
+
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct PingBinary {
+  /// Path or name on PATH of the executable; empty for the system ping.
+  #[serde(default)]
+  pub program: String,
+  /// Argument template, e.g. `ping {args} {address}` for busybox; empty passes the system
+  /// ping's arguments unchanged.
+  #[serde(default)]
+  pub args: String,
+}
+
+impl PingBinary {
+  pub fn is_set(&self) -> bool {
+    !self.program.trim().is_empty()
+  }
+}
+
+/// The binary a target's pings use: its own, else the global one, else `None` for the system ping.
+pub fn effective(global: &PingBinary, target: Option<&PingBinary>) -> Option<PingBinary> {
+  target
+    .filter(|binary| binary.is_set())
+    .or(Some(global).filter(|binary| binary.is_set()))
+    .cloned()
+}
+
+pub fn validate(binary: &PingBinary) -> Result<(), String> {
+  let program = binary.program.trim();
+  if program.chars().any(char::is_control) {
+    return Err("ping 程序路径不能包含控制字符".to_string());
+  }
+  if !binary.is_set() {
+    if !binary.args.trim().is_empty() {
+      return Err("设置参数模板时必须同时指定 ping 程序".to_string());
+    }
+    return Ok(());
+  }
+  if binary.args.len() > MAX_TEMPLATE_LEN {
+    return Err(format!("参数模板最多 {MAX_TEMPLATE_LEN} 个字符"));
+  }
+  let mut rest = binary.args.as_str();
+  while let Some(open) = rest.find('{') {
+    let Some(close) = rest[open..].find('}') else {
+      return Err(format!("参数模板中的 {{ 没有闭合: {}", &rest[open..]));
+    };
+    let name = &rest[open + 1..open + close];
+    if !ping_core::TEMPLATE_PLACEHOLDERS.contains(&name) {
+      return Err(format!(
+        "参数模板中有未知变量 {{{name}}}，可用变量: {}",
+        ping_core::TEMPLATE_PLACEHOLDERS
+          .map(|name| format!("{{{name}}}"))
+          .join(" ")
+      ));
+    }
+    rest = &rest[open + close + 1..];
+  }
+  let template = binary.args.trim();
+  if !template.is_empty() && !template.contains("{address}") {
+    return Err("参数模板必须包含 {address}".to_string());
+  }
+  Ok(())
+}
+
+/// The executable `program` names: a path to a file, or a name looked up on PATH (with `.exe`
+/// added on Windows). Checked before monitoring starts rather than when saving, so settings
+/// copied from another machine still load.
+pub fn find(program: &str) -> Option<PathBuf> {
+  let path = Path::new(program);
+  if path.components().count() > 1 || path.is_absolute() {
+    return path.is_file().then(|| path.to_path_buf());
+  }
+  let names = if cfg!(target_os = "windows") && path.extension().is_none() {
+    vec![format!("{program}.exe"), program.to_string()]
+  } else {
+    vec![program.to_string()]
+  };
+  let dirs = env::var_os("PATH")?;
+  env::split_paths(&dirs)
+    .flat_map(|dir| names.iter().map(move |name| dir.join(name)))
+    .find(|candidate| candidate.is_file())
+}
@@ -0,0 +1,124 @@
+use std::io::ErrorKind;
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::lock::LockExt;
+
+const MAX_PORTS: usize = 4096;
+const WORKERS: usize = 64;
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PortState {
+  Open,
+  Closed,
+  Filtered,
+}
+
+impl PortState {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      PortState::Open => "open",
+      PortState::Closed => "closed",
+      PortState::Filtered => "filtered",
+    }
+  }
+}
+
+#[derive(Clone, Serialize)]
+pub struct PortScanResult {
+  pub port: u16,
+  pub state: PortState,
+  pub latency_ms: Option<f64>,
+}
+
+/// Parses a port list such as `22,80,443,8000-8010`.
+pub fn parse_ports(spec: &str) -> Result<Vec<u16>, String> {
+  let mut ports = Vec::new();
+  for part in spec.split([',', ' ', '\n']).map(str::trim).filter(|p| !p.is_empty()) {
+    let (start, end) = match part.split_once('-') {
+      Some((start, end)) => (parse_port(start)?, parse_port(end)?),
+      None => {
+        let port = parse_port(part)?;
+        (port, port)
+      }
+    };
+    if start > end {
+      return Err(format!("端口范围不合法: {part}"));
+    }
+    ports.extend(start..=end);
+    if ports.len() > MAX_PORTS {
+      return Err(format!("一次最多扫描 {MAX_PORTS} 个端口"));
+    }
+  }
+  ports.sort_unstable();
+  ports.dedup();
+  if ports.is_empty() {
+    return Err("端口列表不能为空".to_string());
+  }
+  Ok(ports)
+}
+
+fn parse_port(value: &str) -> Result<u16, String> {
+  match value.trim().parse::<u16>() {
+    Ok(port) if port > 0 => Ok(port),
+    _ => Err(format!("端口不合法: {value}")),
+  }
+}
+
+pub fn scan(host: &str, ports: Vec<u16>, timeout: Duration) -> Result<Vec<PortScanResult>, String> {
+  let ip = (host, 0)
+    .to_socket_addrs()
+    .map_err(|e| format!("无法解析 {host}: {e}"))?
+    .next()
+    .ok_or_else(|| format!("无法解析 {host}"))?
+    .ip();
+
+  let worker_count = ports.len().min(WORKERS);
+  let queue = Arc::new(Mutex::new(ports));
+  let results = Arc::new(Mutex::new(Vec::new()));
+  let workers: Vec<_> = (0..worker_count)
+    .map(|_| {
+      let queue = queue.clone();
+      let results = results.clone();
+      thread::spawn(move || {
+        while let Some(port) = next_port(&queue) {
+          let result = probe(SocketAddr::new(ip, port), timeout);
+          results.lock_or_recover().push(result);
+        }
+      })
+    })
+    .collect();
+  for worker in workers {
+    let _ = worker.join();
+  }
+
+  let mut results = results.lock_or_recover().clone();
+  results.sort_by_key(|r| r.port);
+  Ok(results)
+}
+
+fn next_port(queue: &Mutex<Vec<u16>>) -> Option<u16> {
+  queue.lock_or_recover().pop()
+}
+
+fn probe(addr: SocketAddr, timeout: Duration) -> PortScanResult {
+  let start = Instant::now();
+  let (state, latency_ms) = match TcpStream::connect_timeout(&addr, timeout) {
+    Ok(_) => (PortState::Open, Some(start.elapsed().as_secs_f64() * 1000.0)),
+    // An RST means the host answered but nothing listens there.
+    Err(e) if e.kind() == ErrorKind::ConnectionRefused => {
+      (PortState::Closed, Some(start.elapsed().as_secs_f64() * 1000.0))
+    }
+    Err(_) => (PortState::Filtered, None),
+  };
+  PortScanResult {
+    port: addr.port(),
+    state,
+    latency_ms,
+  }
+}
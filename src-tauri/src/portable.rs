@@ -0,0 +1,25 @@
+// Portable mode keeps settings and logs in a `data` folder next to the executable instead of
+// the per-user app directories, so the tool can run from a USB stick without leaving state
+// behind on the machine. It is enabled by a `portable.flag` file beside the executable or the
+// `--portable` command-line switch.
+
+use std::env;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+const FLAG_FILE: &str = "portable.flag";
+const SWITCH: &str = "--portable";
+const DATA_DIR: &str = "data";
+
+static DATA: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+/// The portable data folder, or `None` when running installed.
+pub fn data_dir() -> Option<&'static PathBuf> {
+  DATA.get_or_init(detect).as_ref()
+}
+
+fn detect() -> Option<PathBuf> {
+  let exe_dir = env::current_exe().ok()?.parent()?.to_path_buf();
+  let enabled = env::args().skip(1).any(|arg| arg == SWITCH) || exe_dir.join(FLAG_FILE).is_file();
+  enabled.then(|| exe_dir.join(DATA_DIR))
+}
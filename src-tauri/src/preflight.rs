@@ -0,0 +1,115 @@
+// Checks run by `start_session` before the worker starts, so a bad address, missing ping
+// binary, locally blocked ICMP or unwritable log directory is reported to the user instead
+// of failing silently on the first iteration.
+
+use std::env;
+use std::fs::{create_dir_all, remove_file, OpenOptions};
+use std::net::{IpAddr, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use ping_core::is_blocked_locally;
+use url::Url;
+
+use crate::error::{AppError, ErrorKind};
+use crate::ping_binary::{self, PingBinary};
+use crate::{local_names, ping_once_in, route_snapshot, targets, PingEncoding, PingOptions};
+
+const RESOLVE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// `binary` is the ping executable configured for the target, `None` for the system ping.
+pub fn check(address: &str, encoding: PingEncoding, binary: Option<&PingBinary>, base_dir: &Path) -> Result<(), AppError> {
+  let host = if targets::is_url_target(address) {
+    let url = Url::parse(address).map_err(|e| AppError::invalid_input(format!("地址格式无效: {e}")))?;
+    url
+      .host_str()
+      .map(|host| host.trim_matches(['[', ']']).to_string())
+      .ok_or_else(|| AppError::invalid_input(format!("地址 {address} 缺少主机名")))?
+  } else {
+    match binary {
+      Some(binary) if ping_binary::find(binary.program.trim()).is_none() => {
+        return Err(AppError::not_found(format!("找不到设置的 ping 程序: {}", binary.program.trim())));
+      }
+      None if find_ping_binary().is_none() => {
+        return Err(AppError::not_found("找不到 ping 程序，请确认其已安装并位于 PATH 中"));
+      }
+      _ => {}
+    }
+    check_local_icmp(encoding, binary)?;
+    address.to_string()
+  };
+  resolve(&host).map_err(|message| AppError::new(ErrorKind::Network, message))?;
+  check_log_dir(base_dir)
+}
+
+fn resolve(host: &str) -> Result<(), String> {
+  if host.parse::<IpAddr>().is_ok() {
+    return Ok(());
+  }
+  // The lookup can hang on a broken resolver; don't hold the caller past the timeout.
+  let (tx, rx) = mpsc::channel();
+  let lookup_host = host.to_string();
+  thread::spawn(move || {
+    let result = (lookup_host.as_str(), 0).to_socket_addrs().map(|mut addrs| addrs.next().is_some());
+    let _ = tx.send(result);
+  });
+  let resolved = match rx.recv_timeout(RESOLVE_TIMEOUT) {
+    Ok(Ok(true)) => Ok(()),
+    Ok(Ok(false)) => Err(format!("无法解析地址 {host}: 没有可用的 IP")),
+    Ok(Err(e)) => Err(format!("无法解析地址 {host}: {e}")),
+    Err(_) => Err(format!("解析地址 {host} 超时")),
+  };
+  // `.local` and NetBIOS names the system resolver doesn't handle.
+  resolved.or_else(|err| local_names::resolve(host).map(|_| ()).ok_or(err))
+}
+
+/// Pings the default gateway: firewalls leave loopback alone, so it is the nearest echo that has
+/// to leave the machine. Only a request ping couldn't even send means a local firewall or policy
+/// is dropping outbound ICMP; plenty of routers just don't answer. Monitoring would otherwise
+/// report every target as down.
+fn check_local_icmp(encoding: PingEncoding, binary: Option<&PingBinary>) -> Result<(), AppError> {
+  let Some(gateway) = route_snapshot::default_gateway() else {
+    return Ok(());
+  };
+  let ping = PingOptions {
+    encoding,
+    dscp: None,
+    binary,
+  };
+  match ping_once_in(&gateway.to_string(), &ping, None, None) {
+    Err(output) if is_blocked_locally(&output) => Err(
+      AppError::new(
+        ErrorKind::IcmpBlocked,
+        format!("本机策略阻止了 ping 发出（连默认网关 {gateway} 也发送失败），请检查防火墙的 ICMP 出站规则"),
+      )
+      .with_details(output),
+    ),
+    _ => Ok(()),
+  }
+}
+
+pub fn find_ping_binary() -> Option<PathBuf> {
+  let name = if cfg!(target_os = "windows") { "ping.exe" } else { "ping" };
+  let path = env::var_os("PATH")?;
+  env::split_paths(&path)
+    .map(|dir| dir.join(name))
+    .find(|candidate| candidate.is_file())
+}
+
+fn check_log_dir(base_dir: &Path) -> Result<(), AppError> {
+  let io_error = |message: &str, e: std::io::Error| {
+    AppError::new(ErrorKind::Io, format!("{message} {}", base_dir.display())).with_details(e.to_string())
+  };
+  create_dir_all(base_dir).map_err(|e| io_error("无法创建日志目录", e))?;
+  let probe = base_dir.join(".write-test");
+  OpenOptions::new()
+    .create(true)
+    .truncate(true)
+    .write(true)
+    .open(&probe)
+    .map_err(|e| io_error("日志目录不可写", e))?;
+  let _ = remove_file(&probe);
+  Ok(())
+}
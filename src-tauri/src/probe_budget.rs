@@ -0,0 +1,60 @@
+// A time budget for each probe cycle. Several packets per cycle, a slow custom ping binary or a
+// DSCP-marked ping that the network drops can keep one cycle running well past the one-second
+// interval; the next cycle then starts late and what it measures is skewed by the backlog. With a
+// budget set, a ping still running when the budget is used up is killed and the cycle counts as
+// failed, so the loop keeps its pace. Probes that aren't ping processes (HTTP, SNMP, NTP, ...)
+// stop at their own timeouts.
+//
+// The app monitors one target in one session, so there are no sessions to stagger or to cap
+// against each other; bounding the cycle is what keeps the probe schedule steady.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+use crate::lock::LockExt;
+use crate::ProbeSlot;
+
+const MIN_BUDGET_MS: u64 = 100;
+const MAX_BUDGET_MS: u64 = 60_000;
+
+/// Kills the ping parked in a probe slot once the budget is used up. Dropping it disarms it.
+pub struct Watchdog {
+  _disarm: mpsc::Sender<()>,
+  expired: Arc<AtomicBool>,
+}
+
+impl Watchdog {
+  pub fn arm(slot: &ProbeSlot, budget: Duration) -> Self {
+    let (disarm, disarmed) = mpsc::channel::<()>();
+    let expired = Arc::new(AtomicBool::new(false));
+    let (slot, flag) = (slot.clone(), expired.clone());
+    thread::spawn(move || {
+      // Dropping the sender ends the wait early with `Disconnected`.
+      if disarmed.recv_timeout(budget) == Err(mpsc::RecvTimeoutError::Timeout) {
+        if let Some(child) = slot.lock_or_recover().as_mut() {
+          flag.store(true, Ordering::SeqCst);
+          let _ = child.kill();
+        }
+      }
+    });
+    Self {
+      _disarm: disarm,
+      expired,
+    }
+  }
+
+  /// Whether the budget ran out with a ping still running.
+  pub fn expired(&self) -> bool {
+    self.expired.load(Ordering::SeqCst)
+  }
+}
+
+/// 0 turns the budget off.
+pub fn validate(budget_ms: u64) -> Result<(), String> {
+  if budget_ms != 0 && !(MIN_BUDGET_MS..=MAX_BUDGET_MS).contains(&budget_ms) {
+    return Err(format!("每轮探测时间预算应在 {MIN_BUDGET_MS} 到 {MAX_BUDGET_MS} 毫秒之间，0 表示不限制"));
+  }
+  Ok(())
+}
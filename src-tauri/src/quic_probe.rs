@@ -0,0 +1,136 @@
+// QUIC reachability for HTTPS targets served over HTTP/3, whose UDP path can be broken (a
+// firewall dropping UDP 443, a load balancer without a QUIC listener) while the TCP check stays
+// green. The probe opens a handshake the way every QUIC client starts one, with a padded
+// 1200-byte long-header Initial, but offers a reserved version. RFC 9000 §6 requires a server
+// to answer that with a Version Negotiation packet, which needs no TLS: it proves the datagram
+// reached a QUIC endpoint and came back, and lists the versions the server speaks.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io::ErrorKind;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+
+use url::Url;
+
+const TIMEOUT: Duration = Duration::from_secs(3);
+/// Servers drop client Initials in smaller datagrams (RFC 9000 §14.1).
+const INITIAL_SIZE: usize = 1200;
+/// A version of the reserved `0x?a?a?a?a` pattern, which no server implements.
+const GREASE_VERSION: u32 = 0x1a2a_3a4a;
+const VERSION_1: u32 = 0x0000_0001;
+const VERSION_2: u32 = 0x6b33_43cf;
+const ID_LEN: usize = 8;
+
+/// Checks the QUIC endpoint of an `https://` URL, on the URL's port. Returns the summary, e.g.
+/// `quic=12ms v1`.
+pub fn probe(address: &str) -> Result<String, String> {
+  let url = Url::parse(address).map_err(|e| format!("invalid url: {e}"))?;
+  let host = url.host_str().ok_or_else(|| "url has no host".to_string())?;
+  let port = url.port_or_known_default().unwrap_or(443);
+  let addr: SocketAddr = (host.trim_matches(['[', ']']), port)
+    .to_socket_addrs()
+    .map_err(|e| format!("dns failed: {e}"))?
+    .next()
+    .ok_or_else(|| "dns returned no address".to_string())?;
+
+  let local = if addr.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+  let socket = UdpSocket::bind(local).map_err(|e| format!("bind failed: {e}"))?;
+  socket.connect(addr).map_err(|e| format!("connect failed: {e}"))?;
+  socket
+    .set_read_timeout(Some(TIMEOUT))
+    .map_err(|e| format!("socket setup failed: {e}"))?;
+
+  let (dcid, scid) = (random_id(), random_id());
+  let start = Instant::now();
+  socket
+    .send(&initial(&dcid, &scid))
+    .map_err(|e| format!("send failed: {e}"))?;
+  let mut buffer = [0u8; 1500];
+  let deadline = start + TIMEOUT;
+  loop {
+    let bytes = match socket.recv(&mut buffer) {
+      Ok(bytes) => bytes,
+      Err(e) if matches!(e.kind(), ErrorKind::ConnectionRefused | ErrorKind::ConnectionReset) => {
+        return Err(format!("QUIC: UDP {port} port unreachable"));
+      }
+      Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+        return Err(format!("QUIC: no answer on UDP {port} within {}s", TIMEOUT.as_secs()));
+      }
+      Err(e) => return Err(format!("QUIC: receive failed: {e}")),
+    };
+    let ms = start.elapsed().as_secs_f64() * 1000.0;
+    // Anything else on the socket is not an answer to this probe; keep waiting for one.
+    if let Some(versions) = version_negotiation(&buffer[..bytes], &dcid, &scid) {
+      return match supported(&versions) {
+        Some(name) => Ok(format!("quic={ms:.0}ms {name}")),
+        None => Err(format!("QUIC: server offers no known version ({})", list(&versions))),
+      };
+    }
+    let left = deadline.saturating_duration_since(Instant::now());
+    if left.is_zero() {
+      return Err(format!("QUIC: no answer on UDP {port} within {}s", TIMEOUT.as_secs()));
+    }
+    let _ = socket.set_read_timeout(Some(left));
+  }
+}
+
+/// Connection IDs only need to be unpredictable enough to match the answer to the probe.
+fn random_id() -> [u8; ID_LEN] {
+  RandomState::new().build_hasher().finish().to_be_bytes()
+}
+
+/// A long-header packet with `GREASE_VERSION`, padded to `INITIAL_SIZE`. Only the invariant
+/// header fields matter; the server never parses past them.
+fn initial(dcid: &[u8; ID_LEN], scid: &[u8; ID_LEN]) -> Vec<u8> {
+  let mut packet = vec![0xc0];
+  packet.extend(GREASE_VERSION.to_be_bytes());
+  packet.push(ID_LEN as u8);
+  packet.extend(dcid);
+  packet.push(ID_LEN as u8);
+  packet.extend(scid);
+  packet.resize(INITIAL_SIZE, 0);
+  packet
+}
+
+/// The versions in a Version Negotiation packet answering ours: long header, version 0 and our
+/// connection IDs echoed back swapped.
+fn version_negotiation(packet: &[u8], dcid: &[u8; ID_LEN], scid: &[u8; ID_LEN]) -> Option<Vec<u32>> {
+  if packet.first()? & 0x80 == 0 || packet.get(1..5)? != [0, 0, 0, 0] {
+    return None;
+  }
+  let mut at = 5;
+  let mut field = || {
+    let len = usize::from(*packet.get(at)?);
+    let value = packet.get(at + 1..at + 1 + len)?;
+    at += 1 + len;
+    Some(value)
+  };
+  if field()? != scid || field()? != dcid {
+    return None;
+  }
+  let versions: Vec<u32> = packet
+    .get(at..)?
+    .chunks_exact(4)
+    .map(|chunk| u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+    .collect();
+  (!versions.is_empty()).then_some(versions)
+}
+
+fn supported(versions: &[u32]) -> Option<&'static str> {
+  if versions.contains(&VERSION_1) {
+    Some("v1")
+  } else if versions.contains(&VERSION_2) {
+    Some("v2")
+  } else {
+    None
+  }
+}
+
+fn list(versions: &[u32]) -> String {
+  versions
+    .iter()
+    .map(|version| format!("{version:#010x}"))
+    .collect::<Vec<_>>()
+    .join(", ")
+}
@@ -0,0 +1,119 @@
+// Networks on which alerting pauses, such as a phone hotspot or in-flight Wi-Fi, where bad
+// latency is expected. Probes and logs carry on as usual; only notifications are held back.
+// The current network is the SSID of the connected Wi-Fi, asked of the OS tools.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::lock::LockExt;
+
+/// Asking the OS spawns a process, so the answer is reused for this long.
+const CACHE_TTL: Duration = Duration::from_secs(30);
+const MAX_NAME_LEN: usize = 64;
+
+static CURRENT: Mutex<Option<(Instant, Option<String>)>> = Mutex::new(None);
+
+pub fn validate(names: &[String]) -> Result<(), String> {
+  for name in names {
+    if name.trim().is_empty() {
+      return Err("静默网络名称不能为空".to_string());
+    }
+    if name.chars().count() > MAX_NAME_LEN || name.chars().any(char::is_control) {
+      return Err(format!("静默网络名称无效: {name}"));
+    }
+  }
+  Ok(())
+}
+
+/// The listed network the machine is on, if any. Names match case-insensitively.
+pub fn matching(names: &[String]) -> Option<String> {
+  if names.is_empty() {
+    return None;
+  }
+  let current = current_ssid()?;
+  names
+    .iter()
+    .any(|name| name.trim().eq_ignore_ascii_case(&current))
+    .then_some(current)
+}
+
+/// The SSID of the connected Wi-Fi network.
+pub fn current_ssid() -> Option<String> {
+  if let Some((checked, ssid)) = CURRENT.lock_or_recover().as_ref() {
+    if checked.elapsed() < CACHE_TTL {
+      return ssid.clone();
+    }
+  }
+  let ssid = platform::ssid().filter(|ssid| !ssid.is_empty());
+  *CURRENT.lock_or_recover() = Some((Instant::now(), ssid.clone()));
+  ssid
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+  use crate::{decode_ping_output, probe_command, PingEncoding};
+
+  /// `netsh wlan show interfaces` prints `    SSID                   : Office` per connected
+  /// interface; the key isn't localized, and `BSSID` lines must not match.
+  pub fn ssid() -> Option<String> {
+    let args = ["wlan", "show", "interfaces"].map(String::from);
+    let output = probe_command("netsh", &args).output().ok()?;
+    let text = decode_ping_output(&output.stdout, PingEncoding::Auto);
+    text.lines().find_map(|line| {
+      let (key, value) = line.split_once(':')?;
+      (key.trim() == "SSID").then(|| value.trim().to_string())
+    })
+  }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+  use std::process::Command;
+
+  /// NetworkManager first (`yes:Office`, with `:` in names escaped as `\:`), then wireless-tools.
+  pub fn ssid() -> Option<String> {
+    if let Ok(output) = Command::new("nmcli").args(["-t", "-f", "active,ssid", "dev", "wifi"]).output() {
+      let text = String::from_utf8_lossy(&output.stdout);
+      if let Some(ssid) = text.lines().find_map(|line| line.strip_prefix("yes:")) {
+        return Some(ssid.replace("\\:", ":"));
+      }
+    }
+    let output = Command::new("iwgetid").arg("-r").output().ok()?;
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+  }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+  use std::process::Command;
+
+  /// `Current Wi-Fi Network: Office`, or `You are not associated with an AirPort network.`
+  pub fn ssid() -> Option<String> {
+    let device = wifi_device().unwrap_or_else(|| "en0".to_string());
+    let output = Command::new("networksetup")
+      .args(["-getairportnetwork", &device])
+      .output()
+      .ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let (_, ssid) = text.trim().split_once("Network: ")?;
+    Some(ssid.trim().to_string())
+  }
+
+  /// The Wi-Fi interface, which is `en1` on Macs with a built-in Ethernet port: the `Device:`
+  /// line after `Hardware Port: Wi-Fi` (`AirPort` on older systems).
+  fn wifi_device() -> Option<String> {
+    let output = Command::new("networksetup").arg("-listallhardwareports").output().ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let mut lines = text.lines().map(str::trim);
+    lines.find(|line| matches!(*line, "Hardware Port: Wi-Fi" | "Hardware Port: AirPort"))?;
+    let device = lines.next()?.strip_prefix("Device: ")?;
+    Some(device.trim().to_string())
+  }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
+mod platform {
+  pub fn ssid() -> Option<String> {
+    None
+  }
+}
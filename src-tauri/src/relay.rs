@@ -0,0 +1,264 @@
+// Probing a target from other places. A relay agent is another copy of the app running in
+// relay mode (`relay_server`); asking every agent to ping the same target alongside a local
+// probe shows whether a host is down for everyone or only from here.
+
+use std::io::{BufRead, BufReader};
+use std::thread;
+use std::time::Duration;
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use crate::proxy::{self, ProxySettings};
+use crate::{parse_rtt_ms, ping_once, PingEncoding};
+
+pub const DEFAULT_COUNT: u32 = 5;
+pub const MAX_COUNT: u32 = 20;
+/// Agents pace their probes a second apart, like the local probe.
+pub const PROBE_SPACING: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct RelayAgent {
+  /// Shown in reports, e.g. the agent's location; also identifies the agent.
+  pub name: String,
+  /// Base URL of the agent, e.g. `https://relay.example.com:8787`.
+  pub url: String,
+  /// Bearer token the agent was started with.
+  #[serde(default)]
+  pub token: String,
+  #[serde(default = "default_enabled")]
+  pub enabled: bool,
+  /// Overrides the global proxy; an empty string connects directly.
+  #[serde(default)]
+  pub proxy: Option<String>,
+}
+
+fn default_enabled() -> bool {
+  true
+}
+
+/// The body of `POST /probe`.
+#[derive(Deserialize, Serialize)]
+pub struct ProbeRequest {
+  pub address: String,
+  pub count: u32,
+}
+
+/// One probe; agents answer `POST /probe` with one of these per line as each probe finishes.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct ProbeResult {
+  pub timestamp: String,
+  pub success: bool,
+  pub line: String,
+  pub rtt_ms: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct Vantage {
+  /// `本机` for the local probe, otherwise the agent's name.
+  pub name: String,
+  pub local: bool,
+  pub sent: u32,
+  pub received: u32,
+  pub loss_percent: Option<f64>,
+  pub avg_rtt_ms: Option<f64>,
+  /// Set when the agent could not be asked or broke off; partial results are kept.
+  pub error: Option<String>,
+  pub results: Vec<ProbeResult>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+  /// Reachable from here and from every agent that answered.
+  Up,
+  /// Unreachable from here and from every agent that answered.
+  Down,
+  /// Unreachable from here only; the problem is on this side.
+  LocalOnly,
+  /// Reachable from here, but not from some agents.
+  Partial,
+  /// Unreachable from here and no agent answered.
+  Unknown,
+}
+
+#[derive(Serialize)]
+pub struct GeoReport {
+  pub address: String,
+  pub count: u32,
+  /// The local probe first, then agents in the order they are configured.
+  pub vantages: Vec<Vantage>,
+  pub verdict: Verdict,
+}
+
+pub fn validate(agent: &RelayAgent) -> Result<(), String> {
+  if agent.name.trim().is_empty() {
+    return Err("中继节点名称不能为空".to_string());
+  }
+  let url = agent.url.trim();
+  if !(url.starts_with("http://") || url.starts_with("https://")) || url::Url::parse(url).is_err() {
+    return Err(format!("中继节点地址应为 http:// 或 https:// 开头的 URL: {url}"));
+  }
+  if let Some(proxy) = &agent.proxy {
+    proxy::validate_url(proxy)?;
+  }
+  Ok(())
+}
+
+pub fn upsert(agents: &mut Vec<RelayAgent>, agent: RelayAgent) {
+  match agents.iter_mut().find(|a| a.name == agent.name) {
+    Some(existing) => *existing = agent,
+    None => agents.push(agent),
+  }
+}
+
+/// Probes `address` `count` times from here and from every enabled agent at once.
+pub fn probe_all(
+  agents: &[RelayAgent],
+  global_proxy: &ProxySettings,
+  address: &str,
+  count: u32,
+  encoding: PingEncoding,
+) -> GeoReport {
+  let request = ProbeRequest {
+    address: address.to_string(),
+    count,
+  };
+  let (local, remote) = thread::scope(|scope| {
+    let remote: Vec<_> = agents
+      .iter()
+      .filter(|agent| agent.enabled)
+      .map(|agent| {
+        let proxy = proxy::effective(global_proxy, &agent.proxy);
+        let request = &request;
+        scope.spawn(move || ask_agent(agent, proxy.as_deref(), request))
+      })
+      .collect();
+    let local = vantage("本机".to_string(), true, probe_locally(address, count, encoding), None);
+    let remote: Vec<Vantage> = remote
+      .into_iter()
+      .map(|handle| handle.join().expect("relay request panicked"))
+      .collect();
+    (local, remote)
+  });
+
+  let verdict = verdict(&local, &remote);
+  let mut vantages = vec![local];
+  vantages.extend(remote);
+  GeoReport {
+    address: address.to_string(),
+    count,
+    vantages,
+    verdict,
+  }
+}
+
+/// Runs the probes of one request here.
+pub fn probe_locally(address: &str, count: u32, encoding: PingEncoding) -> Vec<ProbeResult> {
+  let mut results = Vec::new();
+  probe_each(address, count, encoding, |result| {
+    results.push(result);
+    true
+  });
+  results
+}
+
+/// Runs the probes one by one, handing each result to `sink`; stops early when it returns false.
+pub fn probe_each(
+  address: &str,
+  count: u32,
+  encoding: PingEncoding,
+  mut sink: impl FnMut(ProbeResult) -> bool,
+) {
+  for index in 0..count {
+    if index > 0 {
+      thread::sleep(PROBE_SPACING);
+    }
+    let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let result = match ping_once(address, encoding, None) {
+      Ok(line) => ProbeResult {
+        timestamp,
+        success: true,
+        rtt_ms: parse_rtt_ms(&line),
+        line,
+      },
+      Err(line) => ProbeResult {
+        timestamp,
+        success: false,
+        rtt_ms: None,
+        line,
+      },
+    };
+    if !sink(result) {
+      break;
+    }
+  }
+}
+
+fn ask_agent(agent: &RelayAgent, proxy: Option<&str>, request: &ProbeRequest) -> Vantage {
+  let mut results = Vec::new();
+  let error = stream_results(agent, proxy, request, &mut results).err();
+  vantage(agent.name.clone(), false, results, error)
+}
+
+fn stream_results(
+  agent: &RelayAgent,
+  proxy: Option<&str>,
+  request: &ProbeRequest,
+  results: &mut Vec<ProbeResult>,
+) -> Result<(), String> {
+  // Each probe takes up to the ping timeout plus the spacing.
+  let timeout = Duration::from_secs(10 + 6 * u64::from(request.count));
+  let client = proxy::client(proxy, timeout)?;
+  let url = format!("{}/probe", agent.url.trim().trim_end_matches('/'));
+  let mut builder = client.post(&url).json(request);
+  if !agent.token.trim().is_empty() {
+    builder = builder.bearer_auth(agent.token.trim());
+  }
+  let response = builder.send().map_err(|e| format!("无法连接中继节点: {e}"))?;
+  let status = response.status();
+  if !status.is_success() {
+    let body = response.text().unwrap_or_default();
+    return Err(format!("中继节点返回 {status}: {}", body.trim()));
+  }
+  for line in BufReader::new(response).lines() {
+    let line = line.map_err(|e| format!("读取中继节点结果失败: {e}"))?;
+    if line.trim().is_empty() {
+      continue;
+    }
+    results.push(serde_json::from_str(&line).map_err(|e| format!("中继节点结果格式无效: {e}"))?);
+  }
+  if results.len() < request.count as usize {
+    return Err(format!("中继节点只返回了 {} 个结果", results.len()));
+  }
+  Ok(())
+}
+
+fn vantage(name: String, local: bool, results: Vec<ProbeResult>, error: Option<String>) -> Vantage {
+  let sent = results.len() as u32;
+  let rtts: Vec<f64> = results.iter().filter_map(|result| result.rtt_ms).collect();
+  let received = results.iter().filter(|result| result.success).count() as u32;
+  Vantage {
+    name,
+    local,
+    sent,
+    received,
+    loss_percent: (sent > 0).then(|| f64::from(sent - received) * 100.0 / f64::from(sent)),
+    avg_rtt_ms: (!rtts.is_empty()).then(|| rtts.iter().sum::<f64>() / rtts.len() as f64),
+    error,
+    results,
+  }
+}
+
+fn verdict(local: &Vantage, remote: &[Vantage]) -> Verdict {
+  let answered: Vec<&Vantage> = remote.iter().filter(|vantage| vantage.sent > 0).collect();
+  let reachable_here = local.received > 0;
+  let reachable_remotely = answered.iter().filter(|vantage| vantage.received > 0).count();
+  match (reachable_here, reachable_remotely) {
+    (true, n) if n == answered.len() => Verdict::Up,
+    (true, _) => Verdict::Partial,
+    (false, _) if answered.is_empty() => Verdict::Unknown,
+    (false, 0) => Verdict::Down,
+    (false, _) => Verdict::LocalOnly,
+  }
+}
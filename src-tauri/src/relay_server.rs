@@ -0,0 +1,174 @@
+// Relay mode: serves `POST /probe` so another copy of the app can ping targets from this
+// machine's vantage point (see `relay`). Requests need the configured bearer token; results are
+// streamed back one JSON line per probe as they finish. The listener speaks plain HTTP, so put
+// it behind a TLS-terminating reverse proxy when it is reachable over the internet.
+
+use std::io::{self, BufReader, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+use crate::local_http::{read_request, respond, Listener};
+use crate::lock::LockExt;
+use crate::relay::{self, ProbeRequest};
+use crate::{targets, PingEncoding};
+
+/// Each request occupies a thread and runs pings for up to a minute or two.
+const MAX_CONCURRENT: usize = 4;
+/// Open connections, probing or not; those past `MAX_CONCURRENT` are only told to retry later.
+const MAX_CONNECTIONS: usize = 16;
+const MIN_TOKEN_LEN: usize = 16;
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct RelayServerSettings {
+  #[serde(default)]
+  pub enabled: bool,
+  /// Address and port to listen on.
+  #[serde(default = "default_bind")]
+  pub bind: String,
+  /// Clients send it as `Authorization: Bearer <token>`.
+  #[serde(default)]
+  pub token: String,
+}
+
+impl Default for RelayServerSettings {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      bind: default_bind(),
+      token: String::new(),
+    }
+  }
+}
+
+fn default_bind() -> String {
+  "0.0.0.0:8787".to_string()
+}
+
+pub fn validate(settings: &RelayServerSettings) -> Result<(), String> {
+  if !settings.enabled {
+    return Ok(());
+  }
+  settings
+    .bind
+    .trim()
+    .parse::<SocketAddr>()
+    .map_err(|_| format!("监听地址格式应为 IP:端口: {}", settings.bind))?;
+  if settings.token.trim().chars().count() < MIN_TOKEN_LEN {
+    return Err(format!("中继访问令牌至少 {MIN_TOKEN_LEN} 个字符"));
+  }
+  Ok(())
+}
+
+#[derive(Serialize)]
+pub struct RelayServerStatus {
+  pub running: bool,
+  pub bind: Option<String>,
+  /// Probe requests currently being answered.
+  pub active: usize,
+  pub served: u64,
+  pub rejected: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+  active: AtomicUsize,
+  served: AtomicU64,
+  rejected: AtomicU64,
+}
+
+#[derive(Default)]
+pub struct RelayServerState {
+  inner: Mutex<Option<Listener>>,
+  counters: Arc<Counters>,
+}
+
+impl RelayServerState {
+  /// Stops a running listener and, if the settings enable it, starts a new one.
+  pub fn apply(&self, settings: &RelayServerSettings, encoding: PingEncoding) -> Result<(), AppError> {
+    let mut guard = self.inner.lock_or_recover();
+    // Waits for the old listener to let go of the port, which the new one may want.
+    if let Some(listener) = guard.take() {
+      listener.stop();
+    }
+    if !settings.enabled {
+      return Ok(());
+    }
+    validate(settings).map_err(AppError::invalid_input)?;
+    let token = settings.token.trim().to_string();
+    let counters = self.counters.clone();
+    let listener = Listener::start("relay", settings.bind.trim(), MAX_CONNECTIONS, move |stream, _| {
+      serve(stream, &token, encoding, &counters)
+    })?;
+    *guard = Some(listener);
+    Ok(())
+  }
+
+  pub fn status(&self) -> RelayServerStatus {
+    let guard = self.inner.lock_or_recover();
+    RelayServerStatus {
+      running: guard.is_some(),
+      bind: guard.as_ref().map(|listener| listener.bind().to_string()),
+      active: self.counters.active.load(Ordering::Relaxed),
+      served: self.counters.served.load(Ordering::Relaxed),
+      rejected: self.counters.rejected.load(Ordering::Relaxed),
+    }
+  }
+}
+
+fn serve(stream: TcpStream, token: &str, encoding: PingEncoding, counters: &Counters) -> io::Result<()> {
+  let mut writer = stream.try_clone()?;
+  let request = match read_request(&mut BufReader::new(stream)) {
+    Ok(request) => request,
+    Err(e) => return respond(&mut writer, "400 Bad Request", &e.to_string()),
+  };
+
+  if request.path != "/probe" {
+    return respond(&mut writer, "404 Not Found", "not found");
+  }
+  if request.method != "POST" {
+    return respond(&mut writer, "405 Method Not Allowed", "use POST");
+  }
+  if !request.authorized(token) {
+    counters.rejected.fetch_add(1, Ordering::Relaxed);
+    return respond(&mut writer, "401 Unauthorized", "invalid token");
+  }
+  let probe: ProbeRequest = match serde_json::from_slice(&request.body) {
+    Ok(probe) => probe,
+    Err(e) => return respond(&mut writer, "400 Bad Request", &format!("invalid body: {e}")),
+  };
+  let address = probe.address.trim();
+  if let Err(e) = targets::validate_host(address) {
+    return respond(&mut writer, "400 Bad Request", &e);
+  }
+  if !(1..=relay::MAX_COUNT).contains(&probe.count) {
+    return respond(&mut writer, "400 Bad Request", &format!("count must be 1-{}", relay::MAX_COUNT));
+  }
+  if counters.active.fetch_add(1, Ordering::SeqCst) >= MAX_CONCURRENT {
+    counters.active.fetch_sub(1, Ordering::SeqCst);
+    return respond(&mut writer, "503 Service Unavailable", "too many probe requests");
+  }
+
+  let result = stream_probes(&mut writer, address, probe.count, encoding);
+  counters.active.fetch_sub(1, Ordering::SeqCst);
+  counters.served.fetch_add(1, Ordering::Relaxed);
+  result
+}
+
+/// Writes each result as soon as it is ready; the response ends when the connection closes.
+fn stream_probes(writer: &mut TcpStream, address: &str, count: u32, encoding: PingEncoding) -> io::Result<()> {
+  writer.write_all(
+    b"HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+  )?;
+  let mut outcome = Ok(());
+  relay::probe_each(address, count, encoding, |result| {
+    let line = serde_json::to_string(&result).unwrap_or_default();
+    // A failed write means the client went away; stop probing for it.
+    outcome = writer.write_all(format!("{line}\n").as_bytes()).and_then(|_| writer.flush());
+    outcome.is_ok()
+  });
+  outcome
+}
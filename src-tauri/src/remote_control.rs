@@ -0,0 +1,252 @@
+// Remote control: an optional local HTTP endpoint so orchestration tools (Ansible, scheduled
+// tasks, kiosk provisioning scripts) can start and stop monitoring without driving the UI.
+//
+//   GET    /sessions                      what is being monitored, if anything
+//   POST   /sessions  {"address": "..."}  start monitoring a target
+//   DELETE /sessions                      stop monitoring
+//
+// Every request needs `Authorization: Bearer <token>`. It binds to loopback by default; like the
+// relay server it speaks plain HTTP, so keep it off untrusted networks.
+
+use std::io::{self, BufReader, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::error::{AppError, ErrorKind};
+use crate::local_http::{read_request, respond, Listener, Request};
+use crate::lock::LockExt;
+use crate::{events, start_session, stop_ping, targets, PingState};
+
+/// Every request is answered at once, so a few connections are plenty for a scripted client.
+const MAX_CONNECTIONS: usize = 8;
+const MIN_TOKEN_LEN: usize = 16;
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct RemoteControlSettings {
+  #[serde(default)]
+  pub enabled: bool,
+  /// Address and port to listen on.
+  #[serde(default = "default_bind")]
+  pub bind: String,
+  /// Clients send it as `Authorization: Bearer <token>`.
+  #[serde(default)]
+  pub token: String,
+}
+
+impl Default for RemoteControlSettings {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      bind: default_bind(),
+      token: String::new(),
+    }
+  }
+}
+
+fn default_bind() -> String {
+  "127.0.0.1:8788".to_string()
+}
+
+pub fn validate(settings: &RemoteControlSettings) -> Result<(), String> {
+  if !settings.enabled {
+    return Ok(());
+  }
+  settings
+    .bind
+    .trim()
+    .parse::<SocketAddr>()
+    .map_err(|_| format!("远程控制监听地址格式应为 IP:端口: {}", settings.bind))?;
+  if settings.token.trim().chars().count() < MIN_TOKEN_LEN {
+    return Err(format!("远程控制访问令牌至少 {MIN_TOKEN_LEN} 个字符"));
+  }
+  Ok(())
+}
+
+#[derive(Serialize)]
+pub struct RemoteControlStatus {
+  pub running: bool,
+  pub bind: Option<String>,
+  pub handled: u64,
+  pub rejected: u64,
+}
+
+/// Sent as `remote-command` after a remote client started or stopped monitoring, so the UI can
+/// follow along.
+#[derive(Clone, Serialize)]
+struct RemoteCommand {
+  action: &'static str,
+  address: String,
+  peer: String,
+  timestamp: String,
+}
+
+#[derive(Serialize)]
+struct SessionInfo {
+  running: bool,
+  address: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  log_dir: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct StartRequest {
+  address: String,
+}
+
+#[derive(Default)]
+struct Counters {
+  handled: AtomicU64,
+  rejected: AtomicU64,
+}
+
+#[derive(Default)]
+pub struct RemoteControlState {
+  inner: Mutex<Option<Listener>>,
+  counters: Arc<Counters>,
+}
+
+impl RemoteControlState {
+  /// Stops a running listener and, if the settings enable it, starts a new one.
+  pub fn apply(&self, app: &AppHandle, settings: &RemoteControlSettings) -> Result<(), AppError> {
+    let mut guard = self.inner.lock_or_recover();
+    // Waits for the old listener to let go of the port, which the new one may want.
+    if let Some(listener) = guard.take() {
+      listener.stop();
+    }
+    if !settings.enabled {
+      return Ok(());
+    }
+    validate(settings).map_err(AppError::invalid_input)?;
+    let token = settings.token.trim().to_string();
+    let counters = self.counters.clone();
+    let app = app.clone();
+    let listener = Listener::start("remote control", settings.bind.trim(), MAX_CONNECTIONS, move |stream, peer| {
+      serve(&app, stream, peer, &token, &counters)
+    })?;
+    *guard = Some(listener);
+    Ok(())
+  }
+
+  pub fn status(&self) -> RemoteControlStatus {
+    let guard = self.inner.lock_or_recover();
+    RemoteControlStatus {
+      running: guard.is_some(),
+      bind: guard.as_ref().map(|listener| listener.bind().to_string()),
+      handled: self.counters.handled.load(Ordering::Relaxed),
+      rejected: self.counters.rejected.load(Ordering::Relaxed),
+    }
+  }
+}
+
+fn serve(app: &AppHandle, stream: TcpStream, peer: SocketAddr, token: &str, counters: &Counters) -> io::Result<()> {
+  let mut writer = stream.try_clone()?;
+  let request = match read_request(&mut BufReader::new(stream)) {
+    Ok(request) => request,
+    Err(e) => return respond(&mut writer, "400 Bad Request", &e.to_string()),
+  };
+
+  if request.path.trim_end_matches('/') != "/sessions" {
+    return respond(&mut writer, "404 Not Found", "not found");
+  }
+  if !request.authorized(token) {
+    counters.rejected.fetch_add(1, Ordering::Relaxed);
+    return respond(&mut writer, "401 Unauthorized", "invalid token");
+  }
+  counters.handled.fetch_add(1, Ordering::Relaxed);
+
+  let state = app.state::<PingState>();
+  match request.method.as_str() {
+    "GET" => respond_json(
+      &mut writer,
+      "200 OK",
+      &SessionInfo {
+        running: state.is_monitoring(),
+        address: state.current_address().map(|address| targets::public_address(&address).into_owned()),
+        log_dir: None,
+      },
+    ),
+    "POST" => start(app, &mut writer, &request, peer),
+    "DELETE" => {
+      let address = state.current_address();
+      match stop_ping(state) {
+        Ok(()) => {
+          let address = targets::public_address(&address.unwrap_or_default()).into_owned();
+          notify(app, "stop", &address, peer);
+          respond_json(
+            &mut writer,
+            "200 OK",
+            &SessionInfo {
+              running: false,
+              address: Some(address),
+              log_dir: None,
+            },
+          )
+        }
+        Err(e) => respond_error(&mut writer, &e),
+      }
+    }
+    _ => respond(&mut writer, "405 Method Not Allowed", "use GET, POST or DELETE"),
+  }
+}
+
+fn start(app: &AppHandle, writer: &mut TcpStream, request: &Request, peer: SocketAddr) -> io::Result<()> {
+  let body: StartRequest = match serde_json::from_slice(&request.body) {
+    Ok(body) => body,
+    Err(e) => return respond(writer, "400 Bad Request", &format!("invalid body: {e}")),
+  };
+  let address = body.address.trim().to_string();
+  match start_session(app, &address) {
+    Ok(log_dir) => {
+      notify(app, "start", &address, peer);
+      respond_json(
+        writer,
+        "201 Created",
+        &SessionInfo {
+          running: true,
+          address: Some(targets::public_address(&address).into_owned()),
+          log_dir: Some(log_dir),
+        },
+      )
+    }
+    Err(e) => respond_error(writer, &e),
+  }
+}
+
+fn notify(app: &AppHandle, action: &'static str, address: &str, peer: SocketAddr) {
+  events::emit(
+    app,
+    "remote-command",
+    RemoteCommand {
+      action,
+      address: address.to_string(),
+      peer: peer.ip().to_string(),
+      timestamp: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+    },
+  );
+}
+
+fn respond_error(writer: &mut TcpStream, error: &AppError) -> io::Result<()> {
+  let status = match error.kind {
+    ErrorKind::AlreadyRunning | ErrorKind::NotRunning => "409 Conflict",
+    ErrorKind::InvalidInput | ErrorKind::NotFound => "400 Bad Request",
+    ErrorKind::IcmpBlocked => "422 Unprocessable Entity",
+    _ => "500 Internal Server Error",
+  };
+  respond(writer, status, &error.to_string())
+}
+
+fn respond_json<T: Serialize>(writer: &mut TcpStream, status: &str, body: &T) -> io::Result<()> {
+  let body = serde_json::to_string(body).unwrap_or_default();
+  writer.write_all(
+    format!(
+      "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{body}",
+      body.len()
+    )
+    .as_bytes(),
+  )
+}
@@ -0,0 +1,149 @@
+// Persistence of a session's probe results and event lines. `ping_loop` only talks to the
+// `ResultStore` trait, so another backend (a database, a remote collector) can be added by
+// implementing it and returning it from `open`. `MemoryStore` keeps everything in memory, for
+// driving a session in tests without a log folder.
+
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Local};
+
+use crate::day_index::DayIndexWriter;
+use crate::lock::LockExt;
+use crate::logfile;
+use crate::rollup::RollupWriter;
+use crate::{append_line, minute_log_path};
+
+pub struct ProbeRecord<'a> {
+  pub at: &'a DateTime<Local>,
+  /// The formatted log line, `[timestamp] target | result`.
+  pub line: &'a str,
+  pub success: bool,
+  pub rtt_ms: Option<f64>,
+  /// A synthetic failure from an outage drill: logged, but kept out of aggregates.
+  pub synthetic: bool,
+}
+
+/// Shared between the probe loop and the helper threads it starts (e.g. speed tests).
+pub trait ResultStore: Send + Sync {
+  /// Called before every probe; an error ends the session.
+  fn ready(&self, at: &DateTime<Local>) -> io::Result<()>;
+  fn record_probe(&self, probe: &ProbeRecord) -> io::Result<()>;
+  /// Alert, route, schedule and other non-probe lines, already formatted.
+  fn record_event(&self, at: &DateTime<Local>, line: &str) -> io::Result<()>;
+  /// A confirmed outage began; drills are not reported.
+  fn record_outage(&self, _at: &DateTime<Local>) {}
+  /// Writes out anything buffered; called when the session ends.
+  fn flush(&self) {}
+}
+
+/// The store a session writes to.
+pub fn open(base_dir: PathBuf, address: &str) -> Arc<dyn ResultStore> {
+  Arc::new(TextFileStore::new(base_dir, address))
+}
+
+/// Per-minute text logs plus hourly rollups and day manifests under the log directory.
+pub struct TextFileStore {
+  base_dir: PathBuf,
+  rollups: Mutex<RollupWriter>,
+  index: Mutex<DayIndexWriter>,
+}
+
+impl TextFileStore {
+  pub fn new(base_dir: PathBuf, address: &str) -> Self {
+    Self {
+      base_dir,
+      rollups: Mutex::new(RollupWriter::new(address)),
+      index: Mutex::new(DayIndexWriter::default()),
+    }
+  }
+
+  fn append(&self, at: &DateTime<Local>, line: &str) -> io::Result<()> {
+    append_line(&minute_log_path(&self.base_dir, at)?, &format!("{line}\n"))
+  }
+}
+
+impl ResultStore for TextFileStore {
+  fn ready(&self, at: &DateTime<Local>) -> io::Result<()> {
+    minute_log_path(&self.base_dir, at).map(|_| ())
+  }
+
+  fn record_probe(&self, probe: &ProbeRecord) -> io::Result<()> {
+    if !probe.synthetic {
+      self
+        .rollups
+        .lock_or_recover()
+        .record(&self.base_dir, probe.at, probe.success, probe.rtt_ms);
+      if let Some(sample) = logfile::parse_sample(probe.line) {
+        self
+          .index
+          .lock_or_recover()
+          .record_probe(&self.base_dir, probe.at, &sample.target, probe.success);
+      }
+    }
+    self.append(probe.at, probe.line)
+  }
+
+  fn record_event(&self, at: &DateTime<Local>, line: &str) -> io::Result<()> {
+    self.append(at, line)
+  }
+
+  fn record_outage(&self, at: &DateTime<Local>) {
+    self.index.lock_or_recover().record_outage(&self.base_dir, at);
+  }
+
+  fn flush(&self) {
+    self.rollups.lock_or_recover().flush(&self.base_dir);
+    self.index.lock_or_recover().flush(&self.base_dir);
+  }
+}
+
+/// Keeps the lines and probe outcomes in memory; nothing is written anywhere.
+#[allow(dead_code)]
+#[derive(Default)]
+pub struct MemoryStore {
+  lines: Mutex<Vec<String>>,
+  probes: Mutex<Vec<(bool, Option<f64>)>>,
+  outages: Mutex<Vec<DateTime<Local>>>,
+}
+
+#[allow(dead_code)]
+impl MemoryStore {
+  /// Every probe and event line, in the order recorded.
+  pub fn lines(&self) -> Vec<String> {
+    self.lines.lock_or_recover().clone()
+  }
+
+  /// Success and RTT of the probes that aren't synthetic.
+  pub fn probes(&self) -> Vec<(bool, Option<f64>)> {
+    self.probes.lock_or_recover().clone()
+  }
+
+  pub fn outages(&self) -> Vec<DateTime<Local>> {
+    self.outages.lock_or_recover().clone()
+  }
+}
+
+impl ResultStore for MemoryStore {
+  fn ready(&self, _at: &DateTime<Local>) -> io::Result<()> {
+    Ok(())
+  }
+
+  fn record_probe(&self, probe: &ProbeRecord) -> io::Result<()> {
+    if !probe.synthetic {
+      self.probes.lock_or_recover().push((probe.success, probe.rtt_ms));
+    }
+    self.lines.lock_or_recover().push(probe.line.to_string());
+    Ok(())
+  }
+
+  fn record_event(&self, _at: &DateTime<Local>, line: &str) -> io::Result<()> {
+    self.lines.lock_or_recover().push(line.to_string());
+    Ok(())
+  }
+
+  fn record_outage(&self, at: &DateTime<Local>) {
+    self.outages.lock_or_recover().push(*at);
+  }
+}
@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local, NaiveDate, TimeDelta, TimeZone, Timelike};
+use serde::{Deserialize, Serialize};
+
+use crate::logfile::{self, Sample};
+use crate::{targets, RESUME_GAP_SECS};
+
+pub const ROLLUP_FILE: &str = "rollups.jsonl";
+const SECS_PER_HOUR: usize = 3600;
+
+/// One hour of probes for one target. Stored one JSON object per line in
+/// `<log dir>/<YYYY-MM-DD>/rollups.jsonl`; a session stopped mid-hour writes a partial entry.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct HourRollup {
+  /// `YYYY-MM-DD HH:00`, local time.
+  pub hour: String,
+  pub address: String,
+  pub samples: u64,
+  pub lost: u64,
+  pub loss_percent: f64,
+  pub min_rtt_ms: Option<f64>,
+  pub avg_rtt_ms: Option<f64>,
+  pub max_rtt_ms: Option<f64>,
+  pub p95_rtt_ms: Option<f64>,
+  /// Seconds of each minute during which the target was being probed, so a stopped session,
+  /// a skipped round or a sleeping machine shows up as a gap rather than as uptime. `None` in
+  /// rollups written before this was tracked.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub measured_secs: Option<Vec<u8>>,
+  /// Share of the hour covered by probing.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub completeness_percent: Option<f64>,
+}
+
+impl HourRollup {
+  /// Seconds of the hour during which the target was being probed.
+  pub fn measured_total_secs(&self) -> Option<u64> {
+    self
+      .measured_secs
+      .as_ref()
+      .map(|minutes| minutes.iter().map(|secs| u64::from(*secs)).sum())
+  }
+}
+
+pub struct RollupWriter {
+  address: String,
+  hour: Option<DateTime<Local>>,
+  samples: u64,
+  lost: u64,
+  rtts: Vec<f64>,
+  /// Per second of the hour, whether a probe was running.
+  measured: Vec<bool>,
+  last_probe: Option<DateTime<Local>>,
+}
+
+impl RollupWriter {
+  pub fn new(address: &str) -> Self {
+    Self {
+      address: address.to_string(),
+      hour: None,
+      samples: 0,
+      lost: 0,
+      rtts: Vec::new(),
+      measured: vec![false; SECS_PER_HOUR],
+      last_probe: None,
+    }
+  }
+
+  /// Adds a probe result, writing out the previous hour first when the hour has changed.
+  pub fn record(&mut self, base_dir: &Path, now: &DateTime<Local>, success: bool, rtt_ms: Option<f64>) {
+    if let Some(finished) = self.add(now, success, rtt_ms) {
+      append(base_dir, &finished);
+    }
+  }
+
+  /// Adds a probe result and returns the previous hour when this one starts a new hour. The
+  /// time since the previous probe counts as measured unless it is long enough to mean the
+  /// machine was asleep.
+  fn add(&mut self, now: &DateTime<Local>, success: bool, rtt_ms: Option<f64>) -> Option<HourRollup> {
+    let since = self
+      .last_probe
+      .replace(*now)
+      .filter(|last| (*now - *last).num_milliseconds() as f64 / 1000.0 <= RESUME_GAP_SECS);
+    let key = now.format("%Y-%m-%d %H").to_string();
+    let mut finished = None;
+    if self.hour.is_some_and(|hour| hour.format("%Y-%m-%d %H").to_string() != key) {
+      if let Some(since) = since {
+        self.mark_measured(&since, now);
+      }
+      finished = self.take();
+    }
+    self.hour.get_or_insert(*now);
+    self.mark_measured(&since.unwrap_or(*now), &(*now + TimeDelta::seconds(1)));
+    self.samples += 1;
+    match (success, rtt_ms) {
+      (false, _) => self.lost += 1,
+      (true, Some(rtt)) => self.rtts.push(rtt),
+      (true, None) => {}
+    }
+    finished
+  }
+
+  /// Marks the seconds in `[from, to)` that fall into the current hour.
+  fn mark_measured(&mut self, from: &DateTime<Local>, to: &DateTime<Local>) {
+    let Some(hour) = self.hour else {
+      return;
+    };
+    let start = hour.timestamp() - i64::from(hour.minute() * 60 + hour.second());
+    let end = start + SECS_PER_HOUR as i64;
+    for second in from.timestamp().max(start)..to.timestamp().min(end) {
+      self.measured[(second - start) as usize] = true;
+    }
+  }
+
+  pub fn flush(&mut self, base_dir: &Path) {
+    if let Some(finished) = self.take() {
+      append(base_dir, &finished);
+    }
+  }
+
+  /// The current hour so far, leaving the writer empty.
+  fn take(&mut self) -> Option<HourRollup> {
+    let hour = self.hour.take()?;
+    let mut rtts = std::mem::take(&mut self.rtts);
+    rtts.sort_by(f64::total_cmp);
+    let measured: Vec<u8> = self
+      .measured
+      .chunks(60)
+      .map(|minute| minute.iter().filter(|second| **second).count() as u8)
+      .collect();
+    let measured_total: u32 = measured.iter().map(|secs| u32::from(*secs)).sum();
+    self.measured.fill(false);
+    let rollup = HourRollup {
+      hour: hour.format("%Y-%m-%d %H:00").to_string(),
+      address: self.address.clone(),
+      samples: self.samples,
+      lost: self.lost,
+      loss_percent: if self.samples == 0 { 0.0 } else { self.lost as f64 * 100.0 / self.samples as f64 },
+      min_rtt_ms: rtts.first().copied(),
+      avg_rtt_ms: (!rtts.is_empty()).then(|| rtts.iter().sum::<f64>() / rtts.len() as f64),
+      max_rtt_ms: rtts.last().copied(),
+      p95_rtt_ms: percentile(&rtts, 0.95),
+      completeness_percent: Some(f64::from(measured_total) * 100.0 / SECS_PER_HOUR as f64),
+      measured_secs: Some(measured),
+    };
+    self.samples = 0;
+    self.lost = 0;
+    Some(rollup)
+  }
+}
+
+fn day_file(base_dir: &Path, hour: &str) -> PathBuf {
+  // `hour` starts with the date.
+  base_dir.join(&hour[..hour.len().min(10)]).join(ROLLUP_FILE)
+}
+
+fn append(base_dir: &Path, rollup: &HourRollup) {
+  let path = day_file(base_dir, &rollup.hour);
+  let result = path
+    .parent()
+    .map_or(Ok(()), fs::create_dir_all)
+    .and_then(|_| {
+      let line = serde_json::to_string(rollup).map_err(io::Error::other)?;
+      let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+      writeln!(file, "{line}")
+    });
+  if let Err(e) = result {
+    eprintln!("failed to write hourly rollup: {e}");
+  }
+}
+
+/// Rollups of every target and hour in `samples`, computed as a session would have written them.
+pub fn from_samples(samples: &[Sample]) -> Vec<HourRollup> {
+  let mut writers: HashMap<&str, RollupWriter> = HashMap::new();
+  let mut rollups = Vec::new();
+  for sample in samples {
+    let Some(at) = Local.from_local_datetime(&sample.timestamp).earliest() else {
+      continue;
+    };
+    let address = logfile::address_of(&sample.target);
+    let writer = writers.entry(address).or_insert_with(|| RollupWriter::new(address));
+    rollups.extend(writer.add(&at, sample.success, sample.rtt_ms));
+  }
+  rollups.extend(writers.values_mut().filter_map(RollupWriter::take));
+  rollups.sort_by(|a, b| (&a.hour, &a.address).cmp(&(&b.hour, &b.address)));
+  rollups
+}
+
+/// Replaces the rollups of `day` with `rollups`. The file is written aside and renamed over, so
+/// readers never see it half written.
+pub fn replace_day(base_dir: &Path, day: NaiveDate, rollups: &[HourRollup]) -> io::Result<()> {
+  let path = base_dir.join(day.format("%Y-%m-%d").to_string()).join(ROLLUP_FILE);
+  let mut data = String::new();
+  for rollup in rollups {
+    data.push_str(&serde_json::to_string(rollup).map_err(io::Error::other)?);
+    data.push('\n');
+  }
+  let temp = path.with_extension("jsonl.tmp");
+  fs::write(&temp, data)?;
+  fs::rename(&temp, &path)
+}
+
+/// Nearest-rank percentile of already sorted values.
+pub fn percentile(sorted: &[f64], p: f64) -> Option<f64> {
+  if sorted.is_empty() {
+    return None;
+  }
+  let rank = (p * sorted.len() as f64).ceil() as usize;
+  Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+/// Reads the rollups of every day in `[from, to]`, optionally for one address only.
+pub fn load(base_dir: &Path, from: NaiveDate, to: NaiveDate, address: Option<&str>) -> Vec<HourRollup> {
+  let address = address.map(targets::public_address);
+  let mut rollups = Vec::new();
+  for day in from.iter_days().take_while(|day| *day <= to) {
+    let path = base_dir.join(day.format("%Y-%m-%d").to_string()).join(ROLLUP_FILE);
+    let Ok(contents) = fs::read_to_string(path) else {
+      continue;
+    };
+    rollups.extend(
+      contents
+        .lines()
+        .filter_map(|line| serde_json::from_str::<HourRollup>(line).ok())
+        .filter(|rollup| address.as_deref().is_none_or(|address| rollup.address == address)),
+    );
+  }
+  rollups
+}
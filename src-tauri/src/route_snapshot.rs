@@ -0,0 +1,336 @@
+// What the local routing looked like when an outage began: the IPv4 routing table, the default
+// gateways and which interfaces were up. Written into the log as a block of `ROUTES` lines, so
+// a post-mortem can tell a dead uplink from a VPN that grabbed the default route or a Wi-Fi
+// adapter that had dropped.
+
+use std::net::Ipv4Addr;
+
+use serde::Serialize;
+
+/// Routing tables on VPN-heavy machines get long; the rest is summarized as a count.
+const MAX_ROUTE_LINES: usize = 40;
+
+#[derive(Clone, Serialize)]
+pub struct Route {
+  /// `address/prefix`.
+  pub destination: String,
+  /// `None` for on-link routes.
+  pub gateway: Option<String>,
+  /// Interface name, or its address where the OS reports routes that way (Windows).
+  pub interface: String,
+  pub metric: Option<u32>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct InterfaceState {
+  pub name: String,
+  pub up: bool,
+  pub addresses: Vec<String>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct RouteSnapshot {
+  pub routes: Vec<Route>,
+  pub interfaces: Vec<InterfaceState>,
+}
+
+impl RouteSnapshot {
+  pub fn default_routes(&self) -> impl Iterator<Item = &Route> {
+    self.routes.iter().filter(|route| route.destination == "0.0.0.0/0")
+  }
+
+  /// The block written to the log, one line each, without timestamp and target.
+  pub fn log_lines(&self) -> Vec<String> {
+    let gateways: Vec<String> = self
+      .default_routes()
+      .map(|route| match &route.gateway {
+        Some(gateway) => format!("{gateway} ({})", route.interface),
+        None => format!("直连 ({})", route.interface),
+      })
+      .collect();
+    let mut lines = vec![if gateways.is_empty() {
+      "没有默认路由".to_string()
+    } else {
+      format!("默认网关: {}", gateways.join(", "))
+    }];
+    for route in self.routes.iter().take(MAX_ROUTE_LINES) {
+      let mut line = format!("route {}", route.destination);
+      if let Some(gateway) = &route.gateway {
+        line.push_str(&format!(" via {gateway}"));
+      }
+      line.push_str(&format!(" dev {}", route.interface));
+      if let Some(metric) = route.metric {
+        line.push_str(&format!(" metric {metric}"));
+      }
+      lines.push(line);
+    }
+    if self.routes.len() > MAX_ROUTE_LINES {
+      lines.push(format!("另有 {} 条路由未列出", self.routes.len() - MAX_ROUTE_LINES));
+    }
+    for interface in &self.interfaces {
+      let state = if interface.up { "up" } else { "down" };
+      lines.push(format!("if {} {state} {}", interface.name, interface.addresses.join(" ")).trim_end().to_string());
+    }
+    lines
+  }
+}
+
+/// The gateway of the preferred IPv4 default route, if there is one.
+pub fn default_gateway() -> Option<Ipv4Addr> {
+  platform::routes()
+    .into_iter()
+    .filter(|route| route.destination == "0.0.0.0/0")
+    .filter_map(|route| Some((route.metric.unwrap_or(0), route.gateway?.parse::<Ipv4Addr>().ok()?)))
+    .min_by_key(|(metric, _)| *metric)
+    .map(|(_, gateway)| gateway)
+}
+
+pub fn capture() -> RouteSnapshot {
+  RouteSnapshot {
+    routes: platform::routes(),
+    interfaces: platform::interfaces(),
+  }
+}
+
+fn prefix_len(mask: u32) -> u32 {
+  mask.leading_ones()
+}
+
+/// Name, flags and addresses of every interface, from `getifaddrs`.
+#[cfg(unix)]
+fn unix_interfaces() -> Vec<InterfaceState> {
+  use std::net::{Ipv4Addr, Ipv6Addr};
+
+  let mut addrs: *mut libc::ifaddrs = std::ptr::null_mut();
+  if unsafe { libc::getifaddrs(&mut addrs) } != 0 {
+    return Vec::new();
+  }
+  let mut interfaces: Vec<InterfaceState> = Vec::new();
+  let mut cursor = addrs;
+  while let Some(entry) = unsafe { cursor.as_ref() } {
+    cursor = entry.ifa_next;
+    if entry.ifa_flags & libc::IFF_LOOPBACK as u32 != 0 {
+      continue;
+    }
+    let name = unsafe { std::ffi::CStr::from_ptr(entry.ifa_name) }.to_string_lossy().to_string();
+    let up = entry.ifa_flags & libc::IFF_UP as u32 != 0 && entry.ifa_flags & libc::IFF_RUNNING as u32 != 0;
+    let index = match interfaces.iter().position(|interface| interface.name == name) {
+      Some(index) => index,
+      None => {
+        interfaces.push(InterfaceState {
+          name,
+          up,
+          addresses: Vec::new(),
+        });
+        interfaces.len() - 1
+      }
+    };
+    let (addr, mask) = unsafe { (entry.ifa_addr.as_ref(), entry.ifa_netmask.as_ref()) };
+    let Some(addr) = addr else {
+      continue;
+    };
+    let address = match i32::from(addr.sa_family) {
+      libc::AF_INET => {
+        let v4 = |sockaddr: &libc::sockaddr| {
+          let sockaddr = unsafe { &*(sockaddr as *const libc::sockaddr as *const libc::sockaddr_in) };
+          u32::from_be(sockaddr.sin_addr.s_addr)
+        };
+        let prefix = mask.map_or(32, |mask| prefix_len(v4(mask)));
+        format!("{}/{prefix}", Ipv4Addr::from(v4(addr)))
+      }
+      libc::AF_INET6 => {
+        let sockaddr = unsafe { &*(addr as *const libc::sockaddr as *const libc::sockaddr_in6) };
+        Ipv6Addr::from(sockaddr.sin6_addr.s6_addr).to_string()
+      }
+      _ => continue,
+    };
+    interfaces[index].addresses.push(address);
+  }
+  unsafe { libc::freeifaddrs(addrs) };
+  interfaces
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+  use std::fs;
+  use std::net::Ipv4Addr;
+
+  use super::{InterfaceState, Route};
+
+  /// `RTF_GATEWAY`: the route goes through the gateway column.
+  const RTF_GATEWAY: u32 = 0x0002;
+
+  /// `/proc/net/route`: `Iface Destination Gateway Flags RefCnt Use Metric Mask ...`, addresses
+  /// as the hex of their in-memory (network order) value. Same table as `ip -4 route`.
+  pub fn routes() -> Vec<Route> {
+    let Ok(table) = fs::read_to_string("/proc/net/route") else {
+      return Vec::new();
+    };
+    let address = |hex: &str| u32::from_str_radix(hex, 16).ok().map(|value| Ipv4Addr::from(value.to_ne_bytes()));
+    table
+      .lines()
+      .skip(1)
+      .filter_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let (destination, gateway, mask) = (address(fields.get(1)?)?, address(fields.get(2)?)?, address(fields.get(7)?)?);
+        let flags = u32::from_str_radix(fields.get(3)?, 16).ok()?;
+        Some(Route {
+          destination: format!("{destination}/{}", super::prefix_len(u32::from(mask))),
+          gateway: (flags & RTF_GATEWAY != 0).then(|| gateway.to_string()),
+          interface: fields[0].to_string(),
+          metric: fields.get(6).and_then(|metric| metric.parse().ok()),
+        })
+      })
+      .collect()
+  }
+
+  pub fn interfaces() -> Vec<InterfaceState> {
+    super::unix_interfaces()
+  }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+  use std::process::Command;
+
+  use super::{InterfaceState, Route};
+
+  /// `netstat -rn -f inet`: `Destination Gateway Flags Netif Expire`. Destinations abbreviate
+  /// networks (`192.168.1`, `10/8`) and `default` is the default route; gateways are an address,
+  /// `link#N` for on-link routes or a hardware address for hosts on the local network.
+  pub fn routes() -> Vec<Route> {
+    let Ok(output) = Command::new("netstat").args(["-rn", "-f", "inet"]).output() else {
+      return Vec::new();
+    };
+    String::from_utf8_lossy(&output.stdout)
+      .lines()
+      .filter_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let (destination, gateway, flags, interface) = (fields.first()?, fields.get(1)?, fields.get(2)?, fields.get(3)?);
+        let destination = if *destination == "default" {
+          "0.0.0.0/0".to_string()
+        } else {
+          expand(destination)?
+        };
+        Some(Route {
+          destination,
+          gateway: (flags.contains('G') && gateway.parse::<std::net::Ipv4Addr>().is_ok()).then(|| gateway.to_string()),
+          interface: interface.to_string(),
+          metric: None,
+        })
+      })
+      .collect()
+  }
+
+  /// `10/8` to `10.0.0.0/8`, `192.168.1` to `192.168.1.0/24`, `192.168.1.7` to `.../32`.
+  fn expand(destination: &str) -> Option<String> {
+    let (network, prefix) = match destination.split_once('/') {
+      Some((network, prefix)) => (network, Some(prefix.parse::<u32>().ok()?)),
+      None => (destination, None),
+    };
+    let mut octets: Vec<u8> = network.split('.').map(|octet| octet.parse().ok()).collect::<Option<_>>()?;
+    if octets.is_empty() || octets.len() > 4 {
+      return None;
+    }
+    let prefix = prefix.unwrap_or(octets.len() as u32 * 8);
+    octets.resize(4, 0);
+    Some(format!("{}.{}.{}.{}/{prefix}", octets[0], octets[1], octets[2], octets[3]))
+  }
+
+  pub fn interfaces() -> Vec<InterfaceState> {
+    super::unix_interfaces()
+  }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+  use std::net::Ipv4Addr;
+  use std::ptr;
+
+  use windows_sys::Win32::NetworkManagement::IpHelper::{
+    FreeMibTable, GetIfTable2, IF_TYPE_SOFTWARE_LOOPBACK, MIB_IF_TABLE2,
+  };
+  use windows_sys::Win32::NetworkManagement::Ndis::IfOperStatusUp;
+
+  use super::{InterfaceState, Route};
+  use crate::{decode_ping_output, probe_command, PingEncoding};
+
+  /// Set in `InterfaceAndOperStatusFlags` for NDIS filter rows, which repeat their adapter.
+  const FILTER_INTERFACE: u8 = 0x02;
+  /// Set for adapters backed by hardware; WAN miniports, Teredo and similar pseudo-adapters
+  /// only clutter the list.
+  const HARDWARE_INTERFACE: u8 = 0x01;
+
+  /// `route print -4` rows of the active routes: `Destination Netmask Gateway Interface Metric`.
+  /// The headings and the on-link gateway (`On-link`, `在链路上`) are localized, so rows are
+  /// recognized by their shape and a gateway that isn't an address means on-link.
+  pub fn routes() -> Vec<Route> {
+    let args = ["print", "-4"].map(String::from);
+    let Ok(output) = probe_command("route", &args).output() else {
+      return Vec::new();
+    };
+    let text = decode_ping_output(&output.stdout, PingEncoding::Auto);
+    let mut routes = Vec::new();
+    for line in text.lines() {
+      // The persistent routes section repeats the layout without an interface.
+      if line.trim_start().starts_with('=') && !routes.is_empty() {
+        break;
+      }
+      let fields: Vec<&str> = line.split_whitespace().collect();
+      let [destination, mask, gateway, interface, metric] = fields[..] else {
+        continue;
+      };
+      let (Ok(destination), Ok(mask), Ok(metric)) =
+        (destination.parse::<Ipv4Addr>(), mask.parse::<Ipv4Addr>(), metric.parse::<u32>())
+      else {
+        continue;
+      };
+      routes.push(Route {
+        destination: format!("{destination}/{}", super::prefix_len(u32::from(mask))),
+        gateway: gateway.parse::<Ipv4Addr>().ok().map(|gateway| gateway.to_string()),
+        interface: interface.to_string(),
+        metric: Some(metric),
+      });
+    }
+    routes
+  }
+
+  pub fn interfaces() -> Vec<InterfaceState> {
+    let mut table: *mut MIB_IF_TABLE2 = ptr::null_mut();
+    if unsafe { GetIfTable2(&mut table) } != 0 || table.is_null() {
+      return Vec::new();
+    }
+    let rows = unsafe { std::slice::from_raw_parts((*table).Table.as_ptr(), (*table).NumEntries as usize) };
+    let interfaces = rows
+      .iter()
+      .filter(|row| {
+        row.Type != IF_TYPE_SOFTWARE_LOOPBACK
+          && row.InterfaceAndOperStatusFlags._bitfield & FILTER_INTERFACE == 0
+          && row.InterfaceAndOperStatusFlags._bitfield & HARDWARE_INTERFACE != 0
+      })
+      .map(|row| {
+        let len = row.Alias.iter().position(|&c| c == 0).unwrap_or(row.Alias.len());
+        InterfaceState {
+          name: String::from_utf16_lossy(&row.Alias[..len]),
+          up: row.OperStatus == IfOperStatusUp,
+          addresses: Vec::new(),
+        }
+      })
+      .collect();
+    unsafe { FreeMibTable(table.cast()) };
+    interfaces
+  }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
+mod platform {
+  use super::{InterfaceState, Route};
+
+  pub fn routes() -> Vec<Route> {
+    Vec::new()
+  }
+
+  pub fn interfaces() -> Vec<InterfaceState> {
+    super::unix_interfaces()
+  }
+}
@@ -0,0 +1,64 @@
+use chrono::{DateTime, Datelike, Local, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+/// A recurring window during which a target is monitored, e.g. weekdays 08:00-20:00.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct ScheduleWindow {
+  /// ISO weekdays the window starts on, 1 = Monday ... 7 = Sunday. Empty means every day.
+  #[serde(default)]
+  pub days: Vec<u8>,
+  /// `HH:MM`, local time.
+  pub start: String,
+  /// `HH:MM`, local time. An end before the start makes the window run past midnight.
+  pub end: String,
+}
+
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct MonitorSchedule {
+  #[serde(default)]
+  pub windows: Vec<ScheduleWindow>,
+}
+
+impl MonitorSchedule {
+  /// A schedule without windows imposes no restriction.
+  pub fn is_active(&self, now: &DateTime<Local>) -> bool {
+    self.windows.is_empty() || self.windows.iter().any(|window| window.contains(now))
+  }
+}
+
+impl ScheduleWindow {
+  fn contains(&self, now: &DateTime<Local>) -> bool {
+    let (Some(start), Some(end)) = (parse_time(&self.start), parse_time(&self.end)) else {
+      return false;
+    };
+    let time = now.time();
+    let today = now.weekday().number_from_monday() as u8;
+    let yesterday = now.weekday().pred().number_from_monday() as u8;
+    let runs_on = |day: u8| self.days.is_empty() || self.days.contains(&day);
+
+    if start <= end {
+      runs_on(today) && time >= start && time < end
+    } else {
+      (runs_on(today) && time >= start) || (runs_on(yesterday) && time < end)
+    }
+  }
+}
+
+pub fn validate(schedule: &MonitorSchedule) -> Result<(), String> {
+  for window in &schedule.windows {
+    if parse_time(&window.start).is_none() || parse_time(&window.end).is_none() {
+      return Err(format!("时间格式应为 HH:MM: {}-{}", window.start, window.end));
+    }
+    if window.start == window.end {
+      return Err("监控时段的开始和结束时间不能相同".to_string());
+    }
+    if window.days.iter().any(|day| !(1..=7).contains(day)) {
+      return Err("星期取值应为 1-7（周一至周日）".to_string());
+    }
+  }
+  Ok(())
+}
+
+fn parse_time(value: &str) -> Option<NaiveTime> {
+  NaiveTime::parse_from_str(value.trim(), "%H:%M").ok()
+}
@@ -0,0 +1,102 @@
+// Probes loopback, this machine's own interface address and its default gateway next to the
+// real target, so an outage alert can say when the fault is the local network stack or the
+// local network rather than the remote host. The gateway counts as reachable when it answers
+// either ping or ARP, since plenty of home routers drop ICMP from the LAN. Results only feed
+// alert messages and are never written to the log.
+
+use std::net::{IpAddr, Ipv4Addr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::lock::LockExt;
+use crate::{arp, ping_once, route_snapshot, PingEncoding};
+
+const INTERVAL: Duration = Duration::from_secs(5);
+const LOOPBACK: &str = "127.0.0.1";
+/// Connecting a UDP socket sends nothing; it only picks the interface of the default route.
+const ROUTE_PROBE: &str = "192.0.2.1:9";
+
+#[derive(Clone, Copy, Default)]
+struct LocalStatus {
+  loopback_ok: Option<bool>,
+  /// `None` inside `Some` means there was no default route when last checked.
+  interface: Option<Option<IpAddr>>,
+  interface_ok: Option<bool>,
+  gateway: Option<Ipv4Addr>,
+  gateway_ok: Option<bool>,
+}
+
+/// Runs until dropped.
+pub struct SelfCheck {
+  status: Arc<Mutex<LocalStatus>>,
+  running: Arc<AtomicBool>,
+}
+
+impl SelfCheck {
+  pub fn start(encoding: PingEncoding) -> Self {
+    let status = Arc::new(Mutex::new(LocalStatus::default()));
+    let running = Arc::new(AtomicBool::new(true));
+    let (worker_status, worker_running) = (status.clone(), running.clone());
+    thread::spawn(move || {
+      while worker_running.load(Ordering::Relaxed) {
+        let checked = Instant::now();
+        let current = probe(encoding);
+        *worker_status.lock_or_recover() = current;
+        while worker_running.load(Ordering::Relaxed) && checked.elapsed() < INTERVAL {
+          thread::sleep(Duration::from_millis(200));
+        }
+      }
+    });
+    Self { status, running }
+  }
+
+  /// What is wrong locally, if the last round found anything.
+  pub fn problem(&self) -> Option<String> {
+    let status = *self.status.lock_or_recover();
+    if status.loopback_ok == Some(false) {
+      return Some(format!("本机回环地址 {LOOPBACK} 也无响应，本机网络协议栈可能异常"));
+    }
+    match status.interface {
+      Some(None) => Some("本机没有默认路由，网卡可能已断开".to_string()),
+      Some(Some(ip)) if status.interface_ok == Some(false) => Some(format!("本机网卡地址 {ip} 无响应")),
+      _ => match status.gateway {
+        Some(gateway) if status.gateway_ok == Some(false) => {
+          Some(format!("本机默认网关 {gateway} 也不可达（ping 和 ARP 均无应答）"))
+        }
+        _ => None,
+      },
+    }
+  }
+}
+
+impl Drop for SelfCheck {
+  fn drop(&mut self) {
+    self.running.store(false, Ordering::Relaxed);
+  }
+}
+
+fn probe(encoding: PingEncoding) -> LocalStatus {
+  let loopback_ok = ping_once(LOOPBACK, encoding, None).is_ok();
+  let interface = local_interface();
+  let interface_ok = interface.map(|ip| ping_once(&ip.to_string(), encoding, None).is_ok());
+  let gateway = route_snapshot::default_gateway();
+  let gateway_ok = gateway.map(|ip| {
+    let ip = ip.to_string();
+    ping_once(&ip, encoding, None).is_ok() || arp::probe(&ip).is_ok()
+  });
+  LocalStatus {
+    loopback_ok: Some(loopback_ok),
+    interface: Some(interface),
+    interface_ok,
+    gateway,
+    gateway_ok,
+  }
+}
+
+fn local_interface() -> Option<IpAddr> {
+  let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+  socket.connect(ROUTE_PROBE).ok()?;
+  Some(socket.local_addr().ok()?.ip()).filter(|ip| !ip.is_unspecified())
+}
@@ -0,0 +1,192 @@
+// The app's own footprint and how closely the probe loop keeps its one-second schedule, for
+// people who leave it running for weeks and want to know it stays lightweight.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::lock::LockExt;
+
+/// The probe loop's intended interval.
+pub const PROBE_INTERVAL: Duration = Duration::from_secs(1);
+/// A wakeup this late means the machine or the app is struggling to keep the schedule.
+pub const OVERSLEEP_WARNING: Duration = Duration::from_millis(250);
+/// Drift warnings are written to the log at most this often.
+pub const DRIFT_WARNING_INTERVAL: Duration = Duration::from_secs(600);
+/// Weight of the newest sample in the running averages.
+const EWMA_ALPHA: f64 = 0.05;
+
+#[derive(Clone, Copy, Default, Serialize)]
+pub struct LoopTiming {
+  pub ticks: u64,
+  pub last_interval_ms: Option<f64>,
+  /// Running average of the time between ticks; failing probes that wait out their timeout
+  /// stretch it on purpose.
+  pub avg_interval_ms: Option<f64>,
+  /// How much later than requested the loop woke from its sleep.
+  pub avg_oversleep_ms: Option<f64>,
+  pub max_oversleep_ms: f64,
+  pub late_wakeups: u64,
+  /// Cycles whose ping was killed for running past the probe time budget.
+  pub over_budget_cycles: u64,
+}
+
+#[derive(Serialize)]
+pub struct SelfMetrics {
+  pub uptime_secs: u64,
+  /// Average over the time since the previous call, or since startup on the first one.
+  pub cpu_percent: Option<f64>,
+  pub cpu_time_secs: Option<f64>,
+  pub memory_bytes: Option<u64>,
+  pub peak_memory_bytes: Option<u64>,
+  pub intended_interval_ms: u64,
+  pub timing: LoopTiming,
+}
+
+pub struct SelfMetricsState {
+  started: Instant,
+  timing: Mutex<LoopTiming>,
+  last_cpu: Mutex<Option<(Instant, Duration)>>,
+}
+
+impl Default for SelfMetricsState {
+  fn default() -> Self {
+    Self {
+      started: Instant::now(),
+      timing: Mutex::new(LoopTiming::default()),
+      last_cpu: Mutex::new(None),
+    }
+  }
+}
+
+impl SelfMetricsState {
+  pub fn record_tick(&self, interval: Duration) {
+    let ms = interval.as_secs_f64() * 1000.0;
+    let mut timing = self.timing.lock_or_recover();
+    timing.ticks += 1;
+    timing.last_interval_ms = Some(ms);
+    timing.avg_interval_ms = Some(ewma(timing.avg_interval_ms, ms));
+  }
+
+  pub fn record_over_budget(&self) {
+    self.timing.lock_or_recover().over_budget_cycles += 1;
+  }
+
+  /// Records a sleep of `requested` that took `actual`; returns the oversleep when it is late
+  /// enough to warn about.
+  pub fn record_wakeup(&self, requested: Duration, actual: Duration) -> Option<Duration> {
+    let oversleep = actual.saturating_sub(requested);
+    let ms = oversleep.as_secs_f64() * 1000.0;
+    let mut timing = self.timing.lock_or_recover();
+    timing.avg_oversleep_ms = Some(ewma(timing.avg_oversleep_ms, ms));
+    timing.max_oversleep_ms = timing.max_oversleep_ms.max(ms);
+    if oversleep < OVERSLEEP_WARNING {
+      return None;
+    }
+    timing.late_wakeups += 1;
+    Some(oversleep)
+  }
+
+  pub fn snapshot(&self) -> SelfMetrics {
+    let now = Instant::now();
+    let cpu_time = process::cpu_time();
+    let cpu_percent = cpu_time.map(|cpu| {
+      let mut last = self.last_cpu.lock_or_recover();
+      let (since, cpu_before) = last.unwrap_or((self.started, Duration::ZERO));
+      *last = Some((now, cpu));
+      let wall = now.duration_since(since).as_secs_f64();
+      if wall > 0.0 {
+        cpu.saturating_sub(cpu_before).as_secs_f64() / wall * 100.0
+      } else {
+        0.0
+      }
+    });
+    let (memory_bytes, peak_memory_bytes) = process::memory();
+    SelfMetrics {
+      uptime_secs: now.duration_since(self.started).as_secs(),
+      cpu_percent,
+      cpu_time_secs: cpu_time.map(|cpu| cpu.as_secs_f64()),
+      memory_bytes,
+      peak_memory_bytes,
+      intended_interval_ms: PROBE_INTERVAL.as_millis() as u64,
+      timing: *self.timing.lock_or_recover(),
+    }
+  }
+}
+
+fn ewma(current: Option<f64>, sample: f64) -> f64 {
+  current.map_or(sample, |value| value + EWMA_ALPHA * (sample - value))
+}
+
+#[cfg(target_os = "windows")]
+mod process {
+  use std::mem;
+  use std::time::Duration;
+
+  use windows_sys::Win32::Foundation::FILETIME;
+  use windows_sys::Win32::System::ProcessStatus::{K32GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS};
+  use windows_sys::Win32::System::Threading::{GetCurrentProcess, GetProcessTimes};
+
+  pub fn cpu_time() -> Option<Duration> {
+    let empty = FILETIME {
+      dwLowDateTime: 0,
+      dwHighDateTime: 0,
+    };
+    let (mut created, mut exited, mut kernel, mut user) = (empty, empty, empty, empty);
+    let ok = unsafe { GetProcessTimes(GetCurrentProcess(), &mut created, &mut exited, &mut kernel, &mut user) };
+    if ok == 0 {
+      return None;
+    }
+    // FILETIME counts 100 ns units.
+    let ticks = |time: FILETIME| ((time.dwHighDateTime as u64) << 32) | time.dwLowDateTime as u64;
+    Some(Duration::from_nanos((ticks(kernel) + ticks(user)) * 100))
+  }
+
+  pub fn memory() -> (Option<u64>, Option<u64>) {
+    let mut counters: PROCESS_MEMORY_COUNTERS = unsafe { mem::zeroed() };
+    let size = mem::size_of::<PROCESS_MEMORY_COUNTERS>() as u32;
+    counters.cb = size;
+    if unsafe { K32GetProcessMemoryInfo(GetCurrentProcess(), &mut counters, size) } == 0 {
+      return (None, None);
+    }
+    (Some(counters.WorkingSetSize as u64), Some(counters.PeakWorkingSetSize as u64))
+  }
+}
+
+#[cfg(unix)]
+mod process {
+  use std::mem;
+  use std::time::Duration;
+
+  fn usage() -> Option<libc::rusage> {
+    let mut usage: libc::rusage = unsafe { mem::zeroed() };
+    (unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } == 0).then_some(usage)
+  }
+
+  pub fn cpu_time() -> Option<Duration> {
+    let usage = usage()?;
+    let micros = |time: libc::timeval| time.tv_sec as u64 * 1_000_000 + time.tv_usec as u64;
+    Some(Duration::from_micros(micros(usage.ru_utime) + micros(usage.ru_stime)))
+  }
+
+  pub fn memory() -> (Option<u64>, Option<u64>) {
+    // ru_maxrss is in KiB on Linux and in bytes on macOS.
+    let scale = if cfg!(target_os = "macos") { 1 } else { 1024 };
+    let peak = usage().map(|usage| usage.ru_maxrss as u64 * scale);
+    (resident(), peak)
+  }
+
+  #[cfg(target_os = "linux")]
+  fn resident() -> Option<u64> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    Some(pages * u64::try_from(page_size).ok()?)
+  }
+
+  #[cfg(not(target_os = "linux"))]
+  fn resident() -> Option<u64> {
+    None
+  }
+}
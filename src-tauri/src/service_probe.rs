@@ -0,0 +1,198 @@
+// A logical service made of several endpoints on one host, probed together and judged as one:
+// `service://host?tcp=22,3389&https=443,8443&http=80&path=/health&require=all`. TCP endpoints
+// count as up when they accept a connection, HTTP(S) endpoints when a GET to `path` answers
+// below 500. `require` is `all` (default), `any` or a number of endpoints, so a web farm that
+// loses one of its ports raises one alert for the service rather than one per port, or none.
+
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use url::Url;
+
+use crate::http_probe;
+
+const TIMEOUT: Duration = Duration::from_secs(3);
+const MAX_ENDPOINTS: usize = 16;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+  Tcp,
+  Http,
+  Https,
+}
+
+impl Kind {
+  fn as_str(self) -> &'static str {
+    match self {
+      Kind::Tcp => "tcp",
+      Kind::Http => "http",
+      Kind::Https => "https",
+    }
+  }
+}
+
+#[derive(Clone, Copy)]
+enum Require {
+  All,
+  Any,
+  AtLeast(usize),
+}
+
+struct ServiceTarget {
+  /// As it appears in a URL: IPv6 literals keep their brackets.
+  host: String,
+  endpoints: Vec<(Kind, u16)>,
+  path: String,
+  require: Require,
+}
+
+impl ServiceTarget {
+  fn needed(&self) -> usize {
+    match self.require {
+      Require::All => self.endpoints.len(),
+      Require::Any => 1,
+      Require::AtLeast(count) => count,
+    }
+  }
+}
+
+pub fn is_service_target(address: &str) -> bool {
+  address.to_ascii_lowercase().starts_with("service://")
+}
+
+pub fn validate(address: &str) -> Result<(), String> {
+  parse_target(address).map(drop)
+}
+
+fn parse_target(address: &str) -> Result<ServiceTarget, String> {
+  let url = Url::parse(address).map_err(|e| format!("地址格式无效: {e}"))?;
+  let host = url
+    .host_str()
+    .ok_or_else(|| format!("地址 {address} 缺少主机名"))?
+    .to_string();
+  let mut endpoints = Vec::new();
+  let mut path = "/".to_string();
+  let mut require = Require::All;
+  for (key, value) in url.query_pairs() {
+    let kind = match key.as_ref() {
+      "tcp" => Kind::Tcp,
+      "http" => Kind::Http,
+      "https" => Kind::Https,
+      "path" if value.starts_with('/') => {
+        path = value.to_string();
+        continue;
+      }
+      "path" => return Err(format!("检查路径应以 / 开头: {value}")),
+      "require" => {
+        require = match value.as_ref() {
+          "all" => Require::All,
+          "any" => Require::Any,
+          count => Require::AtLeast(
+            count
+              .parse()
+              .ok()
+              .filter(|count| *count > 0)
+              .ok_or_else(|| format!("require 应为 all、any 或端点数量: {count}"))?,
+          ),
+        };
+        continue;
+      }
+      other => return Err(format!("未知的服务参数: {other}")),
+    };
+    for port in value.split(',').map(str::trim).filter(|port| !port.is_empty()) {
+      match port.parse::<u16>() {
+        Ok(port) if port > 0 => endpoints.push((kind, port)),
+        _ => return Err(format!("端口不合法: {port}")),
+      }
+    }
+  }
+  endpoints.dedup();
+  if endpoints.is_empty() {
+    return Err("服务目标至少需要一个端点，如 ?tcp=22&https=443".to_string());
+  }
+  if endpoints.len() > MAX_ENDPOINTS {
+    return Err(format!("一个服务目标最多 {MAX_ENDPOINTS} 个端点"));
+  }
+  let target = ServiceTarget {
+    host,
+    endpoints,
+    path,
+    require,
+  };
+  if target.needed() > target.endpoints.len() {
+    return Err(format!(
+      "require 要求 {} 个端点正常，但只配置了 {} 个",
+      target.needed(),
+      target.endpoints.len()
+    ));
+  }
+  Ok(target)
+}
+
+/// Checks every endpoint in parallel. Returns the summary line, listing each endpoint, and
+/// the time of the slowest endpoint that answered.
+pub fn probe(address: &str, timestamp: &str) -> (Result<String, String>, Option<f64>) {
+  let target = match parse_target(address) {
+    Ok(target) => target,
+    Err(err) => return (Err(err), None),
+  };
+  let results: Vec<Result<f64, String>> = thread::scope(|scope| {
+    let target = &target;
+    let checks: Vec<_> = target
+      .endpoints
+      .iter()
+      .map(|&(kind, port)| scope.spawn(move || check(target, kind, port, timestamp)))
+      .collect();
+    checks
+      .into_iter()
+      .map(|check| check.join().unwrap_or_else(|_| Err("check panicked".to_string())))
+      .collect()
+  });
+
+  let up = results.iter().filter(|result| result.is_ok()).count();
+  let slowest = results.iter().filter_map(|result| result.as_ref().ok()).copied().reduce(f64::max);
+  let details: Vec<String> = target
+    .endpoints
+    .iter()
+    .zip(&results)
+    .map(|((kind, port), result)| match result {
+      Ok(ms) => format!("{}/{port} {ms:.0}ms", kind.as_str()),
+      Err(err) => format!("{}/{port} {err}", kind.as_str()),
+    })
+    .collect();
+  let line = format!(
+    "service {} {up}/{} up: {}",
+    target.host,
+    target.endpoints.len(),
+    details.join(", ")
+  );
+  if up >= target.needed() {
+    let time = slowest.map_or(String::new(), |ms| format!(" time={ms:.0}ms"));
+    (Ok(format!("{line}{time}")), slowest)
+  } else {
+    (Err(line), None)
+  }
+}
+
+fn check(target: &ServiceTarget, kind: Kind, port: u16, timestamp: &str) -> Result<f64, String> {
+  if kind != Kind::Tcp {
+    let url = format!("{}://{}:{port}{}", kind.as_str(), target.host, target.path);
+    let timing = http_probe::probe(&url, timestamp);
+    return match (timing.error, timing.status) {
+      (Some(err), _) => Err(err),
+      (None, Some(status)) if status >= 500 => Err(format!("HTTP {status}")),
+      (None, Some(_)) => Ok(timing.total_ms),
+      (None, None) => Err("no response".to_string()),
+    };
+  }
+  let host = target.host.trim_matches(['[', ']']);
+  let addr: SocketAddr = (host, port)
+    .to_socket_addrs()
+    .map_err(|e| format!("dns failed: {e}"))?
+    .next()
+    .ok_or_else(|| "dns returned no address".to_string())?;
+  let start = Instant::now();
+  TcpStream::connect_timeout(&addr, TIMEOUT).map_err(|e| format!("connect failed: {e}"))?;
+  Ok(start.elapsed().as_secs_f64() * 1000.0)
+}
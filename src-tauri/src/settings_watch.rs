@@ -0,0 +1,113 @@
+// Notices edits to settings.json made outside the app (by hand or by MDM tooling) and
+// validates them. Commands already read the file on every call, so the new values take
+// effect on their own; the watcher tells the UI to refresh and reports problems.
+
+use std::fs;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use chrono::Local;
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::events;
+use crate::lock::LockExt;
+use crate::{
+  alertmanager, alerts, anomaly, chart, digest, feishu, flap, idle_reminder, jobs, latency_histogram, log_sinks, oncall,
+  otlp, proxy, quiet_networks, relay, relay_server, remote_control, settings_path, sms, speech, speedtest, targets,
+  viewer, AppSettings,
+};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// What the file held when last seen, whether read by the watcher or written by the app.
+static LAST_CONTENTS: Mutex<Option<String>> = Mutex::new(None);
+
+#[derive(Clone, Serialize)]
+pub struct SettingsChanged {
+  pub timestamp: String,
+  /// Empty when the new file is valid.
+  pub errors: Vec<String>,
+}
+
+/// Called by `save_settings` before writing, so the app's own saves aren't reported.
+pub fn note_write(contents: &str) {
+  *LAST_CONTENTS.lock_or_recover() = Some(contents.to_string());
+}
+
+pub fn spawn_watcher(app: AppHandle) {
+  thread::spawn(move || {
+    let Ok(path) = settings_path(&app) else {
+      return;
+    };
+    LAST_CONTENTS.lock_or_recover().get_or_insert_with(|| fs::read_to_string(&path).unwrap_or_default());
+    loop {
+      thread::sleep(POLL_INTERVAL);
+      let contents = fs::read_to_string(&path).unwrap_or_default();
+      {
+        let mut last = LAST_CONTENTS.lock_or_recover();
+        if last.as_deref() == Some(contents.as_str()) {
+          continue;
+        }
+        *last = Some(contents.clone());
+      }
+      viewer::refresh(&app);
+      let errors = check(&contents);
+      for error in &errors {
+        eprintln!("settings.json changed externally: {error}");
+      }
+      let event = SettingsChanged {
+        timestamp: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        errors,
+      };
+      events::emit(&app, "settings-changed", event);
+    }
+  });
+}
+
+/// What is wrong with `settings.json` as it is on disk now.
+pub fn problems(app: &AppHandle) -> Vec<String> {
+  settings_path(app)
+    .ok()
+    .and_then(|path| fs::read_to_string(path).ok())
+    .map(|contents| check(&contents))
+    .unwrap_or_default()
+}
+
+/// Runs the same checks the save commands do, over the whole file.
+fn check(contents: &str) -> Vec<String> {
+  if contents.trim().is_empty() {
+    return Vec::new();
+  }
+  let settings: AppSettings = match serde_json::from_str(contents) {
+    Ok(settings) => settings,
+    Err(e) => return vec![format!("settings.json 格式无效，将使用默认设置: {e}")],
+  };
+  let mut results = vec![
+    log_sinks::validate(&settings.sinks),
+    otlp::validate(&settings.otlp),
+    speedtest::validate(&settings.speedtest),
+    anomaly::validate(&settings.anomaly),
+    idle_reminder::validate(&settings.idle_reminder),
+    flap::validate(&settings.flap),
+    latency_histogram::validate(&settings.histogram),
+    digest::validate(&settings.digest),
+    sms::validate(&settings.sms),
+    oncall::validate(&settings.oncall),
+    feishu::validate(&settings.feishu),
+    proxy::validate_url(&settings.proxy.url),
+    relay_server::validate(&settings.relay_server),
+    remote_control::validate(&settings.remote_control),
+    settings.ping.validate(),
+    alerts::validate_subject_template(&settings.smtp.subject_template),
+    chart::validate_minutes(settings.smtp.chart_minutes),
+    quiet_networks::validate(&settings.quiet_networks),
+    speech::validate(&settings.speech),
+    alertmanager::validate(&settings.alertmanager),
+  ];
+  results.extend(settings.targets.iter().map(targets::validate));
+  results.extend(settings.jobs.iter().map(jobs::validate));
+  results.extend(settings.relays.iter().map(relay::validate));
+  results.into_iter().filter_map(Result::err).collect()
+}
@@ -0,0 +1,270 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::Engine;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha1::Sha1;
+
+use crate::proxy;
+
+const ALIYUN_ENDPOINT: &str = "https://dysmsapi.aliyuncs.com/";
+
+/// RFC 3986 unreserved characters stay as-is, as Aliyun's signature scheme requires.
+const RFC3986: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'_').remove(b'.').remove(b'~');
+
+#[derive(Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmsProvider {
+  #[default]
+  Twilio,
+  Aliyun,
+}
+
+/// SMS for outage start and recovery only; it still works when the recipient's own network is down.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct SmsSettings {
+  #[serde(default)]
+  pub enabled: bool,
+  #[serde(default)]
+  pub provider: SmsProvider,
+  /// Comma-separated phone numbers, E.164 for Twilio (`+8613800000000`).
+  #[serde(default)]
+  pub to: String,
+  /// Twilio message body; `{event}`, `{target}`, `{time}` and `{incident}` are substituted.
+  #[serde(default = "default_template")]
+  pub template: String,
+  #[serde(default)]
+  pub twilio: TwilioSettings,
+  #[serde(default)]
+  pub aliyun: AliyunSmsSettings,
+  /// Overrides the global alert proxy; an empty string connects directly.
+  #[serde(default)]
+  pub proxy: Option<String>,
+}
+
+impl Default for SmsSettings {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      provider: SmsProvider::default(),
+      to: String::new(),
+      template: default_template(),
+      twilio: TwilioSettings::default(),
+      aliyun: AliyunSmsSettings::default(),
+      proxy: None,
+    }
+  }
+}
+
+fn default_template() -> String {
+  "[Ping Tool] {target} 网络{event}，时间 {time} {incident}".to_string()
+}
+
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct TwilioSettings {
+  #[serde(default)]
+  pub account_sid: String,
+  #[serde(default)]
+  pub auth_token: String,
+  #[serde(default)]
+  pub from: String,
+}
+
+/// Aliyun sends pre-approved templates only; the template receives `event`, `target`, `time` and
+/// `incident` params.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct AliyunSmsSettings {
+  #[serde(default)]
+  pub access_key_id: String,
+  #[serde(default)]
+  pub access_key_secret: String,
+  #[serde(default)]
+  pub sign_name: String,
+  #[serde(default)]
+  pub template_code: String,
+  #[serde(default = "default_region")]
+  pub region: String,
+}
+
+impl Default for AliyunSmsSettings {
+  fn default() -> Self {
+    Self {
+      access_key_id: String::new(),
+      access_key_secret: String::new(),
+      sign_name: String::new(),
+      template_code: String::new(),
+      region: default_region(),
+    }
+  }
+}
+
+fn default_region() -> String {
+  "cn-hangzhou".to_string()
+}
+
+pub fn validate(settings: &SmsSettings) -> Result<(), String> {
+  if !settings.enabled {
+    return Ok(());
+  }
+  if let Some(proxy) = &settings.proxy {
+    proxy::validate_url(proxy)?;
+  }
+  if recipients(&settings.to).is_empty() {
+    return Err("短信接收号码不能为空".to_string());
+  }
+  match settings.provider {
+    SmsProvider::Twilio => {
+      let twilio = &settings.twilio;
+      if twilio.account_sid.trim().is_empty() || twilio.auth_token.trim().is_empty() {
+        return Err("Twilio Account SID 和 Auth Token 不能为空".to_string());
+      }
+      if twilio.from.trim().is_empty() {
+        return Err("Twilio 发送号码不能为空".to_string());
+      }
+    }
+    SmsProvider::Aliyun => {
+      let aliyun = &settings.aliyun;
+      if aliyun.access_key_id.trim().is_empty() || aliyun.access_key_secret.trim().is_empty() {
+        return Err("阿里云 AccessKey 不能为空".to_string());
+      }
+      if aliyun.sign_name.trim().is_empty() || aliyun.template_code.trim().is_empty() {
+        return Err("阿里云短信签名和模板编号不能为空".to_string());
+      }
+    }
+  }
+  Ok(())
+}
+
+fn recipients(to: &str) -> Vec<&str> {
+  to.split([',', ';']).map(str::trim).filter(|n| !n.is_empty()).collect()
+}
+
+fn template_params(event: &str, target: &str, time: &str, incident: &str) -> Value {
+  json!({ "event": event, "target": target, "time": time, "incident": incident })
+}
+
+/// What the provider receives: the Twilio body, or the Aliyun template code and parameters.
+pub fn render(settings: &SmsSettings, event: &str, target: &str, time: &str, incident: &str) -> String {
+  match settings.provider {
+    SmsProvider::Twilio => {
+      // Templates saved before `{incident}` existed still get the ID, at the end.
+      let mut template = settings.template.clone();
+      if !template.contains("{incident}") {
+        template.push_str(" {incident}");
+      }
+      template
+        .replace("{event}", event)
+        .replace("{target}", target)
+        .replace("{time}", time)
+        .replace("{incident}", incident)
+        .trim()
+        .to_string()
+    }
+    SmsProvider::Aliyun => json!({
+      "TemplateCode": settings.aliyun.template_code.trim(),
+      "TemplateParam": template_params(event, target, time, incident),
+    })
+    .to_string(),
+  }
+}
+
+/// Sends `event` (e.g. `中断`, `恢复`) to every configured number. `incident` is the outage's ID,
+/// empty for a test message.
+pub fn send(settings: &SmsSettings, event: &str, target: &str, time: &str, incident: &str) -> Result<(), String> {
+  let client = proxy::client(settings.proxy.as_deref(), Duration::from_secs(10))?;
+  match settings.provider {
+    SmsProvider::Twilio => {
+      let body = render(settings, event, target, time, incident);
+      for to in recipients(&settings.to) {
+        send_twilio(&client, &settings.twilio, to, &body)?;
+      }
+      Ok(())
+    }
+    SmsProvider::Aliyun => {
+      let params = template_params(event, target, time, incident);
+      // Aliyun accepts a comma-separated list in one request.
+      send_aliyun(&client, &settings.aliyun, &recipients(&settings.to).join(","), &params)
+    }
+  }
+}
+
+fn send_twilio(client: &Client, twilio: &TwilioSettings, to: &str, body: &str) -> Result<(), String> {
+  let sid = twilio.account_sid.trim();
+  let url = format!("https://api.twilio.com/2010-04-01/Accounts/{sid}/Messages.json");
+  let response = client
+    .post(url)
+    .basic_auth(sid, Some(twilio.auth_token.trim()))
+    .form(&[("From", twilio.from.trim()), ("To", to), ("Body", body)])
+    .send()
+    .map_err(|e| format!("Twilio 请求失败: {e}"))?;
+  if response.status().is_success() {
+    return Ok(());
+  }
+  let status = response.status();
+  let detail: Value = response.json().unwrap_or(Value::Null);
+  Err(format!(
+    "Twilio 发送失败 ({status}): {}",
+    detail["message"].as_str().unwrap_or("unknown error")
+  ))
+}
+
+fn send_aliyun(client: &Client, aliyun: &AliyunSmsSettings, phones: &str, params: &Value) -> Result<(), String> {
+  let nonce = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_nanos())
+    .unwrap_or_default()
+    .to_string();
+  let timestamp = Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
+  let mut query = BTreeMap::from([
+    ("AccessKeyId", aliyun.access_key_id.trim().to_string()),
+    ("Action", "SendSms".to_string()),
+    ("Format", "JSON".to_string()),
+    ("PhoneNumbers", phones.to_string()),
+    ("RegionId", aliyun.region.trim().to_string()),
+    ("SignName", aliyun.sign_name.trim().to_string()),
+    ("SignatureMethod", "HMAC-SHA1".to_string()),
+    ("SignatureNonce", nonce),
+    ("SignatureVersion", "1.0".to_string()),
+    ("TemplateCode", aliyun.template_code.trim().to_string()),
+    ("TemplateParam", params.to_string()),
+    ("Timestamp", timestamp),
+    ("Version", "2017-05-25".to_string()),
+  ]);
+  let signature = aliyun_signature(&query, aliyun.access_key_secret.trim())?;
+  query.insert("Signature", signature);
+
+  let response: Value = client
+    .get(ALIYUN_ENDPOINT)
+    .query(&query)
+    .send()
+    .map_err(|e| format!("阿里云短信请求失败: {e}"))?
+    .json()
+    .map_err(|e| format!("阿里云短信响应无效: {e}"))?;
+  match response["Code"].as_str() {
+    Some("OK") => Ok(()),
+    code => Err(format!(
+      "阿里云短信发送失败 ({}): {}",
+      code.unwrap_or("?"),
+      response["Message"].as_str().unwrap_or("unknown error")
+    )),
+  }
+}
+
+/// Signature version 1.0: HMAC-SHA1 over `GET&%2F&` plus the encoded, sorted query.
+fn aliyun_signature(query: &BTreeMap<&str, String>, secret: &str) -> Result<String, String> {
+  let encode = |value: &str| utf8_percent_encode(value, RFC3986).to_string();
+  let canonical = query
+    .iter()
+    .map(|(key, value)| format!("{}={}", encode(key), encode(value)))
+    .collect::<Vec<_>>()
+    .join("&");
+  let string_to_sign = format!("GET&{}&{}", encode("/"), encode(&canonical));
+  let mut mac = Hmac::<Sha1>::new_from_slice(format!("{secret}&").as_bytes()).map_err(|e| e.to_string())?;
+  mac.update(string_to_sign.as_bytes());
+  Ok(base64::engine::general_purpose::STANDARD.encode(mac.finalize().into_bytes()))
+}
@@ -0,0 +1,157 @@
+// Spoken announcements through the operating system's text-to-speech, so a NOC room hears which
+// target went down and for how long instead of a bare chime: Windows uses System.Speech via
+// PowerShell, macOS `say`, Linux speech-dispatcher (`spd-say`) or `espeak`. Announcements queue
+// behind each other rather than talking over one another.
+
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::alerts::{AlertEvent, AlertKind, AlertSeverity};
+use crate::lock::LockExt;
+use crate::{format_duration, probe_command};
+
+const MAX_VOICE_LEN: usize = 100;
+
+/// Held while speaking, so announcements play one after another.
+static SPEAKING: Mutex<()> = Mutex::new(());
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct SpeechSettings {
+  #[serde(default)]
+  pub enabled: bool,
+  /// Alerts of these severities are spoken.
+  #[serde(default = "default_severities")]
+  pub severities: Vec<AlertSeverity>,
+  /// A voice installed on the system, e.g. `Microsoft Huihui Desktop`; empty for the default.
+  #[serde(default)]
+  pub voice: String,
+}
+
+impl Default for SpeechSettings {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      severities: default_severities(),
+      voice: String::new(),
+    }
+  }
+}
+
+fn default_severities() -> Vec<AlertSeverity> {
+  vec![AlertSeverity::Critical, AlertSeverity::Info]
+}
+
+pub fn validate(settings: &SpeechSettings) -> Result<(), String> {
+  if settings.voice.chars().count() > MAX_VOICE_LEN {
+    return Err(format!("语音名称最多 {MAX_VOICE_LEN} 个字符"));
+  }
+  if settings.voice.chars().any(char::is_control) {
+    return Err("语音名称不能包含控制字符".to_string());
+  }
+  Ok(())
+}
+
+impl SpeechSettings {
+  pub fn speaks(&self, severity: AlertSeverity) -> bool {
+    self.enabled && self.severities.contains(&severity)
+  }
+}
+
+/// A short sentence for an alert, e.g. "办公室上行 中断，已持续 2 分". `lasted` is how long the
+/// outage has gone on, for outage alerts.
+pub fn announcement(event: &AlertEvent, lasted: Option<Duration>) -> String {
+  let name = if event.label.is_empty() {
+    event.address.clone().unwrap_or_default()
+  } else {
+    event.label.clone()
+  };
+  let lasted = lasted.map(format_duration);
+  let text = match (event.kind, lasted) {
+    (AlertKind::OutageStarted, Some(lasted)) => format!("{name} 中断，已持续 {lasted}"),
+    (AlertKind::OutageStarted, None) => format!("{name} 中断"),
+    (AlertKind::OutageReminder, Some(lasted)) => format!("{name} 仍未恢复，已持续 {lasted}"),
+    (AlertKind::OutageRecovered, Some(lasted)) => format!("{name} 已恢复，中断了 {lasted}"),
+    (AlertKind::OutageRecovered, None) => format!("{name} 已恢复"),
+    // Other alerts: the first clause of the message is the gist.
+    _ => event
+      .message
+      .split(['，', '；', '\n'])
+      .next()
+      .unwrap_or_default()
+      .to_string(),
+  };
+  if event.drill {
+    format!("演练，{text}")
+  } else {
+    text
+  }
+}
+
+/// Speaks `text` in the background.
+pub fn announce(settings: SpeechSettings, text: String) {
+  thread::spawn(move || {
+    if let Err(e) = speak(&settings.voice, &text) {
+      eprintln!("failed to speak announcement: {e}");
+    }
+  });
+}
+
+/// Speaks `text` and waits until it has been said.
+pub fn speak(voice: &str, text: &str) -> Result<(), String> {
+  let _speaking = SPEAKING.lock_or_recover();
+  let voice = voice.trim();
+  let attempts = commands(voice, text);
+  let mut last_error = String::new();
+  for (program, args) in &attempts {
+    // Passed through the environment so the text never has to be quoted for a shell.
+    let status = probe_command(program, args)
+      .env("PING_TOOL_SPEECH_TEXT", text)
+      .env("PING_TOOL_SPEECH_VOICE", voice)
+      .stdin(Stdio::null())
+      .stdout(Stdio::null())
+      .stderr(Stdio::null())
+      .status();
+    match status {
+      Ok(status) if status.success() => return Ok(()),
+      Ok(status) => last_error = format!("{program} exited with {status}"),
+      Err(e) => last_error = format!("{program}: {e}"),
+    }
+  }
+  Err(format!("没有可用的语音合成程序（{last_error}）"))
+}
+
+/// Synthesizer invocations to try in order.
+fn commands(voice: &str, text: &str) -> Vec<(&'static str, Vec<String>)> {
+  let args = |items: &[&str]| items.iter().map(|item| item.to_string()).collect::<Vec<_>>();
+  if cfg!(target_os = "windows") {
+    let script = "Add-Type -AssemblyName System.Speech; \
+      $s = New-Object System.Speech.Synthesis.SpeechSynthesizer; \
+      if ($env:PING_TOOL_SPEECH_VOICE) { $s.SelectVoice($env:PING_TOOL_SPEECH_VOICE) }; \
+      $s.Speak($env:PING_TOOL_SPEECH_TEXT)";
+    vec![(
+      "powershell",
+      args(&["-NoProfile", "-NonInteractive", "-Command", script]),
+    )]
+  } else if cfg!(target_os = "macos") {
+    let mut say = Vec::new();
+    if !voice.is_empty() {
+      say.extend(args(&["-v", voice]));
+    }
+    say.extend(args(&["--", text]));
+    vec![("say", say)]
+  } else {
+    let mut spd = args(&["--wait"]);
+    let mut espeak = Vec::new();
+    if !voice.is_empty() {
+      spd.extend(args(&["-y", voice]));
+      espeak.extend(args(&["-v", voice]));
+    }
+    spd.extend(args(&["--", text]));
+    espeak.extend(args(&["--", text]));
+    vec![("spd-say", spd), ("espeak", espeak)]
+  }
+}
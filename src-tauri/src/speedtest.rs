@@ -0,0 +1,156 @@
+use std::io::Read;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct SpeedtestSettings {
+  #[serde(default)]
+  pub enabled: bool,
+  #[serde(default = "default_interval_minutes")]
+  pub interval_minutes: u64,
+  #[serde(default = "default_download_url")]
+  pub download_url: String,
+  /// Leave empty to skip the upload phase.
+  #[serde(default = "default_upload_url")]
+  pub upload_url: String,
+  #[serde(default = "default_upload_bytes")]
+  pub upload_bytes: usize,
+  #[serde(default)]
+  pub min_download_mbps: Option<f64>,
+  #[serde(default)]
+  pub min_upload_mbps: Option<f64>,
+}
+
+impl Default for SpeedtestSettings {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      interval_minutes: default_interval_minutes(),
+      download_url: default_download_url(),
+      upload_url: default_upload_url(),
+      upload_bytes: default_upload_bytes(),
+      min_download_mbps: None,
+      min_upload_mbps: None,
+    }
+  }
+}
+
+fn default_interval_minutes() -> u64 {
+  60
+}
+
+fn default_download_url() -> String {
+  "https://speed.cloudflare.com/__down?bytes=25000000".to_string()
+}
+
+fn default_upload_url() -> String {
+  "https://speed.cloudflare.com/__up".to_string()
+}
+
+fn default_upload_bytes() -> usize {
+  10_000_000
+}
+
+#[derive(Clone, Serialize)]
+pub struct SpeedtestResult {
+  pub timestamp: String,
+  pub latency_ms: f64,
+  pub download_mbps: f64,
+  pub upload_mbps: Option<f64>,
+}
+
+impl SpeedtestResult {
+  pub fn summary(&self) -> String {
+    let upload = self
+      .upload_mbps
+      .map_or("-".to_string(), |mbps| format!("{mbps:.1} Mbps"));
+    format!(
+      "SPEEDTEST | 下载 {:.1} Mbps，上传 {upload}，延迟 {:.0} ms",
+      self.download_mbps, self.latency_ms
+    )
+  }
+
+  /// Describes which configured minimums were missed, if any.
+  pub fn threshold_violation(&self, settings: &SpeedtestSettings) -> Option<String> {
+    let mut problems = Vec::new();
+    if let Some(min) = settings.min_download_mbps {
+      if self.download_mbps < min {
+        problems.push(format!("下载 {:.1} Mbps 低于 {min} Mbps", self.download_mbps));
+      }
+    }
+    if let (Some(min), Some(upload)) = (settings.min_upload_mbps, self.upload_mbps) {
+      if upload < min {
+        problems.push(format!("上传 {upload:.1} Mbps 低于 {min} Mbps"));
+      }
+    }
+    (!problems.is_empty()).then(|| problems.join("，"))
+  }
+}
+
+pub fn validate(settings: &SpeedtestSettings) -> Result<(), String> {
+  if !settings.enabled {
+    return Ok(());
+  }
+  if settings.interval_minutes == 0 {
+    return Err("测速间隔必须大于 0".to_string());
+  }
+  if settings.download_url.trim().is_empty() {
+    return Err("下载测速地址不能为空".to_string());
+  }
+  Ok(())
+}
+
+pub fn run(settings: &SpeedtestSettings, timestamp: &str) -> Result<SpeedtestResult, String> {
+  let client = reqwest::blocking::Client::builder()
+    .timeout(Duration::from_secs(120))
+    .build()
+    .map_err(|e| e.to_string())?;
+
+  let start = Instant::now();
+  let mut response = client
+    .get(settings.download_url.trim())
+    .send()
+    .and_then(|r| r.error_for_status())
+    .map_err(|e| format!("下载测速失败: {e}"))?;
+  let latency_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+  let body_start = Instant::now();
+  let mut buf = vec![0u8; 64 * 1024];
+  let mut received = 0usize;
+  loop {
+    let n = response.read(&mut buf).map_err(|e| format!("下载测速失败: {e}"))?;
+    if n == 0 {
+      break;
+    }
+    received += n;
+  }
+  let download_mbps = mbps(received, body_start.elapsed());
+
+  let upload_url = settings.upload_url.trim();
+  let upload_mbps = if upload_url.is_empty() || settings.upload_bytes == 0 {
+    None
+  } else {
+    let payload = vec![0u8; settings.upload_bytes];
+    let start = Instant::now();
+    client
+      .post(upload_url)
+      .body(payload)
+      .send()
+      .and_then(|r| r.error_for_status())
+      .map_err(|e| format!("上传测速失败: {e}"))?;
+    Some(mbps(settings.upload_bytes, start.elapsed()))
+  };
+
+  Ok(SpeedtestResult {
+    timestamp: timestamp.to_string(),
+    latency_ms,
+    download_mbps,
+    upload_mbps,
+  })
+}
+
+fn mbps(bytes: usize, elapsed: Duration) -> f64 {
+  let secs = elapsed.as_secs_f64().max(0.001);
+  bytes as f64 * 8.0 / secs / 1_000_000.0
+}
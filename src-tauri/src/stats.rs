@@ -0,0 +1,255 @@
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Local};
+use serde::Serialize;
+
+use crate::anomaly::Baseline;
+
+/// Segments older than this are dropped; the raw logs still have them.
+const MAX_DAYS: usize = 31;
+const MAX_BLIPS: usize = 1000;
+
+struct DayAccumulator {
+  date: String,
+  since: String,
+  sent: u64,
+  lost: u64,
+  rtt_sum: f64,
+  rtt_count: u64,
+  min_rtt: Option<f64>,
+  max_rtt: Option<f64>,
+  outages: u32,
+  blips: u32,
+  duplicates: u64,
+  late: u64,
+  reordered: u64,
+}
+
+impl DayAccumulator {
+  fn new(now: &DateTime<Local>) -> Self {
+    Self {
+      date: now.format("%Y-%m-%d").to_string(),
+      since: now.format("%Y-%m-%d %H:%M:%S").to_string(),
+      sent: 0,
+      lost: 0,
+      rtt_sum: 0.0,
+      rtt_count: 0,
+      min_rtt: None,
+      max_rtt: None,
+      outages: 0,
+      blips: 0,
+      duplicates: 0,
+      late: 0,
+      reordered: 0,
+    }
+  }
+
+  fn summary(&self) -> DayStats {
+    DayStats {
+      date: self.date.clone(),
+      since: self.since.clone(),
+      sent: self.sent,
+      lost: self.lost,
+      loss_percent: if self.sent == 0 { 0.0 } else { self.lost as f64 * 100.0 / self.sent as f64 },
+      min_rtt_ms: self.min_rtt,
+      avg_rtt_ms: (self.rtt_count > 0).then(|| self.rtt_sum / self.rtt_count as f64),
+      max_rtt_ms: self.max_rtt,
+      outages: self.outages,
+      blips: self.blips,
+      duplicates: self.duplicates,
+      late: self.late,
+      reordered: self.reordered,
+    }
+  }
+}
+
+#[derive(Clone, Serialize)]
+pub struct DayStats {
+  pub date: String,
+  /// When this segment started counting: session start, midnight, or the last reset.
+  pub since: String,
+  pub sent: u64,
+  pub lost: u64,
+  pub loss_percent: f64,
+  pub min_rtt_ms: Option<f64>,
+  pub avg_rtt_ms: Option<f64>,
+  pub max_rtt_ms: Option<f64>,
+  pub outages: u32,
+  /// Failure bursts that recovered before raising an outage alert.
+  pub blips: u32,
+  /// Duplicate replies, counted separately from `sent`/`lost`; a sign of misbehaving network gear.
+  pub duplicates: u64,
+  /// Replies that came back after the next request of their cycle had been sent.
+  pub late: u64,
+  /// Replies overtaken by the reply to a later request of their cycle.
+  pub reordered: u64,
+}
+
+#[derive(Serialize)]
+pub struct StatsReport {
+  pub session_id: String,
+  /// Set when the session was restarted on a different address.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub previous_session_id: Option<String>,
+  /// Oldest first; the last entry is today.
+  pub days: Vec<DayStats>,
+  /// EWMA latency baseline learned this session.
+  pub baseline: Option<Baseline>,
+}
+
+impl StatsReport {
+  /// Plain-text rendering for pasting into a chat or ticket.
+  pub fn to_text(&self, target_name: &str) -> String {
+    let ms = |value: Option<f64>| value.map_or("-".to_string(), |v| format!("{v:.1}"));
+    let mut text = format!("目标: {target_name}\n会话: {}", self.session_id);
+    if let Some(previous) = &self.previous_session_id {
+      text.push_str(&format!("（接续 {previous}）"));
+    }
+    text.push('\n');
+    for day in &self.days {
+      text.push_str(&format!(
+        "{}（自 {} 起）: 发送 {}，丢失 {}（{:.2}%），延迟 最小/平均/最大 {}/{}/{} ms，中断 {} 次，短暂丢包 {} 次\n",
+        day.date,
+        day.since,
+        day.sent,
+        day.lost,
+        day.loss_percent,
+        ms(day.min_rtt_ms),
+        ms(day.avg_rtt_ms),
+        ms(day.max_rtt_ms),
+        day.outages,
+        day.blips,
+      ));
+    }
+    if let Some(baseline) = self.baseline {
+      text.push_str(&format!(
+        "延迟基线: {:.1} ± {:.1} ms（{} 个样本）\n",
+        baseline.mean_ms, baseline.stddev_ms, baseline.samples
+      ));
+    }
+    text
+  }
+}
+
+/// Aggregates for the running session, split at local midnight.
+#[derive(Default)]
+pub struct SessionStats {
+  session_id: String,
+  previous_session_id: Option<String>,
+  days: Vec<DayAccumulator>,
+  baseline: Option<Baseline>,
+  blips: VecDeque<Blip>,
+}
+
+/// A failure burst too short to alert on.
+#[derive(Clone, Serialize)]
+pub struct Blip {
+  pub started: String,
+  pub ended: String,
+  pub failures: u32,
+  pub duration_ms: u64,
+  /// Met the failure count but not the grace period.
+  pub micro_outage: bool,
+}
+
+impl SessionStats {
+  pub fn begin(&mut self, now: &DateTime<Local>) -> String {
+    self.session_id = format!("S-{}", now.format("%Y%m%d-%H%M%S"));
+    self.days.clear();
+    self.baseline = None;
+    self.blips.clear();
+    self.previous_session_id = None;
+    self.session_id.clone()
+  }
+
+  /// Starts a new session that records which one it replaced.
+  pub fn begin_after(&mut self, now: &DateTime<Local>) -> String {
+    let previous = std::mem::take(&mut self.session_id);
+    let session_id = self.begin(now);
+    self.previous_session_id = Some(previous).filter(|id| !id.is_empty());
+    session_id
+  }
+
+  fn today(&mut self, now: &DateTime<Local>) -> &mut DayAccumulator {
+    let date = now.format("%Y-%m-%d").to_string();
+    if self.days.last().is_none_or(|day| day.date != date) {
+      self.days.push(DayAccumulator::new(now));
+      if self.days.len() > MAX_DAYS {
+        self.days.remove(0);
+      }
+    }
+    let last = self.days.len() - 1;
+    &mut self.days[last]
+  }
+
+  pub fn record(&mut self, now: &DateTime<Local>, success: bool, rtt_ms: Option<f64>) {
+    let day = self.today(now);
+    day.sent += 1;
+    if !success {
+      day.lost += 1;
+      return;
+    }
+    if let Some(rtt) = rtt_ms {
+      day.rtt_sum += rtt;
+      day.rtt_count += 1;
+      day.min_rtt = Some(day.min_rtt.map_or(rtt, |min| min.min(rtt)));
+      day.max_rtt = Some(day.max_rtt.map_or(rtt, |max| max.max(rtt)));
+    }
+  }
+
+  pub fn record_duplicates(&mut self, now: &DateTime<Local>, count: u32) {
+    if count > 0 {
+      self.today(now).duplicates += u64::from(count);
+    }
+  }
+
+  /// Late and out-of-order replies; only a cycle of several packets can have them.
+  pub fn record_late_replies(&mut self, now: &DateTime<Local>, late: u32, reordered: u32) {
+    if late > 0 || reordered > 0 {
+      let day = self.today(now);
+      day.late += u64::from(late);
+      day.reordered += u64::from(reordered);
+    }
+  }
+
+  pub fn record_blip(&mut self, now: &DateTime<Local>, blip: Blip) {
+    self.today(now).blips += 1;
+    if self.blips.len() >= MAX_BLIPS {
+      self.blips.pop_front();
+    }
+    self.blips.push_back(blip);
+  }
+
+  /// Most recent first.
+  pub fn blips(&self, limit: usize) -> Vec<Blip> {
+    self.blips.iter().rev().take(limit).cloned().collect()
+  }
+
+  pub fn set_baseline(&mut self, baseline: Option<Baseline>) {
+    self.baseline = baseline;
+  }
+
+  pub fn record_outage(&mut self, now: &DateTime<Local>) {
+    self.today(now).outages += 1;
+  }
+
+  /// Clears every segment of the session. The ID guards against a stale UI resetting a newer session.
+  pub fn reset(&mut self, session_id: &str, now: &DateTime<Local>) -> Result<(), String> {
+    if self.session_id.is_empty() || self.session_id != session_id {
+      return Err(format!("会话 {session_id} 不存在或已结束"));
+    }
+    self.days.clear();
+    self.days.push(DayAccumulator::new(now));
+    self.blips.clear();
+    Ok(())
+  }
+
+  pub fn report(&self) -> StatsReport {
+    StatsReport {
+      session_id: self.session_id.clone(),
+      previous_session_id: self.previous_session_id.clone(),
+      days: self.days.iter().map(DayAccumulator::summary).collect(),
+      baseline: self.baseline,
+    }
+  }
+}
@@ -0,0 +1,134 @@
+// Recomputes the statistics kept next to the minute logs — hourly rollups and day manifests,
+// which the SLA, outage and comparison reports are built from — for a range of days. Rollups
+// get duplicated when old logs are imported over hours already covered, go stale when the
+// outage threshold changes and can be damaged by a crash mid-write; the minute logs stay the
+// source of truth. Statistics of hours whose minute logs no longer exist (archived or deleted)
+// are kept as they are, so a rebuild never loses history it can't recompute.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use crate::day_index::{self, DayIndex};
+use crate::logfile::{self, Sample};
+use crate::rollup::{self, HourRollup};
+
+/// Sent as `statistics-rebuild-progress` after each day.
+#[derive(Clone, Serialize)]
+pub struct RebuildProgress {
+  pub day: String,
+  pub days_done: u64,
+  pub days_total: u64,
+}
+
+#[derive(Default, Serialize)]
+pub struct RebuildReport {
+  pub from: String,
+  pub to: String,
+  /// Days whose statistics were recomputed.
+  pub days: u64,
+  /// Days left alone because they have no minute logs.
+  pub skipped_days: Vec<String>,
+  pub samples: u64,
+  pub rollups: u64,
+  /// Hourly rollups kept because their minute logs are gone.
+  pub kept_rollups: u64,
+  /// Outages found with the current confirmation threshold.
+  pub outages: u64,
+}
+
+/// Rebuilds every day in `[from, to]`. Failure runs of at least `outage_failures` probes count
+/// as outages.
+pub fn rebuild(
+  base_dir: &Path,
+  from: NaiveDate,
+  to: NaiveDate,
+  outage_failures: u32,
+  mut progress: impl FnMut(RebuildProgress),
+) -> Result<RebuildReport, String> {
+  let mut report = RebuildReport {
+    from: from.format("%Y-%m-%d").to_string(),
+    to: to.format("%Y-%m-%d").to_string(),
+    ..RebuildReport::default()
+  };
+  let days_total = (to - from).num_days() as u64 + 1;
+  for (done, day) in from.iter_days().take_while(|day| *day <= to).enumerate() {
+    let date = day.format("%Y-%m-%d").to_string();
+    if !rebuild_day(base_dir, day, outage_failures, &mut report)? {
+      report.skipped_days.push(date.clone());
+    }
+    progress(RebuildProgress {
+      day: date,
+      days_done: done as u64 + 1,
+      days_total,
+    });
+  }
+  Ok(report)
+}
+
+/// `false` when the day has no minute logs to rebuild from.
+fn rebuild_day(
+  base_dir: &Path,
+  day: NaiveDate,
+  outage_failures: u32,
+  report: &mut RebuildReport,
+) -> Result<bool, String> {
+  let date = day.format("%Y-%m-%d").to_string();
+  let dir = base_dir.join(&date);
+  // Listed afresh rather than taken from the manifest, which may be what needs repairing.
+  let files = day_index::scan_files(&dir);
+  let mut samples: Vec<Sample> = files
+    .iter()
+    .filter_map(|file| fs::read_to_string(dir.join(file)).ok())
+    .flat_map(|contents| contents.lines().filter_map(logfile::parse_sample).collect::<Vec<_>>())
+    .collect();
+  if samples.is_empty() {
+    return Ok(false);
+  }
+  samples.sort_by_key(|sample| sample.timestamp);
+
+  let mut rollups = rollup::from_samples(&samples);
+  let rebuilt: HashSet<(String, String)> = rollups
+    .iter()
+    .map(|rollup| (rollup.hour.clone(), rollup.address.clone()))
+    .collect();
+  let kept: Vec<HourRollup> = rollup::load(base_dir, day, day, None)
+    .into_iter()
+    .filter(|rollup| !rebuilt.contains(&(rollup.hour.clone(), rollup.address.clone())))
+    .collect();
+  report.kept_rollups += kept.len() as u64;
+  report.rollups += rollups.len() as u64;
+  rollups.extend(kept);
+  rollups.sort_by(|a, b| (&a.hour, &a.address).cmp(&(&b.hour, &b.address)));
+  rollup::replace_day(base_dir, day, &rollups).map_err(|e| format!("无法写入 {date} 的小时汇总: {e}"))?;
+
+  let outages = logfile::failure_runs(&samples, outage_failures.max(1)).len() as u64;
+  let mut targets: Vec<String> = Vec::new();
+  for sample in &samples {
+    if !targets.contains(&sample.target) {
+      targets.push(sample.target.clone());
+    }
+  }
+  let format = "%Y-%m-%d %H:%M:%S";
+  let index = DayIndex {
+    date: date.clone(),
+    files,
+    targets,
+    first: samples
+      .first()
+      .map(|sample| sample.timestamp.format(format).to_string()),
+    last: samples.last().map(|sample| sample.timestamp.format(format).to_string()),
+    probes: samples.len() as u64,
+    failures: samples.iter().filter(|sample| !sample.success).count() as u64,
+    outages,
+  };
+  day_index::replace(base_dir, &index).map_err(|e| format!("无法写入 {date} 的日志索引: {e}"))?;
+
+  report.days += 1;
+  report.samples += samples.len() as u64;
+  report.outages += outages;
+  Ok(true)
+}
@@ -0,0 +1,91 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use ping_core::LinkState;
+use serde::Serialize;
+
+/// How often `ping-summary` is emitted while monitoring.
+pub const SUMMARY_INTERVAL: Duration = Duration::from_secs(5);
+const WINDOW: Duration = Duration::from_secs(300);
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkStatus {
+  Up,
+  /// Failing, but not (yet) an alerted outage.
+  Degraded,
+  Down,
+  /// Replying again during an outage, but not yet for long enough to end it.
+  Recovering,
+  /// Outside the target's monitoring window.
+  Paused,
+}
+
+impl From<LinkState> for LinkStatus {
+  fn from(state: LinkState) -> Self {
+    match state {
+      LinkState::Up => LinkStatus::Up,
+      LinkState::Degraded => LinkStatus::Degraded,
+      LinkState::Down => LinkStatus::Down,
+      LinkState::Recovering => LinkStatus::Recovering,
+    }
+  }
+}
+
+/// Sent as `link-state` whenever the outage detector changes state.
+#[derive(Clone, Serialize)]
+pub struct LinkTransition {
+  pub address: String,
+  pub timestamp: String,
+  pub from: LinkStatus,
+  pub to: LinkStatus,
+  /// Loss of the cycle that caused the change, 0 to 1.
+  pub loss_ratio: f64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ActiveOutage {
+  pub incident_id: Option<String>,
+  pub started: String,
+  pub duration_secs: u64,
+}
+
+/// Headline numbers for the dashboard, so it doesn't have to derive them from log lines.
+#[derive(Clone, Serialize)]
+pub struct PingSummary {
+  pub address: String,
+  pub label: String,
+  pub status: LinkStatus,
+  pub last_rtt_ms: Option<f64>,
+  pub loss_1m_percent: Option<f64>,
+  pub loss_5m_percent: Option<f64>,
+  pub outage: Option<ActiveOutage>,
+  /// Outages come and go too often; their alerts are damped.
+  pub flapping: bool,
+}
+
+/// Probe outcomes of the last five minutes.
+#[derive(Default)]
+pub struct RollingLoss {
+  samples: VecDeque<(Instant, bool)>,
+}
+
+impl RollingLoss {
+  pub fn push(&mut self, at: Instant, success: bool) {
+    self.samples.push_back((at, success));
+    while self.samples.front().is_some_and(|(t, _)| at.duration_since(*t) > WINDOW) {
+      self.samples.pop_front();
+    }
+  }
+
+  /// Loss over the trailing `window`, or `None` without samples in it.
+  pub fn loss_percent(&self, now: Instant, window: Duration) -> Option<f64> {
+    let (sent, lost) = self
+      .samples
+      .iter()
+      .rev()
+      .take_while(|(t, _)| now.duration_since(*t) <= window)
+      .fold((0u32, 0u32), |(sent, lost), (_, ok)| (sent + 1, lost + u32::from(!ok)));
+    (sent > 0).then(|| f64::from(lost) * 100.0 / f64::from(sent))
+  }
+}
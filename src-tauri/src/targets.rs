@@ -0,0 +1,370 @@
+use std::borrow::Cow;
+use std::net::{IpAddr, Ipv6Addr};
+
+use lettre::message::Mailbox;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::alertmanager::AlertmanagerSettings;
+use crate::alerts::AlertSeverity;
+use crate::scheduler::{self, MonitorSchedule};
+use crate::feishu::FeishuSettings;
+use crate::oncall::OnCallSettings;
+use crate::ping_binary::{self, PingBinary};
+use crate::proxy::{self, ProxySettings};
+use crate::sms::SmsSettings;
+use crate::speech::SpeechSettings;
+use crate::tunnel::{self, TunnelCheck};
+use crate::{http_probe, ntp_probe, service_probe, snmp, udp_probe, SmtpSettings};
+
+const MAX_LABEL_CHARS: usize = 64;
+const MAX_DESCRIPTION_CHARS: usize = 500;
+
+/// User-supplied metadata for a monitored address, matched by exact address.
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct TargetConfig {
+  pub address: String,
+  #[serde(default)]
+  pub label: String,
+  #[serde(default)]
+  pub description: String,
+  /// CSS color hint such as `#3b82f6`.
+  #[serde(default)]
+  pub color: Option<String>,
+  /// Restricts monitoring to these windows; probes outside them are skipped entirely.
+  #[serde(default)]
+  pub schedule: Option<MonitorSchedule>,
+  #[serde(default)]
+  pub alerts: AlertRouting,
+  /// When a ping goes unanswered, count the host as up if it answers ARP. Only for hosts on
+  /// the local subnet, e.g. laptops and IoT devices that firewall ICMP.
+  #[serde(default)]
+  pub arp_fallback: bool,
+  /// DSCP code point (0-63) to mark ping probes with, e.g. 46 (EF) to see whether the provider
+  /// treats voice traffic differently from best effort.
+  #[serde(default)]
+  pub dscp: Option<u8>,
+  /// Ping executable for this target, replacing the global one.
+  #[serde(default)]
+  pub ping_binary: Option<PingBinary>,
+  /// For an `https://` target, also check that its QUIC (HTTP/3) endpoint answers on UDP; the
+  /// target is only up when both do.
+  #[serde(default)]
+  pub quic: bool,
+  /// Treats the target as the inside end of a VPN tunnel whose outside endpoint is checked when
+  /// it goes down.
+  #[serde(default)]
+  pub tunnel: Option<TunnelCheck>,
+}
+
+/// Per-target override of where alerts go; defaults to the global alert settings.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct AlertRouting {
+  #[serde(default = "default_true")]
+  pub email: bool,
+  /// Comma-separated recipients replacing the global recipient for this target.
+  #[serde(default)]
+  pub email_to: String,
+  #[serde(default = "default_true")]
+  pub sms: bool,
+  #[serde(default = "default_true")]
+  pub oncall: bool,
+  #[serde(default = "default_true")]
+  pub feishu: bool,
+  /// Spoken announcements, where enabled in the alert settings.
+  #[serde(default = "default_true")]
+  pub speech: bool,
+  #[serde(default = "default_true")]
+  pub alertmanager: bool,
+}
+
+impl Default for AlertRouting {
+  fn default() -> Self {
+    Self {
+      email: true,
+      email_to: String::new(),
+      sms: true,
+      oncall: true,
+      feishu: true,
+      speech: true,
+      alertmanager: true,
+    }
+  }
+}
+
+fn default_true() -> bool {
+  true
+}
+
+impl TargetConfig {
+  /// A copy whose alerts go to no channel, for while alerting is paused.
+  pub fn muted(&self) -> Self {
+    Self {
+      alerts: AlertRouting {
+        email: false,
+        email_to: String::new(),
+        sms: false,
+        oncall: false,
+        feishu: false,
+        speech: false,
+        alertmanager: false,
+      },
+      ..self.clone()
+    }
+  }
+
+  /// `Office Router (192.168.1.1)` when labelled, otherwise just the address.
+  pub fn display_name(&self) -> String {
+    let label = self.label.trim();
+    let address = public_address(&self.address);
+    if label.is_empty() {
+      address.into_owned()
+    } else {
+      format!("{label} ({address})")
+    }
+  }
+
+  /// SMTP settings to alert this target with, or `None` when email is off for it.
+  pub fn alert_smtp(&self, smtp: &SmtpSettings, proxy: &ProxySettings) -> Option<SmtpSettings> {
+    if !self.alerts.email {
+      return None;
+    }
+    let mut smtp = smtp.with_proxy(proxy);
+    if !self.alerts.email_to.trim().is_empty() {
+      smtp.to = self.alerts.email_to.trim().to_string();
+    }
+    Some(smtp)
+  }
+
+  /// The HTTP channel getters below also resolve the channel's proxy against the global one.
+  pub fn alert_sms(&self, sms: &SmsSettings, proxy: &ProxySettings) -> Option<SmsSettings> {
+    (sms.enabled && self.alerts.sms).then(|| SmsSettings {
+      proxy: proxy::effective(proxy, &sms.proxy),
+      ..sms.clone()
+    })
+  }
+
+  pub fn alert_oncall(&self, oncall: &OnCallSettings, proxy: &ProxySettings) -> Option<OnCallSettings> {
+    (oncall.enabled && self.alerts.oncall).then(|| OnCallSettings {
+      proxy: proxy::effective(proxy, &oncall.proxy),
+      ..oncall.clone()
+    })
+  }
+
+  pub fn alert_alertmanager(
+    &self,
+    alertmanager: &AlertmanagerSettings,
+    proxy: &ProxySettings,
+  ) -> Option<AlertmanagerSettings> {
+    (alertmanager.enabled && self.alerts.alertmanager).then(|| AlertmanagerSettings {
+      proxy: proxy::effective(proxy, &alertmanager.proxy),
+      ..alertmanager.clone()
+    })
+  }
+
+  pub fn alert_speech(&self, speech: &SpeechSettings, severity: AlertSeverity) -> Option<SpeechSettings> {
+    (self.alerts.speech && speech.speaks(severity)).then(|| speech.clone())
+  }
+
+  pub fn alert_feishu(&self, feishu: &FeishuSettings, proxy: &ProxySettings) -> Option<FeishuSettings> {
+    (feishu.enabled && self.alerts.feishu).then(|| FeishuSettings {
+      proxy: proxy::effective(proxy, &feishu.proxy),
+      ..feishu.clone()
+    })
+  }
+}
+
+pub fn find(targets: &[TargetConfig], address: &str) -> TargetConfig {
+  targets
+    .iter()
+    .find(|target| target.address == address)
+    .cloned()
+    .unwrap_or_else(|| TargetConfig {
+      address: address.to_string(),
+      ..TargetConfig::default()
+    })
+}
+
+/// The address to probe for `address`, which may have lost its credentials on the way (history
+/// keeps `public_address`): the configured target it stands for, else `address` itself.
+pub fn with_credentials(targets: &[TargetConfig], address: &str) -> String {
+  targets
+    .iter()
+    .find(|target| target.address == address || public_address(&target.address) == address)
+    .map_or_else(|| address.to_string(), |target| target.address.clone())
+}
+
+/// Targets probed over a URL scheme rather than by pinging the address.
+pub fn is_url_target(address: &str) -> bool {
+  http_probe::is_http_target(address)
+    || snmp::is_snmp_target(address)
+    || service_probe::is_service_target(address)
+    || udp_probe::is_udp_target(address)
+    || ntp_probe::is_ntp_target(address)
+}
+
+/// `address` without the user info of a URL target, e.g. the community or v3 password of
+/// `snmp://community@host/oid`. Logs, alerts and exports name a target this way; only the probe
+/// sees the credentials.
+pub fn public_address(address: &str) -> Cow<'_, str> {
+  let Some((scheme, rest)) = address.split_once("://") else {
+    return Cow::Borrowed(address);
+  };
+  let authority = &rest[..rest.find(['/', '?', '#']).unwrap_or(rest.len())];
+  match authority.rfind('@') {
+    Some(at) => Cow::Owned(format!("{scheme}://{}", &rest[at + 1..])),
+    None => Cow::Borrowed(address),
+  }
+}
+
+/// Accepts an `http(s)://`, `snmp://`, `service://`, `udp://` or `ntp://` URL, or what `validate_host`
+/// accepts.
+pub fn validate_address(address: &str) -> Result<(), String> {
+  if address.is_empty() {
+    return Err("Address cannot be empty".to_string());
+  }
+  if !is_url_target(address) {
+    return validate_host(address);
+  }
+  if service_probe::is_service_target(address) {
+    return service_probe::validate(address);
+  }
+  if udp_probe::is_udp_target(address) {
+    return udp_probe::validate(address);
+  }
+  if ntp_probe::is_ntp_target(address) {
+    return ntp_probe::validate(address);
+  }
+  if address.chars().any(|c| c.is_whitespace() || c.is_control()) {
+    return Err(format!("地址格式无效: {address}"));
+  }
+  match Url::parse(address) {
+    Ok(url) if url.host_str().is_some() => Ok(()),
+    _ => Err(format!("地址格式无效: {address}")),
+  }
+}
+
+/// An IP literal (IPv6 optionally with a `%zone`) or an RFC 1123 hostname. The address ends
+/// up as an argument of the system ping, so anything else — options, whitespace, shell
+/// metacharacters — is refused.
+pub fn validate_host(host: &str) -> Result<(), String> {
+  let invalid = || format!("地址格式无效: {host}");
+  if host.parse::<IpAddr>().is_ok() {
+    return Ok(());
+  }
+  if let Some((ip, zone)) = host.split_once('%') {
+    let zone_ok = !zone.is_empty()
+      && !zone.starts_with('-')
+      && zone.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+    return if ip.parse::<Ipv6Addr>().is_ok() && zone_ok {
+      Ok(())
+    } else {
+      Err(invalid())
+    };
+  }
+  let name = host.strip_suffix('.').unwrap_or(host);
+  if name.is_empty() || name.len() > 253 {
+    return Err(invalid());
+  }
+  let label_ok = |label: &str| {
+    (1..=63).contains(&label.len())
+      && !label.starts_with('-')
+      && !label.ends_with('-')
+      && label.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+  };
+  if name.split('.').all(label_ok) {
+    Ok(())
+  } else {
+    Err(invalid())
+  }
+}
+
+pub fn validate(target: &TargetConfig) -> Result<(), String> {
+  validate_address(target.address.trim())?;
+  validate_text("标签", &target.label, MAX_LABEL_CHARS)?;
+  validate_text("描述", &target.description, MAX_DESCRIPTION_CHARS)?;
+  if let Some(color) = &target.color {
+    let hex = color.strip_prefix('#').unwrap_or("");
+    if !matches!(hex.len(), 3 | 6) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+      return Err(format!("颜色格式不正确: {color}"));
+    }
+  }
+  for recipient in target.alerts.email_to.split([',', ';']).map(str::trim) {
+    if !recipient.is_empty() && recipient.parse::<Mailbox>().is_err() {
+      return Err(format!("收件人邮箱格式不正确: {recipient}"));
+    }
+  }
+  if let Some(schedule) = &target.schedule {
+    scheduler::validate(schedule)?;
+  }
+  if target.arp_fallback && is_url_target(&target.address) {
+    return Err("ARP 探测只适用于主机地址".to_string());
+  }
+  if let Some(dscp) = target.dscp {
+    if dscp > 63 {
+      return Err(format!("DSCP 值 {dscp} 无效，应在 0-63 之间"));
+    }
+    if is_url_target(&target.address) {
+      return Err("DSCP 标记只适用于 ping 探测的主机地址".to_string());
+    }
+    if !ping_core::supports_dscp(ping_core::Platform::current()) {
+      return Err("Windows 的 ping 无法设置 DSCP/ToS，该设置仅在 Linux 和 macOS 上可用".to_string());
+    }
+  }
+  if let Some(binary) = target.ping_binary.as_ref().filter(|binary| binary.is_set()) {
+    if is_url_target(&target.address) {
+      return Err("自定义 ping 程序只适用于 ping 探测的主机地址".to_string());
+    }
+    ping_binary::validate(binary)?;
+  }
+  if target.quic && !target.address.to_ascii_lowercase().starts_with("https://") {
+    return Err("QUIC 检查只适用于 https:// 目标".to_string());
+  }
+  if let Some(tunnel) = &target.tunnel {
+    tunnel::validate(tunnel, &target.address)?;
+  }
+  Ok(())
+}
+
+/// The label goes into every log line as part of `display_name`, where ` | ` separates the
+/// fields and a newline ends the line.
+fn validate_text(what: &str, text: &str, max_chars: usize) -> Result<(), String> {
+  if text.contains('|') || text.chars().any(char::is_control) {
+    return Err(format!("{what}不能包含 | 或控制字符"));
+  }
+  if text.chars().count() > max_chars {
+    return Err(format!("{what}最长 {max_chars} 个字符"));
+  }
+  Ok(())
+}
+
+pub fn upsert(targets: &mut Vec<TargetConfig>, target: TargetConfig) {
+  match targets.iter_mut().find(|t| t.address == target.address) {
+    Some(existing) => *existing = target,
+    None => targets.push(target),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn target(label: &str, description: &str) -> TargetConfig {
+    TargetConfig {
+      address: "192.168.1.1".to_string(),
+      label: label.to_string(),
+      description: description.to_string(),
+      ..TargetConfig::default()
+    }
+  }
+
+  #[test]
+  fn rejects_labels_that_would_break_log_lines() {
+    assert!(validate(&target("Office Router", "Rack 2, second shelf")).is_ok());
+    assert!(validate(&target("Router | WAN", "")).is_err());
+    assert!(validate(&target("Router\nWAN", "")).is_err());
+    assert!(validate(&target(&"x".repeat(MAX_LABEL_CHARS + 1), "")).is_err());
+    assert!(validate(&target("Router", "a|b")).is_err());
+    assert!(validate(&target("Router", &"x".repeat(MAX_DESCRIPTION_CHARS + 1))).is_err());
+  }
+}
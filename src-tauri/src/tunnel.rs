@@ -0,0 +1,109 @@
+// Tunnel health preset: a target inside an IPsec or WireGuard tunnel (a host on the far site's
+// LAN) paired with the tunnel's outside endpoint (the peer's public address). When the inside
+// target starts failing the outside endpoint is pinged (see `outage_checks`). If it answers, the
+// underlay is fine and the tunnel itself is down, which gets its own alert; if it doesn't, the
+// outage is reported as the link or peer failing instead, and at warning severity. Without a
+// verdict by the time the outage is confirmed, it is alerted as a plain outage.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::alerts::{self, RecoveryAlert};
+use crate::targets::{self, TargetConfig};
+use crate::{ping_once, PingEncoding};
+
+pub const CARD_TITLE: &str = "隧道中断告警";
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TunnelKind {
+  #[default]
+  Ipsec,
+  Wireguard,
+}
+
+impl TunnelKind {
+  fn name(self) -> &'static str {
+    match self {
+      TunnelKind::Ipsec => "IPsec",
+      TunnelKind::Wireguard => "WireGuard",
+    }
+  }
+}
+
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct TunnelCheck {
+  #[serde(default)]
+  pub kind: TunnelKind,
+  /// The peer's address outside the tunnel, pinged directly.
+  #[serde(default)]
+  pub outside: String,
+}
+
+/// What the outside endpoint said when the inside target went down.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+  /// The peer answers outside the tunnel: the tunnel is down.
+  TunnelDown,
+  /// The peer doesn't answer either: the link or the peer is down.
+  UnderlayDown,
+}
+
+pub fn validate(tunnel: &TunnelCheck, inside: &str) -> Result<(), String> {
+  let outside = tunnel.outside.trim();
+  if outside.is_empty() {
+    return Err("隧道检查需要填写隧道外的对端地址".to_string());
+  }
+  targets::validate_host(outside).map_err(|e| format!("隧道外地址无效: {e}"))?;
+  if outside == inside.trim() {
+    return Err("隧道外地址不能与隧道内目标相同".to_string());
+  }
+  Ok(())
+}
+
+/// Pings the outside endpoint; two tries, so one lost packet doesn't blame the tunnel wrongly.
+pub fn check(tunnel: &TunnelCheck, encoding: PingEncoding) -> Verdict {
+  let outside = tunnel.outside.trim();
+  if (0..2).any(|_| ping_once(outside, encoding, None).is_ok()) {
+    Verdict::TunnelDown
+  } else {
+    Verdict::UnderlayDown
+  }
+}
+
+/// The outage alert for a tunnel target.
+pub fn message(
+  target: &TargetConfig,
+  tunnel: &TunnelCheck,
+  verdict: Verdict,
+  failures: u32,
+  start_time: &str,
+) -> String {
+  let (name, outside) = (tunnel.kind.name(), tunnel.outside.trim());
+  let target_name = target.display_name();
+  match verdict {
+    Verdict::TunnelDown => format!(
+      "{name} 隧道中断：隧道内 {target_name} 连续 {failures} 次失败，隧道外对端 {outside} 正常，开始时间 {start_time}"
+    ),
+    Verdict::UnderlayDown => format!(
+      "{target_name} 连续 {failures} 次失败，开始时间 {start_time}；隧道外对端 {outside} 也无响应，故障在底层链路或对端，不是 {name} 隧道本身"
+    ),
+  }
+}
+
+/// The recovery alert of a tunnel outage.
+pub fn recovery(
+  target: &TargetConfig,
+  tunnel: &TunnelCheck,
+  start_time: &str,
+  recover_time: &str,
+  duration: Duration,
+) -> RecoveryAlert {
+  let problem = format!(
+    "{} 隧道中断，隧道外对端 {} 正常",
+    tunnel.kind.name(),
+    tunnel.outside.trim()
+  );
+  alerts::recovery_with(target, start_time, recover_time, duration, CARD_TITLE, &problem)
+}
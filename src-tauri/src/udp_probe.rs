@@ -0,0 +1,168 @@
+// UDP probes for services that neither ping nor a TCP connect represent: game servers, DNS-like
+// query protocols, UDP VPN endpoints. `udp://host:port?hex=ffffffff54&expect=reply&timeout=1000`
+// sends one datagram and waits for the answer: an application reply, an ICMP port unreachable
+// (reported by the OS as a refused or reset connection on a connected socket) or nothing.
+// `expect` decides which of those count as up:
+//
+// - `reply` (default): only an application reply, for servers that answer a query.
+// - `any`: a reply or port unreachable, i.e. the host is there even if the port is closed.
+// - `open`: anything but port unreachable, for endpoints like WireGuard that stay silent to
+//   datagrams they can't authenticate; silence can't tell open from filtered.
+
+use std::io::ErrorKind;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+
+use url::Url;
+
+const DEFAULT_TIMEOUT_MS: u64 = 1000;
+const MAX_TIMEOUT_MS: u64 = 5000;
+/// The largest payload that fits a 1500-byte Ethernet frame over IPv4.
+const MAX_PAYLOAD: usize = 1472;
+const MAX_REPLY: usize = 65_535;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Expect {
+  Reply,
+  Any,
+  Open,
+}
+
+struct UdpTarget {
+  /// As it appears in a URL: IPv6 literals keep their brackets.
+  host: String,
+  port: u16,
+  payload: Vec<u8>,
+  expect: Expect,
+  timeout: Duration,
+}
+
+/// What came back for the datagram.
+enum Answer {
+  Reply { bytes: usize, ms: f64 },
+  Unreachable { ms: f64 },
+  Silence,
+}
+
+pub fn is_udp_target(address: &str) -> bool {
+  address.to_ascii_lowercase().starts_with("udp://")
+}
+
+pub fn validate(address: &str) -> Result<(), String> {
+  parse_target(address).map(drop)
+}
+
+fn parse_target(address: &str) -> Result<UdpTarget, String> {
+  let url = Url::parse(address).map_err(|e| format!("地址格式无效: {e}"))?;
+  let host = url
+    .host_str()
+    .ok_or_else(|| format!("地址 {address} 缺少主机名"))?
+    .to_string();
+  let port = url
+    .port()
+    .filter(|port| *port > 0)
+    .ok_or_else(|| format!("UDP 目标需要端口，如 udp://{host}:27015"))?;
+  let mut payload: Option<Vec<u8>> = None;
+  let mut expect = Expect::Reply;
+  let mut timeout_ms = DEFAULT_TIMEOUT_MS;
+  for (key, value) in url.query_pairs() {
+    match key.as_ref() {
+      "payload" | "hex" if payload.is_some() => return Err("payload 和 hex 只能设置一个".to_string()),
+      "payload" => payload = Some(value.as_bytes().to_vec()),
+      "hex" => payload = Some(decode_hex(&value)?),
+      "expect" => {
+        expect = match value.as_ref() {
+          "reply" => Expect::Reply,
+          "any" => Expect::Any,
+          "open" => Expect::Open,
+          other => return Err(format!("expect 应为 reply、any 或 open: {other}")),
+        }
+      }
+      "timeout" => {
+        timeout_ms = value
+          .parse()
+          .ok()
+          .filter(|ms| (1..=MAX_TIMEOUT_MS).contains(ms))
+          .ok_or_else(|| format!("timeout 应为 1 到 {MAX_TIMEOUT_MS} 毫秒: {value}"))?;
+      }
+      other => return Err(format!("未知的 UDP 参数: {other}")),
+    }
+  }
+  let payload = payload.unwrap_or_default();
+  if payload.len() > MAX_PAYLOAD {
+    return Err(format!("UDP 载荷最多 {MAX_PAYLOAD} 字节"));
+  }
+  Ok(UdpTarget {
+    host,
+    port,
+    payload,
+    expect,
+    timeout: Duration::from_millis(timeout_ms),
+  })
+}
+
+fn decode_hex(hex: &str) -> Result<Vec<u8>, String> {
+  let digits: String = hex.chars().filter(|c| !c.is_whitespace()).collect();
+  if !digits.len().is_multiple_of(2) || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+    return Err(format!("hex 载荷应为偶数个十六进制字符: {hex}"));
+  }
+  Ok(
+    (0..digits.len())
+      .step_by(2)
+      .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).unwrap_or_default())
+      .collect(),
+  )
+}
+
+/// Sends the datagram and judges the answer by `expect`. Returns the summary line and the time
+/// until the answer, when there was one.
+pub fn probe(address: &str) -> (Result<String, String>, Option<f64>) {
+  let target = match parse_target(address) {
+    Ok(target) => target,
+    Err(err) => return (Err(err), None),
+  };
+  let name = format!("udp {}:{}", target.host, target.port);
+  let answer = match exchange(&target) {
+    Ok(answer) => answer,
+    Err(err) => return (Err(format!("{name} {err}")), None),
+  };
+  match (answer, target.expect) {
+    (Answer::Reply { bytes, ms }, _) => (Ok(format!("{name} reply {bytes} bytes time={ms:.0}ms")), Some(ms)),
+    (Answer::Unreachable { ms }, Expect::Any) => (Ok(format!("{name} port unreachable time={ms:.0}ms")), Some(ms)),
+    (Answer::Unreachable { .. }, _) => (Err(format!("{name} port unreachable")), None),
+    (Answer::Silence, Expect::Open) => (Ok(format!("{name} no reply (open or filtered)")), None),
+    (Answer::Silence, _) => (
+      Err(format!("{name} no reply within {}ms", target.timeout.as_millis())),
+      None,
+    ),
+  }
+}
+
+fn exchange(target: &UdpTarget) -> Result<Answer, String> {
+  let host = target.host.trim_matches(['[', ']']);
+  let addr: SocketAddr = (host, target.port)
+    .to_socket_addrs()
+    .map_err(|e| format!("dns failed: {e}"))?
+    .next()
+    .ok_or_else(|| "dns returned no address".to_string())?;
+  let local = if addr.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+  let socket = UdpSocket::bind(local).map_err(|e| format!("bind failed: {e}"))?;
+  // Connected, so an ICMP port unreachable comes back as an error on this socket.
+  socket.connect(addr).map_err(|e| format!("connect failed: {e}"))?;
+  socket
+    .set_read_timeout(Some(target.timeout))
+    .map_err(|e| format!("socket setup failed: {e}"))?;
+  let start = Instant::now();
+  socket.send(&target.payload).map_err(|e| format!("send failed: {e}"))?;
+  let mut buffer = vec![0; MAX_REPLY];
+  let ms = || start.elapsed().as_secs_f64() * 1000.0;
+  match socket.recv(&mut buffer) {
+    Ok(bytes) => Ok(Answer::Reply { bytes, ms: ms() }),
+    // Windows reports the ICMP error as a reset, the others as refused.
+    Err(e) if matches!(e.kind(), ErrorKind::ConnectionRefused | ErrorKind::ConnectionReset) => {
+      Ok(Answer::Unreachable { ms: ms() })
+    }
+    Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => Ok(Answer::Silence),
+    Err(e) => Err(format!("receive failed: {e}")),
+  }
+}
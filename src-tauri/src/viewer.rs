@@ -0,0 +1,129 @@
+// Viewer mode: a read-only session for a shared NOC display. Logs, statistics and settings can
+// be looked at, but nothing can be changed, started or stopped from the UI. It is switched on by
+// the `--viewer` command-line switch or the `viewer_mode` setting, and enforced where commands
+// are dispatched, so a modified or stale frontend can't get around it. Leaving it means editing
+// settings.json or restarting without the switch. Settings that hold tokens or passwords
+// aren't shown either: the remote control token alone would let a viewer stop monitoring.
+
+use std::env;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+use serde::Serialize;
+use tauri::ipc::Invoke;
+use tauri::AppHandle;
+
+use crate::error::{AppError, ErrorKind};
+use crate::load_settings;
+
+const SWITCH: &str = "--viewer";
+
+/// Commands that only read state; everything else is refused in viewer mode, including
+/// commands added later until they are listed here.
+const READ_ONLY_COMMANDS: &[&str] = &[
+  "subscribe_events",
+  "unsubscribe_events",
+  "copy_recent_logs_to_clipboard",
+  "copy_statistics_summary",
+  "compare_ranges",
+  "tail_log_file",
+  "export_statistics_xlsx",
+  "export_outages_ics",
+];
+
+/// Getters whose settings carry tokens, passwords or API keys, refused in viewer mode. Targets,
+/// favorites, history and jobs hold addresses as entered, which may embed credentials such as
+/// `snmp://community@host/oid`.
+const SECRET_GETTERS: &[&str] = &[
+  "get_alert_settings",
+  "get_favorites",
+  "get_jobs",
+  "get_otlp_settings",
+  "get_relay_agents",
+  "get_relay_server_settings",
+  "get_remote_control_settings",
+  "get_target_history",
+  "get_targets",
+];
+
+/// The `viewer_mode` setting as last read, so a command doesn't read settings.json to be let through.
+static SETTING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Serialize)]
+pub struct ViewerMode {
+  pub enabled: bool,
+  /// Set by the command-line switch, so the setting can't turn it off.
+  pub from_switch: bool,
+}
+
+fn switch_given() -> bool {
+  static GIVEN: OnceLock<bool> = OnceLock::new();
+  *GIVEN.get_or_init(|| env::args().skip(1).any(|arg| arg == SWITCH))
+}
+
+/// Reads the setting again; called at startup and when settings.json changes on disk.
+pub fn refresh(app: &AppHandle) {
+  set_setting(load_settings(app).viewer_mode);
+}
+
+/// Called after the app itself saves the setting.
+pub fn set_setting(enabled: bool) {
+  SETTING.store(enabled, Ordering::Relaxed);
+}
+
+pub fn status() -> ViewerMode {
+  let from_switch = switch_given();
+  ViewerMode {
+    enabled: from_switch || SETTING.load(Ordering::Relaxed),
+    from_switch,
+  }
+}
+
+fn is_read_only(command: &str) -> bool {
+  (command.starts_with("get_") && !SECRET_GETTERS.contains(&command)) || READ_ONLY_COMMANDS.contains(&command)
+}
+
+/// `Err` when `command` would change something, or show a secret, and viewer mode is on.
+pub fn check(command: &str) -> Result<(), AppError> {
+  if is_read_only(command) || !status().enabled {
+    return Ok(());
+  }
+  let message = if SECRET_GETTERS.contains(&command) {
+    "当前为只读查看模式，不显示包含密钥或密码的设置"
+  } else {
+    "当前为只读查看模式，不能修改设置或控制监控"
+  };
+  Err(AppError::new(ErrorKind::ReadOnly, message))
+}
+
+/// Wraps the command dispatcher so commands that aren't read-only are refused in viewer mode.
+pub fn guard(
+  handler: impl Fn(Invoke) -> bool + Send + Sync + 'static,
+) -> impl Fn(Invoke) -> bool + Send + Sync + 'static {
+  move |invoke| {
+    if let Err(e) = check(invoke.message.command()) {
+      invoke.resolver.reject(e);
+      return true;
+    }
+    handler(invoke)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn refuses_getters_of_credential_bearing_addresses() {
+    for command in ["get_targets", "get_favorites", "get_target_history", "get_jobs", "get_alert_settings"] {
+      assert!(!is_read_only(command), "{command} must be refused in viewer mode");
+    }
+  }
+
+  #[test]
+  fn allows_plain_getters_and_listed_commands() {
+    assert!(is_read_only("get_statistics"));
+    assert!(is_read_only("compare_ranges"));
+    assert!(!is_read_only("save_target"));
+  }
+}
@@ -170,6 +170,11 @@ function setRunning(next) {
   document.body.dataset.running = next ? "true" : "false";
 }
 
+// Commands reject with `{ kind, message, details }`.
+function errorMessage(err) {
+  return err && typeof err === "object" && "message" in err ? err.message : String(err);
+}
+
 function setError(message) {
   errorText.textContent = message || "";
 }
@@ -269,7 +274,7 @@ async function loadAlertSettings() {
     applySmtpSettings(settingsCache);
     setSmtpStatus("");
   } catch (err) {
-    setSmtpStatus(String(err), "error");
+    setSmtpStatus(errorMessage(err), "error");
   }
 }
 
@@ -285,7 +290,7 @@ async function saveAlertSettings() {
     await invoke("save_alert_settings", { settings: settingsCache });
     setSmtpStatus("已保存配置。", "success");
   } catch (err) {
-    setSmtpStatus(String(err), "error");
+    setSmtpStatus(errorMessage(err), "error");
   }
 }
 
@@ -301,9 +306,9 @@ async function testSmtp() {
   setSmtpStatus("发送中…");
   try {
     const result = await invoke("test_smtp", { smtp });
-    setSmtpStatus(String(result || "发送成功。"), "success");
+    setSmtpStatus(String(result?.message || "发送成功。"), "success");
   } catch (err) {
-    setSmtpStatus(String(err), "error");
+    setSmtpStatus(errorMessage(err), "error");
   } finally {
     setTestSending(false);
   }
@@ -322,7 +327,7 @@ async function exportAlertSettings() {
     }
     setSmtpStatus(`已导出到: ${result}`, "success");
   } catch (err) {
-    setSmtpStatus(String(err), "error");
+    setSmtpStatus(errorMessage(err), "error");
   }
 }
 
@@ -341,7 +346,7 @@ async function importAlertSettings() {
     applySmtpSettings(settingsCache);
     setSmtpStatus("导入成功。", "success");
   } catch (err) {
-    setSmtpStatus(String(err), "error");
+    setSmtpStatus(errorMessage(err), "error");
   }
 }
 
@@ -355,7 +360,7 @@ async function refreshLogDir() {
       logPath.textContent = current;
     }
   } catch (err) {
-    setError(String(err));
+    setError(errorMessage(err));
   }
 }
 
@@ -365,12 +370,50 @@ async function changeLogDir() {
   }
   setError("");
   try {
-    const current = await invoke("select_log_dir");
-    if (typeof current === "string" && logPath) {
-      logPath.textContent = current;
+    const change = await invoke("select_log_dir");
+    if (change && typeof change.path === "string" && logPath) {
+      logPath.textContent = change.path;
+    }
+    if (change && change.previous) {
+      await offerLogMigration(change.previous, change.path);
     }
   } catch (err) {
-    setError(String(err));
+    setError(errorMessage(err));
+  }
+}
+
+async function offerLogMigration(previous, path) {
+  const size = `${previous.files} 个文件，${(previous.bytes / 1048576).toFixed(1)} MB`;
+  let mode = null;
+  if (window.confirm(`原日志目录 ${previous.path} 中有 ${size}。是否移动到新目录？`)) {
+    mode = "move";
+  } else if (window.confirm("是否改为复制一份到新目录（原目录保留）？")) {
+    mode = "copy";
+  }
+  if (!mode) {
+    return;
+  }
+  let stopProgress = null;
+  if (eventApi && typeof eventApi.listen === "function") {
+    stopProgress = await eventApi.listen("log-migration-progress", (event) => {
+      const progress = event && event.payload ? event.payload : null;
+      if (progress && logPath) {
+        logPath.textContent = `${path}（迁移中 ${progress.files_done}/${progress.files_total}）`;
+      }
+    }).catch(() => null);
+  }
+  try {
+    const report = await invoke("migrate_log_dir", { mode });
+    if (report.conflicts > 0) {
+      setError(`已迁移 ${report.files} 个文件，${report.conflicts} 个文件在新目录中已存在，未覆盖。`);
+    }
+  } finally {
+    if (stopProgress) {
+      stopProgress();
+    }
+    if (logPath) {
+      logPath.textContent = path;
+    }
   }
 }
 
@@ -396,7 +439,7 @@ async function startPing() {
     }
     pollTimer = setInterval(fetchLogs, 1000);
   } catch (err) {
-    setError(String(err));
+    setError(errorMessage(err));
   }
 }
 
@@ -411,7 +454,7 @@ async function stopPing() {
       pollTimer = null;
     }
   } catch (err) {
-    setError(String(err));
+    setError(errorMessage(err));
   }
 }
 
@@ -502,6 +545,14 @@ if (!invoke) {
     }).catch(() => {
       // Ignore listener init errors; logging continues to file.
     });
+    eventApi.listen("alert-event", (event) => {
+      const payload = event && event.payload ? event.payload : null;
+      if (payload && payload.kind === "monitoring_idle" && !running) {
+        statusText.textContent = `空闲：${payload.message}`;
+      }
+    }).catch(() => {
+      // Without the listener the reminder still flashes the taskbar and can go by email.
+    });
   }
   if (logList) {
     logList.addEventListener("scroll", () => {